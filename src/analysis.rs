@@ -0,0 +1,1650 @@
+use error::Error;
+use expression::Expression;
+use grammar::Grammar;
+use production::Production;
+use std::collections::{BTreeSet, HashMap, HashSet};
+use std::str;
+use term::{Nonterminal, StartSymbol, Term};
+
+/// Counts of the alternatives of a grammar's nonterminals, see
+/// `Grammar::stats`
+#[derive(Clone, Copy, Debug, Default, PartialEq)]
+pub struct GrammarStats {
+    nonterminals: usize,
+    min_alternatives: usize,
+    max_alternatives: usize,
+    average_alternatives: f64,
+}
+
+impl GrammarStats {
+    /// Get the number of nonterminals with a production
+    pub fn nonterminals(&self) -> usize {
+        self.nonterminals
+    }
+
+    /// Get the fewest alternatives of any nonterminal, or 0 without any
+    pub fn min_alternatives(&self) -> usize {
+        self.min_alternatives
+    }
+
+    /// Get the most alternatives of any nonterminal, or 0 without any
+    pub fn max_alternatives(&self) -> usize {
+        self.max_alternatives
+    }
+
+    /// Get the average number of alternatives of each nonterminal, the same
+    /// as `Grammar::branching_factor`
+    pub fn average_alternatives(&self) -> f64 {
+        self.average_alternatives
+    }
+}
+
+/// Group the alternatives of every production by the name of their lhs
+fn rules(grammar: &Grammar) -> HashMap<&str, Vec<&Expression>> {
+    let mut rules: HashMap<&str, Vec<&Expression>> = HashMap::new();
+    for production in grammar.productions_iter() {
+        if let Term::Nonterminal(ref nt) = *production.lhs() {
+            rules
+                .entry(nt.as_str())
+                .or_default()
+                .extend(production.rhs_iter());
+        }
+    }
+    rules
+}
+
+/// Get the name of the lhs of the first production, the start symbol
+fn start(grammar: &Grammar) -> Option<&str> {
+    grammar
+        .productions_iter()
+        .next()
+        .and_then(|prod| match *prod.lhs() {
+            Term::Nonterminal(ref nt) => Some(nt.as_str()),
+            Term::Terminal(_)
+            | Term::CaselessTerminal(_)
+            | Term::TerminalBytes(_)
+            | Term::CharClass(_)
+            | Term::Regex(_)
+            | Term::Repeat { .. } => None,
+        })
+}
+
+impl Grammar {
+    /// Get a sentence of the fewest characters derivable from the start
+    /// symbol, the lhs of the first production.
+    ///
+    /// The alternatives of every production sharing a lhs are considered, and
+    /// a nonterminal without a production derives its own `<name>` text, the
+    /// same as generation emits. Length is measured in characters (`char`s),
+    /// with a character class contributing its first character. When several
+    /// sentences are equally short any one of them is returned. Returns
+    /// `None` when the start symbol derives no sentence at all, e.g. for
+    /// `<a> ::= <a> "x"`.
+    ///
+    /// # Example
+    ///
+    /// ```rust
+    /// extern crate bnf;
+    /// use bnf::Grammar;
+    ///
+    /// fn main() {
+    ///     let grammar = Grammar::from_str("<dna> ::= <base> <dna> | <base>
+    ///         <base> ::= \"AT\" | \"G\"").unwrap();
+    ///
+    ///     assert_eq!(grammar.shortest_sentence(), Some(String::from("G")));
+    /// }
+    /// ```
+    pub fn shortest_sentence(&self) -> Option<String> {
+        let start = start(self)?;
+        shortest_sentences(self).remove(start)
+    }
+
+    /// Get every string of exactly `length` characters derivable from the
+    /// nonterminal `start`, sorted and without duplicates.
+    ///
+    /// Length is measured in characters (`char`s), the same as
+    /// `count_derivations`, so for grammars whose terminals are single
+    /// characters it is the number of tokens. The alternatives of every
+    /// production sharing a lhs are considered, and a nonterminal without a
+    /// production derives its own `<name>` text. Returns an empty `Vec` when
+    /// no string has that length, or `start` has no production.
+    ///
+    /// The strings of each nonterminal are computed by length, repeatedly
+    /// until no more are found, so that cycles like `<a> ::= <a> | "a"` are
+    /// handled. The result can be exponential in `length`, so this is meant
+    /// for small lengths.
+    ///
+    /// # Example
+    ///
+    /// ```rust
+    /// extern crate bnf;
+    /// use bnf::Grammar;
+    ///
+    /// fn main() {
+    ///     let grammar = Grammar::from_str("<bits> ::= <bit> | <bit> <bits>
+    ///         <bit> ::= \"0\" | \"1\"").unwrap();
+    ///
+    ///     assert_eq!(
+    ///         grammar.derive_all_strings_of_length("bits", 2),
+    ///         vec!["00", "01", "10", "11"]
+    ///     );
+    ///     assert!(grammar.derive_all_strings_of_length("bits", 0).is_empty());
+    /// }
+    /// ```
+    pub fn derive_all_strings_of_length(
+        &self,
+        start: impl Into<Nonterminal>,
+        length: usize,
+    ) -> Vec<String> {
+        let start = start.into();
+        let start = start.name();
+        let expanded = self.expand_repetitions();
+        let rules = rules(&expanded);
+        if !rules.contains_key(start) {
+            return vec![];
+        }
+
+        // the strings derived so far from each nonterminal, indexed by length
+        let mut strings: HashMap<&str, Vec<BTreeSet<String>>> = rules
+            .keys()
+            .map(|&nt| (nt, vec![BTreeSet::new(); length + 1]))
+            .collect();
+        let mut changed = true;
+        while changed {
+            changed = false;
+            for (&nt, expressions) in &rules {
+                for expression in expressions {
+                    let derived = sequence_strings(expression, &strings, length);
+                    let known = strings.get_mut(nt).expect("every lhs has strings");
+                    for (known, derived) in known.iter_mut().zip(derived) {
+                        for string in derived {
+                            changed |= known.insert(string);
+                        }
+                    }
+                }
+            }
+        }
+
+        strings[start][length].iter().cloned().collect()
+    }
+}
+
+/// Whether `term` derives only the empty string by itself
+fn is_empty(term: &Term) -> bool {
+    match *term {
+        Term::Terminal(ref t) | Term::CaselessTerminal(ref t) => t.is_empty(),
+        Term::TerminalBytes(ref bytes) => bytes.is_empty(),
+        Term::Repeat { ref inner, max, .. } => max == Some(0) || inner.terms_iter().all(is_empty),
+        Term::Nonterminal(_) | Term::CharClass(_) | Term::Regex(_) => false,
+    }
+}
+
+/// Whether an alternative is terminals, with a defined nonterminal allowed
+/// only at the position `nonterminal_at` picks from the terms
+fn is_linear<F>(grammar: &Grammar, nonterminal_at: F) -> bool
+where
+    F: Fn(&[&Term]) -> Option<usize>,
+{
+    let rules = rules(grammar);
+    grammar.productions_iter().all(|prod| {
+        prod.rhs_iter().all(|expr| {
+            let terms = expr.terms_iter().collect::<Vec<_>>();
+            let allowed = nonterminal_at(&terms);
+            terms.iter().enumerate().all(|(index, term)| {
+                match **term {
+                Term::Nonterminal(ref nt) if rules.contains_key(nt.as_str()) => {
+                    allowed == Some(index)
+                }
+                // a repetition of terminals is still regular
+                Term::Repeat { ref inner, .. } => !inner.nested_terms_iter().any(|term| {
+                    matches!(*term, Term::Nonterminal(ref nt) if rules.contains_key(nt.as_str()))
+                }),
+                _ => true,
+            }
+            })
+        })
+    })
+}
+
+impl Grammar {
+    /// Whether every alternative is right-linear, having terminals followed
+    /// by at most one nonterminal at its end, so the grammar describes a
+    /// regular language.
+    ///
+    /// Character classes count as terminals, as do nonterminals without a
+    /// production, since they derive their own `<name>` text.
+    ///
+    /// # Example
+    ///
+    /// ```rust
+    /// extern crate bnf;
+    /// use bnf::Grammar;
+    ///
+    /// fn main() {
+    ///     let grammar = Grammar::from_str("<bits> ::= \"0\" <bits> | \"1\" <bits> | \"\"").unwrap();
+    ///     assert!(grammar.is_right_linear());
+    ///     assert!(!grammar.is_left_linear());
+    /// }
+    /// ```
+    pub fn is_right_linear(&self) -> bool {
+        is_linear(self, |terms| terms.len().checked_sub(1))
+    }
+
+    /// Whether every alternative is left-linear, having at most one
+    /// nonterminal at its start followed by terminals, the mirror of
+    /// `Grammar::is_right_linear`
+    pub fn is_left_linear(&self) -> bool {
+        is_linear(self, |_| Some(0))
+    }
+
+    /// Whether any alternative derives only the empty string by itself,
+    /// having no terms or only empty terminals, like `<opt> ::= "x" | ""`,
+    /// or repetitions of none of them or of nothing else
+    pub fn has_epsilon_productions(&self) -> bool {
+        self.productions_iter()
+            .flat_map(|prod| prod.rhs_iter())
+            .any(|expr| expr.terms_iter().all(is_empty))
+    }
+
+    /// Whether any alternative is a lone nonterminal with a production, like
+    /// `<expr> ::= <term>`, which `Grammar::inline_unit_productions` inlines
+    pub fn has_unit_productions(&self) -> bool {
+        let defined = self
+            .productions_iter()
+            .filter_map(|prod| match *prod.lhs() {
+                Term::Nonterminal(ref nt) => Some(nt.as_str()),
+                Term::Terminal(_)
+                | Term::CaselessTerminal(_)
+                | Term::TerminalBytes(_)
+                | Term::CharClass(_)
+                | Term::Regex(_)
+                | Term::Repeat { .. } => None,
+            })
+            .collect::<HashSet<_>>();
+        self.productions_iter()
+            .flat_map(|prod| prod.rhs_iter())
+            .any(|expr| {
+                let mut terms = expr.terms_iter();
+                match (terms.next(), terms.next()) {
+                    (Some(Term::Nonterminal(nt)), None) => defined.contains(nt.as_str()),
+                    _ => false,
+                }
+            })
+    }
+
+    /// Get the average number of alternatives of each nonterminal with a
+    /// production, counting those of every production sharing a lhs. A
+    /// grammar without any has a branching factor of 0.
+    ///
+    /// # Example
+    ///
+    /// ```rust
+    /// extern crate bnf;
+    /// use bnf::Grammar;
+    ///
+    /// fn main() {
+    ///     let grammar = Grammar::from_str("<bit> ::= \"0\" | \"1\"
+    ///         <byte> ::= <bit> <bit> <bit> <bit> <bit> <bit> <bit> <bit>
+    ///         <bit> ::= \"?\"").unwrap();
+    ///
+    ///     assert_eq!(grammar.branching_factor(), 2.0);
+    ///     assert_eq!(grammar.max_alternatives(), 3);
+    /// }
+    /// ```
+    pub fn branching_factor(&self) -> f64 {
+        let rules = rules(self);
+        if rules.is_empty() {
+            return 0.0;
+        }
+        let alternatives: usize = rules.values().map(Vec::len).sum();
+        alternatives as f64 / rules.len() as f64
+    }
+
+    /// Get the most alternatives of any nonterminal, counting those of every
+    /// production sharing a lhs, or 0 for a grammar without productions
+    pub fn max_alternatives(&self) -> usize {
+        rules(self).values().map(Vec::len).max().unwrap_or(0)
+    }
+
+    /// Get the average number of terms of the alternatives of every
+    /// production, or 0 for a grammar without any alternatives.
+    ///
+    /// # Example
+    ///
+    /// ```rust
+    /// extern crate bnf;
+    /// use bnf::Grammar;
+    ///
+    /// fn main() {
+    ///     let grammar = Grammar::from_str("<dna> ::= <base> | <base> <dna>
+    ///         <base> ::= \"A\" | \"C\" | \"G\" | \"T\"").unwrap();
+    ///
+    ///     assert_eq!(grammar.average_expression_length(), 7.0 / 6.0);
+    ///     assert_eq!(grammar.max_expression_length(), 2);
+    /// }
+    /// ```
+    pub fn average_expression_length(&self) -> f64 {
+        let (expressions, terms) = self
+            .productions_iter()
+            .flat_map(|prod| prod.rhs_iter())
+            .fold((0, 0), |(expressions, terms), expr| {
+                (expressions + 1, terms + expr.terms_iter().count())
+            });
+        if expressions == 0 {
+            return 0.0;
+        }
+        terms as f64 / expressions as f64
+    }
+
+    /// Get the most terms of any alternative, or 0 for a grammar without any
+    /// alternatives
+    pub fn max_expression_length(&self) -> usize {
+        self.productions_iter()
+            .flat_map(|prod| prod.rhs_iter())
+            .map(|expr| expr.terms_iter().count())
+            .max()
+            .unwrap_or(0)
+    }
+
+    /// Get the number of alternatives of each nonterminal with a production,
+    /// by its name, counting those of every production sharing a lhs.
+    ///
+    /// # Example
+    ///
+    /// ```rust
+    /// extern crate bnf;
+    /// use bnf::Grammar;
+    ///
+    /// fn main() {
+    ///     let grammar = Grammar::from_str("<bit> ::= \"0\" | \"1\"
+    ///         <byte> ::= <bit> <bit> <bit> <bit> <bit> <bit> <bit> <bit>").unwrap();
+    ///     let counts = grammar.count_expressions_per_production();
+    ///
+    ///     assert_eq!(counts["bit"], 2);
+    ///     assert_eq!(counts["byte"], 1);
+    /// }
+    /// ```
+    pub fn count_expressions_per_production(&self) -> HashMap<String, usize> {
+        rules(self)
+            .into_iter()
+            .map(|(name, alternatives)| (String::from(name), alternatives.len()))
+            .collect()
+    }
+
+    /// Get the fewest, most and average alternatives of the nonterminals
+    /// with a production, all 0 for a grammar without any.
+    ///
+    /// # Example
+    ///
+    /// ```rust
+    /// extern crate bnf;
+    /// use bnf::Grammar;
+    ///
+    /// fn main() {
+    ///     let grammar = Grammar::from_str("<bit> ::= \"0\" | \"1\"
+    ///         <byte> ::= <bit> <bit> <bit> <bit> <bit> <bit> <bit> <bit>").unwrap();
+    ///     let stats = grammar.stats();
+    ///
+    ///     assert_eq!(stats.min_alternatives(), 1);
+    ///     assert_eq!(stats.max_alternatives(), 2);
+    ///     assert_eq!(stats.average_alternatives(), 1.5);
+    /// }
+    /// ```
+    pub fn stats(&self) -> GrammarStats {
+        let counts = self.count_expressions_per_production();
+        if counts.is_empty() {
+            return GrammarStats::default();
+        }
+        GrammarStats {
+            nonterminals: counts.len(),
+            min_alternatives: counts.values().cloned().min().unwrap_or(0),
+            max_alternatives: counts.values().cloned().max().unwrap_or(0),
+            average_alternatives: counts.values().sum::<usize>() as f64 / counts.len() as f64,
+        }
+    }
+
+    /// Whether only finitely many strings are derivable from `start`.
+    ///
+    /// The alternatives of every production sharing a lhs are considered.
+    /// Alternatives which derive no string at all are ignored, as are cycles
+    /// which derive nothing more each time around, like `<a> ::= <a> | "a"`.
+    ///
+    /// # Example
+    ///
+    /// ```rust
+    /// extern crate bnf;
+    /// use bnf::Grammar;
+    ///
+    /// fn main() {
+    ///     let grammar = Grammar::from_str("<dna> ::= <base> | <base> <dna>
+    ///         <base> ::= \"A\" | \"C\" | \"G\" | \"T\"").unwrap();
+    ///
+    ///     assert!(!grammar.is_finite("dna"));
+    ///     assert!(grammar.is_finite("base"));
+    /// }
+    /// ```
+    pub fn is_finite(&self, start: impl StartSymbol) -> bool {
+        let start = start.into_term();
+        let expanded = self.expand_repetitions();
+        let rules = rules(&expanded);
+        let start = match start {
+            Term::Nonterminal(ref nt) if rules.contains_key(nt.as_str()) => nt.as_str(),
+            _ => return true,
+        };
+        let edges = edges(&rules);
+
+        // the language is infinite when a growing edge lies on a cycle
+        !reachable(&edges, start).into_iter().any(|nt| {
+            edges
+                .get(nt)
+                .into_iter()
+                .flatten()
+                .any(|&(child, grows)| grows && reachable(&edges, child).contains(nt))
+        })
+    }
+
+    /// Get every string derivable from `start`, sorted and without duplicates.
+    ///
+    /// The alternatives of every production sharing a lhs are considered, and
+    /// a nonterminal without a production derives its own `<name>` text.
+    /// Returns `Error::InfiniteLanguage` when `Grammar::is_finite` is false.
+    ///
+    /// # Example
+    ///
+    /// ```rust
+    /// extern crate bnf;
+    /// use bnf::Grammar;
+    ///
+    /// fn main() {
+    ///     let grammar = Grammar::from_str("<answer> ::= <word> | <word> \"!\"
+    ///         <word> ::= \"yes\" | \"no\"").unwrap();
+    ///
+    ///     assert_eq!(grammar.language("answer").unwrap(), vec!["no", "no!", "yes", "yes!"]);
+    /// }
+    /// ```
+    pub fn language(&self, start: impl StartSymbol) -> Result<Vec<String>, Error> {
+        let start = start.into_term();
+        if !self.is_finite(&start) {
+            return Err(Error::InfiniteLanguage(format!(
+                "Infinitely many strings are derivable from {}!",
+                start
+            )));
+        }
+
+        let expanded = self.expand_repetitions();
+        let rules = rules(&expanded);
+        // strings derived so far from each nonterminal reachable from `start`
+        // by alternatives which derive strings, as others may be infinite
+        let mut strings: HashMap<&str, BTreeSet<String>> = match start {
+            Term::Nonterminal(ref nt) if rules.contains_key(nt.as_str()) => {
+                reachable(&edges(&rules), nt)
+                    .into_iter()
+                    .map(|nt| (nt, BTreeSet::new()))
+                    .collect()
+            }
+            _ => HashMap::new(),
+        };
+
+        let mut changed = true;
+        while changed {
+            changed = false;
+            for (&nt, expressions) in &rules {
+                if !strings.contains_key(nt) {
+                    continue;
+                }
+                for expression in expressions {
+                    let mut derived = BTreeSet::new();
+                    derived.insert(String::new());
+                    for term in expression.terms_iter() {
+                        let options = finite_term_strings(term, &rules, &strings);
+                        derived = derived
+                            .iter()
+                            .flat_map(|prefix| {
+                                options
+                                    .iter()
+                                    .map(move |option| format!("{}{}", prefix, option))
+                            })
+                            .collect();
+                    }
+                    let known = strings.get_mut(nt).expect("every lhs has strings");
+                    for string in derived {
+                        changed |= known.insert(string);
+                    }
+                }
+            }
+        }
+
+        let language = match start {
+            Term::Nonterminal(ref nt) if rules.contains_key(nt.as_str()) => {
+                strings.remove(nt.as_str()).unwrap_or_default()
+            }
+            ref term => finite_term_strings(term, &rules, &strings),
+        };
+        Ok(language.into_iter().collect())
+    }
+
+    /// Get the pairs of alternatives of a nonterminal which can begin with
+    /// the same character, as `(nonterminal, a, b)` where `a` and `b` are the
+    /// leading terms of the earlier and the later alternative.
+    ///
+    /// Such a pair keeps a parser choosing by the next character from
+    /// telling its alternatives apart, so the grammar isn't LL(1) until they
+    /// are left-factored. The alternatives of every production sharing a lhs
+    /// are considered, and those which derive the empty string begin with
+    /// whatever follows their nullable terms. FIRST sets are measured in
+    /// characters, as this crate parses characters rather than tokens, so
+    /// `"if" | "in"` conflict. A nonterminal without a production begins
+    /// with the `<` of its own text, and regexes, whose first characters
+    /// aren't analyzed, are assumed to conflict with nothing. Conflicts are
+    /// in the order of the productions and then of the alternatives. An
+    /// alternative deriving the empty string may instead conflict with what
+    /// follows its nonterminal, see `Grammar::first_follow_conflicts`.
+    ///
+    /// # Example
+    ///
+    /// ```rust
+    /// extern crate bnf;
+    /// use bnf::{Grammar, Term};
+    ///
+    /// fn main() {
+    ///     let grammar = Grammar::from_str("<stmt> ::= <if> \"else\" | <if> | \"x\"
+    ///         <if> ::= \"if\" <stmt>").unwrap();
+    ///     let if_ = Term::from_str("<if>").unwrap();
+    ///
+    ///     assert_eq!(
+    ///         grammar.first_first_conflicts(),
+    ///         vec![(Term::from_str("<stmt>").unwrap(), if_.clone(), if_)]
+    ///     );
+    /// }
+    /// ```
+    pub fn first_first_conflicts(&self) -> Vec<(Term, Term, Term)> {
+        let expanded = self.expand_repetitions();
+        let rules = rules(&expanded);
+        let nullable = nullable(&rules);
+        let first = first_sets(&rules, &nullable);
+        // the alternatives as written, those expanded keeping their order
+        let written = self::rules(self);
+
+        let mut seen = HashSet::new();
+        let mut conflicts = vec![];
+        for production in self.productions_iter() {
+            let nt = match *production.lhs() {
+                Term::Nonterminal(ref nt) if seen.insert(nt.as_str()) => nt.as_str(),
+                _ => continue,
+            };
+            let alternatives = written[nt]
+                .iter()
+                .zip(&rules[nt])
+                .filter_map(|(written, expression)| {
+                    let leading = written.terms_iter().next()?;
+                    Some((
+                        leading,
+                        sequence_first(expression.terms_iter(), &rules, &nullable, &first),
+                    ))
+                })
+                .collect::<Vec<_>>();
+            for (i, &(a, ref first_a)) in alternatives.iter().enumerate() {
+                for &(b, ref first_b) in &alternatives[i + 1..] {
+                    if overlap(first_a, first_b) {
+                        conflicts.push((production.lhs().clone(), a.clone(), b.clone()));
+                    }
+                }
+            }
+        }
+        conflicts
+    }
+
+    /// Get the nonterminals reachable from `start` which derive the empty
+    /// string and can begin with a character which can also follow them.
+    ///
+    /// A parser choosing by the next character can't tell whether such a
+    /// nonterminal should derive something or nothing there, so the grammar
+    /// isn't LL(1), like `<list> ::= <item> <list> | ""` followed by another
+    /// item. Only the strings derived from `start` are considered in finding
+    /// what can follow a nonterminal, and characters are measured as for
+    /// `Grammar::first_first_conflicts`. Nonterminals are in the order of
+    /// their first productions, and none are reported when `start` has no
+    /// production.
+    ///
+    /// # Example
+    ///
+    /// ```rust
+    /// extern crate bnf;
+    /// use bnf::{Grammar, Term};
+    ///
+    /// fn main() {
+    ///     let grammar = Grammar::from_str("<name> ::= <title> \"Dr\" \"Who\"
+    ///         <title> ::= \"Dr\" | \"Mr\" | \"\"").unwrap();
+    ///
+    ///     assert_eq!(
+    ///         grammar.first_follow_conflicts("name"),
+    ///         vec![Term::from_str("<title>").unwrap()]
+    ///     );
+    /// }
+    /// ```
+    pub fn first_follow_conflicts(&self, start: impl StartSymbol) -> Vec<Term> {
+        let start = start.into_term();
+        let expanded = self.expand_repetitions();
+        let mut rules = rules(&expanded);
+        match start {
+            Term::Nonterminal(ref nt) if rules.contains_key(nt.as_str()) => {}
+            _ => return vec![],
+        }
+        let reachable = expanded.reachable_from(&start);
+        rules.retain(|&nt, _| reachable.contains(&Term::Nonterminal(String::from(nt))));
+        let nullable = nullable(&rules);
+        let first = first_sets(&rules, &nullable);
+        let follow = follow_sets(&rules, &nullable, &first);
+
+        let mut conflicts = vec![];
+        for production in self.productions_iter() {
+            if let Term::Nonterminal(ref nt) = *production.lhs() {
+                let nt = nt.as_str();
+                if nullable.contains(nt)
+                    && overlap(&first[nt], &follow[nt])
+                    && !conflicts.contains(production.lhs())
+                {
+                    conflicts.push(production.lhs().clone());
+                }
+            }
+        }
+        conflicts
+    }
+
+    /// Get the productions which can't take part in deriving a string from
+    /// the nonterminal `start`, in the order of the grammar.
+    ///
+    /// A production is useless when none of its alternatives derives a
+    /// string, or when its lhs can't be reached from `start` through
+    /// alternatives which derive strings, so non-productive alternatives are
+    /// removed before reachability is found. Derivations are as for
+    /// `Grammar::is_finite`, so a nonterminal without a production derives
+    /// its own `<name>` text. Every production is useless when `start`
+    /// derives nothing, or isn't a nonterminal.
+    ///
+    /// # Example
+    ///
+    /// ```rust
+    /// extern crate bnf;
+    /// use bnf::Grammar;
+    ///
+    /// fn main() {
+    ///     let grammar = Grammar::from_str("<s> ::= <a> <b> | \"s\"
+    ///         <a> ::= <a> \"a\"
+    ///         <b> ::= \"b\"").unwrap();
+    ///     let useless = grammar.useless_productions("s");
+    ///
+    ///     // <b> is only reached through <a>, which derives nothing
+    ///     let lhs = useless.iter().map(|prod| prod.lhs().to_string()).collect::<Vec<_>>();
+    ///     assert_eq!(lhs, vec!["<a>", "<b>"]);
+    /// }
+    /// ```
+    pub fn useless_productions(&self, start: impl StartSymbol) -> Vec<&Production> {
+        let start = start.into_term();
+        let expanded = self.expand_repetitions();
+        let rules = rules(&expanded);
+        let productive = productive(&rules);
+        let derives = |expr: &Expression| {
+            expr.terms_iter()
+                .all(|term| is_productive(term, &rules, &productive))
+        };
+
+        let mut reachable = HashSet::new();
+        let mut pending = match start {
+            Term::Nonterminal(ref nt) if productive.contains(nt.as_str()) => vec![nt.as_str()],
+            _ => vec![],
+        };
+        while let Some(nt) = pending.pop() {
+            if !reachable.insert(nt) {
+                continue;
+            }
+            let expressions = rules.get(nt).into_iter().flatten();
+            for expr in expressions.filter(|expr| derives(expr)) {
+                for term in expr.terms_iter() {
+                    if let Term::Nonterminal(ref child) = *term {
+                        if rules.contains_key(child.as_str()) {
+                            pending.push(child.as_str());
+                        }
+                    }
+                }
+            }
+        }
+
+        // each production is followed by the same one expanded
+        self.productions_iter()
+            .zip(expanded.productions_iter())
+            .filter(|&(_, expanded)| match *expanded.lhs() {
+                Term::Nonterminal(ref nt) => {
+                    !reachable.contains(nt.as_str()) || !expanded.rhs_iter().any(&derives)
+                }
+                Term::Terminal(_)
+                | Term::CaselessTerminal(_)
+                | Term::TerminalBytes(_)
+                | Term::CharClass(_)
+                | Term::Regex(_)
+                | Term::Repeat { .. } => true,
+            })
+            .map(|(prod, _)| prod)
+            .collect()
+    }
+}
+
+/// Get the edges from each nonterminal to those of its alternatives which
+/// derive strings, along with whether anything else of the alternative can
+/// derive characters
+fn edges<'a>(
+    rules: &HashMap<&'a str, Vec<&'a Expression>>,
+) -> HashMap<&'a str, Vec<(&'a str, bool)>> {
+    let productive = productive(rules);
+    let nonempty = nonempty(rules, &productive);
+
+    let mut edges: HashMap<&str, Vec<(&str, bool)>> = HashMap::new();
+    for (&nt, expressions) in rules {
+        for expression in expressions {
+            let terms = expression.terms_iter().collect::<Vec<_>>();
+            if !terms
+                .iter()
+                .all(|term| is_productive(term, rules, &productive))
+            {
+                continue;
+            }
+            for (index, term) in terms.iter().enumerate() {
+                if let Term::Nonterminal(ref child) = **term {
+                    if let Some((&child, _)) = rules.get_key_value(child.as_str()) {
+                        let grows = terms.iter().enumerate().any(|(other, term)| {
+                            other != index && is_nonempty(term, rules, &nonempty)
+                        });
+                        edges.entry(nt).or_default().push((child, grows));
+                    }
+                }
+            }
+        }
+    }
+    edges
+}
+
+/// Get the nonterminals reachable along `edges` from `from`, including itself
+fn reachable<'a>(
+    edges: &HashMap<&'a str, Vec<(&'a str, bool)>>,
+    from: &'a str,
+) -> HashSet<&'a str> {
+    let mut seen = HashSet::new();
+    let mut stack = vec![from];
+    while let Some(nt) = stack.pop() {
+        if seen.insert(nt) {
+            stack.extend(edges.get(nt).into_iter().flatten().map(|&(child, _)| child));
+        }
+    }
+    seen
+}
+
+/// Get the strings derived so far from a term of a finite language
+fn finite_term_strings(
+    term: &Term,
+    rules: &HashMap<&str, Vec<&Expression>>,
+    strings: &HashMap<&str, BTreeSet<String>>,
+) -> BTreeSet<String> {
+    match *term {
+        Term::Nonterminal(ref nt) if rules.contains_key(nt.as_str()) => {
+            strings.get(nt.as_str()).cloned().unwrap_or_default()
+        }
+        Term::Nonterminal(_) => Some(term.to_string()).into_iter().collect(),
+        Term::Terminal(ref t) | Term::CaselessTerminal(ref t) => {
+            Some(t.clone()).into_iter().collect()
+        }
+        // bytes which aren't UTF-8 make no strings
+        Term::TerminalBytes(ref bytes) => str::from_utf8(bytes)
+            .ok()
+            .map(String::from)
+            .into_iter()
+            .collect(),
+        Term::CharClass(ref class) => (0..class.len())
+            .filter_map(|n| class.nth(n))
+            .map(String::from)
+            .collect(),
+        // nor do regexes, which aren't generated from
+        Term::Regex(_) => BTreeSet::new(),
+        // repetitions are expanded beforehand
+        Term::Repeat { .. } => BTreeSet::new(),
+    }
+}
+
+/// Get the nonterminals which derive at least one string
+fn productive<'a>(rules: &HashMap<&'a str, Vec<&Expression>>) -> HashSet<&'a str> {
+    let mut productive = HashSet::new();
+    let mut changed = true;
+    while changed {
+        changed = false;
+        for (&nt, expressions) in rules {
+            if !productive.contains(nt)
+                && expressions.iter().any(|expression| {
+                    expression
+                        .terms_iter()
+                        .all(|term| is_productive(term, rules, &productive))
+                })
+            {
+                productive.insert(nt);
+                changed = true;
+            }
+        }
+    }
+    productive
+}
+
+fn is_productive(
+    term: &Term,
+    rules: &HashMap<&str, Vec<&Expression>>,
+    productive: &HashSet<&str>,
+) -> bool {
+    match *term {
+        Term::Nonterminal(ref nt) if rules.contains_key(nt.as_str()) => {
+            productive.contains(nt.as_str())
+        }
+        Term::CharClass(ref class) => !class.is_empty(),
+        Term::Regex(_) | Term::Repeat { .. } => false,
+        Term::Nonterminal(_)
+        | Term::Terminal(_)
+        | Term::CaselessTerminal(_)
+        | Term::TerminalBytes(_) => true,
+    }
+}
+
+/// Get the nonterminals which derive at least one string of some characters
+fn nonempty<'a>(
+    rules: &HashMap<&'a str, Vec<&Expression>>,
+    productive: &HashSet<&str>,
+) -> HashSet<&'a str> {
+    let mut nonempty = HashSet::new();
+    let mut changed = true;
+    while changed {
+        changed = false;
+        for (&nt, expressions) in rules {
+            if !nonempty.contains(nt)
+                && expressions.iter().any(|expression| {
+                    expression
+                        .terms_iter()
+                        .all(|term| is_productive(term, rules, productive))
+                        && expression
+                            .terms_iter()
+                            .any(|term| is_nonempty(term, rules, &nonempty))
+                })
+            {
+                nonempty.insert(nt);
+                changed = true;
+            }
+        }
+    }
+    nonempty
+}
+
+fn is_nonempty(
+    term: &Term,
+    rules: &HashMap<&str, Vec<&Expression>>,
+    nonempty: &HashSet<&str>,
+) -> bool {
+    match *term {
+        Term::Nonterminal(ref nt) if rules.contains_key(nt.as_str()) => {
+            nonempty.contains(nt.as_str())
+        }
+        Term::Terminal(ref t) | Term::CaselessTerminal(ref t) => !t.is_empty(),
+        Term::TerminalBytes(ref bytes) => !bytes.is_empty(),
+        Term::CharClass(ref class) => !class.is_empty(),
+        Term::Regex(_) | Term::Repeat { .. } => false,
+        Term::Nonterminal(_) => true,
+    }
+}
+
+/// Get the strings of at most `max` characters derivable from the terms of
+/// `expression`, given the strings derived so far from each nonterminal
+fn sequence_strings(
+    expression: &Expression,
+    strings: &HashMap<&str, Vec<BTreeSet<String>>>,
+    max: usize,
+) -> Vec<BTreeSet<String>> {
+    let mut derived = vec![BTreeSet::new(); max + 1];
+    derived[0].insert(String::new());
+
+    for term in expression.terms_iter() {
+        let options = term_strings(term, strings, max);
+        let mut next = vec![BTreeSet::new(); max + 1];
+        for (prefix_len, prefixes) in derived.iter().enumerate() {
+            for (option_len, options) in options.iter().enumerate().take(max + 1 - prefix_len) {
+                for prefix in prefixes {
+                    for option in options {
+                        next[prefix_len + option_len].insert(format!("{}{}", prefix, option));
+                    }
+                }
+            }
+        }
+        derived = next;
+    }
+    derived
+}
+
+/// Get the strings of at most `max` characters derivable from `term`,
+/// indexed by length
+fn term_strings(
+    term: &Term,
+    strings: &HashMap<&str, Vec<BTreeSet<String>>>,
+    max: usize,
+) -> Vec<BTreeSet<String>> {
+    let mut options = vec![BTreeSet::new(); max + 1];
+    match *term {
+        Term::Nonterminal(ref nt) if strings.contains_key(nt.as_str()) => {
+            return strings[nt.as_str()].clone();
+        }
+        Term::CharClass(ref class) => {
+            if max > 0 {
+                options[1] = (0..class.len())
+                    .filter_map(|n| class.nth(n))
+                    .map(String::from)
+                    .collect();
+            }
+        }
+        Term::Regex(_) | Term::Repeat { .. } => {}
+        Term::Terminal(_)
+        | Term::CaselessTerminal(_)
+        | Term::TerminalBytes(_)
+        | Term::Nonterminal(_) => {
+            let text = match *term {
+                Term::Terminal(ref t) | Term::CaselessTerminal(ref t) => t.clone(),
+                Term::TerminalBytes(ref bytes) => match str::from_utf8(bytes) {
+                    Ok(text) => String::from(text),
+                    Err(_) => return options,
+                },
+                _ => term.to_string(),
+            };
+            let len = text.chars().count();
+            if len <= max {
+                options[len].insert(text);
+            }
+        }
+    }
+    options
+}
+
+/// Get a sentence of the fewest characters derivable from each nonterminal
+/// which derives any, as for `Grammar::shortest_sentence`
+pub(crate) fn shortest_sentences(grammar: &Grammar) -> HashMap<&str, String> {
+    let expanded = grammar.expand_repetitions();
+    let mut shortest = shortest_of_rules(&rules(&expanded));
+    rules(grammar)
+        .into_keys()
+        .filter_map(|nt| Some((nt, shortest.remove(nt)?)))
+        .collect()
+}
+
+/// Get a sentence of the fewest characters derivable from each nonterminal
+/// of `rules` which derives any
+fn shortest_of_rules<'a>(rules: &HashMap<&'a str, Vec<&Expression>>) -> HashMap<&'a str, String> {
+    // Finalize nonterminals in order of their shortest sentence, each built
+    // only from nonterminals finalized before it, so that no sentence depends
+    // on itself
+    let mut shortest: HashMap<&str, String> = HashMap::new();
+    loop {
+        let mut best: Option<(&str, String)> = None;
+        for (&nt, expressions) in rules {
+            if shortest.contains_key(nt) {
+                continue;
+            }
+            for expression in expressions {
+                let sentence = match shortest_of(expression, rules, &shortest) {
+                    Some(sentence) => sentence,
+                    None => continue,
+                };
+                let is_shorter = best
+                    .as_ref()
+                    .is_none_or(|(_, best)| sentence.chars().count() < best.chars().count());
+                if is_shorter {
+                    best = Some((nt, sentence));
+                }
+            }
+        }
+
+        match best {
+            Some((nt, sentence)) => {
+                shortest.insert(nt, sentence);
+            }
+            // the remaining nonterminals derive no sentence
+            None => return shortest,
+        }
+    }
+}
+
+/// Concatenate the shortest sentence of each term of `expression`, if every
+/// term has one yet
+fn shortest_of(
+    expression: &Expression,
+    rules: &HashMap<&str, Vec<&Expression>>,
+    shortest: &HashMap<&str, String>,
+) -> Option<String> {
+    let mut sentence = String::new();
+    for term in expression.terms_iter() {
+        match *term {
+            Term::Terminal(ref t) | Term::CaselessTerminal(ref t) => sentence.push_str(t),
+            Term::TerminalBytes(ref bytes) => sentence.push_str(str::from_utf8(bytes).ok()?),
+            Term::CharClass(ref class) => sentence.push(class.example()?),
+            Term::Regex(_) | Term::Repeat { .. } => return None,
+            Term::Nonterminal(ref nt) if rules.contains_key(nt.as_str()) => {
+                sentence.push_str(shortest.get(nt.as_str())?)
+            }
+            Term::Nonterminal(_) => sentence.push_str(&term.to_string()),
+        }
+    }
+    Some(sentence)
+}
+
+/// Get the nonterminals which derive the empty string
+fn nullable<'a>(rules: &HashMap<&'a str, Vec<&Expression>>) -> HashSet<&'a str> {
+    let mut nullable = HashSet::new();
+    let mut changed = true;
+    while changed {
+        changed = false;
+        for (&nt, expressions) in rules {
+            if !nullable.contains(nt)
+                && expressions.iter().any(|expression| {
+                    expression
+                        .terms_iter()
+                        .all(|term| is_nullable(term, rules, &nullable))
+                })
+            {
+                nullable.insert(nt);
+                changed = true;
+            }
+        }
+    }
+    nullable
+}
+
+fn is_nullable(
+    term: &Term,
+    rules: &HashMap<&str, Vec<&Expression>>,
+    nullable: &HashSet<&str>,
+) -> bool {
+    match *term {
+        Term::Nonterminal(ref nt) if rules.contains_key(nt.as_str()) => {
+            nullable.contains(nt.as_str())
+        }
+        Term::Terminal(ref t) | Term::CaselessTerminal(ref t) => t.is_empty(),
+        Term::TerminalBytes(ref bytes) => bytes.is_empty(),
+        Term::Nonterminal(_) | Term::CharClass(_) | Term::Regex(_) | Term::Repeat { .. } => false,
+    }
+}
+
+/// Get the ranges of characters each nonterminal's strings can begin with
+fn first_sets<'a>(
+    rules: &HashMap<&'a str, Vec<&Expression>>,
+    nullable: &HashSet<&str>,
+) -> HashMap<&'a str, BTreeSet<(char, char)>> {
+    let mut first: HashMap<&str, BTreeSet<(char, char)>> =
+        rules.keys().map(|&nt| (nt, BTreeSet::new())).collect();
+    let mut changed = true;
+    while changed {
+        changed = false;
+        for (&nt, expressions) in rules {
+            for expression in expressions {
+                let ranges = sequence_first(expression.terms_iter(), rules, nullable, &first);
+                let known = first.get_mut(nt).expect("every lhs has a FIRST set");
+                for range in ranges {
+                    changed |= known.insert(range);
+                }
+            }
+        }
+    }
+    first
+}
+
+/// Get the ranges of characters the strings of a sequence of `terms` can
+/// begin with, given those found so far for each nonterminal
+fn sequence_first<'t, I>(
+    terms: I,
+    rules: &HashMap<&str, Vec<&Expression>>,
+    nullable: &HashSet<&str>,
+    first: &HashMap<&str, BTreeSet<(char, char)>>,
+) -> BTreeSet<(char, char)>
+where
+    I: IntoIterator<Item = &'t Term>,
+{
+    let mut ranges = BTreeSet::new();
+    for term in terms {
+        match *term {
+            Term::Nonterminal(ref nt) if rules.contains_key(nt.as_str()) => {
+                ranges.extend(first[nt.as_str()].iter().cloned())
+            }
+            Term::Nonterminal(_) => {
+                ranges.insert(('<', '<'));
+            }
+            Term::Terminal(ref t) => ranges.extend(t.chars().next().map(|c| (c, c))),
+            Term::CaselessTerminal(ref t) => {
+                if let Some(c) = t.chars().next() {
+                    ranges.insert((c, c));
+                    ranges.extend(c.to_lowercase().chain(c.to_uppercase()).map(|c| (c, c)));
+                }
+            }
+            Term::TerminalBytes(ref bytes) => {
+                ranges.extend(bytes.first().map(|&b| (char::from(b), char::from(b))))
+            }
+            Term::CharClass(ref class) => ranges.extend(class.members().iter().cloned()),
+            Term::Regex(_) | Term::Repeat { .. } => {}
+        }
+        if !is_nullable(term, rules, nullable) {
+            break;
+        }
+    }
+    ranges
+}
+
+/// Get the ranges of characters which can follow each nonterminal in the
+/// strings of the nonterminals of `rules`
+fn follow_sets<'a>(
+    rules: &HashMap<&'a str, Vec<&Expression>>,
+    nullable: &HashSet<&str>,
+    first: &HashMap<&str, BTreeSet<(char, char)>>,
+) -> HashMap<&'a str, BTreeSet<(char, char)>> {
+    let mut follow: HashMap<&str, BTreeSet<(char, char)>> =
+        rules.keys().map(|&nt| (nt, BTreeSet::new())).collect();
+    let mut changed = true;
+    while changed {
+        changed = false;
+        for (&nt, expressions) in rules {
+            for expression in expressions {
+                let terms = expression.terms_iter().collect::<Vec<_>>();
+                for (index, &term) in terms.iter().enumerate() {
+                    let child = match *term {
+                        Term::Nonterminal(ref child) => match rules.get_key_value(child.as_str()) {
+                            Some((&child, _)) => child,
+                            None => continue,
+                        },
+                        _ => continue,
+                    };
+                    let rest = &terms[index + 1..];
+                    let mut ranges = sequence_first(rest.iter().cloned(), rules, nullable, first);
+                    if rest.iter().all(|term| is_nullable(term, rules, nullable)) {
+                        ranges.extend(follow[nt].iter().cloned());
+                    }
+                    let known = follow.get_mut(child).expect("every lhs has a FOLLOW set");
+                    for range in ranges {
+                        changed |= known.insert(range);
+                    }
+                }
+            }
+        }
+    }
+    follow
+}
+
+/// Whether any range of `a` shares a character with any range of `b`
+fn overlap(a: &BTreeSet<(char, char)>, b: &BTreeSet<(char, char)>) -> bool {
+    a.iter().any(|&(lo, hi)| {
+        b.iter()
+            .any(|&(other_lo, other_hi)| lo <= other_hi && other_lo <= hi)
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use production::Production;
+
+    fn shortest(grammar: &str) -> Option<String> {
+        Grammar::from_str(grammar).unwrap().shortest_sentence()
+    }
+
+    #[test]
+    fn shortest_sentences() {
+        assert_eq!(
+            shortest(
+                "<s> ::= <long> | <pair>
+                <long> ::= \"abcd\"
+                <pair> ::= <digit> <digit>
+                <digit> ::= [0-9]"
+            ),
+            Some(String::from("00"))
+        );
+        // measured in characters rather than bytes
+        assert_eq!(
+            shortest("<s> ::= \"éé\" | \"abc\""),
+            Some(String::from("éé"))
+        );
+        assert_eq!(
+            shortest("<s> ::= <undefined> | \"0123456789abc\""),
+            Some(String::from("<undefined>"))
+        );
+        assert_eq!(
+            shortest(
+                "<s> ::= \"xx\"
+                <s> ::= \"y\""
+            ),
+            Some(String::from("y"))
+        );
+    }
+
+    #[test]
+    fn shortest_empty_sentence() {
+        assert_eq!(
+            shortest(
+                "<s> ::= <s> \"a\" | <opt> <opt>
+                <opt> ::= \"\" | \"b\""
+            ),
+            Some(String::new())
+        );
+        assert_eq!(
+            shortest(
+                "<a> ::= <b> | \"a\"
+                <b> ::= <a> | \"\""
+            ),
+            Some(String::new())
+        );
+    }
+
+    #[test]
+    fn shortest_ignores_non_productive() {
+        assert_eq!(
+            shortest(
+                "<s> ::= <loop> | <loop> \"x\" | \"abc\" <s> | \"done\"
+                <loop> ::= <loop> \"a\"
+                <loop> ::= <s> <loop>"
+            ),
+            Some(String::from("done"))
+        );
+    }
+
+    #[test]
+    fn all_strings_of_length() {
+        let grammar = Grammar::from_str(
+            "<s> ::= <a> <s> | \"\"
+            <a> ::= \"x\" | \"yz\" | [0-1]
+            <a> ::= \"x\"",
+        )
+        .unwrap();
+        assert_eq!(grammar.derive_all_strings_of_length("s", 0), vec![""]);
+        assert_eq!(
+            grammar.derive_all_strings_of_length("s", 2),
+            vec!["00", "01", "0x", "10", "11", "1x", "x0", "x1", "xx", "yz"]
+        );
+        for len in 0..6 {
+            let strings = grammar.derive_all_strings_of_length("s", len);
+            assert!(strings.iter().all(|s| s.chars().count() == len));
+            assert!(strings.windows(2).all(|pair| pair[0] < pair[1]));
+        }
+        assert!(grammar
+            .derive_all_strings_of_length("undefined", 1)
+            .is_empty());
+    }
+
+    #[test]
+    fn all_strings_through_cycles() {
+        let grammar = Grammar::from_str(
+            "<a> ::= <a> | <b> <a> | \"a\" <undefined>
+            <b> ::= \"\" | \"b\"",
+        )
+        .unwrap();
+        assert_eq!(
+            grammar.derive_all_strings_of_length("a", 12),
+            vec!["a<undefined>"]
+        );
+        assert_eq!(
+            grammar.derive_all_strings_of_length("a", 13),
+            vec!["ba<undefined>"]
+        );
+        assert!(grammar.derive_all_strings_of_length("a", 1).is_empty());
+    }
+
+    #[test]
+    fn linear_grammars() {
+        let right = Grammar::from_str(
+            "<s> ::= \"a\" [bc] <t> | <t> | \"\" | <undefined> \"x\"
+            <t> ::= \"t\" <s> | \"t\"",
+        )
+        .unwrap();
+        assert!(right.is_right_linear());
+        assert!(!right.is_left_linear());
+
+        let left = Grammar::from_str(
+            "<s> ::= <t> \"a\" [bc] | <t> | \"\" | \"x\" <undefined>
+            <t> ::= <s> \"t\" | \"t\"",
+        )
+        .unwrap();
+        assert!(left.is_left_linear());
+        assert!(!left.is_right_linear());
+
+        let both = Grammar::from_str("<s> ::= <s> | \"a\"").unwrap();
+        assert!(both.is_right_linear() && both.is_left_linear());
+
+        let neither = Grammar::from_str("<s> ::= \"(\" <s> \")\" | \"\"").unwrap();
+        assert!(!neither.is_right_linear() && !neither.is_left_linear());
+        let two = Grammar::from_str("<s> ::= <s> <s> | \"a\"").unwrap();
+        assert!(!two.is_right_linear() && !two.is_left_linear());
+    }
+
+    #[test]
+    fn epsilon_and_unit_productions() {
+        let grammar = Grammar::from_str(
+            "<s> ::= <t> \"x\" | <undefined> | \"a\" \"\"
+            <t> ::= [ab] | <s> <s>",
+        )
+        .unwrap();
+        assert!(!grammar.has_epsilon_productions());
+        assert!(!grammar.has_unit_productions());
+        let mut empty = grammar.clone();
+        empty.add_production(Production::from_parts(
+            Term::Nonterminal(String::from("u")),
+            vec![Expression::new()],
+        ));
+        assert!(empty.has_epsilon_productions());
+
+        let grammar = Grammar::from_str(
+            "<s> ::= <t> \"x\"
+            <t> ::= \"a\" | \"\" %i\"\"",
+        )
+        .unwrap();
+        assert!(grammar.has_epsilon_productions());
+
+        let grammar = Grammar::from_str("<s> ::= \"a\" | <t>\n<t> ::= \"b\"").unwrap();
+        assert!(grammar.has_unit_productions());
+        assert!(!grammar.inline_unit_productions().has_unit_productions());
+        assert!(!Grammar::new().has_epsilon_productions());
+        assert!(!Grammar::new().has_unit_productions());
+    }
+
+    #[test]
+    fn branching_factors() {
+        let grammar = Grammar::from_str(
+            "<s> ::= <a> | <b> | \"s\"
+            <a> ::= \"a\"
+            <b> ::= <b> \"b\" | <undefined>
+            <a> ::= \"a2\" | \"a3\"",
+        )
+        .unwrap();
+        assert!((grammar.branching_factor() - 8.0 / 3.0).abs() < 1e-9);
+        assert_eq!(grammar.max_alternatives(), 3);
+
+        assert_eq!(Grammar::new().branching_factor(), 0.0);
+        assert_eq!(Grammar::new().max_alternatives(), 0);
+        let lhs = Term::Nonterminal(String::from("none"));
+        let grammar = Grammar::from_parts(vec![Production::from_parts(lhs, vec![])]);
+        assert_eq!(grammar.branching_factor(), 0.0);
+        assert_eq!(grammar.max_alternatives(), 0);
+    }
+
+    #[test]
+    fn expression_lengths() {
+        let grammar = Grammar::from_str(
+            "<s> ::= <a> <b> \"s\" | ε
+            <a> ::= \"a\"
+            <a> ::= \"a\" \"a\" | \"\"",
+        )
+        .unwrap();
+        assert!((grammar.average_expression_length() - 7.0 / 5.0).abs() < 1e-9);
+        assert_eq!(grammar.max_expression_length(), 3);
+
+        assert_eq!(Grammar::new().average_expression_length(), 0.0);
+        assert_eq!(Grammar::new().max_expression_length(), 0);
+        let lhs = Term::Nonterminal(String::from("none"));
+        let grammar = Grammar::from_parts(vec![Production::from_parts(lhs, vec![])]);
+        assert_eq!(grammar.average_expression_length(), 0.0);
+        assert_eq!(grammar.max_expression_length(), 0);
+    }
+
+    #[test]
+    fn counts_expressions_per_production() {
+        let grammar = Grammar::from_str(
+            "<s> ::= <a> | <b> | \"s\"
+            <a> ::= \"a\"
+            <b> ::= <b> \"b\" | <undefined>
+            <a> ::= \"a2\" | \"a3\"",
+        )
+        .unwrap();
+        let counts = grammar.count_expressions_per_production();
+        assert_eq!(counts.len(), 3);
+        assert_eq!((counts["s"], counts["a"], counts["b"]), (3, 3, 2));
+        let stats = grammar.stats();
+        assert_eq!(stats.nonterminals(), 3);
+        assert_eq!(stats.min_alternatives(), 2);
+        assert_eq!(stats.max_alternatives(), grammar.max_alternatives());
+        assert!((stats.average_alternatives() - grammar.branching_factor()).abs() < 1e-9);
+
+        assert!(Grammar::new().count_expressions_per_production().is_empty());
+        assert_eq!(Grammar::new().stats(), GrammarStats::default());
+        let lhs = Term::Nonterminal(String::from("none"));
+        let grammar = Grammar::from_parts(vec![Production::from_parts(lhs, vec![])]);
+        assert_eq!(grammar.count_expressions_per_production()["none"], 0);
+        assert_eq!(grammar.stats().min_alternatives(), 0);
+    }
+
+    #[test]
+    fn finite_languages() {
+        let grammar = Grammar::from_str(
+            "<s> ::= <opt> <bit> <opt> | <s> | <dead> <s>
+            <opt> ::= \"\" | \"-\" | <empty> <opt>
+            <bit> ::= [01] | <undefined>
+            <empty> ::= \"\"
+            <dead> ::= <dead> \"x\"",
+        )
+        .unwrap();
+        let s = Term::from_str("<s>").unwrap();
+        assert!(grammar.is_finite(&s));
+        assert_eq!(
+            grammar.language(&s).unwrap(),
+            vec![
+                "-0",
+                "-0-",
+                "-1",
+                "-1-",
+                "-<undefined>",
+                "-<undefined>-",
+                "0",
+                "0-",
+                "1",
+                "1-",
+                "<undefined>",
+                "<undefined>-"
+            ]
+        );
+
+        let terminal = Term::from_str("\"t\"").unwrap();
+        assert_eq!(grammar.language(&terminal).unwrap(), vec!["t"]);
+        assert_eq!(grammar.language("undefined").unwrap(), vec!["<undefined>"]);
+    }
+
+    #[test]
+    fn infinite_languages() {
+        let grammars = [
+            "<s> ::= \"a\" <s> | \"\"",
+            "<s> ::= <t> | \"x\"
+            <t> ::= <s> <s>",
+            "<s> ::= <t> \"x\"
+            <t> ::= \"\" | <t> <t> | \"y\"",
+        ];
+        for grammar in grammars.iter() {
+            let grammar = Grammar::from_str(grammar).unwrap();
+            assert!(!grammar.is_finite("s"));
+            match grammar.language("s") {
+                Err(Error::InfiniteLanguage(_)) => (),
+                result => panic!("should be infinite: {:?}", result),
+            }
+        }
+
+        // only the strings derivable from the start symbol matter
+        let grammar = Grammar::from_str(
+            "<s> ::= \"done\" | <loop> <dead>
+            <loop> ::= <loop> \"x\" | \"\"
+            <dead> ::= <dead>",
+        )
+        .unwrap();
+        assert_eq!(grammar.language("s").unwrap(), vec!["done"]);
+    }
+
+    #[test]
+    fn languages_of_repetitions() {
+        let grammar = Grammar::from_str("<s> ::= 1*2( \"a\" | \"b\" ) 0*1\"-\"").unwrap();
+        assert!(grammar.is_finite("s"));
+        assert_eq!(
+            grammar.language("s").unwrap(),
+            vec!["a", "a-", "aa", "aa-", "ab", "ab-", "b", "b-", "ba", "ba-", "bb", "bb-"]
+        );
+        assert_eq!(grammar.shortest_sentence(), Some(String::from("a")));
+
+        let grammar = Grammar::from_str("<s> ::= 2*\"a\"").unwrap();
+        assert!(!grammar.is_finite("s"));
+        assert_eq!(grammar.shortest_sentence(), Some(String::from("aa")));
+        assert_eq!(
+            shortest("<s> ::= 3\"ab\" | 2\"xyzw\""),
+            Some(String::from("ababab"))
+        );
+    }
+
+    #[test]
+    fn no_shortest_sentence() {
+        assert_eq!(shortest("<a> ::= <a> \"x\""), None);
+        assert_eq!(
+            shortest(
+                "<a> ::= <b>
+                <b> ::= <a> | <a> <b>"
+            ),
+            None
+        );
+        assert_eq!(Grammar::new().shortest_sentence(), None);
+    }
+
+    #[test]
+    fn useless_productions() {
+        let grammar = Grammar::from_str(
+            "<s> ::= <a> <b> | <c> | <undefined>
+            <a> ::= <a> \"a\"
+            <b> ::= \"b\"
+            <c> ::= \"c\" | <b> <a>
+            <c> ::= <a>
+            <d> ::= \"d\"",
+        )
+        .unwrap();
+        let start = Term::from_str("<s>").unwrap();
+        let useless = grammar
+            .useless_productions(&start)
+            .into_iter()
+            .map(ToString::to_string)
+            .collect::<Vec<_>>();
+        // <b> is only reachable through <a>, and the second <c> derives nothing
+        assert_eq!(
+            useless,
+            vec![
+                "<a> ::= <a> \"a\"",
+                "<b> ::= \"b\"",
+                "<c> ::= <a>",
+                "<d> ::= \"d\"",
+            ]
+        );
+
+        let all = grammar.productions_iter().collect::<Vec<_>>();
+        assert_eq!(grammar.useless_productions("a"), all);
+        assert_eq!(grammar.useless_productions("undefined"), all);
+        let terminal = Term::from_str("\"s\"").unwrap();
+        assert_eq!(grammar.useless_productions(&terminal), all);
+        assert!(Grammar::new().useless_productions(&start).is_empty());
+    }
+
+    fn conflicts(grammar: &str) -> Vec<(String, String, String)> {
+        Grammar::from_str(grammar)
+            .unwrap()
+            .first_first_conflicts()
+            .into_iter()
+            .map(|(nt, a, b)| (nt.to_string(), a.to_string(), b.to_string()))
+            .collect()
+    }
+
+    fn conflict(nt: &str, a: &str, b: &str) -> (String, String, String) {
+        (String::from(nt), String::from(a), String::from(b))
+    }
+
+    #[test]
+    fn first_first_conflicts() {
+        assert_eq!(
+            conflicts(
+                "<s> ::= \"if\" <s> | \"in\" | [a-h] | [j-z] | <t>
+                <t> ::= \"j\" | \"\"
+                <t> ::= \"k\""
+            ),
+            vec![
+                conflict("<s>", "\"if\"", "\"in\""),
+                conflict("<s>", "[j-z]", "<t>"),
+            ]
+        );
+        assert_eq!(conflicts("<digit> ::= [0-4] | [5-9] | \"\""), vec![]);
+        assert_eq!(conflicts("<s> ::= [^a] | \"a\""), vec![]);
+        assert_eq!(
+            conflicts("<s> ::= [^a] | \"b\""),
+            vec![conflict("<s>", "[^a]", "\"b\"")]
+        );
+    }
+
+    #[test]
+    fn first_first_conflicts_through_nullables() {
+        assert_eq!(
+            conflicts(
+                "<s> ::= <opt> \"x\" | \"x\" | <opt> <opt>
+                <opt> ::= \"y\" | \"\""
+            ),
+            vec![
+                conflict("<s>", "<opt>", "\"x\""),
+                conflict("<s>", "<opt>", "<opt>"),
+            ]
+        );
+        assert_eq!(
+            conflicts("<s> ::= %i\"a\" | \"A\" | <u> | \"<\""),
+            vec![
+                conflict("<s>", "%i\"a\"", "\"A\""),
+                conflict("<s>", "<u>", "\"<\""),
+            ]
+        );
+    }
+
+    #[test]
+    fn first_follow_conflicts() {
+        let conflicts = |grammar: &str, start: &str| {
+            Grammar::from_str(grammar)
+                .unwrap()
+                .first_follow_conflicts(start)
+                .into_iter()
+                .map(|nt| nt.to_string())
+                .collect::<Vec<_>>()
+        };
+        let list = "<s> ::= <list> \"x\" | <list> \";\"
+            <list> ::= <item> <list> | \"\"
+            <item> ::= \"x\" | \"y\"";
+        assert_eq!(conflicts(list, "s"), vec!["<list>"]);
+        // nothing follows the list of a list alone
+        assert_eq!(conflicts(list, "list"), Vec::<String>::new());
+        assert_eq!(conflicts(list, "undefined"), Vec::<String>::new());
+        let grammar = Grammar::from_str(list).unwrap();
+        assert_eq!(
+            grammar.first_follow_conflicts(&Term::from_str("<s>").unwrap()),
+            vec![Term::from_str("<list>").unwrap()]
+        );
+        assert!(grammar
+            .first_follow_conflicts(&Term::from_str("\"x\"").unwrap())
+            .is_empty());
+
+        // what follows comes through nullable terms and from the lhs
+        assert_eq!(
+            conflicts(
+                "<s> ::= <a> <b> <c> \"z\"
+                <a> ::= \"y\" | \"\"
+                <b> ::= \"z\" | \"\"
+                <c> ::= <d>
+                <d> ::= [x-z] | \"\"",
+                "s"
+            ),
+            vec!["<a>", "<b>", "<c>", "<d>"]
+        );
+        assert_eq!(
+            conflicts(
+                "<s> ::= <a> <b> \"w\"
+                <a> ::= \"y\" | \"\"
+                <b> ::= \"z\" | \"\"
+                <unreachable> ::= <a> \"y\"",
+                "s"
+            ),
+            Vec::<String>::new()
+        );
+    }
+}
@@ -0,0 +1,160 @@
+//! Generators of small grammars for property tests of analyses and
+//! transforms, which need productions referencing each other rather than the
+//! arbitrary names the `Arbitrary` impls for round trips generate
+
+extern crate quickcheck;
+
+use self::quickcheck::{Arbitrary, Gen};
+use expression::Expression;
+use grammar::Grammar;
+use production::Production;
+use term::{CharClass, Term};
+
+/// The most nonterminals of a `SmallGrammar`
+const MAX_NONTERMINALS: usize = 4;
+
+/// The most terms of an alternative of a `SmallGrammar`
+const MAX_TERMS: usize = 3;
+
+/// A `Grammar` of at least one production, whose nonterminals are among
+/// `<n0>` to `<n3>`, `<n0>` being the start symbol, and whose terminals are
+/// over the characters `a` and `b`
+#[derive(Clone, Debug)]
+pub(crate) struct SmallGrammar(pub Grammar);
+
+/// A `Production` over the nonterminals and terminals of a `SmallGrammar`
+#[derive(Clone, Debug)]
+pub(crate) struct SmallProduction(pub Production);
+
+fn nonterminal(index: usize) -> Term {
+    Term::Nonterminal(format!("n{}", index))
+}
+
+fn term<G: Gen>(g: &mut G, nonterminals: usize) -> Term {
+    match u8::arbitrary(g) % 6 {
+        0 | 1 => nonterminal(usize::arbitrary(g) % nonterminals),
+        2 => Term::Terminal(String::from("a")),
+        3 => Term::Terminal(String::from("b")),
+        4 => Term::Terminal(String::from(if bool::arbitrary(g) { "ab" } else { "" })),
+        _ => Term::CharClass(CharClass::from_ranges(vec![('a', 'b')])),
+    }
+}
+
+impl SmallProduction {
+    fn arbitrary_with<G: Gen>(g: &mut G, lhs: Term, nonterminals: usize) -> SmallProduction {
+        let alternatives = 1 + usize::arbitrary(g) % 3;
+        let rhs = (0..alternatives)
+            .map(|_| {
+                let len = 1 + usize::arbitrary(g) % MAX_TERMS;
+                let mut expression =
+                    Expression::from_parts((0..len).map(|_| term(g, nonterminals)).collect());
+                if u8::arbitrary(g) % 4 == 0 {
+                    expression.set_weight(Some(1 + u32::arbitrary(g) % 3));
+                }
+                expression
+            })
+            .collect();
+        SmallProduction(Production::from_parts(lhs, rhs))
+    }
+}
+
+impl Arbitrary for SmallProduction {
+    fn arbitrary<G: Gen>(g: &mut G) -> Self {
+        let nonterminals = 1 + usize::arbitrary(g) % MAX_NONTERMINALS;
+        let lhs = nonterminal(usize::arbitrary(g) % nonterminals);
+        SmallProduction::arbitrary_with(g, lhs, nonterminals)
+    }
+}
+
+impl Arbitrary for SmallGrammar {
+    fn arbitrary<G: Gen>(g: &mut G) -> Self {
+        let nonterminals = 1 + usize::arbitrary(g) % MAX_NONTERMINALS;
+        // every nonterminal defined, some more than once or not at all
+        let mut lhs: Vec<usize> = (0..nonterminals).collect();
+        if bool::arbitrary(g) {
+            lhs.push(usize::arbitrary(g) % nonterminals);
+        }
+        if nonterminals > 1 && bool::arbitrary(g) {
+            lhs.pop();
+        }
+        let productions = lhs
+            .into_iter()
+            .map(|index| SmallProduction::arbitrary_with(g, nonterminal(index), nonterminals).0)
+            .collect();
+        SmallGrammar(Grammar::from_parts(productions))
+    }
+
+    fn shrink(&self) -> Box<dyn Iterator<Item = Self>> {
+        // drop one production other than the start symbol's first, or one
+        // alternative of a production with several
+        let productions = self.0.productions_iter().cloned().collect::<Vec<_>>();
+        let mut smaller = vec![];
+        for index in 1..productions.len() {
+            let mut fewer = productions.clone();
+            fewer.remove(index);
+            smaller.push(SmallGrammar(Grammar::from_parts(fewer)));
+        }
+        for (index, production) in productions.iter().enumerate() {
+            let alternatives = production.rhs_iter().cloned().collect::<Vec<_>>();
+            if alternatives.len() < 2 {
+                continue;
+            }
+            for alternative in 0..alternatives.len() {
+                let mut fewer = alternatives.clone();
+                fewer.remove(alternative);
+                let mut shrunk = productions.clone();
+                shrunk[index] = Production::from_parts(production.lhs().clone(), fewer);
+                smaller.push(SmallGrammar(Grammar::from_parts(shrunk)));
+            }
+        }
+        Box::new(smaller.into_iter())
+    }
+}
+
+/// Get every string over `a` and `b` of up to `max_len` characters, the
+/// inputs worth comparing `SmallGrammar`s on
+pub(crate) fn inputs(max_len: usize) -> Vec<String> {
+    let mut inputs = vec![String::new()];
+    let mut last = vec![String::new()];
+    for _ in 0..max_len {
+        last = last
+            .iter()
+            .flat_map(|input| vec![format!("{}a", input), format!("{}b", input)])
+            .collect();
+        inputs.extend(last.iter().cloned());
+    }
+    inputs
+}
+
+#[cfg(test)]
+mod tests {
+    use self::quickcheck::{QuickCheck, TestResult};
+    use super::*;
+
+    fn prop_small_grammars(small: SmallGrammar) -> TestResult {
+        let grammar = small.0;
+        let first = grammar
+            .productions_iter()
+            .next()
+            .map(|prod| prod.lhs().clone());
+        let nonterminals = grammar
+            .productions_iter()
+            .flat_map(|prod| prod.rhs_iter().flat_map(|expr| expr.terms_iter()))
+            .filter(|term| matches!(**term, Term::Nonterminal(_)))
+            .all(|term| (0..MAX_NONTERMINALS).any(|index| *term == nonterminal(index)));
+        let round_trip = Grammar::from_str(&grammar.to_string()).ok() == Some(grammar.clone());
+        TestResult::from_bool(first == Some(nonterminal(0)) && nonterminals && round_trip)
+    }
+
+    #[test]
+    fn small_grammars() {
+        QuickCheck::new().quickcheck(prop_small_grammars as fn(SmallGrammar) -> TestResult)
+    }
+
+    #[test]
+    fn inputs_up_to_len() {
+        assert_eq!(inputs(0), vec![""]);
+        assert_eq!(inputs(2), vec!["", "a", "b", "aa", "ab", "ba", "bb"]);
+        assert_eq!(inputs(3).len(), 15);
+    }
+}
@@ -0,0 +1,252 @@
+//! Inspect grammars and generate from them on the command line, with the
+//! feature `cli`.
+//!
+//! ```text
+//! bnf-cli validate <file>
+//! bnf-cli generate <file> [--start <rule>] [--count <n>]
+//! bnf-cli check <file> <input> [--start <rule>]
+//! bnf-cli stats <file>
+//! ```
+//!
+//! Each command exits with 1 when the grammar or input is rejected, and with
+//! 2 when the file can't be read or the arguments are invalid.
+
+extern crate bnf;
+extern crate clap;
+
+use bnf::{Expression, Grammar, Nonterminal, Term};
+use clap::{value_parser, Arg, ArgMatches, Command};
+use std::collections::HashSet;
+use std::fs;
+use std::process;
+
+fn command() -> Command {
+    let file = || {
+        Arg::new("file")
+            .required(true)
+            .help("The file of the grammar")
+    };
+    let start = || {
+        Arg::new("start")
+            .long("start")
+            .value_name("rule")
+            .help("The nonterminal to begin from, by default the first one defined")
+    };
+    Command::new("bnf-cli")
+        .about("Inspect BNF grammars and generate sentences from them")
+        .subcommand_required(true)
+        .arg_required_else_help(true)
+        .subcommand(
+            Command::new("validate")
+                .about("Report parse errors and nonterminals which are never defined")
+                .arg(file()),
+        )
+        .subcommand(
+            Command::new("generate")
+                .about("Generate random sentences, one per line")
+                .arg(file())
+                .arg(start())
+                .arg(
+                    Arg::new("count")
+                        .long("count")
+                        .value_name("n")
+                        .default_value("1")
+                        .value_parser(value_parser!(usize))
+                        .help("How many sentences to generate"),
+                ),
+        )
+        .subcommand(
+            Command::new("check")
+                .about("Check whether the grammar derives the input")
+                .arg(file())
+                .arg(Arg::new("input").required(true).help("The text to parse"))
+                .arg(start()),
+        )
+        .subcommand(
+            Command::new("stats")
+                .about("Summarize the productions of the grammar")
+                .arg(file()),
+        )
+}
+
+fn fail(code: i32, message: &str) -> ! {
+    eprintln!("{}", message);
+    process::exit(code)
+}
+
+/// Read the file named by the `file` argument
+fn read(matches: &ArgMatches) -> (String, String) {
+    let path = matches
+        .get_one::<String>("file")
+        .cloned()
+        .unwrap_or_default();
+    match fs::read_to_string(&path) {
+        Ok(text) => (path, text),
+        Err(e) => fail(2, &format!("{}: {}", path, e)),
+    }
+}
+
+/// Parse the file named by the `file` argument, failing on any error
+fn grammar(matches: &ArgMatches) -> Grammar {
+    let (path, text) = read(matches);
+    match Grammar::from_str(&text) {
+        Ok(grammar) => grammar,
+        Err(e) => fail(1, &format!("{}: {}", path, e)),
+    }
+}
+
+/// Get the nonterminals which have a production
+/// Get the terms of `expression`, with those repeated in place of repetitions
+fn nested_terms(expression: &Expression) -> Vec<&Term> {
+    expression
+        .terms_iter()
+        .flat_map(|term| match *term {
+            Term::Repeat { ref inner, .. } => nested_terms(inner),
+            _ => vec![term],
+        })
+        .collect()
+}
+
+fn defined(grammar: &Grammar) -> HashSet<&str> {
+    grammar
+        .productions_iter()
+        .filter_map(|prod| match *prod.lhs() {
+            Term::Nonterminal(ref nt) => Some(nt.as_str()),
+            _ => None,
+        })
+        .collect()
+}
+
+/// Get the nonterminal named by the `start` argument, or else the first one
+/// defined, failing unless it's defined
+fn start(grammar: &Grammar, matches: &ArgMatches) -> Nonterminal {
+    let name = match matches.get_one::<String>("start") {
+        Some(name) => name.trim_start_matches('<').trim_end_matches('>'),
+        None => match grammar
+            .productions_iter()
+            .next()
+            .map(|prod| prod.nonterminal())
+        {
+            Some(Some(nt)) => return nt,
+            Some(None) => fail(1, "The first production doesn't define a nonterminal"),
+            None => fail(1, "The grammar has no productions"),
+        },
+    };
+    if !defined(grammar).contains(name) {
+        fail(2, &format!("<{}> has no production", name));
+    }
+    Nonterminal::from(name)
+}
+
+fn validate(matches: &ArgMatches) {
+    let (path, text) = read(matches);
+    let (grammar, errors) = Grammar::from_str_lenient(&text);
+    for error in &errors {
+        eprintln!("{}: {}", path, error);
+    }
+
+    let defined = defined(&grammar);
+    let mut undefined = grammar
+        .cross_reference()
+        .into_iter()
+        .filter(|(nt, referencing)| !defined.contains(nt.as_str()) && !referencing.is_empty())
+        .collect::<Vec<_>>();
+    undefined.sort();
+    for (nt, referencing) in &undefined {
+        let referencing = referencing
+            .iter()
+            .map(|lhs| format!("<{}>", lhs))
+            .collect::<Vec<_>>()
+            .join(", ");
+        eprintln!(
+            "{}: <{}> is never defined, but used by {}",
+            path, nt, referencing
+        );
+    }
+
+    if !errors.is_empty() || !undefined.is_empty() {
+        process::exit(1);
+    }
+    println!(
+        "{}: {} productions",
+        path,
+        grammar.productions_iter().count()
+    );
+}
+
+fn generate(matches: &ArgMatches) {
+    let grammar = grammar(matches);
+    let start = start(&grammar, matches);
+    let grammar = grammar.subgrammar(&start);
+    let count = matches.get_one::<usize>("count").cloned().unwrap_or(1);
+    for _ in 0..count {
+        match grammar.generate() {
+            Ok(sentence) => println!("{}", sentence),
+            Err(e) => fail(1, &e.to_string()),
+        }
+    }
+}
+
+fn check(matches: &ArgMatches) {
+    let grammar = grammar(matches);
+    let start = start(&grammar, matches);
+    let input = matches
+        .get_one::<String>("input")
+        .cloned()
+        .unwrap_or_default();
+    match grammar.count_parses(&start, &input) {
+        0 => fail(1, &format!("rejected from {}", start)),
+        usize::MAX => println!("accepted from {}, with too many parses to count", start),
+        1 => println!("accepted from {}, with 1 parse", start),
+        parses => println!("accepted from {}, with {} parses", start, parses),
+    }
+}
+
+fn stats(matches: &ArgMatches) {
+    let grammar = grammar(matches);
+    let alternatives = grammar
+        .productions_iter()
+        .map(|prod| prod.rhs_iter().count())
+        .sum::<usize>();
+    let terms = grammar
+        .productions_iter()
+        .flat_map(|prod| prod.rhs_iter().flat_map(nested_terms))
+        .collect::<Vec<_>>();
+    let terminals = terms
+        .iter()
+        .filter(|term| !matches!(***term, Term::Nonterminal(_)))
+        .collect::<HashSet<_>>();
+
+    println!("productions: {}", grammar.productions_iter().count());
+    println!("nonterminals: {}", defined(&grammar).len());
+    println!("distinct terminals: {}", terminals.len());
+    println!("alternatives: {}", alternatives);
+    let stats = grammar.stats();
+    println!("min alternatives: {}", stats.min_alternatives());
+    println!("max alternatives: {}", stats.max_alternatives());
+    println!("branching factor: {:.2}", stats.average_alternatives());
+    println!("epsilon productions: {}", grammar.has_epsilon_productions());
+    println!("unit productions: {}", grammar.has_unit_productions());
+    println!("right linear: {}", grammar.is_right_linear());
+    if let Some(nt) = grammar
+        .productions_iter()
+        .next()
+        .and_then(|prod| prod.nonterminal())
+    {
+        println!("finite from {}: {}", nt, grammar.is_finite(&nt));
+    }
+    if let Some(sentence) = grammar.shortest_sentence() {
+        println!("shortest sentence: {:?}", sentence);
+    }
+}
+
+fn main() {
+    let matches = command().get_matches();
+    match matches.subcommand() {
+        Some(("validate", matches)) => validate(matches),
+        Some(("generate", matches)) => generate(matches),
+        Some(("check", matches)) => check(matches),
+        Some(("stats", matches)) => stats(matches),
+        _ => unreachable!("a subcommand is required"),
+    }
+}
@@ -0,0 +1,139 @@
+use expression::Expression;
+use grammar::Grammar;
+use production::Production;
+use std::collections::HashSet;
+use term::{CharClass, Term};
+
+/// The built-in nonterminals and the ASCII characters each matches
+const BUILTINS: &[(&str, &[(char, char)])] = &[
+    ("digit", &[('0', '9')]),
+    ("hexdigit", &[('0', '9'), ('A', 'F'), ('a', 'f')]),
+    ("upper", &[('A', 'Z')]),
+    ("lower", &[('a', 'z')]),
+    ("letter", &[('A', 'Z'), ('a', 'z')]),
+    ("alnum", &[('0', '9'), ('A', 'Z'), ('a', 'z')]),
+    ("ws", &[('\t', '\n'), ('\r', '\r'), (' ', ' ')]),
+];
+
+/// Get the production defining the built-in nonterminal `name`, if it is one
+fn builtin(name: &str) -> Option<Production> {
+    let &(name, ranges) = BUILTINS.iter().find(|&&(builtin, _)| builtin == name)?;
+    let class = Term::CharClass(CharClass::from_ranges(ranges.to_vec()));
+    Some(Production::from_parts(
+        Term::Nonterminal(String::from(name)),
+        vec![Expression::from_parts(vec![class])],
+    ))
+}
+
+impl Grammar {
+    /// Define each built-in nonterminal which is referenced but not defined,
+    /// appending its production after the others.
+    ///
+    /// Each matches a single ASCII character, by a character class rather
+    /// than an alternative per character, so that generating and parsing
+    /// check a range:
+    ///
+    /// | Nonterminal  | Characters                         |
+    /// |--------------|------------------------------------|
+    /// | `<digit>`    | `[0-9]`                            |
+    /// | `<hexdigit>` | `[0-9A-Fa-f]`                      |
+    /// | `<upper>`    | `[A-Z]`                            |
+    /// | `<lower>`    | `[a-z]`                            |
+    /// | `<letter>`   | `[A-Za-z]`                         |
+    /// | `<alnum>`    | `[0-9A-Za-z]`                      |
+    /// | `<ws>`       | a space, tab, line feed or return  |
+    ///
+    /// None is Unicode-aware, so `<letter>` doesn't match `é`. A grammar's
+    /// own production for one of these names is kept as it is, so defining
+    /// a built-in never changes what an existing grammar means.
+    /// `ParseOptions::builtins` adds them while parsing.
+    ///
+    /// # Example
+    ///
+    /// ```rust
+    /// extern crate bnf;
+    /// use bnf::Grammar;
+    ///
+    /// fn main() {
+    ///     let mut grammar = Grammar::from_str("<id> ::= <letter> | <id> <alnum>
+    ///         <alnum> ::= <letter> | \"_\"").unwrap();
+    ///     grammar.add_builtins();
+    ///
+    ///     assert_eq!(grammar.to_string(), "<id> ::= <letter> | <id> <alnum>
+    /// <alnum> ::= <letter> | \"_\"
+    /// <letter> ::= [A-Za-z]
+    /// ");
+    ///     assert!(grammar.verify_examples("id", &["x", "Ab_c"], &["x1", "_"]).is_ok());
+    /// }
+    /// ```
+    pub fn add_builtins(&mut self) {
+        let mut defined = self
+            .productions_iter()
+            .map(|prod| prod.lhs().clone())
+            .collect::<HashSet<_>>();
+        let referenced = self
+            .productions_iter()
+            .flat_map(|prod| prod.rhs_iter())
+            .flat_map(|expr| expr.nested_terms_iter())
+            .filter_map(|term| match *term {
+                Term::Nonterminal(ref name) => Some(name.as_str()),
+                _ => None,
+            })
+            .filter_map(builtin)
+            .collect::<Vec<_>>();
+        for prod in referenced {
+            if defined.insert(prod.lhs().clone()) {
+                self.add_production(prod);
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn defines_referenced_builtins() {
+        let mut grammar = Grammar::from_str(
+            "<s> ::= <ws> <hexdigit> <digit> | <upper> <lower> <s> | <undefined>
+            <digit> ::= \"0\" | \"1\"",
+        )
+        .unwrap();
+        grammar.add_builtins();
+        assert_eq!(
+            grammar.to_string(),
+            "<s> ::= <ws> <hexdigit> <digit> | <upper> <lower> <s> | <undefined>
+<digit> ::= \"0\" | \"1\"
+<ws> ::= [\t-\n\r ]
+<hexdigit> ::= [0-9A-Fa-f]
+<upper> ::= [A-Z]
+<lower> ::= [a-z]
+"
+        );
+        assert!(grammar
+            .verify_examples("s", &[" f1", "Ab\tA0", "XyAb f1"], &[" f2", "aB f1"])
+            .is_ok());
+
+        // adding them again changes nothing
+        let added = grammar.clone();
+        grammar.add_builtins();
+        assert_eq!(grammar, added);
+        let mut empty = Grammar::new();
+        empty.add_builtins();
+        assert_eq!(empty, Grammar::new());
+    }
+
+    #[test]
+    fn builtins_are_ascii() {
+        let mut grammar = Grammar::from_str("<s> ::= <letter> <alnum> <ws>").unwrap();
+        grammar.add_builtins();
+        assert!(grammar
+            .verify_examples("s", &["a0 ", "Zz\n"], &["é1 ", "a_ ", "a0\u{a0}"])
+            .is_ok());
+        for _ in 0..20 {
+            let sentence = grammar.generate().unwrap();
+            assert!(sentence.is_ascii() && sentence.len() == 3, "{:?}", sentence);
+        }
+    }
+}
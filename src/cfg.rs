@@ -0,0 +1,240 @@
+use error::Error;
+use expression::Expression;
+use grammar::Grammar;
+use production::Production;
+use std::collections::HashSet;
+use term::Term;
+
+/// A symbol of an alternative, before nonterminals are told from terminals
+#[derive(Debug)]
+enum Symbol {
+    Bracketed(String),
+    Quoted(String),
+    Bare(String),
+    Epsilon,
+}
+
+/// Split the rhs of a production into alternatives of symbols
+fn alternatives(rhs: &str, line: usize) -> Result<Vec<Vec<Symbol>>, Error> {
+    let mut alternatives = vec![vec![]];
+    let mut chars = rhs.chars().peekable();
+    while let Some(&c) = chars.peek() {
+        if c.is_whitespace() {
+            chars.next();
+            continue;
+        }
+        if c == '|' {
+            chars.next();
+            alternatives.push(vec![]);
+            continue;
+        }
+
+        let symbol = match c {
+            '"' | '\'' | '<' => {
+                chars.next();
+                let close = if c == '<' { '>' } else { c };
+                let mut text = String::new();
+                loop {
+                    match chars.next() {
+                        Some(next) if next == close => break,
+                        Some(next) => text.push(next),
+                        None => {
+                            return Err(Error::ParseError(format!(
+                                "Line {}: missing closing {:?}",
+                                line, close
+                            )))
+                        }
+                    }
+                }
+                if c == '<' {
+                    Symbol::Bracketed(text)
+                } else {
+                    Symbol::Quoted(text)
+                }
+            }
+            _ => {
+                let mut text = String::new();
+                while let Some(&next) = chars.peek() {
+                    if next.is_whitespace() || next == '|' {
+                        break;
+                    }
+                    text.push(next);
+                    chars.next();
+                }
+                if text == "ε" {
+                    Symbol::Epsilon
+                } else {
+                    Symbol::Bare(text)
+                }
+            }
+        };
+        if let Some(alternative) = alternatives.last_mut() {
+            alternative.push(symbol);
+        }
+    }
+    Ok(alternatives)
+}
+
+/// Get the name of a production's lhs, bracketed or bare
+fn lhs(text: &str, line: usize) -> Result<String, Error> {
+    let name = if text.starts_with('<') && text.ends_with('>') && text.len() > 1 {
+        &text[1..text.len() - 1]
+    } else {
+        text
+    };
+    if name.is_empty() || text.chars().any(char::is_whitespace) {
+        return Err(Error::ParseError(format!(
+            "Line {}: expected a single nonterminal before '->', found {:?}",
+            line, text
+        )));
+    }
+    Ok(String::from(name))
+}
+
+impl Grammar {
+    /// Construct a `Grammar` from the arrow notation of textbooks, where each
+    /// line is a production like `S -> a S b | a b`, with alternatives of
+    /// symbols separated by whitespace.
+    ///
+    /// Symbols in angle brackets are nonterminals, and quoted symbols are
+    /// terminals. Bare symbols are nonterminals when they're the lhs of a
+    /// production, and terminals otherwise, so both styles may be mixed.
+    /// Either `->` or `→` separates a lhs from its alternatives, a line
+    /// beginning with `|` continues the production before it, and `ε` or
+    /// nothing at all is an empty alternative. Blank lines are skipped.
+    ///
+    /// # Example
+    ///
+    /// ```rust
+    /// extern crate bnf;
+    /// use bnf::Grammar;
+    ///
+    /// fn main() {
+    ///     let grammar = Grammar::from_cfg_spec("S -> a S b | ε").unwrap();
+    ///     assert!(grammar.verify_examples("S", &["", "ab", "aabb"], &["a", "ba"]).is_ok());
+    ///
+    ///     let grammar = Grammar::from_cfg_spec("<expr> -> <expr> \"+\" <term> | <term>
+    ///         <term> -> x").unwrap();
+    ///     assert_eq!(grammar, Grammar::from_str("<expr> ::= <expr> \"+\" <term> | <term>
+    ///         <term> ::= \"x\"").unwrap());
+    /// }
+    /// ```
+    pub fn from_cfg_spec(input: &str) -> Result<Grammar, Error> {
+        let mut rules: Vec<(String, Vec<Vec<Symbol>>)> = vec![];
+        for (index, text) in input.lines().enumerate() {
+            let line = index + 1;
+            let text = text.trim();
+            if text.is_empty() {
+                continue;
+            }
+            if let Some(continued) = text.strip_prefix('|') {
+                match rules.last_mut() {
+                    Some(&mut (_, ref mut rhs)) => rhs.extend(alternatives(continued, line)?),
+                    None => {
+                        return Err(Error::ParseError(format!(
+                            "Line {}: '|' continues no production",
+                            line
+                        )))
+                    }
+                }
+                continue;
+            }
+
+            let arrow = ["->", "→"]
+                .iter()
+                .filter_map(|arrow| text.find(arrow).map(|at| (at, arrow.len())))
+                .min();
+            let (at, len) = match arrow {
+                Some(arrow) => arrow,
+                None => {
+                    return Err(Error::ParseError(format!(
+                        "Line {}: expected '->' in {:?}",
+                        line, text
+                    )))
+                }
+            };
+            let name = lhs(text[..at].trim(), line)?;
+            rules.push((name, alternatives(&text[at + len..], line)?));
+        }
+
+        let lhs: HashSet<String> = rules.iter().map(|(name, _)| name.clone()).collect();
+        let productions = rules
+            .into_iter()
+            .map(|(name, rhs)| {
+                let expressions = rhs
+                    .into_iter()
+                    .map(|symbols| {
+                        let terms = symbols
+                            .into_iter()
+                            .filter_map(|symbol| match symbol {
+                                Symbol::Bracketed(nt) => Some(Term::Nonterminal(nt)),
+                                Symbol::Quoted(t) => Some(Term::Terminal(t)),
+                                Symbol::Bare(ref nt) if lhs.contains(nt) => {
+                                    Some(Term::Nonterminal(nt.clone()))
+                                }
+                                Symbol::Bare(t) => Some(Term::Terminal(t)),
+                                Symbol::Epsilon => None,
+                            })
+                            .collect();
+                        Expression::from_parts(terms)
+                    })
+                    .collect();
+                Production::from_parts(Term::Nonterminal(name), expressions)
+            })
+            .collect();
+        Ok(Grammar::from_parts(productions))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn parses_arrow_notation() {
+        let grammar = Grammar::from_cfg_spec(
+            "
+            E -> E + T | T
+            T -> T * F
+               | F
+            F → ( E ) | id | <num> | '|'
+
+            <num> -> 0 | 1 <num> |",
+        )
+        .unwrap();
+        let expected = Grammar::from_str(
+            "<E> ::= <E> \"+\" <T> | <T>
+            <T> ::= <T> \"*\" <F> | <F>
+            <F> ::= \"(\" <E> \")\" | \"id\" | <num> | \"|\"
+            <num> ::= \"0\" | \"1\" <num> | \"\"",
+        )
+        .unwrap();
+        let empty = Expression::from_str("\"\"").unwrap();
+        let expected = expected
+            .into_productions()
+            .into_iter()
+            .map(|mut prod| {
+                if prod.remove_from_rhs(&empty).is_some() {
+                    prod.add_to_rhs(Expression::new());
+                }
+                prod
+            })
+            .collect();
+        let expected = Grammar::from_parts(expected);
+        assert_eq!(grammar, expected);
+    }
+
+    #[test]
+    fn rejects_malformed_specs() {
+        let error = |input: &str| match Grammar::from_cfg_spec(input) {
+            Err(Error::ParseError(message)) => message,
+            result => panic!("should fail to parse: {:?}", result),
+        };
+        assert_eq!(error("S -> a\nS a b"), "Line 2: expected '->' in \"S a b\"");
+        assert!(error("| a").starts_with("Line 1: "));
+        assert!(error("S T -> a").starts_with("Line 1: "));
+        assert!(error("-> a").starts_with("Line 1: "));
+        assert!(error("\nS -> \"a").starts_with("Line 2: "));
+        assert_eq!(Grammar::from_cfg_spec(""), Ok(Grammar::new()));
+    }
+}
@@ -0,0 +1,199 @@
+//! Parsers for the pieces of this crate's BNF, for building a dialect of
+//! it without reimplementing them.
+//!
+//! Each parser takes the input to begin parsing at and returns what it
+//! parsed along with the rest of the input, after any whitespace following
+//! it, or an `Error` when the input doesn't begin with what it parses. An
+//! `Error::ParseIncomplete` means the input ended before it could be parsed,
+//! like in the middle of a quoted terminal.
+//!
+//! These functions, their signatures and what they accept are the stable
+//! public API for parsing. The `nom` parsers implementing them and
+//! `Grammar::from_str` are internal, and how they are built may change in
+//! any release.
+//!
+//! # Example
+//!
+//! A dialect defining nonterminals like `digits = [0-9] <digits>` may reuse
+//! the parser of an expression for what follows the `=`:
+//!
+//! ```rust
+//! extern crate bnf;
+//! use bnf::combinators;
+//! use bnf::{Error, Grammar, ParseOptions, Production, Term};
+//!
+//! fn dialect(mut input: &str) -> Result<Grammar, Error> {
+//!     let options = ParseOptions::new();
+//!     let mut grammar = Grammar::new();
+//!     input = combinators::whitespace(input);
+//!     while !input.is_empty() {
+//!         let end = input.find('=').ok_or(Error::ParseError(String::from("missing '='")))?;
+//!         let lhs = Term::Nonterminal(String::from(input[..end].trim()));
+//!         let rhs = combinators::whitespace(&input[end + 1..]);
+//!         let (rest, expression) = combinators::expression(rhs, &options)?;
+//!         grammar.add_production(Production::from_parts(lhs, vec![expression]));
+//!         input = rest.trim_start_matches(';');
+//!         input = combinators::whitespace(input);
+//!     }
+//!     Ok(grammar)
+//! }
+//!
+//! fn main() {
+//!     let grammar = dialect("digits = [0-9] <digits>; digits = [0-9]").unwrap();
+//!
+//!     assert_eq!(grammar, Grammar::from_str("<digits> ::= [0-9] <digits>
+//!         <digits> ::= [0-9]").unwrap());
+//! }
+//! ```
+
+use error::Error;
+use expression::Expression;
+use nom::IResult;
+use parsers::{self, ParseOptions};
+use production::Production;
+use term::Term;
+
+/// Run the nom parser `parser` on `input`, getting the rest of `input` as a
+/// `&str` again
+fn parse<'i, T, F>(input: &'i str, parser: F) -> Result<(&'i str, T), Error>
+where
+    F: Fn(&'i [u8]) -> IResult<&'i [u8], T>,
+{
+    match parser(input.as_bytes()) {
+        Ok((rest, parsed)) => Ok((&input[input.len() - rest.len()..], parsed)),
+        Err(e) => Err(parsers::parse_error(input.as_bytes(), e)),
+    }
+}
+
+/// Skip the whitespace beginning `input`, including a `\` ending a line,
+/// which continues a production onto the next
+pub fn whitespace(input: &str) -> &str {
+    match parse(input, parsers::whitespace) {
+        Ok((rest, _)) => rest,
+        Err(_) => input,
+    }
+}
+
+/// Parse a quoted terminal, like `"a"` or `'"'`, decoding its escapes,
+/// prefixed by `%i` to match it in any case or by `%s` not to
+pub fn terminal(input: &str) -> Result<(&str, Term), Error> {
+    parse(input, parsers::terminal)
+}
+
+/// Parse a nonterminal, like `<digit>`, which isn't the lhs of a production
+pub fn nonterminal(input: &str) -> Result<(&str, Term), Error> {
+    parse(input, parsers::nonterminal)
+}
+
+/// Parse any term: a terminal, nonterminal, character class like `[a-z]` or
+/// the wildcard `%any`, range like `"a".."z"`, bytes like `%x0D.0A`, or with
+/// the feature `regex` a regex like `#"[a-z]+"#`
+pub fn term(input: &str) -> Result<(&str, Term), Error> {
+    parse(input, parsers::term)
+}
+
+/// Parse the lhs of a production, like the `<digit> ::=` of `<digit> ::=
+/// [0-9]`, getting its nonterminal
+pub fn production_lhs(input: &str) -> Result<(&str, Term), Error> {
+    parse(input, parsers::prod_lhs)
+}
+
+/// Parse a weight annotation, like `%weight 3`
+pub fn weight(input: &str) -> Result<(&str, u32), Error> {
+    parse(input, parsers::weight)
+}
+
+/// Parse one alternative of a production, and its weight, recognizing the
+/// epsilon keywords of `options`.
+///
+/// The alternative ends before a `;`, a closing bracket, the lhs of another
+/// production or the end of `input`, or with the `|` before another
+/// alternative, which is skipped. Groups, and the postfix and repetition
+/// operators, are only supported by `production`, which defines them.
+pub fn expression<'i>(
+    input: &'i str,
+    options: &ParseOptions,
+) -> Result<(&'i str, Expression), Error> {
+    let (rest, expression) = parse(input, |input| parsers::expression_with(input, options))?;
+    parsers::reject_groups(Some(&expression))?;
+    Ok((rest, expression))
+}
+
+/// Parse a production, like `<digits> ::= [0-9] | [0-9] <digits>`, as
+/// `options` specifies, ending before the lhs of another production, at the
+/// end of `input`, or with a `;`, which is skipped.
+///
+/// Each group in the production is defined by a production following it,
+/// named as `Grammar::from_str` names them, like `<digits (1)>`.
+pub fn production<'i>(
+    input: &'i str,
+    options: &ParseOptions,
+) -> Result<(&'i str, Vec<Production>), Error> {
+    let (rest, production) = parse(input, |input| parsers::production_with(input, options))?;
+    Ok((rest, parsers::desugar(vec![production], options)))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use grammar::Grammar;
+    use term::CharClass;
+
+    #[test]
+    fn parses_pieces() {
+        assert_eq!(whitespace(" \\\n\t<a>"), "<a>");
+        assert_eq!(whitespace("<a> "), "<a> ");
+        assert_eq!(
+            terminal("%i\"a\\tb\"  <c>"),
+            Ok(("<c>", Term::CaselessTerminal(String::from("a\tb"))))
+        );
+        assert_eq!(
+            nonterminal("<a b> | <c>"),
+            Ok(("| <c>", Term::Nonterminal(String::from("a b"))))
+        );
+        assert_eq!(
+            term("\"a\"..\"z\"é"),
+            Ok((
+                "é",
+                Term::CharClass(CharClass::from_ranges(vec![('a', 'z')]))
+            ))
+        );
+        assert_eq!(
+            production_lhs("<a> ::= \"x\""),
+            Ok(("\"x\"", Term::Nonterminal(String::from("a"))))
+        );
+        assert_eq!(weight("%weight 3 | <b>"), Ok(("| <b>", 3)));
+
+        assert!(nonterminal("<a> ::= \"x\"").is_err());
+        assert!(terminal("<a>").is_err());
+        assert!(matches!(terminal("\"a"), Err(Error::ParseIncomplete(_))));
+        assert_eq!(
+            term("\"a\\qb\""),
+            Err(Error::ParseError(String::from(
+                "invalid escape `\\q` at line 1, column 3"
+            )))
+        );
+    }
+
+    #[test]
+    fn parses_expressions_and_productions() {
+        let options = ParseOptions::new().epsilon("EMPTY");
+        let (rest, expression) = expression("<a> \"b\" %weight 2 | EMPTY ;", &options).unwrap();
+        let mut expected = Expression::from_str("<a> \"b\"").unwrap();
+        expected.set_weight(Some(2));
+        assert_eq!((rest, expression), ("EMPTY ;", expected));
+        assert_eq!(
+            super::expression(rest, &options),
+            Ok((";", Expression::new()))
+        );
+        assert!(super::expression("( <a> | <b> )", &options).is_err());
+
+        let (rest, productions) =
+            production("<a> ::= ( \"x\" | <b> )+ ; <b> ::= \"y\"", &options).unwrap();
+        assert_eq!(rest, "<b> ::= \"y\"");
+        assert_eq!(
+            Grammar::from_parts(productions),
+            Grammar::from_str("<a> ::= ( \"x\" | <b> )+").unwrap()
+        );
+    }
+}
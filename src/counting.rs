@@ -0,0 +1,511 @@
+use earley::{self, Symbol};
+use grammar::Grammar;
+use num_bigint::BigUint;
+use rand::Rng;
+use std::collections::HashMap;
+use term::{Nonterminal, Term};
+
+/// Counts the derivations of terminal strings of a given length, and samples
+/// uniformly among them.
+///
+/// Lengths are measured in characters (`char`s) of the emitted terminals.
+/// Counts are of derivations, not of distinct strings: an ambiguous grammar
+/// which derives the same string in two ways counts it twice.
+///
+/// Results are memoized per (nonterminal, length), so asking for many lengths
+/// from the same counter only computes each table entry once.
+///
+/// A nonterminal which can derive itself without consuming any characters
+/// (e.g. `<a> ::= <a> | "a"`) has infinitely many derivations. Such cycles
+/// are cut, so only derivations which never re-enter the same nonterminal at
+/// the same remaining length are counted.
+pub struct DerivationCounter<'a> {
+    start: Option<Symbol<'a>>,
+    rules: HashMap<Symbol<'a>, Vec<Vec<Symbol<'a>>>>,
+    nonterminals: HashMap<(Symbol<'a>, usize), BigUint>,
+    sequences: HashMap<(Symbol<'a>, usize, usize, usize), BigUint>,
+    in_progress: HashMap<(Symbol<'a>, usize), usize>,
+    fresh_len: Option<usize>,
+}
+
+impl<'a> DerivationCounter<'a> {
+    /// Construct a new `DerivationCounter`, starting from the lhs of the first production
+    pub fn new(grammar: &'a Grammar) -> DerivationCounter<'a> {
+        let mut rules: HashMap<Symbol<'a>, Vec<Vec<Symbol<'a>>>> = HashMap::new();
+        for rule in earley::rules(grammar) {
+            rules.entry(rule.symbol).or_default().push(rule.terms);
+        }
+
+        let start = grammar
+            .productions_iter()
+            .next()
+            .and_then(|prod| match *prod.lhs() {
+                Term::Nonterminal(_) => Some(Symbol::Term(prod.lhs())),
+                Term::Terminal(_)
+                | Term::CaselessTerminal(_)
+                | Term::TerminalBytes(_)
+                | Term::CharClass(_)
+                | Term::Regex(_)
+                | Term::Repeat { .. } => None,
+            });
+
+        DerivationCounter {
+            start,
+            rules,
+            nonterminals: HashMap::new(),
+            sequences: HashMap::new(),
+            in_progress: HashMap::new(),
+            fresh_len: None,
+        }
+    }
+
+    /// Count the derivations of strings of `len` characters from the start symbol
+    pub fn count(&mut self, len: usize) -> BigUint {
+        match self.start {
+            Some(start) => self.nonterminal(start, len).0,
+            None => BigUint::from(0u32),
+        }
+    }
+
+    /// Count the derivations of strings of `len` characters from the nonterminal `name`
+    pub fn count_from(&mut self, name: impl Into<Nonterminal>, len: usize) -> BigUint {
+        match self.key(name) {
+            Some(key) => self.nonterminal(key, len).0,
+            None => BigUint::from(0u32),
+        }
+    }
+
+    /// Sample a string of `len` characters from the start symbol.
+    ///
+    /// Every derivation of that length is equally likely, so strings are
+    /// weighted by how many ways they can be derived. Returns `None` if no
+    /// string of that length exists.
+    pub fn sample<R: Rng>(&mut self, len: usize, rng: &mut R) -> Option<String> {
+        let start = self.start?;
+        self.sample_key(start, len, rng)
+    }
+
+    /// Sample a string of `len` characters from the nonterminal `name`.
+    ///
+    /// See `sample` for the distribution of strings.
+    pub fn sample_from<R: Rng>(
+        &mut self,
+        name: impl Into<Nonterminal>,
+        len: usize,
+        rng: &mut R,
+    ) -> Option<String> {
+        let key = self.key(name)?;
+        self.sample_key(key, len, rng)
+    }
+
+    /// Get the symbol of the nonterminal `name`, when it has a production
+    fn key(&self, name: impl Into<Nonterminal>) -> Option<Symbol<'a>> {
+        let term = Term::from(name.into());
+        self.rules
+            .keys()
+            .cloned()
+            .find(|&key| key == Symbol::Term(&term))
+    }
+
+    fn sample_key<R: Rng>(&mut self, key: Symbol<'a>, len: usize, rng: &mut R) -> Option<String> {
+        let mut sentence = String::new();
+        if self.sample_nonterminal(key, len, rng, &mut sentence) {
+            Some(sentence)
+        } else {
+            None
+        }
+    }
+
+    // Sampling descends the same frames as counting, so cut cycles weigh the
+    // same. Counts at the length of the innermost frame may differ from the
+    // memoized ones while it is in progress, so those are recounted fresh.
+    fn sample_nonterminal<R: Rng>(
+        &mut self,
+        symbol: Symbol<'a>,
+        len: usize,
+        rng: &mut R,
+        sentence: &mut String,
+    ) -> bool {
+        let (total, _) = self.nonterminal(symbol, len);
+        if total == BigUint::from(0u32) {
+            return false;
+        }
+
+        let depth = self.in_progress.len();
+        self.in_progress.insert((symbol, len), depth);
+        let outer_len = self.fresh_len.replace(len);
+
+        let mut choice = random_below(&total, rng);
+        let mut chosen = None;
+        for alt in 0..self.alternatives(symbol) {
+            let (count, _) = self.sequence(symbol, alt, 0, len);
+            if choice < count {
+                chosen = Some(alt);
+                break;
+            }
+            choice -= count;
+        }
+        let sampled = match chosen {
+            Some(alt) => self.sample_sequence(symbol, alt, 0, len, rng, sentence),
+            None => false,
+        };
+
+        self.fresh_len = outer_len;
+        self.in_progress.remove(&(symbol, len));
+        sampled
+    }
+
+    fn sample_sequence<R: Rng>(
+        &mut self,
+        symbol: Symbol<'a>,
+        alt: usize,
+        offset: usize,
+        len: usize,
+        rng: &mut R,
+        sentence: &mut String,
+    ) -> bool {
+        let part = match self.part(symbol, alt, offset) {
+            Some(part) => part,
+            None => return len == 0,
+        };
+
+        let term = part.term();
+        match *term {
+            Term::Terminal(_)
+            | Term::CaselessTerminal(_)
+            | Term::TerminalBytes(_)
+            | Term::Regex(_) => {
+                let t = match term.terminal_text() {
+                    Some(t) => t,
+                    None => return false,
+                };
+                sentence.push_str(t);
+                let term_len = t.chars().count();
+                term_len <= len
+                    && self.sample_sequence(symbol, alt, offset + 1, len - term_len, rng, sentence)
+            }
+            Term::CharClass(ref class) => match class.choose(rng) {
+                Some(c) if len > 0 => {
+                    sentence.push(c);
+                    self.sample_sequence(symbol, alt, offset + 1, len - 1, rng, sentence)
+                }
+                _ => false,
+            },
+            Term::Nonterminal(_) | Term::Repeat { .. } => {
+                let (total, _) = self.sequence(symbol, alt, offset, len);
+                if total == BigUint::from(0u32) {
+                    return false;
+                }
+
+                let mut choice = random_below(&total, rng);
+                for head_len in 0..=len {
+                    let (head, _) = self.nonterminal(part, head_len);
+                    if head == BigUint::from(0u32) {
+                        continue;
+                    }
+                    let (rest, _) = self.sequence(symbol, alt, offset + 1, len - head_len);
+                    let count = head * rest;
+                    if choice < count {
+                        return self.sample_nonterminal(part, head_len, rng, sentence)
+                            && self.sample_sequence(
+                                symbol,
+                                alt,
+                                offset + 1,
+                                len - head_len,
+                                rng,
+                                sentence,
+                            );
+                    }
+                    choice -= count;
+                }
+                false
+            }
+        }
+    }
+
+    /// Number of alternatives of `symbol`
+    pub(crate) fn alternatives(&self, symbol: Symbol<'a>) -> usize {
+        self.rules.get(&symbol).map_or(0, |alts| alts.len())
+    }
+
+    /// Get the term at `offset` of an alternative of `symbol`
+    fn part(&self, symbol: Symbol<'a>, alt: usize, offset: usize) -> Option<Symbol<'a>> {
+        self.rules[&symbol][alt].get(offset).cloned()
+    }
+
+    /// Derivation count of `symbol`, along with the stack depth of the
+    /// outermost cycle that was cut while counting, if any
+    pub(crate) fn nonterminal(
+        &mut self,
+        symbol: Symbol<'a>,
+        len: usize,
+    ) -> (BigUint, Option<usize>) {
+        if let Some(&depth) = self.in_progress.get(&(symbol, len)) {
+            return (BigUint::from(0u32), Some(depth));
+        }
+        let fresh = self.fresh_len == Some(len);
+        if let Some(count) = self.nonterminals.get(&(symbol, len)).filter(|_| !fresh) {
+            return (count.clone(), None);
+        }
+
+        let depth = self.in_progress.len();
+        self.in_progress.insert((symbol, len), depth);
+
+        let mut total = BigUint::from(0u32);
+        let mut cut = None;
+        for alt in 0..self.alternatives(symbol) {
+            let (count, alt_cut) = self.sequence(symbol, alt, 0, len);
+            total += count;
+            cut = outermost(cut, alt_cut);
+        }
+
+        self.in_progress.remove(&(symbol, len));
+
+        // a cycle back to this very nonterminal doesn't depend on the callers
+        if cut.is_none_or(|cut| cut >= depth) {
+            if !fresh {
+                self.nonterminals.insert((symbol, len), total.clone());
+            }
+            cut = None;
+        }
+        (total, cut)
+    }
+
+    /// Derivation count of the terms of an alternative from `offset` onward
+    pub(crate) fn sequence(
+        &mut self,
+        symbol: Symbol<'a>,
+        alt: usize,
+        offset: usize,
+        len: usize,
+    ) -> (BigUint, Option<usize>) {
+        let part = match self.part(symbol, alt, offset) {
+            Some(part) => part,
+            None => return (BigUint::from((len == 0) as u32), None),
+        };
+
+        let key = (symbol, alt, offset, len);
+        let fresh = self.fresh_len == Some(len);
+        if let Some(count) = self.sequences.get(&key).filter(|_| !fresh) {
+            return (count.clone(), None);
+        }
+
+        let mut total = BigUint::from(0u32);
+        let mut cut = None;
+        let term = part.term();
+        match *term {
+            Term::Terminal(_)
+            | Term::CaselessTerminal(_)
+            | Term::TerminalBytes(_)
+            | Term::Regex(_) => {
+                let term_len = term.terminal_text().map(|t| t.chars().count());
+                if let Some(term_len) = term_len.filter(|&term_len| term_len <= len) {
+                    let (count, rest_cut) = self.sequence(symbol, alt, offset + 1, len - term_len);
+                    total = count;
+                    cut = rest_cut;
+                }
+            }
+            Term::CharClass(ref class) => {
+                if len > 0 {
+                    let (count, rest_cut) = self.sequence(symbol, alt, offset + 1, len - 1);
+                    total = count * BigUint::from(class.len());
+                    cut = rest_cut;
+                }
+            }
+            Term::Nonterminal(_) | Term::Repeat { .. } => {
+                if self.rules.contains_key(&part) {
+                    for head_len in 0..=len {
+                        let (head, head_cut) = self.nonterminal(part, head_len);
+                        cut = outermost(cut, head_cut);
+                        if head == BigUint::from(0u32) {
+                            continue;
+                        }
+                        let (rest, rest_cut) =
+                            self.sequence(symbol, alt, offset + 1, len - head_len);
+                        cut = outermost(cut, rest_cut);
+                        total += head * rest;
+                    }
+                }
+            }
+        }
+
+        if cut.is_none() && !fresh {
+            self.sequences.insert(key, total.clone());
+        }
+        (total, cut)
+    }
+}
+
+/// Uniformly random number in `0..bound`, by rejection of out of range bits
+fn random_below<R: Rng>(bound: &BigUint, rng: &mut R) -> BigUint {
+    let bits = bound.bits();
+    let mut bytes = vec![0u8; bits.div_ceil(8) as usize];
+    loop {
+        rng.fill_bytes(&mut bytes);
+        if !bits.is_multiple_of(8) {
+            if let Some(last) = bytes.last_mut() {
+                *last &= (1u8 << (bits % 8)) - 1;
+            }
+        }
+        let candidate = BigUint::from_bytes_le(&bytes);
+        if candidate < *bound {
+            return candidate;
+        }
+    }
+}
+
+pub(crate) fn outermost(a: Option<usize>, b: Option<usize>) -> Option<usize> {
+    match (a, b) {
+        (Some(a), Some(b)) => Some(a.min(b)),
+        (a, None) => a,
+        (None, b) => b,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    extern crate rand;
+
+    use super::*;
+    use rand::{SeedableRng, StdRng};
+
+    fn count(grammar: &str, len: usize) -> BigUint {
+        Grammar::from_str(grammar).unwrap().count_derivations(len)
+    }
+
+    #[test]
+    fn counts_single_strings() {
+        let grammar = "<s> ::= \"a\" <s> | \"a\"";
+        assert_eq!(count(grammar, 0), BigUint::from(0u32));
+        for len in 1..10 {
+            assert_eq!(count(grammar, len), BigUint::from(1u32));
+        }
+    }
+
+    #[test]
+    fn counts_terminals_by_chars() {
+        let grammar = "<s> ::= \"ab\" | \"a\" \"b\" | \"é\"";
+        assert_eq!(count(grammar, 1), BigUint::from(1u32));
+        assert_eq!(count(grammar, 2), BigUint::from(2u32));
+    }
+
+    #[test]
+    fn counts_char_classes_by_members() {
+        let grammar = "<s> ::= [0-9] | [a-c] [x_]";
+        assert_eq!(count(grammar, 1), BigUint::from(10u32));
+        assert_eq!(count(grammar, 2), BigUint::from(6u32));
+
+        let mut rng = rand::thread_rng();
+        let digits = Grammar::from_str(grammar).unwrap();
+        for _ in 0..100 {
+            let digit = digits.sample_uniform(1, &mut rng).unwrap();
+            assert!(digit.chars().all(|c| c.is_ascii_digit()), "{}", digit);
+        }
+    }
+
+    #[test]
+    fn counts_ambiguous_derivations() {
+        // derivations of a^n are counted by the catalan numbers
+        let grammar = Grammar::from_str("<s> ::= <s> <s> | \"a\"").unwrap();
+        let mut counter = grammar.derivation_counter();
+        let mut catalan = BigUint::from(1u32);
+        for len in 1..=30 {
+            assert_eq!(counter.count(len), catalan, "length {}", len);
+            let n = len as u32;
+            catalan = catalan * BigUint::from(2 * (2 * n - 1)) / BigUint::from(n + 1);
+        }
+    }
+
+    #[test]
+    fn counts_with_empty_alternative() {
+        let grammar = "<s> ::= \"\" | \"x\" <s>";
+        assert_eq!(count(grammar, 0), BigUint::from(1u32));
+        assert_eq!(count(grammar, 3), BigUint::from(1u32));
+    }
+
+    #[test]
+    fn cuts_unit_cycles() {
+        let grammar = "<s> ::= <s> | \"a\"";
+        assert_eq!(count(grammar, 1), BigUint::from(1u32));
+        assert_eq!(count(grammar, 2), BigUint::from(0u32));
+    }
+
+    #[test]
+    fn counts_repetitions() {
+        let grammar = "<s> ::= 2*3( \"a\" | \"bb\" )";
+        let counts = (1..=7).map(|len| count(grammar, len)).collect::<Vec<_>>();
+        let expected = [0u32, 1, 3, 4, 3, 1, 0];
+        assert_eq!(
+            counts,
+            expected
+                .iter()
+                .map(|&n| BigUint::from(n))
+                .collect::<Vec<_>>()
+        );
+        assert_eq!(count("<s> ::= 0*\"a\"", 100), BigUint::from(1u32));
+    }
+
+    #[test]
+    fn undefined_nonterminals_count_nothing() {
+        let grammar = "<s> ::= <undefined> | \"a\"";
+        assert_eq!(count(grammar, 1), BigUint::from(1u32));
+        assert_eq!(
+            Grammar::from_str(grammar)
+                .unwrap()
+                .derivation_counter()
+                .count_from("undefined", 1),
+            BigUint::from(0u32)
+        );
+    }
+
+    #[test]
+    fn samples_exact_lengths_uniformly() {
+        let grammar = Grammar::from_str(
+            "<s> ::= <c> | <c> <s>
+            <c> ::= \"a\" | \"b\"",
+        )
+        .unwrap();
+        let mut counter = grammar.derivation_counter();
+        let seed: &[_] = &[1, 2, 3, 4];
+        let mut rng: StdRng = SeedableRng::from_seed(seed);
+
+        let mut seen = HashMap::new();
+        for _ in 0..8000 {
+            let sentence = counter.sample(3, &mut rng).unwrap();
+            assert_eq!(sentence.len(), 3);
+            *seen.entry(sentence).or_insert(0) += 1;
+        }
+        assert_eq!(seen.len(), 8);
+        for (sentence, &hits) in &seen {
+            assert!(
+                hits > 850 && hits < 1150,
+                "{} sampled {} times",
+                sentence,
+                hits
+            );
+        }
+    }
+
+    #[test]
+    fn samples_none_without_sentence_of_length() {
+        let grammar = Grammar::from_str("<s> ::= \"ab\" | \"ab\" <s>").unwrap();
+        let mut rng = rand::thread_rng();
+        assert_eq!(grammar.sample_uniform(3, &mut rng), None);
+        assert_eq!(
+            grammar.sample_uniform(4, &mut rng),
+            Some(String::from("abab"))
+        );
+        assert_eq!(Grammar::new().sample_uniform(1, &mut rng), None);
+    }
+
+    #[test]
+    fn samples_through_cut_cycles() {
+        let grammar = Grammar::from_str("<s> ::= <s> | <s> <s> | \"a\" | \"\"").unwrap();
+        let mut counter = grammar.derivation_counter();
+        let mut rng = rand::thread_rng();
+        for _ in 0..100 {
+            assert_eq!(counter.sample(3, &mut rng), Some(String::from("aaa")));
+        }
+        assert_eq!(counter.sample_from("undefined", 1, &mut rng), None);
+    }
+}
@@ -0,0 +1,1513 @@
+use counting::outermost;
+use expression::Expression;
+use grammar::Grammar;
+#[cfg(feature = "regex")]
+use regex_automata::{meta::Regex, Anchored};
+use std::collections::{HashMap, HashSet};
+use std::str;
+#[cfg(feature = "regex")]
+use term::compile_regex;
+use term::{caseless_prefix, Nonterminal, StartSymbol, Term};
+use tree::{ParseTree, ParseTreeNode};
+
+/// Get the longest prefix of at most `max` bytes of `input` which is UTF-8
+fn utf8_prefix(input: &[u8], max: usize) -> &str {
+    let input = &input[..input.len().min(max)];
+    match str::from_utf8(input) {
+        Ok(text) => text,
+        Err(e) => str::from_utf8(&input[..e.valid_up_to()]).unwrap_or_default(),
+    }
+}
+
+/// What a rule is of, and each of the terms a rule recognizes: a term, or
+/// the rest of a repetition after some copies of its inner expression
+#[derive(Clone, Copy, Debug, Eq, Hash, PartialEq)]
+pub(crate) enum Symbol<'a> {
+    Term(&'a Term),
+    Repeat(&'a Term, usize),
+}
+
+impl<'a> Symbol<'a> {
+    /// Get the symbol of a term of an expression, a repetition before any
+    /// of its copies
+    pub(crate) fn of(term: &'a Term) -> Symbol<'a> {
+        match *term {
+            Term::Repeat { .. } => Symbol::Repeat(term, 0),
+            _ => Symbol::Term(term),
+        }
+    }
+
+    /// Get the term of the symbol, the repetition itself for its rest
+    pub(crate) fn term(&self) -> &'a Term {
+        match *self {
+            Symbol::Term(term) | Symbol::Repeat(term, _) => term,
+        }
+    }
+}
+
+/// An alternative of a nonterminal, with the terms to be recognized
+pub(crate) struct Rule<'a> {
+    pub(crate) lhs: &'a Term,
+    pub(crate) symbol: Symbol<'a>,
+    pub(crate) expression: &'a Expression,
+    // the index of the expression in its production
+    pub(crate) alternative: usize,
+    pub(crate) terms: Vec<Symbol<'a>>,
+}
+
+impl<'a> Rule<'a> {
+    /// Get the node derived by the rule with `children`, the children of the
+    /// rest of a repetition following those of the copy before it
+    fn node(&self, mut children: Vec<ParseTreeNode>) -> ParseTreeNode {
+        match self.symbol {
+            Symbol::Term(_) => {
+                let mut tree =
+                    ParseTree::from_parts(self.lhs.clone(), self.expression.clone(), children);
+                tree.set_alternative_index(Some(self.alternative));
+                ParseTreeNode::Nonterminal(tree)
+            }
+            Symbol::Repeat(..) => {
+                if let Some(ParseTreeNode::Repeat(rest)) = children.pop() {
+                    children.extend(rest);
+                }
+                ParseTreeNode::Repeat(children)
+            }
+        }
+    }
+}
+
+/// Get the rules of every alternative of `grammar`, followed by those of the
+/// rest of each repetition after each number of its copies.
+///
+/// The rest of a repetition is another copy followed by the rest after it,
+/// while there are fewer than `max` copies, or else nothing once there are
+/// at least `min`, so the rules grow linearly with the counts. Copies past
+/// `min` of a repetition without a `max` are counted as `min`.
+pub(crate) fn rules(grammar: &Grammar) -> Vec<Rule<'_>> {
+    let mut rules = vec![];
+    for production in grammar.productions_iter() {
+        if let Term::Nonterminal(_) = *production.lhs() {
+            for (alternative, expression) in production.rhs_iter().enumerate() {
+                rules.push(Rule {
+                    lhs: production.lhs(),
+                    symbol: Symbol::Term(production.lhs()),
+                    expression,
+                    alternative,
+                    terms: expression.terms_iter().map(Symbol::of).collect(),
+                });
+            }
+        }
+    }
+
+    let mut seen = HashSet::new();
+    let mut pending = rules
+        .iter()
+        .flat_map(|rule| rule.terms.iter().cloned())
+        .collect::<Vec<_>>();
+    while let Some(symbol) = pending.pop() {
+        let (term, copies) = match symbol {
+            Symbol::Repeat(term, copies) if seen.insert(symbol) => (term, copies),
+            _ => continue,
+        };
+        let (inner, min, max) = match *term {
+            Term::Repeat {
+                ref inner,
+                min,
+                max,
+            } => (&**inner, min, max),
+            _ => continue,
+        };
+        let mut alternatives = vec![];
+        if max.is_none_or(|max| copies < max) {
+            let next = match max {
+                Some(_) => copies + 1,
+                None => min.min(copies + 1),
+            };
+            let mut terms = inner.terms_iter().map(Symbol::of).collect::<Vec<_>>();
+            terms.push(Symbol::Repeat(term, next));
+            alternatives.push(terms);
+        }
+        if copies >= min {
+            alternatives.push(vec![]);
+        }
+        for terms in alternatives {
+            pending.extend(terms.iter().cloned());
+            rules.push(Rule {
+                lhs: term,
+                symbol,
+                expression: inner,
+                alternative: 0,
+                terms,
+            });
+        }
+    }
+    rules
+}
+
+/// A rule, how many of its terms have been recognized, and where the rule
+/// began being recognized
+type Item = (usize, usize, usize);
+
+/// What a chart recognizes: bytes, of which terms match UTF-8 text, or
+/// tokens, each of which a term matches all of
+#[derive(Clone, Copy)]
+enum Input<'a> {
+    Bytes(&'a [u8]),
+    Tokens(&'a [&'a str]),
+}
+
+impl<'a> Input<'a> {
+    fn len(&self) -> usize {
+        match *self {
+            Input::Bytes(bytes) => bytes.len(),
+            Input::Tokens(tokens) => tokens.len(),
+        }
+    }
+
+    /// Get the text from `start` to `end`
+    fn text(&self, start: usize, end: usize) -> String {
+        match *self {
+            Input::Bytes(bytes) => String::from_utf8_lossy(&bytes[start..end]).into_owned(),
+            Input::Tokens(tokens) => tokens[start..end].concat(),
+        }
+    }
+}
+
+/// Earley recognition of an input from a nonterminal.
+///
+/// The alternatives of every production of a nonterminal are recognized, not
+/// just those of its first production. Positions are byte offsets into the
+/// input, which may be bytes of any kind for `Term::TerminalBytes`, while
+/// other terms match UTF-8. A nonterminal without a production matches its
+/// own `<name>` text, the same as generation emits for it. A `Term::Regex`
+/// matches the longest text it can, and nothing without the feature `regex`.
+/// Positions of an input of tokens are instead token indices, each term
+/// matching a whole token, or none for an empty terminal. A repetition is
+/// recognized by the rules of its rest after each number of its copies.
+pub(crate) struct Chart<'a> {
+    pub(crate) rules: Vec<Rule<'a>>,
+    by_lhs: HashMap<Symbol<'a>, Vec<usize>>,
+    #[cfg(feature = "regex")]
+    regexes: HashMap<&'a str, Regex>,
+    input: Input<'a>,
+    start: Option<Symbol<'a>>,
+    items: Vec<HashSet<Item>>,
+    // the items of each position in the order they were added
+    ordered: Vec<Vec<Item>>,
+    completed: HashSet<(usize, usize, usize)>,
+    spans: HashSet<(Symbol<'a>, usize, usize)>,
+}
+
+impl<'a> Chart<'a> {
+    /// Recognize every prefix of `input` from nonterminal `start`
+    pub(crate) fn parse(grammar: &'a Grammar, start: &str, input: &'a str) -> Chart<'a> {
+        Chart::parse_bytes(grammar, start, input.as_bytes())
+    }
+
+    /// Recognize every prefix of `input` from nonterminal `start`
+    pub(crate) fn parse_bytes(grammar: &'a Grammar, start: &str, input: &'a [u8]) -> Chart<'a> {
+        Chart::parse_input(grammar, start, Input::Bytes(input))
+    }
+
+    /// Recognize every prefix of `tokens` from nonterminal `start`
+    pub(crate) fn parse_tokens(
+        grammar: &'a Grammar,
+        start: &str,
+        tokens: &'a [&'a str],
+    ) -> Chart<'a> {
+        Chart::parse_input(grammar, start, Input::Tokens(tokens))
+    }
+
+    fn parse_input(grammar: &'a Grammar, start: &str, input: Input<'a>) -> Chart<'a> {
+        let rules = rules(grammar);
+        let mut by_lhs: HashMap<Symbol<'a>, Vec<usize>> = HashMap::new();
+        for (index, rule) in rules.iter().enumerate() {
+            by_lhs.entry(rule.symbol).or_default().push(index);
+        }
+
+        #[cfg(feature = "regex")]
+        let mut regexes = HashMap::new();
+        #[cfg(feature = "regex")]
+        for symbol in rules.iter().flat_map(|rule| rule.terms.iter()) {
+            if let Term::Regex(ref pattern) = *symbol.term() {
+                if !regexes.contains_key(pattern.as_str()) {
+                    regexes.extend(compile_regex(pattern).map(|regex| (pattern.as_str(), regex)));
+                }
+            }
+        }
+
+        let start = by_lhs.keys().cloned().find(|lhs| match *lhs.term() {
+            Term::Nonterminal(ref nt) => nt == start,
+            _ => false,
+        });
+        let mut chart = Chart {
+            rules,
+            by_lhs,
+            #[cfg(feature = "regex")]
+            regexes,
+            input,
+            start,
+            items: vec![HashSet::new(); input.len() + 1],
+            ordered: vec![],
+            completed: HashSet::new(),
+            spans: HashSet::new(),
+        };
+        chart.recognize();
+        chart
+    }
+
+    fn recognize(&mut self) {
+        let mut sets: Vec<Vec<Item>> = vec![vec![]; self.input.len() + 1];
+        if let Some(start) = self.start {
+            for rule in self.by_lhs[&start].clone() {
+                self.add(&mut sets, 0, (rule, 0, 0));
+            }
+        }
+
+        for position in 0..=self.input.len() {
+            // nonterminals recognized as empty at this position
+            let mut nullable = HashSet::new();
+            let mut index = 0;
+            while index < sets[position].len() {
+                let (rule, dot, origin) = sets[position][index];
+                index += 1;
+
+                let lhs = self.rules[rule].symbol;
+                match self.rules[rule].terms.get(dot).cloned() {
+                    None => {
+                        self.completed.insert((rule, origin, position));
+                        self.spans.insert((lhs, origin, position));
+                        if origin == position {
+                            nullable.insert(lhs);
+                        }
+                        let waiting = sets[origin]
+                            .iter()
+                            .filter(|&&(waiting, dot, _)| {
+                                self.nonterminal_at(waiting, dot) == Some(lhs)
+                            })
+                            .cloned()
+                            .collect::<Vec<_>>();
+                        for (waiting, dot, origin) in waiting {
+                            self.add(&mut sets, position, (waiting, dot + 1, origin));
+                        }
+                    }
+                    Some(term) => match self.defined(term) {
+                        Some(symbol) => {
+                            let predicted = self.by_lhs.get(&symbol).cloned();
+                            for predicted in predicted.into_iter().flatten() {
+                                self.add(&mut sets, position, (predicted, 0, position));
+                            }
+                            if nullable.contains(&symbol) {
+                                self.add(&mut sets, position, (rule, dot + 1, origin));
+                            }
+                        }
+                        None => {
+                            if let Some(end) = self.scan(term.term(), position) {
+                                self.add(&mut sets, end, (rule, dot + 1, origin));
+                            }
+                        }
+                    },
+                }
+            }
+        }
+        self.ordered = sets;
+    }
+
+    fn add(&mut self, sets: &mut [Vec<Item>], position: usize, item: Item) {
+        if self.items[position].insert(item) {
+            sets[position].push(item);
+        }
+    }
+
+    /// Get `symbol` when it has rules, a nonterminal with a production or
+    /// the rest of a repetition
+    pub(crate) fn defined(&self, symbol: Symbol<'a>) -> Option<Symbol<'a>> {
+        match symbol {
+            Symbol::Term(_) if !self.by_lhs.contains_key(&symbol) => None,
+            _ => Some(symbol),
+        }
+    }
+
+    fn nonterminal_at(&self, rule: usize, dot: usize) -> Option<Symbol<'a>> {
+        self.rules[rule]
+            .terms
+            .get(dot)
+            .and_then(|&symbol| self.defined(symbol))
+    }
+
+    /// Match a term which isn't a defined nonterminal at `position`, getting
+    /// the position after it
+    pub(crate) fn scan(&self, term: &Term, position: usize) -> Option<usize> {
+        let rest = match self.input {
+            Input::Bytes(bytes) => &bytes[position..],
+            Input::Tokens(tokens) => return self.scan_token(term, position, tokens),
+        };
+        match *term {
+            Term::Terminal(ref t) => {
+                if rest.starts_with(t.as_bytes()) {
+                    Some(position + t.len())
+                } else {
+                    None
+                }
+            }
+            Term::TerminalBytes(ref bytes) => {
+                if rest.starts_with(bytes) {
+                    Some(position + bytes.len())
+                } else {
+                    None
+                }
+            }
+            // no character takes more than 4 bytes
+            Term::CaselessTerminal(ref t) => {
+                caseless_prefix(t, utf8_prefix(rest, 4 * t.len())).map(|len| position + len)
+            }
+            Term::CharClass(ref class) => match utf8_prefix(rest, 4).chars().next() {
+                Some(c) if class.contains(c) => Some(position + c.len_utf8()),
+                _ => None,
+            },
+            Term::Regex(ref pattern) => self.scan_regex(pattern, position),
+            // a repetition is recognized by its rules instead
+            Term::Repeat { .. } => None,
+            Term::Nonterminal(_) => {
+                let text = term.to_string();
+                if rest.starts_with(text.as_bytes()) {
+                    Some(position + text.len())
+                } else {
+                    None
+                }
+            }
+        }
+    }
+
+    /// Match a term which isn't a defined nonterminal to the token at
+    /// `position`, or to no token for an empty terminal
+    fn scan_token(&self, term: &Term, position: usize, tokens: &[&str]) -> Option<usize> {
+        let matches = |token: &str| match *term {
+            Term::Terminal(ref t) => t == token,
+            Term::TerminalBytes(ref bytes) => bytes.as_slice() == token.as_bytes(),
+            Term::CaselessTerminal(ref t) => caseless_prefix(t, token) == Some(token.len()),
+            Term::CharClass(ref class) => {
+                let mut chars = token.chars();
+                match (chars.next(), chars.next()) {
+                    (Some(c), None) => class.contains(c),
+                    _ => false,
+                }
+            }
+            Term::Regex(ref pattern) => self.matches_regex(pattern, token),
+            Term::Repeat { .. } => false,
+            Term::Nonterminal(_) => term.to_string() == token,
+        };
+        match term.terminal_text() {
+            Some("") => Some(position),
+            _ => tokens
+                .get(position)
+                .filter(|&&token| matches(token))
+                .map(|_| position + 1),
+        }
+    }
+
+    /// Match the longest text of the regex `pattern` at `position`, seen in
+    /// the context of the whole input for anchors like `\b`
+    #[cfg(feature = "regex")]
+    fn scan_regex(&self, pattern: &str, position: usize) -> Option<usize> {
+        let bytes = match self.input {
+            Input::Bytes(bytes) => bytes,
+            Input::Tokens(_) => return None,
+        };
+        let input = regex_automata::Input::new(bytes)
+            .range(position..)
+            .anchored(Anchored::Yes);
+        self.regexes
+            .get(pattern)?
+            .find(input)
+            .map(|found| found.end())
+    }
+
+    #[cfg(not(feature = "regex"))]
+    fn scan_regex(&self, _pattern: &str, _position: usize) -> Option<usize> {
+        None
+    }
+
+    /// Whether the regex `pattern` matches all of `token`
+    #[cfg(feature = "regex")]
+    fn matches_regex(&self, pattern: &str, token: &str) -> bool {
+        let input = regex_automata::Input::new(token).anchored(Anchored::Yes);
+        self.regexes
+            .get(pattern)
+            .and_then(|regex| regex.find(input))
+            .is_some_and(|found| found.end() == token.len())
+    }
+
+    #[cfg(not(feature = "regex"))]
+    fn matches_regex(&self, _pattern: &str, _token: &str) -> bool {
+        false
+    }
+
+    /// Get the length of the input
+    pub(crate) fn len(&self) -> usize {
+        self.input.len()
+    }
+
+    /// Get the start nonterminal, when it has a production
+    pub(crate) fn start(&self) -> Option<Symbol<'a>> {
+        self.start
+    }
+
+    /// Whether the whole input was recognized from the start nonterminal
+    pub(crate) fn accepts(&self) -> bool {
+        self.start
+            .is_some_and(|start| self.spans.contains(&(start, 0, self.input.len())))
+    }
+
+    /// Get the rules of `symbol` which were recognized from `start` to `end`
+    pub(crate) fn completed_rules(
+        &self,
+        symbol: Symbol<'a>,
+        start: usize,
+        end: usize,
+    ) -> Vec<usize> {
+        self.by_lhs.get(&symbol).map_or(vec![], |rules| {
+            rules
+                .iter()
+                .cloned()
+                .filter(|&rule| self.completed.contains(&(rule, start, end)))
+                .collect()
+        })
+    }
+
+    /// Whether `symbol` was recognized from `start` to `end`
+    pub(crate) fn spans(&self, symbol: Symbol<'a>, start: usize, end: usize) -> bool {
+        self.spans.contains(&(symbol, start, end))
+    }
+
+    /// Whether the first `dot` terms of `rule` were recognized from `start`
+    /// to `end`
+    pub(crate) fn has_prefix(&self, rule: usize, dot: usize, start: usize, end: usize) -> bool {
+        self.items[end].contains(&(rule, dot, start))
+    }
+}
+
+/// Counts the parse trees of spans of a `Chart`.
+///
+/// Cycles through the same nonterminal and span are cut while counting, the
+/// same as `DerivationCounter` does, so only trees which never repeat a
+/// (nonterminal, span) along a path are counted. Counts saturate at
+/// `usize::MAX`.
+pub(crate) struct ParseCounter<'c, 'a: 'c> {
+    chart: &'c Chart<'a>,
+    nonterminals: HashMap<(Symbol<'a>, usize, usize), usize>,
+    sequences: HashMap<(usize, usize, usize, usize), usize>,
+    in_progress: HashMap<(Symbol<'a>, usize, usize), usize>,
+}
+
+impl<'c, 'a: 'c> ParseCounter<'c, 'a> {
+    pub(crate) fn new(chart: &'c Chart<'a>) -> ParseCounter<'c, 'a> {
+        ParseCounter {
+            chart,
+            nonterminals: HashMap::new(),
+            sequences: HashMap::new(),
+            in_progress: HashMap::new(),
+        }
+    }
+
+    /// Count the trees of `symbol` from `start` to `end`, along with the
+    /// stack depth of the outermost cycle that was cut while counting, if any
+    pub(crate) fn nonterminal(
+        &mut self,
+        symbol: Symbol<'a>,
+        start: usize,
+        end: usize,
+    ) -> (usize, Option<usize>) {
+        let key = (symbol, start, end);
+        if let Some(&depth) = self.in_progress.get(&key) {
+            return (0, Some(depth));
+        }
+        if let Some(&count) = self.nonterminals.get(&key) {
+            return (count, None);
+        }
+
+        let depth = self.in_progress.len();
+        self.in_progress.insert(key, depth);
+
+        let mut total = 0usize;
+        let mut cut = None;
+        for rule in self.chart.completed_rules(symbol, start, end) {
+            let (count, rule_cut) = self.sequence(rule, 0, start, end);
+            total = total.saturating_add(count);
+            cut = outermost(cut, rule_cut);
+        }
+
+        self.in_progress.remove(&key);
+
+        // a cycle back to this very span doesn't depend on the callers
+        if cut.is_none_or(|cut| cut >= depth) {
+            self.nonterminals.insert(key, total);
+            cut = None;
+        }
+        (total, cut)
+    }
+
+    /// Count the trees of the terms of `rule` from `dot` onward, from `start`
+    /// to `end`
+    pub(crate) fn sequence(
+        &mut self,
+        rule: usize,
+        dot: usize,
+        start: usize,
+        end: usize,
+    ) -> (usize, Option<usize>) {
+        let chart = self.chart;
+        let term = match chart.rules[rule].terms.get(dot) {
+            Some(&term) => term,
+            None => return ((start == end) as usize, None),
+        };
+
+        let key = (rule, dot, start, end);
+        if let Some(&count) = self.sequences.get(&key) {
+            return (count, None);
+        }
+
+        let mut total = 0usize;
+        let mut cut = None;
+        match chart.defined(term) {
+            Some(symbol) => {
+                for split in start..=end {
+                    if !chart.spans(symbol, start, split) {
+                        continue;
+                    }
+                    let (rest, rest_cut) = self.sequence(rule, dot + 1, split, end);
+                    cut = outermost(cut, rest_cut);
+                    if rest == 0 {
+                        continue;
+                    }
+                    let (head, head_cut) = self.nonterminal(symbol, start, split);
+                    cut = outermost(cut, head_cut);
+                    total = total.saturating_add(head.saturating_mul(rest));
+                }
+            }
+            None => {
+                if let Some(split) = chart.scan(term.term(), start).filter(|&split| split <= end) {
+                    let (count, rest_cut) = self.sequence(rule, dot + 1, split, end);
+                    total = count;
+                    cut = rest_cut;
+                }
+            }
+        }
+
+        if cut.is_none() {
+            self.sequences.insert(key, total);
+        }
+        (total, cut)
+    }
+
+    /// Whether a tree of `symbol` from `start` to `end` can contain another
+    /// tree of the same symbol and span, making the number of trees infinite
+    pub(crate) fn is_cyclic(&mut self, symbol: Symbol<'a>, start: usize, end: usize) -> bool {
+        let mut visiting = HashSet::new();
+        let mut done = HashSet::new();
+        self.visit((symbol, start, end), &mut visiting, &mut done)
+    }
+
+    fn visit(
+        &mut self,
+        node: (Symbol<'a>, usize, usize),
+        visiting: &mut HashSet<(Symbol<'a>, usize, usize)>,
+        done: &mut HashSet<(Symbol<'a>, usize, usize)>,
+    ) -> bool {
+        if done.contains(&node) {
+            return false;
+        }
+        if !visiting.insert(node) {
+            return true;
+        }
+
+        let chart = self.chart;
+        let (symbol, start, end) = node;
+        for rule in chart.completed_rules(symbol, start, end) {
+            for (dot, &term) in chart.rules[rule].terms.iter().enumerate() {
+                let child = match chart.defined(term) {
+                    Some(child) => child,
+                    None => continue,
+                };
+                for child_start in start..=end {
+                    if !chart.has_prefix(rule, dot, start, child_start) {
+                        continue;
+                    }
+                    for child_end in child_start..=end {
+                        let used = chart.spans(child, child_start, child_end)
+                            && self.sequence(rule, dot + 1, child_end, end).0 > 0
+                            && self.nonterminal(child, child_start, child_end).0 > 0;
+                        if used && self.visit((child, child_start, child_end), visiting, done) {
+                            return true;
+                        }
+                    }
+                }
+            }
+        }
+
+        visiting.remove(&node);
+        done.insert(node);
+        false
+    }
+
+    /// Build a tree of the nonterminal `symbol` from `start` to `end`, if
+    /// there is one.
+    ///
+    /// A tree never contains another tree of the same nonterminal and span,
+    /// so this terminates for cyclic grammars too.
+    pub(crate) fn tree(
+        &mut self,
+        symbol: Symbol<'a>,
+        start: usize,
+        end: usize,
+    ) -> Option<ParseTree> {
+        match self.node(symbol, start, end)? {
+            ParseTreeNode::Nonterminal(tree) => Some(tree),
+            ParseTreeNode::Terminal(_) | ParseTreeNode::Repeat(_) => None,
+        }
+    }
+
+    /// Build a node of `symbol` from `start` to `end`, if there is one
+    fn node(&mut self, symbol: Symbol<'a>, start: usize, end: usize) -> Option<ParseTreeNode> {
+        let key = (symbol, start, end);
+        if self.in_progress.contains_key(&key) {
+            return None;
+        }
+        let depth = self.in_progress.len();
+        self.in_progress.insert(key, depth);
+
+        let mut node = None;
+        for rule in self.chart.completed_rules(symbol, start, end) {
+            if self.sequence(rule, 0, start, end).0 == 0 {
+                continue;
+            }
+            let mut children = vec![];
+            if self.sequence_tree(rule, 0, start, end, &mut children) {
+                node = Some(self.chart.rules[rule].node(children));
+                break;
+            }
+        }
+
+        self.in_progress.remove(&key);
+        node
+    }
+
+    /// Build the children of the terms of `rule` from `dot` onward, from
+    /// `start` to `end`, returning whether they could be built
+    fn sequence_tree(
+        &mut self,
+        rule: usize,
+        dot: usize,
+        start: usize,
+        end: usize,
+        children: &mut Vec<ParseTreeNode>,
+    ) -> bool {
+        let chart = self.chart;
+        let term = match chart.rules[rule].terms.get(dot) {
+            Some(&term) => term,
+            None => return start == end,
+        };
+
+        match chart.defined(term) {
+            Some(symbol) => {
+                for split in start..=end {
+                    let possible = chart.spans(symbol, start, split)
+                        && self.sequence(rule, dot + 1, split, end).0 > 0
+                        && self.nonterminal(symbol, start, split).0 > 0;
+                    if !possible {
+                        continue;
+                    }
+                    if let Some(child) = self.node(symbol, start, split) {
+                        children.push(child);
+                        if self.sequence_tree(rule, dot + 1, split, end, children) {
+                            return true;
+                        }
+                        children.pop();
+                    }
+                }
+                false
+            }
+            None => match chart.scan(term.term(), start).filter(|&split| split <= end) {
+                Some(split) => {
+                    children.push(ParseTreeNode::Terminal(chart.input.text(start, split)));
+                    if self.sequence_tree(rule, dot + 1, split, end, children) {
+                        return true;
+                    }
+                    children.pop();
+                    false
+                }
+                None => false,
+            },
+        }
+    }
+
+    /// Build every tree of the nonterminal `symbol` from `start` to `end`
+    /// which never contains another tree of the same nonterminal and span
+    pub(crate) fn trees(&mut self, symbol: Symbol<'a>, start: usize, end: usize) -> Vec<ParseTree> {
+        self.nodes(symbol, start, end)
+            .into_iter()
+            .filter_map(|node| match node {
+                ParseTreeNode::Nonterminal(tree) => Some(tree),
+                ParseTreeNode::Terminal(_) | ParseTreeNode::Repeat(_) => None,
+            })
+            .collect()
+    }
+
+    /// Build every node of `symbol` from `start` to `end`
+    fn nodes(&mut self, symbol: Symbol<'a>, start: usize, end: usize) -> Vec<ParseTreeNode> {
+        let key = (symbol, start, end);
+        if self.in_progress.contains_key(&key) {
+            return vec![];
+        }
+        let depth = self.in_progress.len();
+        self.in_progress.insert(key, depth);
+
+        let mut nodes = vec![];
+        for rule in self.chart.completed_rules(symbol, start, end) {
+            for children in self.sequence_trees(rule, 0, start, end) {
+                nodes.push(self.chart.rules[rule].node(children));
+            }
+        }
+
+        self.in_progress.remove(&key);
+        nodes
+    }
+
+    /// Build every way of deriving the terms of `rule` from `dot` onward,
+    /// from `start` to `end`, as the children for each term
+    fn sequence_trees(
+        &mut self,
+        rule: usize,
+        dot: usize,
+        start: usize,
+        end: usize,
+    ) -> Vec<Vec<ParseTreeNode>> {
+        let chart = self.chart;
+        let term = match chart.rules[rule].terms.get(dot) {
+            Some(&term) => term,
+            None if start == end => return vec![vec![]],
+            None => return vec![],
+        };
+
+        // the children of the term, each with where the rest begins
+        let heads = match chart.defined(term) {
+            Some(symbol) => (start..=end)
+                .filter(|&split| chart.spans(symbol, start, split))
+                .flat_map(|split| {
+                    self.nodes(symbol, start, split)
+                        .into_iter()
+                        .map(move |node| (node, split))
+                        .collect::<Vec<_>>()
+                })
+                .collect::<Vec<_>>(),
+            None => chart
+                .scan(term.term(), start)
+                .filter(|&split| split <= end)
+                .map(|split| {
+                    (
+                        ParseTreeNode::Terminal(chart.input.text(start, split)),
+                        split,
+                    )
+                })
+                .into_iter()
+                .collect(),
+        };
+
+        let mut sequences = vec![];
+        for (head, split) in heads {
+            for rest in self.sequence_trees(rule, dot + 1, split, end) {
+                let mut children = vec![head.clone()];
+                children.extend(rest);
+                sequences.push(children);
+            }
+        }
+        sequences
+    }
+}
+
+/// An alternative of a nonterminal of which the first `dot` terms were
+/// recognized from `start` to `end`, positions being token indices
+#[derive(Clone, Copy, Debug, Eq, Hash, PartialEq)]
+pub struct EarleyItem<'a> {
+    /// The nonterminal the alternative is of
+    pub lhs: &'a Term,
+    /// The alternative being recognized
+    pub expression: &'a Expression,
+    /// The number of the alternative's terms recognized
+    pub dot: usize,
+    /// The index of the first token recognized
+    pub start: usize,
+    /// The index after the last token recognized
+    pub end: usize,
+}
+
+/// The Earley chart of tokens parsed from a nonterminal, built by
+/// `Grammar::parse_earley`
+pub struct EarleyChart<'a> {
+    chart: Chart<'a>,
+}
+
+impl<'a> EarleyChart<'a> {
+    /// Whether all the tokens were recognized from the start nonterminal
+    pub fn recognized(&self) -> bool {
+        self.chart.accepts()
+    }
+
+    /// Get iterator of the chart's items, by their end and then in the order
+    /// they were added, predictions before what completes them. The items
+    /// recognizing the copies of a repetition aren't included, only those
+    /// of the alternatives it's a term of.
+    pub fn items_iter(&self) -> impl Iterator<Item = EarleyItem<'a>> + '_ {
+        let chart = &self.chart;
+        chart
+            .ordered
+            .iter()
+            .enumerate()
+            .flat_map(move |(end, items)| {
+                items
+                    .iter()
+                    .filter(move |&&(rule, _, _)| {
+                        matches!(chart.rules[rule].symbol, Symbol::Term(_))
+                    })
+                    .map(move |&(rule, dot, start)| EarleyItem {
+                        lhs: chart.rules[rule].lhs,
+                        expression: chart.rules[rule].expression,
+                        dot,
+                        start,
+                        end,
+                    })
+            })
+    }
+
+    /// Get every parse tree of all the tokens from the start nonterminal,
+    /// none when they weren't recognized.
+    ///
+    /// A tree never contains another tree of the same nonterminal and span,
+    /// so a cycle like `<a> ::= <a> | "a"` doesn't give infinitely many
+    /// trees. An ambiguous grammar can have exponentially many trees of its
+    /// tokens, all of which are built.
+    pub fn extract_trees(&self) -> Vec<ParseTree> {
+        match self.chart.start() {
+            Some(start) if self.chart.accepts() => {
+                ParseCounter::new(&self.chart).trees(start, 0, self.chart.len())
+            }
+            _ => vec![],
+        }
+    }
+}
+
+impl Grammar {
+    /// Count the distinct parse trees of `input` from the nonterminal `start`.
+    ///
+    /// The alternatives of every production sharing a lhs are parsed with,
+    /// and a nonterminal without a production matches its own `<name>` text.
+    /// Returns 0 when the input doesn't parse, and `usize::MAX` when there
+    /// are too many trees to count, including infinitely many trees through
+    /// a cycle like `<a> ::= <a> | "a"`.
+    ///
+    /// # Example
+    ///
+    /// ```rust
+    /// extern crate bnf;
+    /// use bnf::Grammar;
+    ///
+    /// fn main() {
+    ///     let grammar = Grammar::from_str("<sum> ::= <sum> \"+\" <sum> | \"1\"").unwrap();
+    ///
+    ///     assert_eq!(grammar.count_parses("sum", "1+1"), 1);
+    ///     assert_eq!(grammar.count_parses("sum", "1+1+1"), 2);
+    ///     assert_eq!(grammar.count_parses("sum", "1+"), 0);
+    /// }
+    /// ```
+    pub fn count_parses(&self, start: impl StartSymbol, input: &str) -> usize {
+        self.count_parses_bytes(start, input.as_bytes())
+    }
+
+    /// Count the distinct parse trees of `input` from the nonterminal `start`
+    /// as `Grammar::count_parses` does, for input of any bytes, which
+    /// `Term::TerminalBytes` match exactly and other terms match as UTF-8.
+    ///
+    /// # Example
+    ///
+    /// ```rust
+    /// extern crate bnf;
+    /// use bnf::Grammar;
+    ///
+    /// fn main() {
+    ///     let grammar = Grammar::from_str("<record> ::= %xCA.FE <len> \"!\"
+    ///         <len> ::= %x00 | %xFF").unwrap();
+    ///
+    ///     assert_eq!(grammar.count_parses_bytes("record", b"\xCA\xFE\xFF!"), 1);
+    ///     assert_eq!(grammar.count_parses_bytes("record", b"\xCA\xFE\x01!"), 0);
+    /// }
+    /// ```
+    pub fn count_parses_bytes(&self, start: impl StartSymbol, input: &[u8]) -> usize {
+        let start = match start.into_term() {
+            Term::Nonterminal(nt) => nt,
+            _ => return 0,
+        };
+        let chart = Chart::parse_bytes(self, &start, input);
+        let start = match chart.start() {
+            Some(start) if chart.accepts() => start,
+            _ => return 0,
+        };
+
+        let mut counter = ParseCounter::new(&chart);
+        let (count, _) = counter.nonterminal(start, 0, chart.len());
+        if counter.is_cyclic(start, 0, chart.len()) {
+            usize::MAX
+        } else {
+            count
+        }
+    }
+
+    /// Get the leftmost derivation of `input` from the nonterminal `start`,
+    /// as each sentential form from `start` alone to the input, or `None`
+    /// when it doesn't parse.
+    ///
+    /// Each step rewrites the leftmost nonterminal by the alternative of one
+    /// parse tree of the input, so an ambiguous input has one of its
+    /// derivations. The terms of a form are those of the alternatives, but
+    /// for character classes, caseless terminals and regexes, which are the
+    /// `Term::Terminal` of the text they matched, so the last form is the
+    /// input. A repetition is the terms of each of the copies it matched, and
+    /// a nonterminal without a production matches its own `<name>` text and
+    /// is never rewritten.
+    ///
+    /// # Example
+    ///
+    /// ```rust
+    /// extern crate bnf;
+    /// use bnf::{Expression, Grammar};
+    ///
+    /// fn main() {
+    ///     let grammar = Grammar::from_str("<sum> ::= <num> \"+\" <sum> | <num>
+    ///         <num> ::= [0-9]").unwrap();
+    ///     let steps = grammar.leftmost_derivation("sum", "1+2");
+    ///     let steps: Vec<String> = steps.unwrap().iter().map(Expression::to_string).collect();
+    ///
+    ///     assert_eq!(steps, vec![
+    ///         "<sum>",
+    ///         "<num> \"+\" <sum>",
+    ///         "\"1\" \"+\" <sum>",
+    ///         "\"1\" \"+\" <num>",
+    ///         "\"1\" \"+\" \"2\"",
+    ///     ]);
+    /// }
+    /// ```
+    pub fn leftmost_derivation(
+        &self,
+        start: impl StartSymbol,
+        input: &str,
+    ) -> Option<Vec<Expression>> {
+        // a term of a sentential form, or a nonterminal still to be rewritten
+        // by its tree
+        enum Form<'t> {
+            Term(Term),
+            Tree(&'t ParseTree),
+        }
+        // add the forms of `terms` derived as `children` to `form`, the terms
+        // of a repetition once for each of its copies
+        fn rewrite<'t>(terms: &[&Term], children: &[&'t ParseTreeNode], form: &mut Vec<Form<'t>>) {
+            for (&term, &child) in terms.iter().cycle().zip(children) {
+                match *child {
+                    ParseTreeNode::Nonterminal(ref tree) => form.push(Form::Tree(tree)),
+                    ParseTreeNode::Terminal(ref text) => form.push(match *term {
+                        Term::CharClass(_) | Term::CaselessTerminal(_) | Term::Regex(_) => {
+                            Form::Term(Term::Terminal(text.clone()))
+                        }
+                        _ => Form::Term(term.clone()),
+                    }),
+                    ParseTreeNode::Repeat(ref copies) => {
+                        let inner = match *term {
+                            Term::Repeat { ref inner, .. } => inner.terms_iter().collect(),
+                            _ => vec![],
+                        };
+                        rewrite(&inner, &copies.iter().collect::<Vec<_>>(), form);
+                    }
+                }
+            }
+        }
+        let form_expression = |form: &[Form]| {
+            let terms = form.iter().map(|form| match *form {
+                Form::Term(ref term) => term.clone(),
+                Form::Tree(tree) => tree.lhs.clone(),
+            });
+            Expression::from_parts(terms.collect())
+        };
+
+        let start = start.into_term();
+        let nonterminal = match start {
+            Term::Nonterminal(ref nt) => nt,
+            _ => return None,
+        };
+        let chart = Chart::parse(self, nonterminal, input);
+        let tree = match chart.start() {
+            Some(start) if chart.accepts() => {
+                ParseCounter::new(&chart).tree(start, 0, chart.len())?
+            }
+            _ => return None,
+        };
+
+        let mut form = vec![Form::Tree(&tree)];
+        let mut steps = vec![Expression::from_parts(vec![start.clone()])];
+        while let Some(index) = form.iter().position(|form| matches!(*form, Form::Tree(_))) {
+            let tree = match form[index] {
+                Form::Tree(tree) => tree,
+                Form::Term(_) => unreachable!(),
+            };
+            let terms = tree.expression.terms_iter().collect::<Vec<_>>();
+            let mut rewritten = vec![];
+            rewrite(
+                &terms,
+                &tree.children_iter().collect::<Vec<_>>(),
+                &mut rewritten,
+            );
+            form.splice(index..=index, rewritten);
+            steps.push(form_expression(&form));
+        }
+        Some(steps)
+    }
+
+    /// Parse `tokens` from the nonterminal `start` by Earley's algorithm,
+    /// getting the chart of every alternative recognized.
+    ///
+    /// Each terminal matches a whole token, as `Grammar::tokenize` splits
+    /// input, and an empty terminal matches no token. A character class
+    /// matches a token of one of its characters, a regex a token it matches
+    /// all of, and a nonterminal without a production the token of its own
+    /// `<name>` text. The alternatives of every production sharing a lhs are
+    /// parsed with, so any context-free grammar is parsed, including
+    /// ambiguous and left recursive ones. A `start` without a production
+    /// recognizes nothing.
+    ///
+    /// # Example
+    ///
+    /// ```rust
+    /// extern crate bnf;
+    /// use bnf::Grammar;
+    ///
+    /// fn main() {
+    ///     let grammar = Grammar::from_str("<sum> ::= <sum> \"+\" <sum> | <num>
+    ///         <num> ::= \"1\" | \"10\"").unwrap();
+    ///     let tokens = grammar.tokenize("1+10+1").unwrap();
+    ///     let tokens = tokens.iter().map(String::as_str).collect::<Vec<_>>();
+    ///     let chart = grammar.parse_earley(&tokens, "sum");
+    ///
+    ///     assert!(chart.recognized());
+    ///     let trees = chart.extract_trees();
+    ///     assert_eq!(trees.len(), 2);
+    ///     assert!(trees.iter().all(|tree| tree.flatten() == "1+10+1"));
+    /// }
+    /// ```
+    pub fn parse_earley<'a>(
+        &'a self,
+        tokens: &'a [&'a str],
+        start: impl Into<Nonterminal>,
+    ) -> EarleyChart<'a> {
+        EarleyChart {
+            chart: Chart::parse_tokens(self, start.into().name(), tokens),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn count(grammar: &str, start: &str, input: &str) -> usize {
+        let grammar = Grammar::from_str(grammar).unwrap();
+        grammar.count_parses(start, input)
+    }
+
+    #[test]
+    fn recognizes_inputs() {
+        let grammar = Grammar::from_str(
+            "<dna> ::= <base> | <base> <dna>
+            <base> ::= \"A\" | \"C\" | \"G\" | \"T\"",
+        )
+        .unwrap();
+        assert!(Chart::parse(&grammar, "dna", "GATTACA").accepts());
+        assert!(!Chart::parse(&grammar, "dna", "GATTACA!").accepts());
+        assert!(!Chart::parse(&grammar, "dna", "").accepts());
+        assert!(!Chart::parse(&grammar, "undefined", "A").accepts());
+        assert!(Chart::parse(&grammar, "base", "A").accepts());
+    }
+
+    #[test]
+    fn recognizes_nullable_and_multichar_terms() {
+        let grammar = Grammar::from_str(
+            "<s> ::= <opt> <opt> \"ab\" <opt> [é-ë] <undefined>
+            <opt> ::= \"\" | \"x\"",
+        )
+        .unwrap();
+        assert!(Chart::parse(&grammar, "s", "abê<undefined>").accepts());
+        assert!(Chart::parse(&grammar, "s", "xxabxé<undefined>").accepts());
+        assert!(!Chart::parse(&grammar, "s", "xxxabé<undefined>").accepts());
+        assert!(!Chart::parse(&grammar, "s", "abe<undefined>").accepts());
+    }
+
+    #[test]
+    fn recognizes_caseless_terminals() {
+        let grammar = Grammar::from_str(
+            "<query> ::= %i\"select\" \" \" <column> | %s\"Select\" \"!\"
+            <column> ::= %i\"É\" | \"id\"",
+        )
+        .unwrap();
+        for input in &["select é", "SELECT É", "sElEcT id", "Select!"] {
+            assert!(
+                Chart::parse(&grammar, "query", input).accepts(),
+                "{}",
+                input
+            );
+        }
+        for input in &["select ID", "select!", "SELECT!", "selec é"] {
+            assert!(
+                !Chart::parse(&grammar, "query", input).accepts(),
+                "{}",
+                input
+            );
+        }
+    }
+
+    #[test]
+    fn recognizes_bytes() {
+        let grammar = Grammar::from_str(
+            "<file> ::= %x89.50.4E.47 <chunks>
+            <chunks> ::= <chunk> <chunks> | \"\"
+            <chunk> ::= [a-z] %xFF | \"é\" %xC3",
+        )
+        .unwrap();
+        assert!(Chart::parse_bytes(&grammar, "file", b"\x89PNG").accepts());
+        assert!(Chart::parse_bytes(&grammar, "file", b"\x89PNGa\xFFz\xFF").accepts());
+        assert!(Chart::parse_bytes(&grammar, "file", b"\x89PNG\xC3\xA9\xC3").accepts());
+        assert!(!Chart::parse_bytes(&grammar, "file", b"\x89PNGa").accepts());
+        assert!(!Chart::parse_bytes(&grammar, "file", b"\x89PNG\xFF\xFF").accepts());
+
+        let grammar = Grammar::from_str("<text> ::= %x61 %xC3.A9 | %i\"b\" [à-é]").unwrap();
+        let text = Term::from_str("<text>").unwrap();
+        assert_eq!(grammar.count_parses(&text, "aé"), 1);
+        assert_eq!(grammar.count_parses(&text, "Bé"), 1);
+        assert_eq!(grammar.count_parses_bytes(&text, b"B\xC3"), 0);
+        let terminal = Term::from_str("\"a\"").unwrap();
+        assert_eq!(grammar.count_parses_bytes(&terminal, b"a"), 0);
+    }
+
+    #[cfg(feature = "regex")]
+    #[test]
+    fn recognizes_longest_regex_matches() {
+        let grammar = Grammar::from_str(
+            "<assign> ::= <ident> #\"\\s*=\\s*\"# <value>
+            <ident> ::= #\"[a-z_][a-z0-9_]*\"#
+            <value> ::= #\"[0-9]+\"# | <ident> | #\"\\bé+\"#",
+        )
+        .unwrap();
+        let assign = Nonterminal::from("assign");
+        assert_eq!(grammar.count_parses(&assign, "x = 42"), 1);
+        assert_eq!(grammar.count_parses(&assign, "total_2=x"), 1);
+        assert_eq!(grammar.count_parses(&assign, "a=éé"), 1);
+        assert_eq!(grammar.count_parses(&assign, "2x = 42"), 0);
+        assert_eq!(grammar.count_parses(&assign, "x = 4 2"), 0);
+
+        // only the longest match is tried, never a shorter one
+        let grammar = Grammar::from_str("<s> ::= #\"a+\"# \"a\" | #\"b*\"# \"c\"").unwrap();
+        let s = Nonterminal::from("s");
+        assert_eq!(grammar.count_parses(&s, "aa"), 0);
+        assert_eq!(grammar.count_parses(&s, "c"), 1);
+        assert_eq!(grammar.count_parses(&s, "bbc"), 1);
+        let chart = Chart::parse(&grammar, "s", "bbc");
+        let tree = ParseCounter::new(&chart)
+            .tree(chart.start().unwrap(), 0, 3)
+            .unwrap();
+        assert_eq!(tree.flatten(), "bbc");
+        assert_eq!(grammar.validate_tree(&tree), Ok(()));
+
+        let error = grammar.generate().unwrap_err().to_string();
+        assert!(
+            error.starts_with("Can't generate from the regex #\""),
+            "{}",
+            error
+        );
+    }
+
+    #[test]
+    fn counts_unambiguous_parses() {
+        let dna = "<dna> ::= <base> | <base> <dna>
+            <base> ::= \"A\" | \"C\" | \"G\" | \"T\"";
+        assert_eq!(count(dna, "dna", "GATTACA"), 1);
+        assert_eq!(count(dna, "dna", "GATTACAX"), 0);
+        assert_eq!(count(dna, "undefined", "A"), 0);
+    }
+
+    #[test]
+    fn counts_ambiguous_parses() {
+        // parses of a sum of n ones are counted by the catalan numbers
+        let sum = "<sum> ::= <sum> \"+\" <sum> | \"1\"";
+        let catalan = [1, 1, 2, 5, 14, 42, 132, 429, 1430];
+        for (n, &expected) in catalan.iter().enumerate() {
+            let input = vec!["1"; n + 1].join("+");
+            assert_eq!(count(sum, "sum", &input), expected, "{}", input);
+        }
+
+        let nullable = "<s> ::= <a> <a>
+            <a> ::= \"\" | \"x\"";
+        assert_eq!(count(nullable, "s", "x"), 2);
+        assert_eq!(count(nullable, "s", ""), 1);
+    }
+
+    #[test]
+    fn counts_productions_sharing_lhs() {
+        let grammar = "<s> ::= \"a\"
+            <s> ::= \"a\" | \"b\"";
+        assert_eq!(count(grammar, "s", "a"), 2);
+        assert_eq!(count(grammar, "s", "b"), 1);
+    }
+
+    #[test]
+    fn builds_trees() {
+        let grammar = Grammar::from_str(
+            "<sum> ::= <sum> \"+\" <sum> | <digit> | <sum>
+            <digit> ::= [0-9] | <blank> <digit>
+            <blank> ::= \"\" | <undefined>",
+        )
+        .unwrap();
+        for input in &["1", "1+2", "1+2+3", "<undefined>4+5"] {
+            let chart = Chart::parse(&grammar, "sum", input);
+            let tree = ParseCounter::new(&chart).tree(chart.start().unwrap(), 0, chart.len());
+            let tree = tree.expect("input should parse");
+            assert_eq!(tree.flatten(), *input);
+            assert!(grammar.validate_tree(&tree).is_ok(), "{:?}", tree);
+        }
+
+        let chart = Chart::parse(&grammar, "sum", "1+");
+        assert_eq!(
+            ParseCounter::new(&chart).tree(chart.start().unwrap(), 0, chart.len()),
+            None
+        );
+    }
+
+    #[test]
+    fn parses_repetitions() {
+        let copies = "<s> ::= 2*( \"b\" | \"bb\" )";
+        assert_eq!(count(copies, "s", "b"), 0);
+        assert_eq!(count(copies, "s", "bb"), 1);
+        assert_eq!(count(copies, "s", "bbb"), 3);
+
+        // each copy is one more rule, however high the maximum
+        let grammar = Grammar::from_str(
+            "<s> ::= 0*200\"a\" 1*2<t>
+            <t> ::= \"t\"",
+        )
+        .unwrap();
+        let s = Nonterminal::from("s");
+        assert_eq!(grammar.count_parses(&s, &("a".repeat(150) + "tt")), 1);
+        assert_eq!(grammar.count_parses(&s, &("a".repeat(201) + "t")), 0);
+        assert_eq!(grammar.count_parses(&s, "ttt"), 0);
+
+        let chart = Chart::parse(&grammar, "s", "aatt");
+        let tree = ParseCounter::new(&chart).tree(chart.start().unwrap(), 0, chart.len());
+        let tree = tree.expect("input should parse");
+        assert_eq!(tree.flatten(), "aatt");
+        assert_eq!(grammar.validate_tree(&tree), Ok(()));
+        let copies = tree
+            .children_iter()
+            .map(|child| match *child {
+                ParseTreeNode::Repeat(ref copies) => copies.len(),
+                _ => 0,
+            })
+            .collect::<Vec<_>>();
+        assert_eq!(copies, vec![2, 2]);
+        assert_eq!(
+            grammar
+                .leftmost_derivation(&s, "at")
+                .map(|steps| steps.iter().map(Expression::to_string).collect::<Vec<_>>()),
+            Some(vec![
+                String::from("<s>"),
+                String::from("\"a\" <t>"),
+                String::from("\"a\" \"t\""),
+            ])
+        );
+    }
+
+    #[test]
+    fn derives_leftmost() {
+        let grammar = Grammar::from_str(
+            "<s> ::= <a> <b> | <undefined> <b>
+            <a> ::= %i\"x\" <a> | \"\"
+            <b> ::= [0-9] | ε",
+        )
+        .unwrap();
+        let s = Term::from_str("<s>").unwrap();
+        let steps = |input: &str| {
+            grammar
+                .leftmost_derivation(&s, input)
+                .map(|steps| steps.iter().map(Expression::to_string).collect::<Vec<_>>())
+        };
+
+        assert_eq!(
+            steps("X1"),
+            Some(vec![
+                String::from("<s>"),
+                String::from("<a> <b>"),
+                String::from("\"X\" <a> <b>"),
+                String::from("\"X\" \"\" <b>"),
+                String::from("\"X\" \"\" \"1\""),
+            ])
+        );
+        assert_eq!(
+            steps("<undefined>"),
+            Some(vec![
+                String::from("<s>"),
+                String::from("<undefined> <b>"),
+                String::from("<undefined>"),
+            ])
+        );
+        assert_eq!(steps("1x"), None);
+        assert_eq!(
+            grammar.leftmost_derivation(&Term::from_str("\"x\"").unwrap(), "x"),
+            None
+        );
+    }
+
+    #[test]
+    fn trees_record_alternatives() {
+        fn alternatives(tree: &ParseTree, found: &mut Vec<(String, String, Option<usize>)>) {
+            found.push((
+                tree.lhs.to_string(),
+                tree.flatten(),
+                tree.alternative_index(),
+            ));
+            for child in tree.children_iter() {
+                if let ParseTreeNode::Nonterminal(ref child) = *child {
+                    alternatives(child, found);
+                }
+            }
+        }
+
+        let grammar = Grammar::from_str(
+            "<list> ::= <digit> | <digit> \",\" <list>
+            <digit> ::= \"0\"
+            <digit> ::= \"1\" | \"2\"",
+        )
+        .unwrap();
+        let chart = Chart::parse(&grammar, "list", "0,2");
+        let tree = ParseCounter::new(&chart)
+            .tree(chart.start().unwrap(), 0, chart.len())
+            .unwrap();
+        let mut found = vec![];
+        alternatives(&tree, &mut found);
+        // indices are within the production an alternative is from
+        let expected = [
+            ("<list>", "0,2", Some(1)),
+            ("<digit>", "0", Some(0)),
+            ("<list>", "2", Some(0)),
+            ("<digit>", "2", Some(1)),
+        ];
+        let expected = expected
+            .iter()
+            .map(|&(lhs, text, index)| (String::from(lhs), String::from(text), index))
+            .collect::<Vec<_>>();
+        assert_eq!(found, expected);
+
+        let tree = ParseTree::from_parts(tree.lhs.clone(), tree.expression.clone(), vec![]);
+        assert_eq!(tree.alternative_index(), None);
+    }
+
+    #[test]
+    fn saturates_on_cycles() {
+        assert_eq!(count("<a> ::= <a> | \"a\"", "a", "a"), usize::MAX);
+        assert_eq!(count("<a> ::= <a> | \"a\"", "a", "b"), 0);
+        let nullable_cycle = "<a> ::= <b> <a> | \"a\"
+            <b> ::= \"\"";
+        assert_eq!(count(nullable_cycle, "a", "a"), usize::MAX);
+        // a cycle which can't complete a tree doesn't make more trees
+        let dead_cycle = "<a> ::= <a> \"x\" | \"a\"";
+        assert_eq!(count(dead_cycle, "a", "a"), 1);
+        assert_eq!(count(dead_cycle, "a", "axx"), 1);
+
+        // too many trees to count
+        let sum = "<sum> ::= <sum> <sum> | \"1\"";
+        assert_eq!(count(sum, "sum", &"1".repeat(60)), usize::MAX);
+    }
+
+    #[test]
+    fn parses_tokens() {
+        let grammar = Grammar::from_str(
+            "<s> ::= \"if\" <s> | \"x\" | [0-9] | %i\"yes\" | <undefined> | \"\" \"z\"",
+        )
+        .unwrap();
+        let parses = |tokens: &[&str]| grammar.parse_earley(tokens, "s").recognized();
+        assert!(parses(&["if", "if", "x"]));
+        assert!(parses(&["if", "7"]));
+        assert!(parses(&["YeS"]));
+        assert!(parses(&["if", "<undefined>"]));
+        assert!(parses(&["z"]));
+        // terms match whole tokens
+        assert!(!parses(&["i", "f", "x"]));
+        assert!(!parses(&["ifx"]));
+        assert!(!parses(&["if", "77"]));
+        assert!(!parses(&[]));
+        assert!(!grammar.parse_earley(&["x"], "undefined").recognized());
+
+        let tokens = ["if", "x"];
+        let chart = grammar.parse_earley(&tokens, "s");
+        let s = Nonterminal::from("s");
+        let x = Expression::from_str("\"x\"").unwrap();
+        let items = chart.items_iter().collect::<Vec<_>>();
+        assert!(items.contains(&EarleyItem {
+            lhs: &Term::from(s),
+            expression: &x,
+            dot: 1,
+            start: 1,
+            end: 2,
+        }));
+        assert!(items
+            .iter()
+            .all(|item| item.start <= item.end && item.end <= 2));
+        let trees = chart.extract_trees();
+        assert_eq!(trees.len(), 1);
+        assert_eq!(trees[0].flatten(), "ifx");
+        assert_eq!(trees[0].alternative_index(), Some(0));
+        assert_eq!(grammar.validate_tree(&trees[0]), Ok(()));
+    }
+
+    #[test]
+    fn extracts_every_tree() {
+        let grammar = Grammar::from_str("<sum> ::= <sum> \"+\" <sum> | \"1\"").unwrap();
+        let sum = Nonterminal::from("sum");
+        for input in &["1", "1+1", "1+1+1", "1+1+1+1"] {
+            let tokens = grammar.tokenize(input).unwrap();
+            let tokens = tokens.iter().map(String::as_str).collect::<Vec<_>>();
+            let trees = grammar.parse_earley(&tokens, "sum").extract_trees();
+            assert_eq!(trees.len(), grammar.count_parses(&sum, input));
+            let distinct = trees.iter().collect::<HashSet<_>>();
+            assert_eq!(distinct.len(), trees.len());
+            for tree in &trees {
+                assert_eq!(tree.flatten(), *input);
+                assert_eq!(grammar.validate_tree(tree), Ok(()));
+            }
+        }
+        assert!(grammar
+            .parse_earley(&["1", "+"], "sum")
+            .extract_trees()
+            .is_empty());
+
+        // a cycle through the same span is cut
+        let grammar = Grammar::from_str(
+            "<a> ::= <a> | \"a\" | <b>
+            <b> ::= \"\" \"a\"",
+        )
+        .unwrap();
+        let trees = grammar.parse_earley(&["a"], "a").extract_trees();
+        assert_eq!(trees.len(), 2);
+    }
+
+    #[cfg(feature = "regex")]
+    #[test]
+    fn parses_tokens_of_regexes() {
+        let grammar = Grammar::from_str("<s> ::= #\"[a-z]+\"# \"=\" #\"[0-9]+\"#").unwrap();
+        assert!(grammar.parse_earley(&["abc", "=", "12"], "s").recognized());
+        assert!(!grammar.parse_earley(&["abc", "=", "12a"], "s").recognized());
+        assert!(!grammar.parse_earley(&["abc=12"], "s").recognized());
+    }
+}
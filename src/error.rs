@@ -1,7 +1,10 @@
+use generation::LimitError;
 use nom::{error::ErrorKind, Err, Needed};
 use std::error;
 use std::fmt;
+use std::io;
 use std::str;
+use std::sync::Arc;
 
 #[derive(PartialEq, Debug, Clone)]
 pub enum Error {
@@ -9,6 +12,50 @@ pub enum Error {
     ParseIncomplete(String),
     GenerateError(String),
     RecursionLimit(String),
+    NoExpansionPossible(String),
+    InvalidTree(String),
+    TokenizeError(String),
+    InfiniteLanguage(String),
+    NotRightLinear(String),
+    IndexOutOfBounds(String),
+    LimitError(LimitError),
+    RetriesExhausted(usize, Box<Error>),
+    IoError(IoError),
+}
+
+/// An `io::Error`, shared so that `Error` may be cloned, and compared by its
+/// kind and message
+#[derive(Clone, Debug)]
+pub struct IoError(Arc<io::Error>);
+
+impl IoError {
+    /// Get the kind of the underlying `io::Error`
+    pub fn kind(&self) -> io::ErrorKind {
+        self.0.kind()
+    }
+
+    /// Get the underlying `io::Error`
+    pub fn get_ref(&self) -> &io::Error {
+        &self.0
+    }
+}
+
+impl PartialEq for IoError {
+    fn eq(&self, other: &IoError) -> bool {
+        self.kind() == other.kind() && self.0.to_string() == other.0.to_string()
+    }
+}
+
+impl fmt::Display for IoError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "{}", self.0)
+    }
+}
+
+impl From<io::Error> for Error {
+    fn from(err: io::Error) -> Self {
+        Error::IoError(IoError(Arc::new(err)))
+    }
 }
 
 impl fmt::Display for Error {
@@ -18,6 +65,17 @@ impl fmt::Display for Error {
             Error::ParseIncomplete(ref s) => write!(f, "{}", s),
             Error::GenerateError(ref s) => write!(f, "{}", s),
             Error::RecursionLimit(ref s) => write!(f, "{}", s),
+            Error::NoExpansionPossible(ref s) => write!(f, "{}", s),
+            Error::InvalidTree(ref s) => write!(f, "{}", s),
+            Error::TokenizeError(ref s) => write!(f, "{}", s),
+            Error::InfiniteLanguage(ref s) => write!(f, "{}", s),
+            Error::NotRightLinear(ref s) => write!(f, "{}", s),
+            Error::IndexOutOfBounds(ref s) => write!(f, "{}", s),
+            Error::LimitError(ref e) => write!(f, "{}", e),
+            Error::RetriesExhausted(attempts, ref e) => {
+                write!(f, "Failed after {} attempts: {}", attempts, e)
+            }
+            Error::IoError(ref e) => write!(f, "{}", e),
         }
     }
 }
@@ -64,6 +122,7 @@ impl From<Needed> for Error {
 mod tests {
     use error::Error;
     use nom::Err;
+    use std::io;
 
     named!(
         give_error_kind,
@@ -139,6 +198,73 @@ mod tests {
         }
     }
 
+    #[test]
+    fn uses_error_no_expansion_possible() {
+        let bnf_error = Error::NoExpansionPossible(String::from("nothing to expand!"));
+        match bnf_error {
+            Error::NoExpansionPossible(_) => (),
+            e => panic!("should match on no expansion possible: {:?}", e),
+        }
+    }
+
+    #[test]
+    fn uses_error_invalid_tree() {
+        let bnf_error = Error::InvalidTree(String::from("tree doesn't match!"));
+        match bnf_error {
+            Error::InvalidTree(_) => (),
+            e => panic!("should match on invalid tree: {:?}", e),
+        }
+    }
+
+    #[test]
+    fn uses_error_tokenize() {
+        let bnf_error = Error::TokenizeError(String::from("no token matches!"));
+        match bnf_error {
+            Error::TokenizeError(_) => (),
+            e => panic!("should match on tokenize error: {:?}", e),
+        }
+    }
+
+    #[test]
+    fn uses_error_infinite_language() {
+        let bnf_error = Error::InfiniteLanguage(String::from("too many strings!"));
+        match bnf_error {
+            Error::InfiniteLanguage(_) => (),
+            e => panic!("should match on infinite language: {:?}", e),
+        }
+    }
+
+    #[test]
+    fn uses_error_not_right_linear() {
+        let bnf_error = Error::NotRightLinear(String::from("not a regular grammar!"));
+        match bnf_error {
+            Error::NotRightLinear(_) => (),
+            e => panic!("should match on not right linear: {:?}", e),
+        }
+    }
+
+    #[test]
+    fn uses_error_io() {
+        let io_error = || io::Error::new(io::ErrorKind::WriteZero, "sink is full!");
+        let bnf_error = Error::from(io_error());
+        match bnf_error {
+            Error::IoError(ref e) => assert_eq!(e.kind(), io::ErrorKind::WriteZero),
+            ref e => panic!("should match on io error: {:?}", e),
+        }
+        assert_eq!(bnf_error.to_string(), "sink is full!");
+        assert_eq!(bnf_error.clone(), Error::from(io_error()));
+    }
+
+    #[test]
+    fn uses_error_retries_exhausted() {
+        let last = Error::RecursionLimit(String::from("recursion limit reached!"));
+        let bnf_error = Error::RetriesExhausted(3, Box::new(last));
+        assert_eq!(
+            bnf_error.to_string(),
+            "Failed after 3 attempts: recursion limit reached!"
+        );
+    }
+
     #[test]
     fn test_error_display() {
         let parse_error = Error::ParseError(String::from("syntax error!"));
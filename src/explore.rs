@@ -0,0 +1,511 @@
+use expression::Expression;
+use grammar::Grammar;
+use production::Production;
+use term::{CharClass, Nonterminal, Term};
+
+/// A step of a depth first exploration of a grammar's derivations
+#[derive(Clone, Debug, Eq, PartialEq)]
+pub enum ExploreEvent {
+    /// `nonterminal` was expanded to its alternative `expression`, found at
+    /// index `alternative` of its production
+    Expand {
+        nonterminal: Term,
+        alternative: usize,
+        expression: Expression,
+        depth: usize,
+    },
+    /// `repetition` was expanded to `count` copies of its inner expression
+    Repeat {
+        repetition: Term,
+        count: usize,
+        depth: usize,
+    },
+    /// `terminal` was reached as the next part of the derivation
+    Terminal { terminal: String, depth: usize },
+    /// Any character of `class` was reached as the next part of the derivation
+    CharClass { class: CharClass, depth: usize },
+    /// Any text `pattern` matches was reached as the next part of the
+    /// derivation
+    Regex { pattern: String, depth: usize },
+    /// `nonterminal` has no production to expand it with
+    Undefined { nonterminal: Term, depth: usize },
+    /// Expanding `nonterminal` would exceed the maximum depth, so the branch
+    /// was abandoned
+    DepthLimit { nonterminal: Term, depth: usize },
+    /// Every term of the current derivation has been reached
+    Complete,
+}
+
+/// What a choice is among, the alternatives of a production or the counts
+/// of copies of a repetition
+#[derive(Clone, Copy)]
+enum Options<'a> {
+    Alternatives(&'a Production),
+    Copies(&'a Term),
+}
+
+/// Point to resume exploring from, with the next alternative of a production
+/// or count of a repetition
+struct Choice<'a> {
+    pending: Vec<(&'a Term, usize)>,
+    options: Options<'a>,
+    next: usize,
+    depth: usize,
+}
+
+/// Depth first explorer of a grammar's leftmost derivations.
+///
+/// Alternatives are tried in order, and so are the counts of copies of a
+/// repetition, from its `min` up. Once a derivation is `Complete`, or a
+/// branch hits the maximum depth, exploration backtracks to the most recent
+/// nonterminal or repetition with untried alternatives or counts. Callers
+/// may also `backtrack` or `skip_branch` themselves between events.
+pub struct Explorer<'a> {
+    grammar: &'a Grammar,
+    max_depth: Option<usize>,
+    pending: Vec<(&'a Term, usize)>,
+    choices: Vec<Choice<'a>>,
+    queued: Option<ExploreEvent>,
+    last_expanded: Option<usize>,
+    started: bool,
+    done: bool,
+    start: Term,
+}
+
+impl<'a> Explorer<'a> {
+    fn new(grammar: &'a Grammar, start: Nonterminal) -> Explorer<'a> {
+        Explorer {
+            grammar,
+            max_depth: None,
+            pending: vec![],
+            choices: vec![],
+            queued: None,
+            last_expanded: None,
+            started: false,
+            done: false,
+            start: Term::from(start),
+        }
+    }
+
+    /// Abandon branches which would expand nonterminals deeper than
+    /// `max_depth`, and try repetitions without a `max` with at most
+    /// `max_depth` more copies than their `min`
+    pub fn max_depth(mut self, max_depth: usize) -> Explorer<'a> {
+        self.max_depth = Some(max_depth);
+        self
+    }
+
+    /// Abandon the current branch, resuming from the most recent nonterminal
+    /// or repetition with untried alternatives or counts.
+    ///
+    /// Returns `false` when there is nothing left to explore.
+    pub fn backtrack(&mut self) -> bool {
+        self.queued = None;
+        while let Some(mut choice) = self.choices.pop() {
+            let (event, more) = match choice.options {
+                Options::Alternatives(production) => {
+                    let expression = match production.rhs_iter().nth(choice.next) {
+                        Some(expression) => expression,
+                        None => continue,
+                    };
+                    self.pending = choice.pending.clone();
+                    self.push_expression(expression, choice.depth);
+                    let event = ExploreEvent::Expand {
+                        nonterminal: production.lhs().clone(),
+                        alternative: choice.next,
+                        expression: expression.clone(),
+                        depth: choice.depth,
+                    };
+                    (event, choice.next + 1 < production.len())
+                }
+                Options::Copies(repetition) => {
+                    self.pending = choice.pending.clone();
+                    self.push_copies(repetition, choice.next, choice.depth);
+                    let event = ExploreEvent::Repeat {
+                        repetition: repetition.clone(),
+                        count: choice.next,
+                        depth: choice.depth,
+                    };
+                    (event, self.allows(repetition, choice.next + 1))
+                }
+            };
+            self.queued = Some(event);
+            self.last_expanded = Some(choice.depth);
+
+            choice.next += 1;
+            if more {
+                self.choices.push(choice);
+            }
+            return true;
+        }
+        self.done = true;
+        false
+    }
+
+    /// Skip the remaining terms of the most recently expanded nonterminal or
+    /// repetition, treating it as fully derived
+    pub fn skip_branch(&mut self) {
+        if let Some(depth) = self.last_expanded {
+            while self.pending.last().is_some_and(|&(_, d)| d > depth) {
+                self.pending.pop();
+            }
+        }
+    }
+
+    fn push_expression(&mut self, expression: &'a Expression, depth: usize) {
+        let terms = expression.terms_iter().collect::<Vec<_>>();
+        self.pending
+            .extend(terms.into_iter().rev().map(|term| (term, depth + 1)));
+    }
+
+    fn push_copies(&mut self, repetition: &'a Term, count: usize, depth: usize) {
+        if let Term::Repeat { ref inner, .. } = *repetition {
+            for _ in 0..count {
+                self.push_expression(inner, depth);
+            }
+        }
+    }
+
+    /// Whether `count` copies of `repetition` may be tried
+    fn allows(&self, repetition: &Term, count: usize) -> bool {
+        match *repetition {
+            Term::Repeat { max: Some(max), .. } => count <= max,
+            Term::Repeat { min, .. } => self
+                .max_depth
+                .is_none_or(|max_depth| count - min <= max_depth),
+            _ => false,
+        }
+    }
+
+    fn repeat(&mut self, repetition: &'a Term, depth: usize) -> ExploreEvent {
+        let min = match *repetition {
+            Term::Repeat { min, .. } => min,
+            _ => 0,
+        };
+        if self.allows(repetition, min + 1) {
+            self.choices.push(Choice {
+                pending: self.pending.clone(),
+                options: Options::Copies(repetition),
+                next: min + 1,
+                depth,
+            });
+        }
+        self.last_expanded = Some(depth);
+        self.push_copies(repetition, min, depth);
+        ExploreEvent::Repeat {
+            repetition: repetition.clone(),
+            count: min,
+            depth,
+        }
+    }
+
+    fn expand(&mut self, production: &'a Production, depth: usize) -> ExploreEvent {
+        if self.max_depth.is_some_and(|max_depth| depth > max_depth) {
+            // the resumed branch is queued behind this event
+            self.backtrack();
+            return ExploreEvent::DepthLimit {
+                nonterminal: production.lhs().clone(),
+                depth,
+            };
+        }
+
+        if production.len() > 1 {
+            self.choices.push(Choice {
+                pending: self.pending.clone(),
+                options: Options::Alternatives(production),
+                next: 1,
+                depth,
+            });
+        }
+        self.last_expanded = Some(depth);
+
+        match production.rhs_iter().next() {
+            Some(expression) => {
+                self.push_expression(expression, depth);
+                ExploreEvent::Expand {
+                    nonterminal: production.lhs().clone(),
+                    alternative: 0,
+                    expression: expression.clone(),
+                    depth,
+                }
+            }
+            None => ExploreEvent::Undefined {
+                nonterminal: production.lhs().clone(),
+                depth,
+            },
+        }
+    }
+
+    fn find_production(&self, term: &Term) -> Option<&'a Production> {
+        self.grammar
+            .productions_iter()
+            .find(|prod| *prod.lhs() == *term)
+    }
+}
+
+impl<'a> Iterator for Explorer<'a> {
+    type Item = ExploreEvent;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        if let Some(event) = self.queued.take() {
+            return Some(event);
+        }
+        if self.done {
+            return None;
+        }
+
+        if !self.started {
+            self.started = true;
+            return match self.find_production(&self.start) {
+                Some(production) => Some(self.expand(production, 0)),
+                None => {
+                    self.done = true;
+                    Some(ExploreEvent::Undefined {
+                        nonterminal: self.start.clone(),
+                        depth: 0,
+                    })
+                }
+            };
+        }
+
+        match self.pending.pop() {
+            Some((term, depth)) => match *term {
+                Term::Terminal(ref t) | Term::CaselessTerminal(ref t) => {
+                    Some(ExploreEvent::Terminal {
+                        terminal: t.clone(),
+                        depth,
+                    })
+                }
+                Term::TerminalBytes(ref bytes) => Some(ExploreEvent::Terminal {
+                    terminal: String::from_utf8_lossy(bytes).into_owned(),
+                    depth,
+                }),
+                Term::CharClass(ref class) => Some(ExploreEvent::CharClass {
+                    class: class.clone(),
+                    depth,
+                }),
+                Term::Regex(ref pattern) => Some(ExploreEvent::Regex {
+                    pattern: pattern.clone(),
+                    depth,
+                }),
+                Term::Nonterminal(_) => match self.find_production(term) {
+                    Some(production) => Some(self.expand(production, depth)),
+                    None => Some(ExploreEvent::Undefined {
+                        nonterminal: term.clone(),
+                        depth,
+                    }),
+                },
+                Term::Repeat { .. } => Some(self.repeat(term, depth)),
+            },
+            None => {
+                self.backtrack();
+                Some(ExploreEvent::Complete)
+            }
+        }
+    }
+}
+
+impl Grammar {
+    /// Explore the leftmost derivations of nonterminal `start`, depth first.
+    ///
+    /// The returned `Explorer` yields an `ExploreEvent` for every step, and
+    /// may be steered between steps with `Explorer::backtrack` and
+    /// `Explorer::skip_branch`. Recursive grammars have infinitely many derivations,
+    /// which `Explorer::max_depth` bounds.
+    ///
+    /// # Example
+    ///
+    /// ```rust
+    /// extern crate bnf;
+    /// use bnf::{ExploreEvent, Grammar};
+    ///
+    /// fn main() {
+    ///     let grammar = Grammar::from_str("<bit> ::= \"0\" | \"1\"").unwrap();
+    ///     let terminals = grammar
+    ///         .explore("bit")
+    ///         .filter_map(|event| match event {
+    ///             ExploreEvent::Terminal { terminal, .. } => Some(terminal),
+    ///             _ => None,
+    ///         })
+    ///         .collect::<Vec<_>>();
+    ///
+    ///     assert_eq!(terminals, vec!["0", "1"]);
+    /// }
+    /// ```
+    pub fn explore(&self, start: impl Into<Nonterminal>) -> Explorer<'_> {
+        Explorer::new(self, start.into())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn nonterminal(name: &str) -> Term {
+        Term::Nonterminal(String::from(name))
+    }
+
+    fn terminals(explorer: Explorer) -> Vec<String> {
+        explorer
+            .filter_map(|event| match event {
+                ExploreEvent::Terminal { terminal, .. } => Some(terminal),
+                _ => None,
+            })
+            .collect()
+    }
+
+    #[test]
+    fn explores_in_depth_first_order() {
+        let grammar = Grammar::from_str(
+            "<pair> ::= <bit> <bit>
+            <bit> ::= \"0\" | \"1\"",
+        )
+        .unwrap();
+        let events = grammar.explore("pair").collect::<Vec<_>>();
+
+        assert_eq!(
+            events[0],
+            ExploreEvent::Expand {
+                nonterminal: nonterminal("pair"),
+                alternative: 0,
+                expression: Expression::from_str("<bit> <bit>").unwrap(),
+                depth: 0,
+            }
+        );
+        assert_eq!(
+            events[1],
+            ExploreEvent::Expand {
+                nonterminal: nonterminal("bit"),
+                alternative: 0,
+                expression: Expression::from_str("\"0\"").unwrap(),
+                depth: 1,
+            }
+        );
+        assert_eq!(
+            events[2],
+            ExploreEvent::Terminal {
+                terminal: String::from("0"),
+                depth: 2,
+            }
+        );
+        let completions = events
+            .iter()
+            .filter(|&event| *event == ExploreEvent::Complete)
+            .count();
+        assert_eq!(completions, 4);
+    }
+
+    #[test]
+    fn bounds_recursion_with_max_depth() {
+        let grammar = Grammar::from_str("<ones> ::= \"1\" <ones> | \"\"").unwrap();
+        let events = grammar.explore("ones").max_depth(3).collect::<Vec<_>>();
+
+        let limits = events
+            .iter()
+            .filter(|event| matches!(**event, ExploreEvent::DepthLimit { .. }))
+            .count();
+        assert_eq!(limits, 1);
+        let completions = events
+            .iter()
+            .filter(|&event| *event == ExploreEvent::Complete)
+            .count();
+        assert_eq!(completions, 4);
+    }
+
+    #[test]
+    fn backtracks_on_request() {
+        let grammar = Grammar::from_str("<bit> ::= \"0\" | \"1\"").unwrap();
+        let mut explorer = grammar.explore("bit");
+
+        assert_eq!(
+            explorer.next(),
+            Some(ExploreEvent::Expand {
+                nonterminal: nonterminal("bit"),
+                alternative: 0,
+                expression: Expression::from_str("\"0\"").unwrap(),
+                depth: 0,
+            })
+        );
+        assert!(explorer.backtrack());
+        match explorer.next() {
+            Some(ExploreEvent::Expand { alternative, .. }) => assert_eq!(alternative, 1),
+            e => panic!("should expand the next alternative: {:?}", e),
+        }
+        assert!(!explorer.backtrack());
+        assert_eq!(explorer.next(), None);
+    }
+
+    #[test]
+    fn skips_branches() {
+        let grammar = Grammar::from_str(
+            "<pair> ::= <word> <word>
+            <word> ::= \"a\" \"b\" \"c\"",
+        )
+        .unwrap();
+        let mut explorer = grammar.explore("pair");
+        explorer.next();
+        explorer.next();
+        explorer.skip_branch();
+
+        assert_eq!(
+            explorer.next(),
+            Some(ExploreEvent::Expand {
+                nonterminal: nonterminal("word"),
+                alternative: 0,
+                expression: Expression::from_str("\"a\" \"b\" \"c\"").unwrap(),
+                depth: 1,
+            })
+        );
+        assert_eq!(terminals(explorer), vec!["a", "b", "c"]);
+    }
+
+    #[test]
+    fn undefined_nonterminals() {
+        let grammar = Grammar::from_str("<start> ::= <undefined>").unwrap();
+        let events = grammar.explore("start").collect::<Vec<_>>();
+        assert_eq!(
+            events[1],
+            ExploreEvent::Undefined {
+                nonterminal: nonterminal("undefined"),
+                depth: 1,
+            }
+        );
+        assert_eq!(events.len(), 3);
+
+        let events = grammar.explore("undefined").collect::<Vec<_>>();
+        assert_eq!(
+            events,
+            vec![ExploreEvent::Undefined {
+                nonterminal: nonterminal("undefined"),
+                depth: 0,
+            }]
+        );
+    }
+
+    #[test]
+    fn explores_counts_of_repetitions() {
+        let grammar = Grammar::from_str("<s> ::= 1*2\"a\" \"b\"").unwrap();
+        let counts = grammar
+            .explore("s")
+            .filter_map(|event| match event {
+                ExploreEvent::Repeat { count, .. } => Some(count),
+                _ => None,
+            })
+            .collect::<Vec<_>>();
+        assert_eq!(counts, vec![1, 2]);
+        assert_eq!(
+            terminals(grammar.explore("s")),
+            vec!["a", "b", "a", "a", "b"]
+        );
+
+        // counts past the minimum of an unbounded repetition are bounded
+        // by the maximum depth
+        let grammar = Grammar::from_str("<s> ::= 1*\"a\"").unwrap();
+        let events = grammar.explore("s").max_depth(2).collect::<Vec<_>>();
+        let completions = events
+            .iter()
+            .filter(|&event| *event == ExploreEvent::Complete)
+            .count();
+        assert_eq!(completions, 3);
+    }
+}
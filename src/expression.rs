@@ -1,32 +1,48 @@
 use error::Error;
 use parsers;
 use std::fmt;
+use std::iter;
+use std::mem;
+use std::ops::Index;
 use std::slice;
 use std::str::FromStr;
 use term::Term;
 
-/// An Expression is comprised of any number of Terms
-#[derive(Clone, Debug, Eq, Hash, PartialEq)]
+/// An Expression is comprised of any number of Terms, and may be weighted
+/// relative to the other alternatives of its production.
+///
+/// Expressions are ordered lexicographically by their terms, a prefix of
+/// another before it, and then by weight, no weight first.
+#[derive(Clone, Debug, Eq, Hash, Ord, PartialEq, PartialOrd)]
 pub struct Expression {
     terms: Vec<Term>,
+    weight: Option<u32>,
 }
 
 impl Expression {
     /// Construct a new `Expression`
+    #[allow(clippy::new_without_default)]
     pub fn new() -> Expression {
-        Expression { terms: vec![] }
+        Expression {
+            terms: vec![],
+            weight: None,
+        }
     }
 
     /// Construct an `Expression` from `Term`s
     pub fn from_parts(v: Vec<Term>) -> Expression {
-        Expression { terms: v }
+        Expression {
+            terms: v,
+            weight: None,
+        }
     }
 
     // Get `Expression` by parsing a string
+    #[allow(clippy::should_implement_trait)]
     pub fn from_str(s: &str) -> Result<Self, Error> {
         match parsers::expression_complete(s.as_bytes()) {
-            Result::Ok((_, o)) => Ok(o),
-            Result::Err(e) => Err(Error::from(e)),
+            Result::Ok((_, o)) => parsers::reject_groups(Some(&o)).map(|_| o),
+            Result::Err(e) => Err(parsers::parse_error(s.as_bytes(), e)),
         }
     }
 
@@ -35,6 +51,108 @@ impl Expression {
         self.terms.push(term)
     }
 
+    /// Insert `term` at `index`, shifting the terms after it along, or get
+    /// `Error::IndexOutOfBounds` when `index` is greater than the number of
+    /// terms
+    pub fn insert_term(&mut self, index: usize, term: Term) -> Result<(), Error> {
+        if index > self.terms.len() {
+            return Err(self.out_of_bounds(index));
+        }
+        self.terms.insert(index, term);
+        Ok(())
+    }
+
+    /// Insert `term` at `index`, shifting the terms after it along
+    ///
+    /// # Panics
+    ///
+    /// Panics if `index` is greater than the number of terms.
+    #[deprecated(note = "use `Expression::insert_term`, which fails instead of panicking")]
+    pub fn insert(&mut self, index: usize, term: Term) {
+        if let Err(error) = self.insert_term(index, term) {
+            panic!("{}", error)
+        }
+    }
+
+    /// Swap the terms at `i` and `j`, or get `Error::IndexOutOfBounds` when
+    /// either isn't less than the number of terms
+    ///
+    /// # Example
+    ///
+    /// ```
+    /// extern crate bnf;
+    /// use bnf::{Expression, Term};
+    ///
+    /// fn main() {
+    ///     let mut expression = Expression::from_str("<dna> <base>").unwrap();
+    ///     expression.swap_terms(0, 1).unwrap();
+    ///     expression.insert_term(1, Term::from_str("\",\"").unwrap()).unwrap();
+    ///
+    ///     assert_eq!(expression, Expression::from_str("<base> \",\" <dna>").unwrap());
+    ///     assert!(expression.swap_terms(0, 3).is_err());
+    /// }
+    /// ```
+    pub fn swap_terms(&mut self, i: usize, j: usize) -> Result<(), Error> {
+        if let Some(&index) = [i, j].iter().find(|&&index| index >= self.terms.len()) {
+            return Err(self.out_of_bounds(index));
+        }
+        self.terms.swap(i, j);
+        Ok(())
+    }
+
+    /// Swap the terms at `i` and `j`
+    ///
+    /// # Panics
+    ///
+    /// Panics if `i` or `j` is out of bounds.
+    #[deprecated(note = "use `Expression::swap_terms`, which fails instead of panicking")]
+    pub fn swap(&mut self, i: usize, j: usize) {
+        if let Err(error) = self.swap_terms(i, j) {
+            panic!("{}", error)
+        }
+    }
+
+    /// Replace the term at `index` with `new_term`, getting the term it
+    /// replaced, or `Error::IndexOutOfBounds` when `index` isn't less than
+    /// the number of terms
+    ///
+    /// # Example
+    ///
+    /// ```
+    /// extern crate bnf;
+    /// use bnf::{Expression, Term};
+    ///
+    /// fn main() {
+    ///     let mut expression = Expression::from_str("<base> <dna>").unwrap();
+    ///     let old = expression.replace_term_at(0, Term::from_str("\"A\"").unwrap());
+    ///
+    ///     assert_eq!(old, Ok(Term::from_str("<base>").unwrap()));
+    ///     assert_eq!(expression, Expression::from_str("\"A\" <dna>").unwrap());
+    ///     assert!(expression.replace_term_at(2, Term::from_str("<dna>").unwrap()).is_err());
+    /// }
+    /// ```
+    pub fn replace_term_at(&mut self, index: usize, new_term: Term) -> Result<Term, Error> {
+        match self.terms.get_mut(index) {
+            Some(term) => Ok(mem::replace(term, new_term)),
+            None => Err(self.out_of_bounds(index)),
+        }
+    }
+
+    /// Replace the term at `index` with `new_term`, as
+    /// `Expression::replace_term_at` does
+    #[deprecated(note = "renamed to `Expression::replace_term_at`")]
+    pub fn replace_term(&mut self, index: usize, new_term: Term) -> Result<Term, Error> {
+        self.replace_term_at(index, new_term)
+    }
+
+    fn out_of_bounds(&self, index: usize) -> Error {
+        Error::IndexOutOfBounds(format!(
+            "Index {} is out of bounds of an expression of {} terms!",
+            index,
+            self.terms.len()
+        ))
+    }
+
     /// Remove `Term` from `Expression`
     ///
     /// If interested if `Term` was removed, then inspect the returned `Option`.
@@ -66,31 +184,154 @@ impl Expression {
         }
     }
 
+    /// Remove the term at `index`, shifting the terms after it back, or get
+    /// `Error::IndexOutOfBounds` when `index` isn't less than the number of
+    /// terms
+    ///
+    /// # Example
+    ///
+    /// ```
+    /// extern crate bnf;
+    /// use bnf::{Expression, Term};
+    ///
+    /// fn main() {
+    ///     let mut expression = Expression::from_str("<base> \",\" <dna>").unwrap();
+    ///
+    ///     assert_eq!(expression.remove_term_at(1), Ok(Term::from_str("\",\"").unwrap()));
+    ///     assert!(expression.remove_term_at(2).is_err());
+    ///     assert_eq!(expression, Expression::from_str("<base> <dna>").unwrap());
+    /// }
+    /// ```
+    pub fn remove_term_at(&mut self, index: usize) -> Result<Term, Error> {
+        if index < self.terms.len() {
+            Ok(self.terms.remove(index))
+        } else {
+            Err(self.out_of_bounds(index))
+        }
+    }
+
+    /// Whether `term` is one of the terms of `Expression`
+    ///
+    /// # Example
+    ///
+    /// ```
+    /// extern crate bnf;
+    /// use bnf::{Expression, Term};
+    ///
+    /// fn main() {
+    ///     let expression = Expression::from_str("<base> <dna>").unwrap();
+    ///
+    ///     assert!(expression.contains(&Term::from_str("<dna>").unwrap()));
+    ///     assert!(!expression.contains(&Term::from_str("\"dna\"").unwrap()));
+    /// }
+    /// ```
+    pub fn contains(&self, term: &Term) -> bool {
+        self.terms.contains(term)
+    }
+
+    /// Get number of `Term`s within `Expression`
+    pub fn len(&self) -> usize {
+        self.terms.len()
+    }
+
+    /// Whether `Expression` has no `Term`s, deriving only the empty string
+    pub fn is_empty(&self) -> bool {
+        self.terms.is_empty()
+    }
+
+    /// Get the `Term` at `index`, if there is one
+    ///
+    /// # Example
+    ///
+    /// ```
+    /// extern crate bnf;
+    /// use bnf::{Expression, Term};
+    ///
+    /// fn main() {
+    ///     let expression = Expression::from_str("<base> <dna>").unwrap();
+    ///
+    ///     assert_eq!(expression.get(1), Some(&Term::from_str("<dna>").unwrap()));
+    ///     assert_eq!(expression[0], Term::from_str("<base>").unwrap());
+    ///     assert_eq!(expression.get(2), None);
+    /// }
+    /// ```
+    pub fn get(&self, index: usize) -> Option<&Term> {
+        self.terms.get(index)
+    }
+
+    /// Get the `Term` at `index` mutably, if there is one
+    pub fn get_mut(&mut self, index: usize) -> Option<&mut Term> {
+        self.terms.get_mut(index)
+    }
+
+    /// Get the weight for generation, written as `%weight n` after the terms.
+    ///
+    /// An alternative is chosen with probability proportional to its weight,
+    /// alternatives without a weight having a weight of 1.
+    pub fn weight(&self) -> Option<u32> {
+        self.weight
+    }
+
+    /// Set the weight for generation
+    pub fn set_weight(&mut self, weight: Option<u32>) {
+        self.weight = weight
+    }
+
     /// Get iterator of `Term`s within `Expression`
-    pub fn terms_iter(&self) -> Iter {
+    pub fn terms_iter(&self) -> Iter<'_> {
         Iter {
             iterator: self.terms.iter(),
         }
     }
 
     /// Get mutable iterator of `Term`s within `Expression`
-    pub fn terms_iter_mut(&mut self) -> IterMut {
+    pub fn terms_iter_mut(&mut self) -> IterMut<'_> {
         IterMut {
             iterator: self.terms.iter_mut(),
         }
     }
+
+    /// Get the terms of the expression in order, each repetition followed by
+    /// the terms of its inner expression
+    pub(crate) fn nested_terms_iter(&self) -> Box<dyn Iterator<Item = &Term> + '_> {
+        Box::new(self.terms.iter().flat_map(|term| {
+            let nested = match *term {
+                Term::Repeat { ref inner, .. } => Some(inner.nested_terms_iter()),
+                _ => None,
+            };
+            iter::once(term).chain(nested.into_iter().flatten())
+        }))
+    }
+}
+
+impl Index<usize> for Expression {
+    type Output = Term;
+
+    fn index(&self, index: usize) -> &Term {
+        &self.terms[index]
+    }
 }
 
 impl fmt::Display for Expression {
     fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
-        let display = self
-            .terms
-            .iter()
-            .map(|s| s.to_string())
-            .collect::<Vec<_>>()
-            .join(" ");
-
-        write!(f, "{}", display)
+        // an expression of no terms is displayed as the keyword for epsilon
+        let display = match self.terms.len() {
+            0 => String::from(parsers::EPSILON),
+            _ => self
+                .terms
+                .iter()
+                .map(|term| match f.alternate() {
+                    true => format!("{:#}", term),
+                    false => term.to_string(),
+                })
+                .collect::<Vec<_>>()
+                .join(" "),
+        };
+
+        match self.weight {
+            Some(weight) => write!(f, "{} %weight {}", display, weight),
+            None => write!(f, "{}", display),
+        }
     }
 }
 
@@ -132,15 +373,21 @@ mod tests {
 
     use self::quickcheck::{Arbitrary, Gen, QuickCheck, TestResult};
     use super::*;
+    use grammar::Grammar;
 
     impl Arbitrary for Expression {
         fn arbitrary<G: Gen>(g: &mut G) -> Self {
             let mut terms = Vec::<Term>::arbitrary(g);
             // expressions must always have atleast one term
-            if terms.len() < 1 {
+            if terms.is_empty() {
                 terms.push(Term::arbitrary(g));
             }
-            Expression { terms }
+            let weight = if bool::arbitrary(g) {
+                Some(u32::arbitrary(g))
+            } else {
+                None
+            };
+            Expression { terms, weight }
         }
     }
 
@@ -274,6 +521,208 @@ mod tests {
         }
     }
 
+    #[test]
+    fn contains_term() {
+        let expression = Expression::from_str("<base> \"a\" [0-9]").unwrap();
+        assert!(expression.contains(&Term::from_str("<base>").unwrap()));
+        assert!(expression.contains(&Term::from_str("\"a\"").unwrap()));
+        assert!(expression.contains(&Term::from_str("[0-9]").unwrap()));
+        assert!(!expression.contains(&Term::from_str("<a>").unwrap()));
+        assert!(!Expression::new().contains(&Term::from_str("<base>").unwrap()));
+    }
+
+    #[test]
+    fn len_and_is_empty() {
+        let expression = Expression::from_str("<base> \"A\" [CG]").unwrap();
+        assert_eq!(expression.len(), 3);
+        assert!(!expression.is_empty());
+
+        let mut expression = Expression::new();
+        assert_eq!(expression.len(), 0);
+        assert!(expression.is_empty());
+        expression.add_term(Term::Terminal(String::new()));
+        assert_eq!(expression.len(), 1);
+        assert!(!expression.is_empty());
+    }
+
+    #[test]
+    fn get_by_index() {
+        let mut expression = Expression::from_str("<base> \"A\" [CG]").unwrap();
+        assert_eq!(expression.get(1), Some(&Term::Terminal(String::from("A"))));
+        assert_eq!(expression.get(3), None);
+        assert_eq!(expression[0], Term::Nonterminal(String::from("base")));
+
+        if let Some(term) = expression.get_mut(1) {
+            *term = Term::Terminal(String::from("T"));
+        }
+        assert_eq!(expression.get_mut(3), None);
+        assert_eq!(expression.to_string(), "<base> \"T\" [CG]");
+    }
+
+    #[test]
+    fn insert_and_swap_terms() {
+        let mut expression = Expression::from_str("<base> %weight 2").unwrap();
+        assert_eq!(
+            expression.insert_term(0, Term::Terminal(String::from("A"))),
+            Ok(())
+        );
+        // inserting at the number of terms appends
+        assert_eq!(
+            expression.insert_term(2, Term::Terminal(String::from("C"))),
+            Ok(())
+        );
+        assert_eq!(expression.to_string(), "\"A\" <base> \"C\" %weight 2");
+
+        assert_eq!(expression.swap_terms(0, 2), Ok(()));
+        assert_eq!(expression.to_string(), "\"C\" <base> \"A\" %weight 2");
+        assert_eq!(expression.swap_terms(1, 1), Ok(()));
+        assert_eq!(expression.to_string(), "\"C\" <base> \"A\" %weight 2");
+    }
+
+    #[test]
+    fn insert_and_swap_terms_out_of_bounds() {
+        let mut expression = Expression::new();
+        assert_eq!(
+            expression.insert_term(1, Term::Terminal(String::from("A"))),
+            Err(Error::IndexOutOfBounds(String::from(
+                "Index 1 is out of bounds of an expression of 0 terms!"
+            )))
+        );
+        assert!(expression.is_empty());
+
+        let mut expression = Expression::from_str("<base> \"A\"").unwrap();
+        assert_eq!(
+            expression.swap_terms(0, 2),
+            Err(Error::IndexOutOfBounds(String::from(
+                "Index 2 is out of bounds of an expression of 2 terms!"
+            )))
+        );
+        assert!(expression.swap_terms(3, 1).is_err());
+        assert_eq!(expression.to_string(), "<base> \"A\"");
+    }
+
+    #[test]
+    #[allow(deprecated)]
+    fn insert_and_swap() {
+        let mut expression = Expression::from_str("<base> %weight 2").unwrap();
+        expression.insert(0, Term::Terminal(String::from("A")));
+        expression.insert(2, Term::Terminal(String::from("C")));
+        assert_eq!(expression.to_string(), "\"A\" <base> \"C\" %weight 2");
+
+        expression.swap(0, 2);
+        assert_eq!(expression.to_string(), "\"C\" <base> \"A\" %weight 2");
+        expression.swap(1, 1);
+        assert_eq!(expression.to_string(), "\"C\" <base> \"A\" %weight 2");
+    }
+
+    #[test]
+    #[should_panic]
+    #[allow(deprecated)]
+    fn insert_out_of_bounds() {
+        let mut expression = Expression::new();
+        expression.insert(1, Term::Terminal(String::from("A")));
+    }
+
+    #[test]
+    #[should_panic]
+    #[allow(deprecated)]
+    fn swap_out_of_bounds() {
+        let mut expression = Expression::from_str("<base>").unwrap();
+        expression.swap(0, 1);
+    }
+
+    #[test]
+    #[should_panic]
+    fn index_out_of_bounds() {
+        let expression = Expression::from_str("<base>").unwrap();
+        let _ = &expression[1];
+    }
+
+    #[test]
+    fn replace_term_at() {
+        let mut expression = Expression::from_str("<base> \"A\" %weight 2").unwrap();
+        let old = expression.replace_term_at(1, Term::Nonterminal(String::from("dna")));
+        assert_eq!(old, Ok(Term::Terminal(String::from("A"))));
+        assert_eq!(expression.to_string(), "<base> <dna> %weight 2");
+
+        let error = expression.replace_term_at(2, Term::Terminal(String::from("C")));
+        assert_eq!(
+            error,
+            Err(Error::IndexOutOfBounds(String::from(
+                "Index 2 is out of bounds of an expression of 2 terms!"
+            )))
+        );
+        assert_eq!(expression.to_string(), "<base> <dna> %weight 2");
+        assert!(Expression::new()
+            .replace_term_at(0, Term::Terminal(String::from("C")))
+            .is_err());
+    }
+
+    #[test]
+    #[allow(deprecated)]
+    fn replace_term() {
+        let mut expression = Expression::from_str("<base> \"A\" %weight 2").unwrap();
+        let old = expression.replace_term(1, Term::Nonterminal(String::from("dna")));
+        assert_eq!(old, Ok(Term::Terminal(String::from("A"))));
+        assert_eq!(expression.to_string(), "<base> <dna> %weight 2");
+
+        let error = expression.replace_term(2, Term::Terminal(String::from("C")));
+        assert_eq!(
+            error,
+            Err(Error::IndexOutOfBounds(String::from(
+                "Index 2 is out of bounds of an expression of 2 terms!"
+            )))
+        );
+        assert_eq!(expression.to_string(), "<base> <dna> %weight 2");
+        assert!(Expression::new()
+            .replace_term(0, Term::Terminal(String::from("C")))
+            .is_err());
+    }
+
+    #[test]
+    fn remove_term_at() {
+        let mut expression = Expression::from_str("<base> \"A\" <dna> %weight 2").unwrap();
+        assert_eq!(
+            expression.remove_term_at(3),
+            Err(Error::IndexOutOfBounds(String::from(
+                "Index 3 is out of bounds of an expression of 3 terms!"
+            )))
+        );
+        assert_eq!(
+            expression.remove_term_at(2),
+            Ok(Term::Nonterminal(String::from("dna")))
+        );
+        assert_eq!(
+            expression.remove_term_at(0),
+            Ok(Term::Nonterminal(String::from("base")))
+        );
+        assert_eq!(expression.to_string(), "\"A\" %weight 2");
+
+        // removing the last term leaves the empty expression, not nothing
+        assert_eq!(
+            expression.remove_term_at(0),
+            Ok(Term::Terminal(String::from("A")))
+        );
+        assert!(expression.is_empty());
+        assert_eq!(expression.to_string(), "ε %weight 2");
+        assert!(expression.remove_term_at(0).is_err());
+    }
+
+    #[test]
+    fn weight_to_string_and_back() {
+        let expression = Expression::from_str("<base> \"a\" %weight 3").unwrap();
+        assert_eq!(expression.weight(), Some(3));
+        assert_eq!(expression.to_string(), "<base> \"a\" %weight 3");
+
+        let mut unweighted = expression.clone();
+        unweighted.set_weight(None);
+        assert_eq!(unweighted.to_string(), "<base> \"a\"");
+        assert_ne!(unweighted, expression);
+
+        let grammar = "<bit> ::= \"0\" %weight 3 | \"1\"\n";
+        assert_eq!(Grammar::from_str(grammar).unwrap().to_string(), grammar);
+    }
+
     #[test]
     fn parse_incomplete() {
         let result = Expression::from_str("");
@@ -286,4 +735,31 @@ mod tests {
             Ok(s) => panic!("should should be Error::ParseIncomplete: {}", s),
         }
     }
+
+    #[test]
+    fn orders_expressions() {
+        let expression = |text: &str| Expression::from_str(text).unwrap();
+        let mut weighted = expression("\"a\" <b>");
+        weighted.set_weight(Some(2));
+        let mut expressions = vec![
+            weighted.clone(),
+            expression("<a>"),
+            expression("\"a\" <b>"),
+            expression("\"b\""),
+            expression("\"a\""),
+            Expression::new(),
+        ];
+        expressions.sort();
+        assert_eq!(
+            expressions,
+            vec![
+                Expression::new(),
+                expression("\"a\""),
+                expression("\"a\" <b>"),
+                weighted,
+                expression("\"b\""),
+                expression("<a>"),
+            ]
+        );
+    }
 }
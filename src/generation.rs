@@ -0,0 +1,3379 @@
+use error::Error;
+use expression;
+use grammar::Grammar;
+use num_bigint::BigUint;
+use production::Production;
+use rand::Rng;
+use rand::{thread_rng, SeedableRng, StdRng};
+use stacker;
+use std::collections::{HashMap, HashSet};
+use std::fmt;
+use std::io;
+use std::str;
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::sync::Arc;
+use std::time::Instant;
+use term::{CharClass, Nonterminal, StartSymbol, Term};
+use tree::{ParseTree, ParseTreeNode};
+
+/// Receives the steps of a random, depth first generation
+pub(crate) trait Visitor {
+    /// Choose which alternative of `production` to expand
+    fn choose<R: Rng>(&mut self, production: &Production, rng: &mut R) -> Option<usize> {
+        choose_weighted(production, rng)
+    }
+
+    /// Called before the terms of the chosen alternative are generated
+    fn enter(&mut self, _production: &Production, _alternative: usize) -> Result<(), Error> {
+        Ok(())
+    }
+
+    /// Called once the terms of the chosen alternative have been generated
+    fn exit(&mut self, _production: &Production) -> Result<(), Error> {
+        Ok(())
+    }
+
+    /// Choose how many times in a row to generate the terms of `inner`, from
+    /// `min` to `max` times, or at least `min` times without a `max`
+    fn count<R: Rng>(
+        &mut self,
+        _inner: &expression::Expression,
+        min: usize,
+        max: Option<usize>,
+        rng: &mut R,
+    ) -> usize {
+        random_count(min, max, rng)
+    }
+
+    /// Called once the copies of a repetition have been generated
+    fn repeated(&mut self) -> Result<(), Error> {
+        Ok(())
+    }
+
+    /// Called with each emitted terminal
+    fn terminal(&mut self, terminal: &str) -> Result<(), Error>;
+
+    /// Called with each emitted terminal of bytes, failing unless they're
+    /// UTF-8 for visitors of text
+    fn bytes(&mut self, bytes: &[u8]) -> Result<(), Error> {
+        match str::from_utf8(bytes) {
+            Ok(terminal) => self.terminal(terminal),
+            Err(_) => Err(Error::GenerateError(format!(
+                "Terminal {} isn't UTF-8, see Grammar::generate_bytes!",
+                Term::TerminalBytes(bytes.to_vec())
+            ))),
+        }
+    }
+
+    /// Called before a nonterminal is expanded, so that the expansion may be
+    /// retried from here
+    fn checkpoint(&mut self) {}
+
+    /// Called when expanding a nonterminal with `alternatives` failed,
+    /// restoring the last checkpoint and returning whether to retry
+    fn retry(&mut self, _alternatives: usize) -> bool {
+        false
+    }
+
+    /// Called once a nonterminal's expansion is finished, successfully or not,
+    /// discarding its checkpoint
+    fn commit(&mut self) {}
+}
+
+/// Choose an alternative of `production` with probability proportional to its
+/// weight, or `None` when every alternative weighs nothing
+pub(crate) fn choose_weighted<R: Rng>(production: &Production, rng: &mut R) -> Option<usize> {
+    if production.rhs_iter().all(|expr| expr.weight().is_none()) {
+        return if production.is_empty() {
+            None
+        } else {
+            Some(rng.gen_range(0, production.len()))
+        };
+    }
+
+    let weights = production
+        .rhs_iter()
+        .map(|expr| u64::from(expr.weight().unwrap_or(1)))
+        .collect::<Vec<_>>();
+    let total = weights.iter().sum::<u64>();
+    if total == 0 {
+        return None;
+    }
+
+    let mut choice = rng.gen_range(0, total);
+    for (index, &weight) in weights.iter().enumerate() {
+        if choice < weight {
+            return Some(index);
+        }
+        choice -= weight;
+    }
+    None
+}
+
+/// Choose a count from `min` to `max` uniformly, or without a `max` one more
+/// than `min` as often as it's `min`, and so on, as though repeating were as
+/// likely as stopping each time
+pub(crate) fn random_count<R: Rng>(min: usize, max: Option<usize>, rng: &mut R) -> usize {
+    match max {
+        Some(max) => rng.gen_range(min, max + 1),
+        None => {
+            let mut count = min;
+            while rng.gen() {
+                count += 1;
+            }
+            count
+        }
+    }
+}
+
+/// Choose an alternative of `production` as `choose_weighted` does, but only
+/// among those which fit, or `None` when those weigh nothing
+fn choose_fitting<R: Rng>(production: &Production, fitting: &[bool], rng: &mut R) -> Option<usize> {
+    let weights = production
+        .rhs_iter()
+        .zip(fitting)
+        .map(|(expr, &fit)| match expr.weight() {
+            _ if !fit => 0,
+            Some(weight) => u64::from(weight),
+            None => 1,
+        })
+        .collect::<Vec<_>>();
+    let total = weights.iter().sum::<u64>();
+    if total == 0 {
+        return None;
+    }
+    let mut choice = rng.gen_range(0, total);
+    for (index, &weight) in weights.iter().enumerate() {
+        if choice < weight {
+            return Some(index);
+        }
+        choice -= weight;
+    }
+    None
+}
+
+impl Visitor for String {
+    fn terminal(&mut self, terminal: &str) -> Result<(), Error> {
+        self.push_str(terminal);
+        Ok(())
+    }
+}
+
+impl Visitor for Vec<u8> {
+    fn terminal(&mut self, terminal: &str) -> Result<(), Error> {
+        self.extend_from_slice(terminal.as_bytes());
+        Ok(())
+    }
+
+    fn bytes(&mut self, bytes: &[u8]) -> Result<(), Error> {
+        self.extend_from_slice(bytes);
+        Ok(())
+    }
+}
+
+/// Get what to insert between two adjacent terminals
+type Separate = dyn Fn(&str, &str) -> String + Send + Sync;
+
+/// What to insert between adjacent terminals of a generated sentence
+#[derive(Clone)]
+enum Separator {
+    Text(String),
+    With(Arc<Separate>),
+}
+
+impl Separator {
+    fn between(&self, previous: &str, next: &str) -> String {
+        match *self {
+            Separator::Text(ref text) => text.clone(),
+            Separator::With(ref separate) => separate(previous, next),
+        }
+    }
+}
+
+impl fmt::Debug for Separator {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match *self {
+            Separator::Text(ref text) => f.debug_tuple("Text").field(text).finish(),
+            Separator::With(_) => f.write_str("With(..)"),
+        }
+    }
+}
+
+impl PartialEq for Separator {
+    fn eq(&self, other: &Separator) -> bool {
+        match (self, other) {
+            (Separator::Text(a), Separator::Text(b)) => a == b,
+            (Separator::With(a), Separator::With(b)) => Arc::ptr_eq(a, b),
+            _ => false,
+        }
+    }
+}
+
+impl Eq for Separator {}
+
+/// Whether a partially generated sentence is acceptable, given the lhs of
+/// each production being expanded
+type Constrain = dyn Fn(&str, &[Term]) -> bool + Send + Sync;
+
+/// A side constraint on generated sentences
+#[derive(Clone)]
+struct Constraint(Arc<Constrain>);
+
+impl fmt::Debug for Constraint {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        f.write_str("Constraint(..)")
+    }
+}
+
+impl PartialEq for Constraint {
+    fn eq(&self, other: &Constraint) -> bool {
+        Arc::ptr_eq(&self.0, &other.0)
+    }
+}
+
+impl Eq for Constraint {}
+
+/// A flag to cancel generating with from another thread
+#[derive(Clone, Debug)]
+struct Cancel(Arc<AtomicBool>);
+
+impl PartialEq for Cancel {
+    fn eq(&self, other: &Cancel) -> bool {
+        Arc::ptr_eq(&self.0, &other.0)
+    }
+}
+
+impl Eq for Cancel {}
+
+/// Limits on generating sentences, and how their terminals are joined, with
+/// no limits and terminals concatenated directly by default
+#[derive(Clone, Debug, Eq, PartialEq)]
+pub struct GenerateOptions {
+    max_depth: Option<usize>,
+    max_len: Option<usize>,
+    max_stale_attempts: usize,
+    separator: Option<Separator>,
+    constraint: Option<Constraint>,
+    constraint_retries: usize,
+    deadline: Option<Instant>,
+    cancel: Option<Cancel>,
+    max_retries: usize,
+}
+
+impl GenerateOptions {
+    /// Construct the default `GenerateOptions`
+    pub fn new() -> GenerateOptions {
+        GenerateOptions {
+            max_depth: None,
+            max_len: None,
+            max_stale_attempts: 100,
+            separator: None,
+            constraint: None,
+            constraint_retries: 100,
+            deadline: None,
+            cancel: None,
+            max_retries: 0,
+        }
+    }
+
+    /// Fail generating a sentence which nests expansions more than
+    /// `max_depth` deep, the start symbol's expansion being depth 1.
+    ///
+    /// At the last depth, alternatives without nonterminals are chosen when
+    /// there are any, so that recursions like repetitions end in time.
+    pub fn max_depth(mut self, max_depth: usize) -> GenerateOptions {
+        self.max_depth = Some(max_depth);
+        self
+    }
+
+    /// Fail generating a sentence longer than `max_len` characters.
+    ///
+    /// Once the sentence is `max_len` characters long, alternatives of only
+    /// empty terminals are chosen when there are any.
+    pub fn max_len(mut self, max_len: usize) -> GenerateOptions {
+        self.max_len = Some(max_len);
+        self
+    }
+
+    /// Stop looking for new sentences in `Grammar::generate_distinct` after
+    /// `attempts` in a row find none, 100 by default
+    pub fn max_stale_attempts(mut self, attempts: usize) -> GenerateOptions {
+        self.max_stale_attempts = attempts;
+        self
+    }
+
+    /// Insert `separator` between every two adjacent terminals of a sentence,
+    /// e.g. `" "` for grammars of whole tokens.
+    ///
+    /// Empty terminals emit nothing, and so are never separated. Separators
+    /// count towards `GenerateOptions::max_len`.
+    pub fn separator(mut self, separator: &str) -> GenerateOptions {
+        self.separator = Some(Separator::Text(String::from(separator)));
+        self
+    }
+
+    /// Insert what `separate` returns between every two adjacent terminals
+    /// of a sentence, given the terminal before and the terminal after it, as
+    /// for `GenerateOptions::separator`
+    pub fn separator_with<F>(mut self, separate: F) -> GenerateOptions
+    where
+        F: Fn(&str, &str) -> String + Send + Sync + 'static,
+    {
+        self.separator = Some(Separator::With(Arc::new(separate)));
+        self
+    }
+
+    /// Only generate sentences which `accept` returns true for.
+    ///
+    /// `accept` is called with the sentence so far after every terminal is
+    /// emitted, along with the lhs of each production being expanded,
+    /// outermost first, and once more with the complete sentence. When it
+    /// returns false, the innermost expansion is generated again, up to once
+    /// per alternative of its production before the expansion enclosing it
+    /// is generated again instead. Generating fails once
+    /// `GenerateOptions::constraint_retries` expansions have been retried.
+    pub fn constraint<F>(mut self, accept: F) -> GenerateOptions
+    where
+        F: Fn(&str, &[Term]) -> bool + Send + Sync + 'static,
+    {
+        self.constraint = Some(Constraint(Arc::new(accept)));
+        self
+    }
+
+    /// Fail generating a sentence after `retries` expansions were generated
+    /// again for `GenerateOptions::constraint`, 100 by default
+    pub fn constraint_retries(mut self, retries: usize) -> GenerateOptions {
+        self.constraint_retries = retries;
+        self
+    }
+
+    /// Fail generating a sentence still unfinished at `deadline`.
+    ///
+    /// The deadline is checked every 64 expansions, so generating may run
+    /// briefly past it.
+    pub fn deadline(mut self, deadline: Instant) -> GenerateOptions {
+        self.deadline = Some(deadline);
+        self
+    }
+
+    /// Fail generating a sentence once `cancel` is set, e.g. from another
+    /// thread, checked as often as `GenerateOptions::deadline`
+    pub fn cancel(mut self, cancel: Arc<AtomicBool>) -> GenerateOptions {
+        self.cancel = Some(Cancel(cancel));
+        self
+    }
+
+    /// Generate a sentence again up to `retries` times when generating fails
+    /// on a depth or length limit, the recursion limit, or
+    /// `GenerateOptions::constraint`, none by default.
+    ///
+    /// `Grammar::generate_with_options` retries with the random numbers
+    /// which follow, and `Grammar::generate_retrying` re-seeds each attempt.
+    /// Once every attempt fails, the last failure is returned in an
+    /// `Error::RetriesExhausted` with the number of attempts.
+    pub fn max_retries(mut self, retries: usize) -> GenerateOptions {
+        self.max_retries = retries;
+        self
+    }
+}
+
+impl Default for GenerateOptions {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+/// A limit of `GenerateOptions` on generating sentences
+#[derive(Clone, Copy, Debug, Eq, PartialEq)]
+pub enum Limit {
+    /// `GenerateOptions::max_depth`
+    Depth(usize),
+    /// `GenerateOptions::max_len`
+    Length(usize),
+    /// `GenerateOptions::deadline`
+    Deadline(Instant),
+    /// `GenerateOptions::cancel`
+    Cancelled,
+}
+
+/// Where generating a sentence reached a limit of `GenerateOptions`
+#[derive(Clone, Debug, Eq, PartialEq)]
+pub struct LimitError {
+    limit: Limit,
+    path: Vec<Term>,
+    partial: String,
+}
+
+impl LimitError {
+    /// Get the limit which was reached
+    pub fn limit(&self) -> Limit {
+        self.limit
+    }
+
+    /// Get the nonterminal being expanded when the limit was reached
+    pub fn nonterminal(&self) -> Option<&Term> {
+        self.path.last()
+    }
+
+    /// Get the nonterminals being expanded when the limit was reached, from
+    /// the start symbol down to the innermost
+    pub fn path(&self) -> &[Term] {
+        &self.path
+    }
+
+    /// Get how deeply expansions were nested when the limit was reached
+    pub fn depth(&self) -> usize {
+        self.path.len()
+    }
+
+    /// Get the sentence generated before the limit was reached
+    pub fn partial(&self) -> &str {
+        &self.partial
+    }
+}
+
+impl fmt::Display for LimitError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self.limit {
+            Limit::Depth(max_depth) => write!(f, "Limit for depth of {} reached: ", max_depth)?,
+            Limit::Length(max_len) => {
+                write!(f, "Limit for length of {} characters reached: ", max_len)?
+            }
+            Limit::Deadline(_) => write!(f, "Deadline for generating reached: ")?,
+            Limit::Cancelled => write!(f, "Generating cancelled: ")?,
+        }
+        let path = self
+            .path
+            .iter()
+            .map(Term::to_string)
+            .collect::<Vec<_>>()
+            .join(" -> ");
+        write!(f, "{}", path)
+    }
+}
+
+/// Whether generating a sentence again might not fail with `error`
+fn is_retryable(error: &Error) -> bool {
+    match *error {
+        Error::RecursionLimit(_) => true,
+        Error::LimitError(ref e) => matches!(e.limit(), Limit::Depth(_) | Limit::Length(_)),
+        _ => false,
+    }
+}
+
+/// Generate a sentence from `start` within the limits of `options`, failing
+/// with whether generating it again is worth attempting
+fn attempt<R: Rng>(
+    grammar: &Grammar,
+    start: &str,
+    options: &GenerateOptions,
+    budget: &Budget,
+    rng: &mut R,
+) -> Result<String, (Error, bool)> {
+    let mut limited = Limited::new(options, budget);
+    match traverse(grammar, start, rng, &mut limited).and_then(|_| limited.constrain()) {
+        Ok(()) => Ok(limited.sentence),
+        Err(e) => {
+            let retryable = limited.rejected || is_retryable(&e);
+            Err((e, retryable))
+        }
+    }
+}
+
+/// Make attempts until one succeeds, isn't worth retrying, or
+/// `GenerateOptions::max_retries` is exhausted, giving the number made
+fn retrying<F>(options: &GenerateOptions, mut attempt: F) -> Result<(String, usize), Error>
+where
+    F: FnMut(usize) -> Result<String, (Error, bool)>,
+{
+    let mut attempts = 0;
+    loop {
+        attempts += 1;
+        match attempt(attempts - 1) {
+            Ok(sentence) => return Ok((sentence, attempts)),
+            Err((_, true)) if attempts <= options.max_retries => continue,
+            Err((e, _)) if attempts == 1 => return Err(e),
+            Err((e, _)) => return Err(Error::RetriesExhausted(attempts, Box::new(e))),
+        }
+    }
+}
+
+/// The fewest nested expansions and characters the first production of each
+/// nonterminal generates, for steering generation away from alternatives
+/// which can only exceed the limits of `GenerateOptions`
+#[derive(Default)]
+struct Budget {
+    defined: HashSet<Term>,
+    depths: HashMap<String, usize>,
+    lens: HashMap<String, usize>,
+}
+
+impl Budget {
+    fn new(grammar: &Grammar, options: &GenerateOptions) -> Budget {
+        let mut budget = Budget::default();
+        let productions = grammar
+            .productions_iter()
+            .filter(|prod| budget.defined.insert(prod.lhs().clone()))
+            .collect::<Vec<_>>();
+        if options.max_depth.is_some() {
+            let depths = least(&productions, |expr, depths| budget.depth(expr, depths));
+            budget.depths = depths;
+        }
+        if options.max_len.is_some() {
+            let lens = least(&productions, |expr, lens| budget.len(expr, lens));
+            budget.lens = lens;
+        }
+        budget
+    }
+
+    /// Get the fewest nested expansions of `expr`, itself included, given
+    /// those of its nonterminals
+    fn depth(
+        &self,
+        expr: &expression::Expression,
+        depths: &HashMap<String, usize>,
+    ) -> Option<usize> {
+        let mut depth = 0;
+        for term in expr.terms_iter() {
+            match *term {
+                Term::Nonterminal(ref name) if self.defined.contains(term) => {
+                    depth = depth.max(*depths.get(name)?);
+                }
+                // a repetition nests its terms no deeper than itself, and
+                // not at all when it may be empty
+                Term::Repeat { ref inner, min, .. } if min > 0 => {
+                    depth = depth.max(self.depth(inner, depths)? - 1)
+                }
+                _ => {}
+            }
+        }
+        Some(depth + 1)
+    }
+
+    /// Get the fewest characters `expr` generates, given those of its
+    /// nonterminals
+    fn len(&self, expr: &expression::Expression, lens: &HashMap<String, usize>) -> Option<usize> {
+        let mut len = 0;
+        for term in expr.terms_iter() {
+            len += match *term {
+                Term::Nonterminal(ref name) if self.defined.contains(term) => *lens.get(name)?,
+                Term::Nonterminal(_) => term.to_string().chars().count(),
+                Term::Terminal(ref text) | Term::CaselessTerminal(ref text) => text.chars().count(),
+                Term::TerminalBytes(ref bytes) => {
+                    str::from_utf8(bytes).map_or(0, |text| text.chars().count())
+                }
+                Term::CharClass(_) => 1,
+                Term::Regex(_) => 0,
+                Term::Repeat { min: 0, .. } => 0,
+                Term::Repeat { ref inner, min, .. } => self.len(inner, lens)?.saturating_mul(min),
+            };
+        }
+        Some(len)
+    }
+}
+
+/// Get the least cost of each nonterminal's alternatives, given the costs
+/// found so far, for those which have any
+fn least<C, F>(productions: &[&Production], cost: F) -> HashMap<String, C>
+where
+    C: Copy + Ord,
+    F: Fn(&expression::Expression, &HashMap<String, C>) -> Option<C>,
+{
+    let mut least = HashMap::new();
+    loop {
+        let mut changed = false;
+        for prod in productions {
+            let name = match *prod.lhs() {
+                Term::Nonterminal(ref name) => name,
+                _ => continue,
+            };
+            let fewest = prod.rhs_iter().filter_map(|expr| cost(expr, &least)).min();
+            if let Some(fewest) = fewest {
+                if least.get(name).is_none_or(|&known| fewest < known) {
+                    least.insert(name.clone(), fewest);
+                    changed = true;
+                }
+            }
+        }
+        if !changed {
+            return least;
+        }
+    }
+}
+
+/// The fewest bytes each nonterminal generates, along with the fewest nested
+/// expansions generating that few, for `Grammar::generate_bounded`
+struct Shortest<'a> {
+    productions: HashMap<&'a Term, &'a Production>,
+    least: HashMap<String, (usize, usize)>,
+}
+
+impl<'a> Shortest<'a> {
+    fn new(grammar: &'a Grammar) -> Shortest<'a> {
+        let mut shortest = Shortest {
+            productions: HashMap::new(),
+            least: HashMap::new(),
+        };
+        for prod in grammar.productions_iter() {
+            shortest.productions.entry(prod.lhs()).or_insert(prod);
+        }
+        let productions = shortest.productions.values().cloned().collect::<Vec<_>>();
+        shortest.least = least(&productions, |expr, least| shortest.cost(expr, least));
+        shortest
+    }
+
+    /// Get the fewest bytes `expr` generates and the fewest nested
+    /// expansions, itself included, generating that few, given those of its
+    /// nonterminals, or `None` if it can't generate text
+    fn cost(
+        &self,
+        expr: &expression::Expression,
+        least: &HashMap<String, (usize, usize)>,
+    ) -> Option<(usize, usize)> {
+        let (mut len, mut depth) = (0, 0);
+        for term in expr.terms_iter() {
+            let (term_len, term_depth) = match *term {
+                Term::Nonterminal(ref name) if self.productions.contains_key(term) => {
+                    *least.get(name)?
+                }
+                Term::Repeat { .. } => self.repeat(term, least)?,
+                _ => (self.terminal(term)?, 0),
+            };
+            len += term_len;
+            depth = depth.max(term_depth);
+        }
+        Some((len, depth + 1))
+    }
+
+    /// Get the fewest bytes repetition `term` generates and the fewest nested
+    /// expansions generating that few, none when it may be empty
+    fn repeat(
+        &self,
+        term: &Term,
+        least: &HashMap<String, (usize, usize)>,
+    ) -> Option<(usize, usize)> {
+        match *term {
+            Term::Repeat { min: 0, .. } => Some((0, 0)),
+            Term::Repeat { ref inner, min, .. } => {
+                let (len, depth) = self.cost(inner, least)?;
+                Some((len.saturating_mul(min), depth - 1))
+            }
+            _ => None,
+        }
+    }
+
+    /// Get the fewest bytes a term which isn't expanded generates
+    fn terminal(&self, term: &Term) -> Option<usize> {
+        match *term {
+            Term::Nonterminal(_) => Some(term.to_string().len()),
+            Term::Terminal(ref text) | Term::CaselessTerminal(ref text) => Some(text.len()),
+            Term::TerminalBytes(ref bytes) => str::from_utf8(bytes).ok().map(str::len),
+            Term::CharClass(ref class) => smallest(class).map(char::len_utf8),
+            Term::Regex(_) => None,
+            Term::Repeat { .. } => self.repeat(term, &self.least).map(|(len, _)| len),
+        }
+    }
+
+    /// Get the fewest bytes `term` generates, with those of a nonterminal
+    /// which isn't defined being its own
+    fn len(&self, term: &Term) -> Option<usize> {
+        match *term {
+            Term::Nonterminal(ref name) if self.productions.contains_key(term) => {
+                self.least.get(name).map(|&(len, _)| len)
+            }
+            _ => self.terminal(term),
+        }
+    }
+}
+
+/// Get the character of `class` which encodes to the fewest bytes
+fn smallest(class: &CharClass) -> Option<char> {
+    class.members().iter().map(|&(lo, _)| lo).min()
+}
+
+/// Generates a sentence within the limits of `GenerateOptions`
+struct Limited<'a> {
+    options: &'a GenerateOptions,
+    budget: &'a Budget,
+    depth: usize,
+    expansions: usize,
+    len: usize,
+    // the fewest characters the terms yet to be generated take, for
+    // `GenerateOptions::max_len`
+    reserved: usize,
+    sentence: String,
+    previous: Option<String>,
+    // the lhs being expanded, then for `GenerateOptions::constraint` the
+    // checkpoints and how often each was retried, retries left, and whether
+    // it rejected
+    lhs: Vec<Term>,
+    checkpoints: Vec<(Checkpoint, usize)>,
+    retries: usize,
+    rejected: bool,
+}
+
+/// The state of `Limited` to retry an expansion from
+struct Checkpoint {
+    depth: usize,
+    len: usize,
+    reserved: usize,
+    sentence: usize,
+    previous: Option<String>,
+    lhs: usize,
+}
+
+impl<'a> Limited<'a> {
+    fn new(options: &'a GenerateOptions, budget: &'a Budget) -> Limited<'a> {
+        Limited {
+            options,
+            budget,
+            depth: 0,
+            expansions: 0,
+            len: 0,
+            reserved: 0,
+            sentence: String::new(),
+            previous: None,
+            lhs: vec![],
+            checkpoints: vec![],
+            retries: options.constraint_retries,
+            rejected: false,
+        }
+    }
+
+    /// Check the sentence so far against `GenerateOptions::constraint`
+    fn constrain(&mut self) -> Result<(), Error> {
+        match self.options.constraint {
+            Some(Constraint(ref accept)) if !accept(&self.sentence, &self.lhs) => {
+                self.rejected = true;
+                Err(Error::GenerateError(format!(
+                    "Constraint rejected {:?} after {} retries!",
+                    self.sentence,
+                    self.options.constraint_retries - self.retries
+                )))
+            }
+            _ => Ok(()),
+        }
+    }
+
+    fn limit(&self, limit: Limit) -> Error {
+        Error::LimitError(LimitError {
+            limit,
+            path: self.lhs.clone(),
+            partial: self.sentence.clone(),
+        })
+    }
+
+    fn push(&mut self, text: &str) -> Result<(), Error> {
+        self.len += text.chars().count();
+        if let Some(max_len) = self.options.max_len {
+            if self.len > max_len {
+                return Err(self.limit(Limit::Length(max_len)));
+            }
+        }
+        self.sentence.push_str(text);
+        Ok(())
+    }
+
+    /// Get the characters reserved for terms yet to be generated besides
+    /// `production`'s lhs, which is about to be expanded
+    fn pending(&self, production: &Production) -> usize {
+        let least = match *production.lhs() {
+            // the start symbol is never reserved
+            Term::Nonterminal(ref name) if !self.lhs.is_empty() => {
+                self.budget.lens.get(name).cloned().unwrap_or(0)
+            }
+            _ => 0,
+        };
+        self.reserved.saturating_sub(least)
+    }
+}
+
+impl<'a> Visitor for Limited<'a> {
+    fn choose<R: Rng>(&mut self, production: &Production, rng: &mut R) -> Option<usize> {
+        // never choose an alternative which can only exceed a limit while
+        // another may not, so that repetitions end rather than fail
+        let budget = self.budget;
+        let pending = self.pending(production);
+        let fits = |expr: &expression::Expression| {
+            let depth = self.options.max_depth.is_none_or(|max_depth| {
+                budget
+                    .depth(expr, &budget.depths)
+                    .is_some_and(|depth| self.depth + depth <= max_depth)
+            });
+            let len = self.options.max_len.is_none_or(|max_len| {
+                budget
+                    .len(expr, &budget.lens)
+                    .is_some_and(|len| self.len + pending + len <= max_len)
+            });
+            depth && len
+        };
+        let fitting = production.rhs_iter().map(fits).collect::<Vec<_>>();
+        if fitting.iter().all(|&fit| fit) {
+            return choose_weighted(production, rng);
+        }
+        choose_fitting(production, &fitting, rng).or_else(|| choose_weighted(production, rng))
+    }
+
+    fn count<R: Rng>(
+        &mut self,
+        inner: &expression::Expression,
+        min: usize,
+        max: Option<usize>,
+        rng: &mut R,
+    ) -> usize {
+        // never repeat more than `min` times where that can only exceed a
+        // limit, as with choosing alternatives
+        let budget = self.budget;
+        let depth = self.options.max_depth.is_none_or(|max_depth| {
+            budget
+                .depth(inner, &budget.depths)
+                .is_some_and(|depth| self.depth + depth - 1 <= max_depth)
+        });
+        let each = match self.options.max_len {
+            Some(_) => budget.len(inner, &budget.lens),
+            None => Some(0),
+        };
+        let count = random_count(min, max, rng);
+        let count = match (depth, each, self.options.max_len) {
+            (false, _, _) | (_, None, _) => min,
+            // the `min` copies are already reserved
+            (true, Some(each), Some(max_len)) if each > 0 => {
+                let room = max_len.saturating_sub(self.len + self.reserved) / each;
+                count.min(min + room)
+            }
+            _ => count,
+        };
+        if let Some(each) = each {
+            self.reserved += (count - min) * each;
+        }
+        count
+    }
+
+    fn enter(&mut self, production: &Production, alternative: usize) -> Result<(), Error> {
+        if self.options.max_len.is_some() {
+            let budget = self.budget;
+            let len = production
+                .rhs_iter()
+                .nth(alternative)
+                .and_then(|expr| budget.len(expr, &budget.lens));
+            self.reserved = self.pending(production) + len.unwrap_or(0);
+        }
+        self.lhs.push(production.lhs().clone());
+        self.depth += 1;
+        // checking the clock costs more than expanding, so only now and then
+        if self.expansions.is_multiple_of(64) {
+            if let Some(Cancel(ref cancel)) = self.options.cancel {
+                if cancel.load(Ordering::Relaxed) {
+                    return Err(self.limit(Limit::Cancelled));
+                }
+            }
+            if let Some(deadline) = self.options.deadline {
+                if Instant::now() >= deadline {
+                    return Err(self.limit(Limit::Deadline(deadline)));
+                }
+            }
+        }
+        self.expansions += 1;
+        match self.options.max_depth {
+            Some(max_depth) if self.depth > max_depth => Err(self.limit(Limit::Depth(max_depth))),
+            _ => Ok(()),
+        }
+    }
+
+    fn exit(&mut self, _production: &Production) -> Result<(), Error> {
+        self.lhs.pop();
+        self.depth -= 1;
+        Ok(())
+    }
+
+    fn terminal(&mut self, terminal: &str) -> Result<(), Error> {
+        self.reserved = self.reserved.saturating_sub(terminal.chars().count());
+        match self.options.separator {
+            Some(ref separator) if !terminal.is_empty() => {
+                if let Some(previous) = self.previous.take() {
+                    self.push(&separator.between(&previous, terminal))?;
+                }
+                self.previous = Some(String::from(terminal));
+                self.push(terminal)?;
+            }
+            _ => self.push(terminal)?,
+        }
+        self.constrain()
+    }
+
+    fn checkpoint(&mut self) {
+        if self.options.constraint.is_some() {
+            let checkpoint = Checkpoint {
+                depth: self.depth,
+                len: self.len,
+                reserved: self.reserved,
+                sentence: self.sentence.len(),
+                previous: self.previous.clone(),
+                lhs: self.lhs.len(),
+            };
+            self.checkpoints.push((checkpoint, 0));
+        }
+    }
+
+    fn retry(&mut self, alternatives: usize) -> bool {
+        let (checkpoint, retried) = match self.checkpoints.last_mut() {
+            Some(&mut (ref checkpoint, ref mut retried)) => (checkpoint, retried),
+            None => return false,
+        };
+        if !self.rejected || self.retries == 0 || *retried >= alternatives {
+            return false;
+        }
+        *retried += 1;
+        self.retries -= 1;
+        self.rejected = false;
+        self.depth = checkpoint.depth;
+        self.len = checkpoint.len;
+        self.reserved = checkpoint.reserved;
+        self.sentence.truncate(checkpoint.sentence);
+        self.previous = checkpoint.previous.clone();
+        self.lhs.truncate(checkpoint.lhs);
+        true
+    }
+
+    fn commit(&mut self) {
+        self.checkpoints.pop();
+    }
+}
+
+/// Get the nonterminal generation begins from, the lhs of the first production
+pub(crate) fn start_symbol(grammar: &Grammar) -> Result<&str, Error> {
+    match grammar.productions_iter().next() {
+        Some(production) => match *production.lhs() {
+            Term::Nonterminal(ref nt) => Ok(nt),
+            Term::Terminal(_)
+            | Term::CaselessTerminal(_)
+            | Term::TerminalBytes(_)
+            | Term::CharClass(_)
+            | Term::Regex(_)
+            | Term::Repeat { .. } => Err(Error::GenerateError(format!(
+                "Termainal type cannot define a production in '{}'!",
+                production
+            ))),
+        },
+        None => Err(Error::GenerateError(String::from(
+            "Failed to get first production!",
+        ))),
+    }
+}
+
+/// Randomly expand nonterminal `ident`, reporting each step to `visitor`
+pub(crate) fn traverse<R: Rng, V: Visitor>(
+    grammar: &Grammar,
+    ident: &str,
+    rng: &mut R,
+    visitor: &mut V,
+) -> Result<(), Error> {
+    const STACK_RED_ZONE: usize = 32 * 1024; // 32KB
+                                             // heavy recursion happening, we've hit out tolerable threshold
+    if let Some(remaining) = stacker::remaining_stack() {
+        if remaining < STACK_RED_ZONE {
+            return Err(Error::RecursionLimit(format!(
+                "Limit for recursion reached processing <{}>!",
+                ident
+            )));
+        }
+    }
+
+    let nonterm = Term::Nonterminal(String::from(ident));
+    let production = match grammar.productions_iter().find(|&x| *x.lhs() == nonterm) {
+        Some(p) => p,
+        None => return visitor.terminal(&nonterm.to_string()),
+    };
+
+    visitor.checkpoint();
+    loop {
+        match expand(grammar, production, rng, visitor) {
+            Err(_) if visitor.retry(production.len()) => continue,
+            expanded => {
+                visitor.commit();
+                return expanded;
+            }
+        }
+    }
+}
+
+/// Randomly expand an alternative of `production`, reporting each step to
+/// `visitor`
+fn expand<R: Rng, V: Visitor>(
+    grammar: &Grammar,
+    production: &Production,
+    rng: &mut R,
+    visitor: &mut V,
+) -> Result<(), Error> {
+    let alternative = match visitor.choose(production, rng) {
+        Some(alternative) => alternative,
+        None => {
+            return Err(Error::GenerateError(String::from(
+                "Couldn't select random Expression!",
+            )));
+        }
+    };
+    let expression = match production.rhs_iter().nth(alternative) {
+        Some(expression) => expression,
+        None => {
+            return Err(Error::GenerateError(String::from(
+                "Couldn't select random Expression!",
+            )));
+        }
+    };
+
+    visitor.enter(production, alternative)?;
+    emit(grammar, expression, rng, visitor)?;
+    visitor.exit(production)
+}
+
+/// Randomly generate the terms of `expression`, reporting each step to
+/// `visitor`
+fn emit<R: Rng, V: Visitor>(
+    grammar: &Grammar,
+    expression: &expression::Expression,
+    rng: &mut R,
+    visitor: &mut V,
+) -> Result<(), Error> {
+    for term in expression.terms_iter() {
+        match *term {
+            Term::Nonterminal(ref nt) => traverse(grammar, nt, rng, visitor)?,
+            Term::Terminal(ref t) | Term::CaselessTerminal(ref t) => visitor.terminal(t)?,
+            Term::TerminalBytes(ref bytes) => visitor.bytes(bytes)?,
+            Term::CharClass(ref class) => match class.choose(rng) {
+                Some(c) => visitor.terminal(c.encode_utf8(&mut [0; 4]))?,
+                None => {
+                    return Err(Error::GenerateError(format!(
+                        "Couldn't select random character from {}!",
+                        class
+                    )));
+                }
+            },
+            Term::Regex(_) => {
+                return Err(Error::GenerateError(format!(
+                    "Can't generate from the regex {}!",
+                    term
+                )));
+            }
+            Term::Repeat {
+                ref inner,
+                min,
+                max,
+            } => {
+                for _ in 0..visitor.count(inner, min, max, rng) {
+                    emit(grammar, inner, rng, visitor)?;
+                }
+                visitor.repeated()?;
+            }
+        }
+    }
+    Ok(())
+}
+
+/// Builds the derivation tree of a generated sentence
+#[derive(Default)]
+struct TreeBuilder {
+    stack: Vec<ParseTree>,
+    // the children of the innermost tree before each repetition being built
+    repeats: Vec<usize>,
+    root: Option<ParseTree>,
+}
+
+impl Visitor for TreeBuilder {
+    fn enter(&mut self, production: &Production, alternative: usize) -> Result<(), Error> {
+        let expression = production.rhs_iter().nth(alternative).cloned();
+        let mut tree = ParseTree::from_parts(
+            production.lhs().clone(),
+            expression.unwrap_or_else(expression::Expression::new),
+            vec![],
+        );
+        tree.set_alternative_index(Some(alternative));
+        self.stack.push(tree);
+        Ok(())
+    }
+
+    fn exit(&mut self, _production: &Production) -> Result<(), Error> {
+        let tree = match self.stack.pop() {
+            Some(tree) => tree,
+            None => return Ok(()),
+        };
+        match self.stack.last_mut() {
+            Some(parent) => parent.push_child(ParseTreeNode::Nonterminal(tree)),
+            None => self.root = Some(tree),
+        }
+        Ok(())
+    }
+
+    fn count<R: Rng>(
+        &mut self,
+        _inner: &expression::Expression,
+        min: usize,
+        max: Option<usize>,
+        rng: &mut R,
+    ) -> usize {
+        let children = self
+            .stack
+            .last()
+            .map_or(0, |tree| tree.children_iter().count());
+        self.repeats.push(children);
+        random_count(min, max, rng)
+    }
+
+    fn repeated(&mut self) -> Result<(), Error> {
+        if let (Some(tree), Some(children)) = (self.stack.last_mut(), self.repeats.pop()) {
+            tree.repeat_children(children);
+        }
+        Ok(())
+    }
+
+    fn terminal(&mut self, terminal: &str) -> Result<(), Error> {
+        if let Some(parent) = self.stack.last_mut() {
+            parent.push_child(ParseTreeNode::Terminal(String::from(terminal)));
+        }
+        Ok(())
+    }
+}
+
+/// Randomly derive a tree of nonterminal `ident`, which must have a production
+pub(crate) fn generate_tree_from<R: Rng>(
+    grammar: &Grammar,
+    ident: &str,
+    rng: &mut R,
+) -> Result<ParseTree, Error> {
+    let mut builder = TreeBuilder::default();
+    traverse(grammar, ident, rng, &mut builder)?;
+    builder.root.ok_or_else(|| {
+        // an undefined nonterminal is emitted without being expanded
+        Error::GenerateError(format!("No production to derive <{}> from!", ident))
+    })
+}
+
+/// Builds a sentence choosing alternatives by weights given by the name of
+/// their lhs and their index, see `Grammar::generate_with_weights`
+struct Weighted<'w> {
+    sentence: String,
+    weights: &'w HashMap<(String, usize), f64>,
+}
+
+impl<'w> Visitor for Weighted<'w> {
+    fn choose<R: Rng>(&mut self, production: &Production, rng: &mut R) -> Option<usize> {
+        let name = match *production.lhs() {
+            Term::Nonterminal(ref nt) => nt.clone(),
+            _ => return choose_weighted(production, rng),
+        };
+        let weights = production
+            .rhs_iter()
+            .enumerate()
+            .map(
+                |(index, expr)| match self.weights.get(&(name.clone(), index)) {
+                    Some(&weight) => weight,
+                    None => expr.weight().map_or(1.0, f64::from),
+                },
+            )
+            .collect::<Vec<_>>();
+        let total = weights.iter().sum::<f64>();
+        if total <= 0.0 {
+            return None;
+        }
+
+        let mut choice = rng.gen_range(0.0, total);
+        for (index, &weight) in weights.iter().enumerate() {
+            if choice < weight {
+                return Some(index);
+            }
+            choice -= weight;
+        }
+        // rounding can leave a choice past the last alternative weighing any
+        weights.iter().rposition(|&weight| weight > 0.0)
+    }
+
+    fn terminal(&mut self, terminal: &str) -> Result<(), Error> {
+        self.sentence.push_str(terminal);
+        Ok(())
+    }
+}
+
+/// Endless iterator of independently generated sentences, see
+/// `Grammar::generate_iter` and `Grammar::generate_iter_with`
+pub struct GenerateIter<'a, R: Rng> {
+    grammar: &'a Grammar,
+    rng: R,
+    // the options each sentence is generated within, if any
+    limits: Option<(&'a GenerateOptions, Budget)>,
+}
+
+impl<'a, R: Rng> Iterator for GenerateIter<'a, R> {
+    type Item = Result<String, Error>;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        let grammar = self.grammar;
+        let start = match start_symbol(grammar) {
+            Ok(start) => start,
+            Err(e) => return Some(Err(e)),
+        };
+        let rng = &mut self.rng;
+        let generated = match self.limits {
+            Some((options, ref budget)) => {
+                retrying(options, |_| attempt(grammar, start, options, budget, rng))
+                    .map(|(sentence, _)| sentence)
+            }
+            None => {
+                let mut sentence = String::new();
+                traverse(grammar, start, rng, &mut sentence).map(|_| sentence)
+            }
+        };
+        Some(generated)
+    }
+}
+
+/// Lazy iterator of the terminals of a generated sentence, see
+/// `Grammar::generate_terms`
+pub struct GenerateTerms<'a> {
+    grammar: &'a Grammar,
+    rng: StdRng,
+    start: Option<&'a Term>,
+    // the terms left of each expansion or repetition, with the expression
+    // and how many more times it's repeated
+    stack: Vec<(expression::Iter<'a>, &'a expression::Expression, usize)>,
+    max_depth: usize,
+    truncated: bool,
+}
+
+impl<'a> GenerateTerms<'a> {
+    /// End iteration early once expansions would nest more than `max_depth`
+    /// deep, 10,000 by default
+    pub fn max_depth(mut self, max_depth: usize) -> GenerateTerms<'a> {
+        self.max_depth = max_depth;
+        self
+    }
+
+    /// Whether iteration ended early, where generating would fail or
+    /// expansions would nest deeper than `GenerateTerms::max_depth`, so that
+    /// the terminals iterated so far don't make up a whole sentence
+    pub fn truncated(&self) -> bool {
+        self.truncated
+    }
+
+    /// End iteration early, marking it as truncated
+    fn truncate(&mut self) {
+        self.stack.clear();
+        self.truncated = true;
+    }
+
+    /// Get the terminal `term` emits, or expand it and get nothing
+    fn expand(&mut self, term: &'a Term) -> Option<Term> {
+        let production = match self
+            .grammar
+            .productions_iter()
+            .find(|prod| *prod.lhs() == *term)
+        {
+            Some(production) => production,
+            None => return Some(Term::Terminal(term.to_string())),
+        };
+        let expression = choose_weighted(production, &mut self.rng)
+            .and_then(|alternative| production.rhs_iter().nth(alternative));
+        match expression {
+            Some(expression) => self.push(expression, 1),
+            None => self.truncate(),
+        }
+        None
+    }
+
+    /// Iterate the terms of `expression` `count` times in a row
+    fn push(&mut self, expression: &'a expression::Expression, count: usize) {
+        if count == 0 {
+            return;
+        }
+        if self.stack.len() >= self.max_depth {
+            self.truncate();
+        } else {
+            self.stack
+                .push((expression.terms_iter(), expression, count - 1));
+        }
+    }
+}
+
+impl<'a> Iterator for GenerateTerms<'a> {
+    type Item = Term;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        if let Some(start) = self.start.take() {
+            if let Some(terminal) = self.expand(start) {
+                return Some(terminal);
+            }
+        }
+        loop {
+            let (ref mut terms, expression, ref mut repeats) = *self.stack.last_mut()?;
+            let term = match terms.next() {
+                Some(term) => term,
+                None if *repeats > 0 => {
+                    *terms = expression.terms_iter();
+                    *repeats -= 1;
+                    continue;
+                }
+                None => {
+                    self.stack.pop();
+                    continue;
+                }
+            };
+            match *term {
+                Term::Nonterminal(_) => match self.expand(term) {
+                    Some(terminal) => return Some(terminal),
+                    None => continue,
+                },
+                Term::Terminal(_) | Term::TerminalBytes(_) => return Some(term.clone()),
+                Term::CaselessTerminal(ref t) => return Some(Term::Terminal(t.clone())),
+                Term::CharClass(ref class) => match class.choose(&mut self.rng) {
+                    Some(c) => return Some(Term::Terminal(c.to_string())),
+                    None => self.truncate(),
+                },
+                Term::Regex(_) => self.truncate(),
+                Term::Repeat {
+                    ref inner,
+                    min,
+                    max,
+                } => {
+                    let count = random_count(min, max, &mut self.rng);
+                    self.push(inner, count);
+                }
+            }
+        }
+    }
+}
+
+/// Sentences generated to cover a grammar, along with how often each
+/// alternative was expanded while generating them
+#[derive(Clone, Debug, Eq, PartialEq)]
+pub struct Coverage {
+    sentences: Vec<String>,
+    alternatives: Vec<(Term, usize)>,
+    counts: HashMap<(Term, usize), usize>,
+}
+
+impl Coverage {
+    /// Get the generated sentences
+    pub fn sentences(&self) -> &[String] {
+        &self.sentences
+    }
+
+    /// Get how many times alternative `index` of nonterminal `lhs` was expanded.
+    ///
+    /// Alternatives are indexed in grammar order, continuing across
+    /// productions which share a lhs.
+    pub fn count(&self, lhs: &Term, index: usize) -> usize {
+        self.counts.get(&(lhs.clone(), index)).cloned().unwrap_or(0)
+    }
+
+    /// Get the counts of every alternative that was expanded at least once
+    pub fn counts(&self) -> &HashMap<(Term, usize), usize> {
+        &self.counts
+    }
+
+    /// Get the alternatives which were never expanded, in grammar order
+    pub fn uncovered(&self) -> Vec<(Term, usize)> {
+        self.alternatives
+            .iter()
+            .filter(|key| !self.counts.contains_key(key))
+            .cloned()
+            .collect()
+    }
+
+    /// Whether every alternative of the grammar was expanded
+    pub fn is_complete(&self) -> bool {
+        self.alternatives
+            .iter()
+            .all(|key| self.counts.contains_key(key))
+    }
+}
+
+/// Prefers alternatives which haven't yet been expanded in any sentence
+struct Covering<'a> {
+    covered: &'a HashMap<(Term, usize), usize>,
+    fired: HashMap<(Term, usize), usize>,
+    sentence: String,
+}
+
+impl<'a> Visitor for Covering<'a> {
+    fn choose<R: Rng>(&mut self, production: &Production, rng: &mut R) -> Option<usize> {
+        let uncovered = (0..production.len())
+            .filter(|&index| {
+                let key = (production.lhs().clone(), index);
+                !self.covered.contains_key(&key) && !self.fired.contains_key(&key)
+            })
+            .collect::<Vec<usize>>();
+        match rng.choose(&uncovered) {
+            Some(&index) => Some(index),
+            None => choose_weighted(production, rng),
+        }
+    }
+
+    fn enter(&mut self, production: &Production, alternative: usize) -> Result<(), Error> {
+        *self
+            .fired
+            .entry((production.lhs().clone(), alternative))
+            .or_insert(0) += 1;
+        Ok(())
+    }
+
+    fn terminal(&mut self, terminal: &str) -> Result<(), Error> {
+        self.sentence.push_str(terminal);
+        Ok(())
+    }
+}
+
+/// The distinct sentences found by `Grammar::generate_distinct`, and how many
+/// fewer there were than were asked for
+#[derive(Clone, Debug, Default, Eq, PartialEq)]
+pub struct DistinctSentences {
+    sentences: Vec<String>,
+    shortfall: usize,
+    exhausted: bool,
+}
+
+impl DistinctSentences {
+    /// Get the sentences, in the order they were first generated
+    pub fn sentences(&self) -> &[String] {
+        &self.sentences
+    }
+
+    /// Take the sentences, in the order they were first generated
+    pub fn into_sentences(self) -> Vec<String> {
+        self.sentences
+    }
+
+    /// Get how many fewer sentences were found than were asked for, 0 when
+    /// all of them were
+    pub fn shortfall(&self) -> usize {
+        self.shortfall
+    }
+
+    /// Whether every sentence within the `GenerateOptions::max_len` was
+    /// found, so the shortfall is because there are no more, rather than
+    /// because sampling went stale
+    pub fn is_exhausted(&self) -> bool {
+        self.exhausted
+    }
+}
+
+/// How a sentence was generated, see `Grammar::generate_traced`
+#[derive(Clone, Debug, Default, Eq, PartialEq)]
+pub struct GenerationTrace {
+    counts: HashMap<(Term, usize), usize>,
+    max_depth: usize,
+    expansions: usize,
+}
+
+impl GenerationTrace {
+    /// Get how many times alternative `index` of nonterminal `lhs` was expanded
+    pub fn count(&self, lhs: &Term, index: usize) -> usize {
+        self.counts.get(&(lhs.clone(), index)).cloned().unwrap_or(0)
+    }
+
+    /// Get the counts of every alternative that was expanded at least once
+    pub fn counts(&self) -> &HashMap<(Term, usize), usize> {
+        &self.counts
+    }
+
+    /// Get the deepest nesting of expansions, the start symbol's expansion
+    /// being depth 1
+    pub fn max_depth(&self) -> usize {
+        self.max_depth
+    }
+
+    /// Get the total number of alternatives expanded
+    pub fn expansions(&self) -> usize {
+        self.expansions
+    }
+}
+
+/// Generates a sentence within the limits of `GenerateOptions`, recording
+/// each expansion
+struct Tracing<'a> {
+    limited: Limited<'a>,
+    trace: GenerationTrace,
+}
+
+impl<'a> Visitor for Tracing<'a> {
+    fn choose<R: Rng>(&mut self, production: &Production, rng: &mut R) -> Option<usize> {
+        self.limited.choose(production, rng)
+    }
+
+    fn enter(&mut self, production: &Production, alternative: usize) -> Result<(), Error> {
+        self.limited.enter(production, alternative)?;
+        *self
+            .trace
+            .counts
+            .entry((production.lhs().clone(), alternative))
+            .or_insert(0) += 1;
+        self.trace.expansions += 1;
+        self.trace.max_depth = self.trace.max_depth.max(self.limited.depth);
+        Ok(())
+    }
+
+    fn exit(&mut self, production: &Production) -> Result<(), Error> {
+        self.limited.exit(production)
+    }
+
+    fn count<R: Rng>(
+        &mut self,
+        inner: &expression::Expression,
+        min: usize,
+        max: Option<usize>,
+        rng: &mut R,
+    ) -> usize {
+        self.limited.count(inner, min, max, rng)
+    }
+
+    fn terminal(&mut self, terminal: &str) -> Result<(), Error> {
+        self.limited.terminal(terminal)
+    }
+
+    fn checkpoint(&mut self) {
+        self.limited.checkpoint()
+    }
+
+    fn retry(&mut self, alternatives: usize) -> bool {
+        self.limited.retry(alternatives)
+    }
+
+    fn commit(&mut self) {
+        self.limited.commit()
+    }
+}
+
+/// Generates a sentence within the limits of `GenerateOptions`, writing it
+/// out as it's generated unless a `GenerateOptions::constraint` may retry it
+struct Streaming<'a, W: io::Write> {
+    limited: Limited<'a>,
+    writer: &'a mut W,
+}
+
+impl<'a, W: io::Write> Streaming<'a, W> {
+    fn flush(&mut self) -> Result<(), Error> {
+        self.writer.write_all(self.limited.sentence.as_bytes())?;
+        self.limited.sentence.clear();
+        Ok(())
+    }
+}
+
+impl<'a, W: io::Write> Visitor for Streaming<'a, W> {
+    fn choose<R: Rng>(&mut self, production: &Production, rng: &mut R) -> Option<usize> {
+        self.limited.choose(production, rng)
+    }
+
+    fn enter(&mut self, production: &Production, alternative: usize) -> Result<(), Error> {
+        self.limited.enter(production, alternative)
+    }
+
+    fn exit(&mut self, production: &Production) -> Result<(), Error> {
+        self.limited.exit(production)
+    }
+
+    fn count<R: Rng>(
+        &mut self,
+        inner: &expression::Expression,
+        min: usize,
+        max: Option<usize>,
+        rng: &mut R,
+    ) -> usize {
+        self.limited.count(inner, min, max, rng)
+    }
+
+    fn terminal(&mut self, terminal: &str) -> Result<(), Error> {
+        self.limited.terminal(terminal)?;
+        if self.limited.options.constraint.is_none() {
+            self.flush()?;
+        }
+        Ok(())
+    }
+
+    fn checkpoint(&mut self) {
+        self.limited.checkpoint()
+    }
+
+    fn retry(&mut self, alternatives: usize) -> bool {
+        self.limited.retry(alternatives)
+    }
+
+    fn commit(&mut self) {
+        self.limited.commit()
+    }
+}
+
+impl Grammar {
+    /// Generate a random sentence from the start symbol within the limits of
+    /// `options`, joining its terminals as `options` specifies.
+    ///
+    /// With the default options this generates the same sentences as
+    /// `Grammar::generate_seeded` given the same random numbers.
+    ///
+    /// # Example
+    ///
+    /// ```rust
+    /// extern crate bnf;
+    /// extern crate rand;
+    /// use bnf::{GenerateOptions, Grammar};
+    ///
+    /// fn main() {
+    ///     let grammar = Grammar::from_str("<call> ::= \"f\" \"(\" \"x\" \",\" \"y\" \")\"").unwrap();
+    ///     let options = GenerateOptions::new().separator_with(|previous, next| {
+    ///         match (previous, next) {
+    ///             (_, ",") | (_, ")") | ("f", "(") | ("(", _) => String::new(),
+    ///             _ => String::from(" "),
+    ///         }
+    ///     });
+    ///     let sentence = grammar.generate_with_options(&options, &mut rand::thread_rng());
+    ///
+    ///     assert_eq!(sentence, Ok(String::from("f(x, y)")));
+    /// }
+    /// ```
+    pub fn generate_with_options<R: Rng>(
+        &self,
+        options: &GenerateOptions,
+        rng: &mut R,
+    ) -> Result<String, Error> {
+        let start = start_symbol(self)?;
+        let budget = Budget::new(self, options);
+        retrying(options, |_| attempt(self, start, options, &budget, rng))
+            .map(|(sentence, _)| sentence)
+    }
+
+    /// Generate a random sentence as `Grammar::generate_with_options` does,
+    /// writing its terminals to `writer` as they're generated rather than
+    /// building the sentence in memory.
+    ///
+    /// When generating fails, whatever was generated before the failure has
+    /// already been written, so a `LimitError` has no partial sentence.
+    /// Failures to write are returned as `Error::IoError`. With a
+    /// `GenerateOptions::constraint` the sentence is built in memory after all,
+    /// since the constraint may retry what was generated, and is only written
+    /// once it's complete. Output can't be taken back, so
+    /// `GenerateOptions::max_retries` is ignored.
+    ///
+    /// # Example
+    ///
+    /// ```rust
+    /// extern crate bnf;
+    /// extern crate rand;
+    /// use bnf::{GenerateOptions, Grammar};
+    ///
+    /// fn main() {
+    ///     let grammar = Grammar::from_str("<list> ::= <item> | <item> <list>
+    ///         <item> ::= \"x\" | \"y\"").unwrap();
+    ///     let options = GenerateOptions::new().separator(",");
+    ///     let mut output = vec![];
+    ///     grammar.generate_into(&mut output, &options, &mut rand::thread_rng()).unwrap();
+    ///
+    ///     let output = String::from_utf8(output).unwrap();
+    ///     assert!(output.split(',').all(|item| item == "x" || item == "y"));
+    /// }
+    /// ```
+    pub fn generate_into<W: io::Write, R: Rng>(
+        &self,
+        writer: &mut W,
+        options: &GenerateOptions,
+        rng: &mut R,
+    ) -> Result<(), Error> {
+        let start = start_symbol(self)?;
+        let budget = Budget::new(self, options);
+        let mut streaming = Streaming {
+            limited: Limited::new(options, &budget),
+            writer,
+        };
+        traverse(self, start, rng, &mut streaming)?;
+        streaming.limited.constrain()?;
+        streaming.flush()
+    }
+
+    /// Generate a random sentence as `Grammar::generate_with_options` does,
+    /// reproducibly for a given `seed`, along with the number of attempts it
+    /// took within `GenerateOptions::max_retries`.
+    ///
+    /// Attempt `i`, counting from 0, is generated with a `StdRng` seeded
+    /// from `&[s >> 32, s & 0xffff_ffff]` for `s` being `seed + i`.
+    ///
+    /// # Example
+    ///
+    /// ```rust
+    /// extern crate bnf;
+    /// use bnf::{Error, GenerateOptions, Grammar};
+    ///
+    /// fn main() {
+    ///     let grammar = Grammar::from_str("<as> ::= \"a\" <as> | \"\"").unwrap();
+    ///     let options = GenerateOptions::new().max_len(3).max_retries(1000);
+    ///     let (sentence, attempts) = grammar.generate_retrying(&options, 42).unwrap();
+    ///
+    ///     assert!(sentence.len() <= 3);
+    ///     assert!(attempts >= 1);
+    ///     assert_eq!(grammar.generate_retrying(&options, 42), Ok((sentence, attempts)));
+    ///
+    ///     let options = GenerateOptions::new().max_len(0).max_retries(2);
+    ///     let always_a = Grammar::from_str("<a> ::= \"a\"").unwrap();
+    ///     match always_a.generate_retrying(&options, 42) {
+    ///         Err(Error::RetriesExhausted(attempts, _)) => assert_eq!(attempts, 3),
+    ///         result => panic!("should exhaust retries: {:?}", result),
+    ///     }
+    /// }
+    /// ```
+    pub fn generate_retrying(
+        &self,
+        options: &GenerateOptions,
+        seed: u64,
+    ) -> Result<(String, usize), Error> {
+        let start = start_symbol(self)?;
+        let budget = Budget::new(self, options);
+        retrying(options, |index| {
+            let seed = seed.wrapping_add(index as u64);
+            let seed: &[_] = &[(seed >> 32) as usize, (seed & 0xffff_ffff) as usize];
+            let mut rng: StdRng = SeedableRng::from_seed(seed);
+            attempt(self, start, options, &budget, &mut rng)
+        })
+    }
+
+    /// Generate a random sentence from the start symbol as bytes, so that
+    /// terminals of bytes which aren't UTF-8, see `Term::TerminalBytes`, may
+    /// be generated. Other terms generate their UTF-8 text, the same as
+    /// `Grammar::generate_seeded` given the same random numbers.
+    ///
+    /// # Example
+    ///
+    /// ```rust
+    /// extern crate bnf;
+    /// extern crate rand;
+    /// use bnf::Grammar;
+    /// use rand::{SeedableRng, StdRng};
+    ///
+    /// fn main() {
+    ///     let grammar = Grammar::from_str("<record> ::= %xCA.FE <len> \"!\"
+    ///         <len> ::= %x00 | %xFF").unwrap();
+    ///     let seed: &[_] = &[1, 2, 3, 4];
+    ///     let bytes = grammar.generate_bytes(&mut StdRng::from_seed(seed)).unwrap();
+    ///
+    ///     assert!(bytes == b"\xCA\xFE\x00!" || bytes == b"\xCA\xFE\xFF!");
+    ///     assert!(grammar.generate().is_err());
+    /// }
+    /// ```
+    pub fn generate_bytes<R: Rng>(&self, rng: &mut R) -> Result<Vec<u8>, Error> {
+        let start = start_symbol(self)?;
+        let mut bytes = vec![];
+        traverse(self, start, rng, &mut bytes)?;
+        Ok(bytes)
+    }
+
+    /// Generate a random sentence as `Grammar::generate_with_options` does,
+    /// along with a trace of which alternatives were expanded to produce it.
+    ///
+    /// Given the same random numbers, the same sentence is generated as
+    /// without tracing. Alternatives are indexed within their production.
+    ///
+    /// # Example
+    ///
+    /// ```rust
+    /// extern crate bnf;
+    /// extern crate rand;
+    /// use bnf::{GenerateOptions, Grammar, Term};
+    ///
+    /// fn main() {
+    ///     let grammar = Grammar::from_str("<dna> ::= <base> | <base> <dna>
+    ///         <base> ::= \"A\" | \"C\" | \"G\" | \"T\"").unwrap();
+    ///     let options = GenerateOptions::new();
+    ///     let (sentence, trace) = grammar.generate_traced(&options, &mut rand::thread_rng()).unwrap();
+    ///
+    ///     let dna = Term::from_str("<dna>").unwrap();
+    ///     assert_eq!(trace.count(&dna, 0), 1);
+    ///     assert_eq!(trace.count(&dna, 1), sentence.len() - 1);
+    ///     assert_eq!(trace.expansions(), 2 * sentence.len());
+    ///     assert_eq!(trace.max_depth(), sentence.len() + 1);
+    /// }
+    /// ```
+    pub fn generate_traced<R: Rng>(
+        &self,
+        options: &GenerateOptions,
+        rng: &mut R,
+    ) -> Result<(String, GenerationTrace), Error> {
+        let start = start_symbol(self)?;
+        let budget = Budget::new(self, options);
+        let mut tracing = Tracing {
+            limited: Limited::new(options, &budget),
+            trace: GenerationTrace::default(),
+        };
+        traverse(self, start, rng, &mut tracing)?;
+        tracing.limited.constrain()?;
+        Ok((tracing.limited.sentence, tracing.trace))
+    }
+
+    /// Lazily generate the terminals of a random sentence from the start
+    /// symbol, expanding depth first as they're iterated, so that very long
+    /// sentences can be consumed without building them, or cut short.
+    ///
+    /// Given the same `seed`, the terminals make up the same sentence as
+    /// `Grammar::generate_seeded` seeded from `&[seed >> 32, seed & 0xffff_ffff]`.
+    /// Character classes and nonterminals without a production are emitted
+    /// as terminals of the text they generate, and terminals of bytes as they
+    /// are. Iteration ends early where generating would fail, or expansions
+    /// nest deeper than `GenerateTerms::max_depth`, which
+    /// `GenerateTerms::truncated` tells apart from a whole sentence.
+    ///
+    /// # Example
+    ///
+    /// ```rust
+    /// extern crate bnf;
+    /// use bnf::{Grammar, Term};
+    ///
+    /// fn main() {
+    ///     let grammar = Grammar::from_str("<forever> ::= \"a\" <forever> | \"b\" <forever>").unwrap();
+    ///     let terms = grammar.generate_terms(42).take(1000).collect::<Vec<_>>();
+    ///
+    ///     assert_eq!(terms.len(), 1000);
+    ///     assert!(terms.iter().all(|term| *term == Term::Terminal(String::from("a"))
+    ///         || *term == Term::Terminal(String::from("b"))));
+    /// }
+    /// ```
+    pub fn generate_terms(&self, seed: u64) -> GenerateTerms<'_> {
+        let seed: &[_] = &[(seed >> 32) as usize, (seed & 0xffff_ffff) as usize];
+        GenerateTerms {
+            grammar: self,
+            rng: SeedableRng::from_seed(seed),
+            start: self.productions_iter().next().map(|prod| prod.lhs()),
+            stack: vec![],
+            max_depth: 10_000,
+            truncated: false,
+        }
+    }
+
+    /// Generate a random sentence of at most `max_bytes` bytes,
+    /// reproducibly for a given `seed`, as for fuzzing inputs under a size
+    /// cap.
+    ///
+    /// Before expanding a nonterminal, the fewest bytes which each term
+    /// still to be generated can generate are set aside, and only
+    /// alternatives which fit in what's left are chosen, so generating never
+    /// needs to backtrack. A character class emits only characters which
+    /// fit. After 100 expansions per byte of `max_bytes`, only alternatives
+    /// leading most directly to the fewest bytes are chosen, so that
+    /// generating ends even for grammars which can expand without emitting
+    /// anything. It fails only if no sentence of the first production's
+    /// nonterminal has at most `max_bytes` bytes.
+    ///
+    /// # Example
+    ///
+    /// ```rust
+    /// extern crate bnf;
+    /// use bnf::Grammar;
+    ///
+    /// fn main() {
+    ///     let grammar = Grammar::from_str("<list> ::= \"[\" <items> \"]\"
+    ///         <items> ::= <item> | <item> \",\" <items>
+    ///         <item> ::= \"null\" | <list>").unwrap();
+    ///
+    ///     for seed in 0..100 {
+    ///         assert!(grammar.generate_bounded(seed, 20).unwrap().len() <= 20);
+    ///     }
+    ///     assert!(grammar.generate_bounded(0, 5).is_err());
+    /// }
+    /// ```
+    pub fn generate_bounded(&self, seed: u64, max_bytes: usize) -> Result<String, Error> {
+        let seed: &[_] = &[(seed >> 32) as usize, (seed & 0xffff_ffff) as usize];
+        let mut rng: StdRng = SeedableRng::from_seed(seed);
+        let start = Term::Nonterminal(String::from(start_symbol(self)?));
+        let shortest = Shortest::new(self);
+        let mut reserved = match shortest.len(&start) {
+            Some(len) if len <= max_bytes => len,
+            Some(len) => {
+                return Err(Error::GenerateError(format!(
+                    "No sentence of {} is within {} bytes, the shortest having {}!",
+                    start, max_bytes, len
+                )))
+            }
+            None => {
+                return Err(Error::GenerateError(format!(
+                    "Couldn't generate any sentence of {}!",
+                    start
+                )))
+            }
+        };
+
+        let patience = max_bytes.saturating_add(1).saturating_mul(100);
+        let mut expansions = 0;
+        let mut sentence = String::new();
+        // the terms still to be generated, the next one last
+        let mut pending = vec![&start];
+        while let Some(term) = pending.pop() {
+            // only terms which can generate text are pending
+            let len = shortest.len(term).unwrap_or(0);
+            reserved -= len;
+            let room = max_bytes - sentence.len() - reserved;
+            match *term {
+                Term::Nonterminal(_) if shortest.productions.contains_key(term) => {
+                    let production = shortest.productions[term];
+                    let costs = production
+                        .rhs_iter()
+                        .map(|expr| shortest.cost(expr, &shortest.least))
+                        .collect::<Vec<_>>();
+                    let fitting = costs
+                        .iter()
+                        .map(|cost| cost.is_some_and(|(len, _)| len <= room))
+                        .collect::<Vec<_>>();
+                    let most_direct = || {
+                        (0..costs.len())
+                            .filter(|&index| costs[index].is_some())
+                            .min_by_key(|&index| costs[index])
+                    };
+                    let alternative = if expansions < patience {
+                        choose_fitting(production, &fitting, &mut rng).or_else(most_direct)
+                    } else {
+                        most_direct()
+                    };
+                    let expr = match alternative.and_then(|index| production.rhs_iter().nth(index))
+                    {
+                        Some(expr) => expr,
+                        None => {
+                            return Err(Error::GenerateError(String::from(
+                                "Couldn't select random Expression!",
+                            )))
+                        }
+                    };
+                    expansions += 1;
+                    reserved += expr
+                        .terms_iter()
+                        .filter_map(|term| shortest.len(term))
+                        .sum::<usize>();
+                    let next = pending.len();
+                    pending.extend(expr.terms_iter());
+                    pending[next..].reverse();
+                }
+                Term::Nonterminal(_) => sentence.push_str(&term.to_string()),
+                Term::Terminal(ref text) | Term::CaselessTerminal(ref text) => {
+                    sentence.push_str(text)
+                }
+                Term::TerminalBytes(ref bytes) => {
+                    sentence.push_str(str::from_utf8(bytes).unwrap_or_default())
+                }
+                Term::CharClass(ref class) => {
+                    let c = class
+                        .choose(&mut rng)
+                        .filter(|c| c.len_utf8() <= room)
+                        .or_else(|| smallest(class))
+                        .unwrap_or_default();
+                    sentence.push(c);
+                }
+                Term::Regex(_) => {}
+                Term::Repeat {
+                    ref inner,
+                    min,
+                    max,
+                } => {
+                    // repeat no more than fits, the room counting the `min`
+                    // copies reserved, nor more than `min` times once out of
+                    // patience, nor at all when the terms can't generate text
+                    let count = match shortest.cost(inner, &shortest.least) {
+                        None => 0,
+                        Some(_) if expansions >= patience => min,
+                        Some((0, _)) => random_count(min, max, &mut rng),
+                        Some((each, _)) => {
+                            random_count(min, max, &mut rng).min(room / each).max(min)
+                        }
+                    };
+                    expansions += 1;
+                    let each = inner
+                        .terms_iter()
+                        .filter_map(|term| shortest.len(term))
+                        .sum::<usize>();
+                    reserved += each * count;
+                    let next = pending.len();
+                    for _ in 0..count {
+                        pending.extend(inner.terms_iter());
+                    }
+                    pending[next..].reverse();
+                }
+            }
+        }
+        Ok(sentence)
+    }
+
+    /// Generate `n` random sentences in parallel on rayon's thread pool,
+    /// reproducibly for a given `base_seed`, with the feature `rayon`.
+    ///
+    /// The sentence at index `i` is the one `Grammar::generate_seeded` gives
+    /// with a `StdRng` seeded from `&[base_seed >> 32, base_seed & 0xffff_ffff, i]`,
+    /// whichever thread generates it. Only failures on the recursion limit,
+    /// which depends on the stack each thread has left, may differ.
+    ///
+    /// # Example
+    ///
+    /// ```rust
+    /// extern crate bnf;
+    /// extern crate rand;
+    /// use bnf::Grammar;
+    /// use rand::{SeedableRng, StdRng};
+    ///
+    /// fn main() {
+    ///     let grammar = Grammar::from_str("<dna> ::= <base> | <base> <dna>
+    ///         <base> ::= \"A\" | \"C\" | \"G\" | \"T\"").unwrap();
+    ///     let batch = grammar.generate_batch(100, 42);
+    ///
+    ///     assert_eq!(batch.len(), 100);
+    ///     let mut rng: StdRng = SeedableRng::from_seed(&[0, 42, 7][..]);
+    ///     assert_eq!(batch[7], grammar.generate_seeded(&mut rng));
+    /// }
+    /// ```
+    #[cfg(feature = "rayon")]
+    pub fn generate_batch(&self, n: usize, base_seed: u64) -> Vec<Result<String, Error>> {
+        use rayon::prelude::*;
+
+        match start_symbol(self) {
+            Ok(start) => self.generate_indexed(start, n, base_seed).collect(),
+            Err(e) => vec![Err(e); n],
+        }
+    }
+
+    /// The sentences from `start` at the indices `0..n`, each generated with
+    /// a `StdRng` seeded from `&[seed >> 32, seed & 0xffff_ffff, index]`
+    #[cfg(feature = "rayon")]
+    fn generate_indexed<'a>(
+        &'a self,
+        start: &'a str,
+        n: usize,
+        seed: u64,
+    ) -> impl rayon::iter::IndexedParallelIterator<Item = Result<String, Error>> + 'a {
+        use rayon::prelude::*;
+
+        let seed = [(seed >> 32) as usize, (seed & 0xffff_ffff) as usize];
+        (0..n).into_par_iter().map(move |index| {
+            let mut rng: StdRng = SeedableRng::from_seed(&[seed[0], seed[1], index][..]);
+            let mut sentence = String::new();
+            traverse(self, start, &mut rng, &mut sentence)?;
+            Ok(sentence)
+        })
+    }
+
+    /// Generate `count` random sentences from the nonterminal `start` in
+    /// parallel on rayon's thread pool, with the feature `rayon`.
+    ///
+    /// The sentence at index `i` is generated with a `StdRng` of its own,
+    /// seeded from `&[seed >> 32, seed & 0xffff_ffff, i]` as in
+    /// `Grammar::generate_batch`, so the sentences are reproducible for a
+    /// given `seed` whichever thread generates them. The first sentence which
+    /// fails to generate fails them all.
+    ///
+    /// # Example
+    ///
+    /// ```rust
+    /// extern crate bnf;
+    /// use bnf::Grammar;
+    ///
+    /// fn main() {
+    ///     let grammar = Grammar::from_str("<dna> ::= <base> | <base> <dna>
+    ///         <base> ::= \"A\" | \"C\" | \"G\" | \"T\"").unwrap();
+    ///     let bases = grammar.generate_parallel("base", 1000, 42).unwrap();
+    ///
+    ///     assert_eq!(bases.len(), 1000);
+    ///     assert!(bases.iter().all(|base| base.len() == 1));
+    ///     assert_eq!(grammar.generate_parallel("base", 1000, 42), Ok(bases));
+    /// }
+    /// ```
+    #[cfg(feature = "rayon")]
+    pub fn generate_parallel(
+        &self,
+        start: impl Into<Nonterminal>,
+        count: usize,
+        seed: u64,
+    ) -> Result<Vec<String>, Error> {
+        use rayon::prelude::*;
+
+        let start = start.into();
+        self.generate_indexed(start.name(), count, seed).collect()
+    }
+
+    /// Generate up to `n` distinct sentences within the limits of `options`.
+    ///
+    /// Sampling stops once `n` distinct sentences are found, or after
+    /// `GenerateOptions::max_stale_attempts` samples in a row which fail or
+    /// only repeat earlier sentences. With a `GenerateOptions::max_len` set,
+    /// it also stops once every sentence within that length has been found.
+    /// When sampling stops early, the result has fewer than `n` sentences,
+    /// and its `DistinctSentences::shortfall` says how many fewer.
+    ///
+    /// # Example
+    ///
+    /// ```rust
+    /// extern crate bnf;
+    /// use bnf::{GenerateOptions, Grammar};
+    ///
+    /// fn main() {
+    ///     let grammar = Grammar::from_str("<bit> ::= \"0\" | \"1\"").unwrap();
+    ///     let options = GenerateOptions::new().max_len(1);
+    ///     let bits = grammar.generate_distinct(5, &options).unwrap();
+    ///
+    ///     assert_eq!(bits.sentences().len(), 2);
+    ///     assert_eq!(bits.shortfall(), 3);
+    ///     assert!(bits.is_exhausted());
+    /// }
+    /// ```
+    pub fn generate_distinct(
+        &self,
+        n: usize,
+        options: &GenerateOptions,
+    ) -> Result<DistinctSentences, Error> {
+        let start = start_symbol(self)?;
+        let most = options
+            .max_len
+            .and_then(|max_len| self.count_sentences_within(start, max_len));
+
+        let seed: Vec<usize> = thread_rng().gen_iter::<usize>().take(1000).collect();
+        let mut rng: StdRng = SeedableRng::from_seed(&seed[..]);
+
+        let mut sentences = vec![];
+        let mut seen = HashSet::new();
+        let budget = Budget::new(self, options);
+        let mut stale = 0;
+        let mut exhausted = false;
+        while sentences.len() < n && stale < options.max_stale_attempts {
+            if most.as_ref() == Some(&BigUint::from(sentences.len())) {
+                exhausted = true;
+                break;
+            }
+
+            let mut limited = Limited::new(options, &budget);
+            let generated = traverse(self, start, &mut rng, &mut limited);
+            match generated.and_then(|_| limited.constrain()) {
+                Ok(()) if seen.insert(limited.sentence.clone()) => {
+                    sentences.push(limited.sentence);
+                    stale = 0;
+                }
+                _ => stale += 1,
+            }
+        }
+        Ok(DistinctSentences {
+            shortfall: n - sentences.len(),
+            sentences,
+            exhausted,
+        })
+    }
+
+    /// Generate up to `count` distinct sentences from the nonterminal
+    /// `start`, reproducibly for a given `seed`, as for a test corpus.
+    ///
+    /// Sampling stops once `count` distinct sentences are found, or after as
+    /// many samples in a row which fail or only repeat earlier sentences as
+    /// `GenerateOptions::max_stale_attempts` allows by default, so a language
+    /// of fewer sentences gives fewer. The sentences are in the order they
+    /// were first generated. A `start` which isn't a nonterminal gives none.
+    ///
+    /// # Example
+    ///
+    /// ```rust
+    /// extern crate bnf;
+    /// use bnf::Grammar;
+    ///
+    /// fn main() {
+    ///     let grammar = Grammar::from_str("<dna> ::= <base> | <base> <dna>
+    ///         <base> ::= \"A\" | \"C\" | \"G\" | \"T\"").unwrap();
+    ///
+    ///     assert_eq!(grammar.sample("dna", 10, 42).len(), 10);
+    ///     assert_eq!(grammar.sample("dna", 10, 42), grammar.sample("dna", 10, 42));
+    ///     assert_eq!(grammar.sample("base", 10, 42).len(), 4);
+    /// }
+    /// ```
+    pub fn sample(&self, start: impl StartSymbol, count: usize, seed: u64) -> Vec<String> {
+        let start = match start.into_term() {
+            Term::Nonterminal(nt) => nt,
+            _ => return vec![],
+        };
+        let seed: &[_] = &[(seed >> 32) as usize, (seed & 0xffff_ffff) as usize];
+        let mut rng: StdRng = SeedableRng::from_seed(seed);
+        let max_stale_attempts = GenerateOptions::new().max_stale_attempts;
+
+        let mut sentences = vec![];
+        let mut seen = HashSet::new();
+        let mut stale = 0;
+        while sentences.len() < count && stale < max_stale_attempts {
+            let mut sentence = String::new();
+            match traverse(self, &start, &mut rng, &mut sentence) {
+                Ok(()) if seen.insert(sentence.clone()) => {
+                    sentences.push(sentence);
+                    stale = 0;
+                }
+                _ => stale += 1,
+            }
+        }
+        sentences
+    }
+
+    /// Bound the number of distinct sentences of at most `max_len` characters
+    /// derived from `start`, when every reachable nonterminal is defined
+    fn count_sentences_within(&self, start: &str, max_len: usize) -> Option<BigUint> {
+        let start_term = Term::Nonterminal(String::from(start));
+        let defined = self
+            .reachable_from(&start_term)
+            .into_iter()
+            .all(|term| self.productions_iter().any(|prod| *prod.lhs() == *term));
+        if !defined {
+            // undefined nonterminals are generated as-is, which isn't counted
+            return None;
+        }
+
+        let mut counter = self.derivation_counter();
+        Some(
+            (0..=max_len)
+                .map(|len| counter.count_from(start, len))
+                .sum(),
+        )
+    }
+
+    /// Get an endless iterator of random sentences from the start symbol.
+    ///
+    /// Every sentence is generated independently, sharing only `rng`. A
+    /// sentence which fails to generate, e.g. on the recursion limit, is
+    /// yielded as an `Err` without ending the iterator.
+    ///
+    /// # Example
+    ///
+    /// ```rust
+    /// extern crate bnf;
+    /// extern crate rand;
+    /// use bnf::Grammar;
+    ///
+    /// fn main() {
+    ///     let grammar = Grammar::from_str("<dna> ::= <base> | <base> <dna>
+    ///         <base> ::= \"A\" | \"C\" | \"G\" | \"T\"").unwrap();
+    ///     let corpus = grammar
+    ///         .generate_iter(rand::thread_rng())
+    ///         .take(100)
+    ///         .collect::<Result<Vec<_>, _>>();
+    ///
+    ///     assert_eq!(corpus.map(|sentences| sentences.len()), Ok(100));
+    /// }
+    /// ```
+    pub fn generate_iter<R: Rng>(&self, rng: R) -> GenerateIter<'_, R> {
+        GenerateIter {
+            grammar: self,
+            rng,
+            limits: None,
+        }
+    }
+
+    /// Get an endless iterator of random sentences from the start symbol,
+    /// each generated within the limits of `options` as
+    /// `Grammar::generate_with_options` does.
+    ///
+    /// As for `Grammar::generate_iter`, every sentence is generated
+    /// independently, sharing only `rng`, and a sentence which fails to
+    /// generate, e.g. on exceeding a limit, is yielded as an `Err` without
+    /// ending the iterator.
+    ///
+    /// # Example
+    ///
+    /// ```rust
+    /// extern crate bnf;
+    /// extern crate rand;
+    /// use bnf::{GenerateOptions, Grammar};
+    ///
+    /// fn main() {
+    ///     let grammar = Grammar::from_str("<dna> ::= <base> | <base> <dna>
+    ///         <base> ::= \"A\" | \"C\" | \"G\" | \"T\"").unwrap();
+    ///     let options = GenerateOptions::new().max_len(5);
+    ///     let corpus = grammar
+    ///         .generate_iter_with(&options, rand::thread_rng())
+    ///         .take(100)
+    ///         .collect::<Result<Vec<_>, _>>()
+    ///         .unwrap();
+    ///
+    ///     assert!(corpus.iter().all(|sentence| sentence.len() <= 5));
+    /// }
+    /// ```
+    pub fn generate_iter_with<'a, R: Rng>(
+        &'a self,
+        options: &'a GenerateOptions,
+        rng: R,
+    ) -> GenerateIter<'a, R> {
+        GenerateIter {
+            grammar: self,
+            rng,
+            limits: Some((options, Budget::new(self, options))),
+        }
+    }
+
+    /// Generate a random sentence from the nonterminal `start`, choosing each
+    /// alternative in proportion to its weight in `weights`.
+    ///
+    /// Weights are keyed by the name of a production's lhs, without its angle
+    /// brackets, and the index of the alternative in the production. An
+    /// alternative without a weight there weighs its `%weight`, or 1.0 when
+    /// it has none either. Returns `Error::GenerateError` for a weight which
+    /// is negative or not finite, or when every alternative of a production
+    /// being expanded weighs 0.
+    ///
+    /// # Example
+    ///
+    /// ```rust
+    /// extern crate bnf;
+    /// extern crate rand;
+    /// use bnf::Grammar;
+    /// use std::collections::HashMap;
+    ///
+    /// fn main() {
+    ///     let grammar = Grammar::from_str("<word> ::= <letter> | <letter> <word>
+    ///         <letter> ::= \"e\" | \"q\"").unwrap();
+    ///     let mut weights = HashMap::new();
+    ///     weights.insert((String::from("word"), 1), 3.0);
+    ///     weights.insert((String::from("letter"), 1), 0.0);
+    ///     let word = grammar.generate_with_weights("word", &weights, rand::thread_rng()).unwrap();
+    ///
+    ///     assert!(word.chars().all(|c| c == 'e'));
+    /// }
+    /// ```
+    pub fn generate_with_weights<R: Rng>(
+        &self,
+        start: impl Into<Nonterminal>,
+        weights: &HashMap<(String, usize), f64>,
+        mut rng: R,
+    ) -> Result<String, Error> {
+        if let Some(((name, index), weight)) = weights
+            .iter()
+            .find(|&(_, &weight)| weight < 0.0 || !weight.is_finite())
+        {
+            return Err(Error::GenerateError(format!(
+                "Weight {} of alternative {} of <{}> isn't a finite, non-negative number!",
+                weight, index, name
+            )));
+        }
+
+        let mut weighted = Weighted {
+            sentence: String::new(),
+            weights,
+        };
+        traverse(self, start.into().name(), &mut rng, &mut weighted)?;
+        Ok(weighted.sentence)
+    }
+
+    /// Generate a random sentence from the start symbol, along with the
+    /// `ParseTree` it was derived by.
+    ///
+    /// The tree flattens back to the sentence, and is valid for the grammar
+    /// according to `Grammar::validate_tree`.
+    ///
+    /// # Example
+    ///
+    /// ```rust
+    /// extern crate bnf;
+    /// extern crate rand;
+    /// use bnf::Grammar;
+    ///
+    /// fn main() {
+    ///     let grammar = Grammar::from_str("<dna> ::= <base> | <base> <dna>
+    ///         <base> ::= \"A\" | \"C\" | \"G\" | \"T\"").unwrap();
+    ///     let (sentence, tree) = grammar.generate_tree(&mut rand::thread_rng()).unwrap();
+    ///
+    ///     assert_eq!(tree.flatten(), sentence);
+    ///     assert!(grammar.validate_tree(&tree).is_ok());
+    /// }
+    /// ```
+    pub fn generate_tree<R: Rng>(&self, rng: &mut R) -> Result<(String, ParseTree), Error> {
+        let start = start_symbol(self)?;
+        let tree = generate_tree_from(self, start, rng)?;
+        Ok((tree.flatten(), tree))
+    }
+
+    /// Generate sentences until every alternative reachable from the start
+    /// symbol has been expanded at least once, or `max_attempts` sentences
+    /// have been attempted.
+    ///
+    /// Alternatives which have not been expanded yet are preferred. Attempts
+    /// which fail, e.g. on the recursion limit, are not counted towards
+    /// coverage. Alternatives of productions which are unreachable, or which
+    /// share the lhs of an earlier production and so are never generated
+    /// from, remain uncovered.
+    ///
+    /// # Example
+    ///
+    /// ```rust
+    /// extern crate bnf;
+    /// extern crate rand;
+    /// use bnf::Grammar;
+    ///
+    /// fn main() {
+    ///     let grammar = Grammar::from_str("<dna> ::= <base> | <base> <dna>
+    ///         <base> ::= \"A\" | \"C\" | \"G\" | \"T\"").unwrap();
+    ///     let coverage = grammar.generate_covering(&mut rand::thread_rng(), 100).unwrap();
+    ///
+    ///     assert!(coverage.is_complete());
+    ///     assert!(coverage.sentences().len() <= 100);
+    /// }
+    /// ```
+    pub fn generate_covering<R: Rng>(
+        &self,
+        rng: &mut R,
+        max_attempts: usize,
+    ) -> Result<Coverage, Error> {
+        let start = start_symbol(self)?;
+
+        let mut alternatives = vec![];
+        let mut offsets: HashMap<&Term, usize> = HashMap::new();
+        for production in self.productions_iter() {
+            let offset = offsets.entry(production.lhs()).or_insert(0);
+            alternatives.extend(
+                (0..production.len()).map(|index| (production.lhs().clone(), *offset + index)),
+            );
+            *offset += production.len();
+        }
+
+        // only the first production of a reachable lhs is ever generated from
+        let start_term = Term::Nonterminal(String::from(start));
+        let reachable = self.reachable_from(&start_term);
+        let mut targets = vec![];
+        for lhs in reachable {
+            if let Some(production) = self.productions_iter().find(|prod| *prod.lhs() == *lhs) {
+                targets.extend((0..production.len()).map(|index| (lhs.clone(), index)));
+            }
+        }
+
+        let mut sentences = vec![];
+        let mut counts = HashMap::new();
+        for _ in 0..max_attempts {
+            if targets.iter().all(|key| counts.contains_key(key)) {
+                break;
+            }
+
+            let (fired, sentence) = {
+                let mut covering = Covering {
+                    covered: &counts,
+                    fired: HashMap::new(),
+                    sentence: String::new(),
+                };
+                match traverse(self, start, rng, &mut covering) {
+                    Ok(()) => (covering.fired, covering.sentence),
+                    Err(_) => continue,
+                }
+            };
+
+            for (key, count) in fired {
+                *counts.entry(key).or_insert(0) += count;
+            }
+            sentences.push(sentence);
+        }
+
+        Ok(Coverage {
+            sentences,
+            alternatives,
+            counts,
+        })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::time::Duration;
+
+    #[test]
+    fn generates_with_weights() {
+        let grammar = Grammar::from_str(
+            "<s> ::= <coin> <coin> <coin> <coin>
+            <coin> ::= \"H\" | \"T\" %weight 5 | \"E\" %weight 0",
+        )
+        .unwrap();
+        let mut weights = HashMap::new();
+        weights.insert((String::from("coin"), 0), 0.25);
+        weights.insert((String::from("coin"), 1), 0.75);
+        let mut rng: StdRng = SeedableRng::from_seed(&[1, 2, 3][..]);
+        let mut heads = 0;
+        for _ in 0..250 {
+            let sentence = grammar
+                .generate_with_weights("s", &weights, &mut rng)
+                .unwrap();
+            assert_eq!(sentence.len(), 4);
+            assert!(!sentence.contains('E'));
+            heads += sentence.matches('H').count();
+        }
+        // a quarter of 1000 coins, give or take
+        assert!(heads > 180 && heads < 320, "{}", heads);
+
+        // unweighted alternatives keep their %weight
+        weights.remove(&(String::from("coin"), 1));
+        weights.insert((String::from("coin"), 0), 0.0);
+        let sentence = grammar.generate_with_weights("coin", &weights, &mut rng);
+        assert_eq!(sentence, Ok(String::from("T")));
+        assert_eq!(
+            grammar.generate_with_weights("undefined", &weights, &mut rng),
+            Ok(String::from("<undefined>"))
+        );
+    }
+
+    #[test]
+    fn rejects_invalid_weights() {
+        let grammar = Grammar::from_str("<coin> ::= \"H\" | \"T\"").unwrap();
+        let mut rng = thread_rng();
+        let mut weights = HashMap::new();
+        weights.insert((String::from("coin"), 0), 0.0);
+        weights.insert((String::from("coin"), 1), 0.0);
+        assert!(grammar
+            .generate_with_weights("coin", &weights, &mut rng)
+            .is_err());
+        for &weight in &[-1.0, f64::NAN, f64::INFINITY] {
+            weights.insert((String::from("coin"), 1), weight);
+            assert!(matches!(
+                grammar.generate_with_weights("coin", &weights, &mut rng),
+                Err(Error::GenerateError(_))
+            ));
+        }
+    }
+
+    #[test]
+    fn covers_every_alternative() {
+        let grammar = Grammar::from_str(
+            "<sentence> ::= <subject> <verb> | <subject> <verb> <object>
+            <subject> ::= \"I\" | \"you\" | \"they\"
+            <verb> ::= \"see\" | \"hear\"
+            <object> ::= \"it\" | \"them\"",
+        )
+        .unwrap();
+        let coverage = grammar
+            .generate_covering(&mut rand::thread_rng(), 1000)
+            .unwrap();
+
+        assert!(coverage.is_complete(), "{:?}", coverage.uncovered());
+        assert!(coverage.uncovered().is_empty());
+        // every alternative of <subject> needs its own sentence
+        assert!(coverage.sentences().len() >= 3);
+        assert!(coverage.sentences().len() < 1000);
+        let subject = Term::Nonterminal(String::from("subject"));
+        for index in 0..3 {
+            assert!(coverage.count(&subject, index) > 0);
+        }
+        assert_eq!(coverage.count(&subject, 3), 0);
+    }
+
+    #[test]
+    fn reports_unreachable_alternatives() {
+        let grammar = Grammar::from_str(
+            "<start> ::= \"a\" | \"b\"
+            <unused> ::= \"c\"
+            <start> ::= \"d\"",
+        )
+        .unwrap();
+        let coverage = grammar
+            .generate_covering(&mut rand::thread_rng(), 1000)
+            .unwrap();
+
+        assert!(!coverage.is_complete());
+        assert_eq!(
+            coverage.uncovered(),
+            vec![
+                (Term::Nonterminal(String::from("unused")), 0),
+                (Term::Nonterminal(String::from("start")), 2),
+            ]
+        );
+        // stops once the reachable alternatives are covered
+        assert_eq!(coverage.sentences().len(), 2);
+    }
+
+    #[test]
+    fn generates_repetitions() {
+        let grammar = Grammar::from_str("<s> ::= 1000\"a\" 0*2( \"b\" | \"c\" )").unwrap();
+        for seed in 0..10 {
+            let mut rng: StdRng = SeedableRng::from_seed(&[seed][..]);
+            let sentence = grammar.generate_seeded(&mut rng).unwrap();
+            assert!(sentence.starts_with(&"a".repeat(1000)), "{}", sentence);
+            assert!(sentence.len() <= 1002, "{}", sentence);
+            let (sentence, tree) = grammar.generate_tree(&mut rng).unwrap();
+            assert_eq!(tree.flatten(), sentence);
+            assert_eq!(grammar.validate_tree(&tree), Ok(()));
+        }
+    }
+
+    #[test]
+    fn generates_valid_trees() {
+        let grammar = Grammar::from_str(
+            "<expr> ::= <num> | <expr> <op> <expr> | \"(\" <expr> \")\" | <undefined>
+            <op> ::= \"+\" | \"*\"
+            <num> ::= [0-9] | [1-9] <num>",
+        )
+        .unwrap();
+        let mut rng = rand::thread_rng();
+        let mut generated = 0;
+        while generated < 100 {
+            let (sentence, tree) = match grammar.generate_tree(&mut rng) {
+                Ok(generated) => generated,
+                Err(Error::RecursionLimit(_)) => continue,
+                Err(e) => panic!("should generate: {}", e),
+            };
+            generated += 1;
+            assert_eq!(tree.flatten(), sentence);
+            assert_eq!(tree.lhs, Term::Nonterminal(String::from("expr")));
+            assert!(grammar.validate_tree(&tree).is_ok(), "{:?}", tree);
+            let alternative = tree
+                .alternative_index()
+                .and_then(|index| grammar.productions_iter().next()?.rhs_iter().nth(index));
+            assert_eq!(alternative, Some(&tree.expression));
+        }
+
+        let lhs = Term::Nonterminal(String::from("start"));
+        let grammar = Grammar::from_parts(vec![Production::from_parts(lhs, vec![])]);
+        assert!(grammar.generate_tree(&mut rng).is_err());
+    }
+
+    #[test]
+    fn iterates_independent_sentences() {
+        let grammar = Grammar::from_str(
+            "<dna> ::= <base> | <base> <dna>
+            <base> ::= \"A\" | \"C\" | \"G\" | \"T\"",
+        )
+        .unwrap();
+        let mut rng = rand::thread_rng();
+        let sentences = grammar
+            .generate_iter(&mut rng)
+            .take(1000)
+            .collect::<Result<Vec<_>, _>>()
+            .unwrap();
+        assert_eq!(sentences.len(), 1000);
+        assert!(sentences
+            .iter()
+            .all(|s| !s.is_empty() && s.chars().all(|c| "ACGT".contains(c))));
+        // a single base is derived with probability 1/2 every time
+        let single = sentences.iter().filter(|s| s.len() == 1).count();
+        assert!(single > 400 && single < 600, "{} single bases", single);
+
+        let seed: &[_] = &[1, 2, 3, 4];
+        let seeded: StdRng = SeedableRng::from_seed(seed);
+        let mut rng: StdRng = SeedableRng::from_seed(seed);
+        let first = grammar.generate_iter(seeded).take(5).collect::<Vec<_>>();
+        let again = (0..5)
+            .map(|_| grammar.generate_seeded(&mut rng))
+            .collect::<Vec<_>>();
+        assert_eq!(first, again);
+    }
+
+    #[test]
+    fn iterates_within_limits() {
+        let grammar = Grammar::from_str(
+            "<dna> ::= <base> | <base> <dna>
+            <base> ::= \"A\" | \"C\" | \"G\" | \"T\"",
+        )
+        .unwrap();
+        let options = GenerateOptions::new().max_len(3);
+        let sentences = grammar
+            .generate_iter_with(&options, rand::thread_rng())
+            .take(500)
+            .collect::<Result<Vec<_>, _>>()
+            .unwrap();
+        assert!(sentences.iter().all(|s| !s.is_empty() && s.len() <= 3));
+        assert!(sentences.iter().any(|s| s.len() == 3));
+
+        let options = GenerateOptions::new().max_depth(2);
+        let sentences = grammar
+            .generate_iter_with(&options, rand::thread_rng())
+            .take(500)
+            .collect::<Result<Vec<_>, _>>()
+            .unwrap();
+        // <dna> and <base> are each expanded at most once
+        assert!(sentences.iter().all(|s| s.len() == 1));
+
+        // a failure is an item of its own, and the iterator goes on
+        let grammar = Grammar::from_str("<as> ::= \"a\" <as> | \"a\" \"a\" \"a\"").unwrap();
+        let options = GenerateOptions::new().max_len(2);
+        let results = grammar
+            .generate_iter_with(&options, rand::thread_rng())
+            .take(3)
+            .collect::<Vec<_>>();
+        assert_eq!(results.len(), 3);
+        assert!(results
+            .iter()
+            .all(|result| matches!(*result, Err(Error::LimitError(_)))));
+    }
+
+    #[test]
+    fn iterates_past_failures() {
+        let grammar = Grammar::from_str("<nonterm> ::= <nonterm>").unwrap();
+        let results = grammar
+            .generate_iter(rand::thread_rng())
+            .take(3)
+            .collect::<Vec<_>>();
+        assert_eq!(results.len(), 3);
+        assert!(results
+            .iter()
+            .all(|result| matches!(*result, Err(Error::RecursionLimit(_)))));
+        assert!(Grammar::new()
+            .generate_iter(rand::thread_rng())
+            .take(2)
+            .all(|result| result.is_err()));
+    }
+
+    #[test]
+    fn generates_distinct_sentences() {
+        let grammar = Grammar::from_str(
+            "<dna> ::= <base> | <base> <dna>
+            <base> ::= \"A\" | \"C\" | \"G\" | \"T\"",
+        )
+        .unwrap();
+        let distinct = grammar
+            .generate_distinct(50, &GenerateOptions::new())
+            .unwrap();
+        assert_eq!(distinct.shortfall(), 0);
+        let sentences = distinct.into_sentences();
+        assert_eq!(sentences.len(), 50);
+        let unique = sentences.iter().collect::<HashSet<_>>();
+        assert_eq!(unique.len(), 50);
+
+        // there are only 4 + 16 sentences of at most 2 bases
+        let options = GenerateOptions::new().max_len(2).max_stale_attempts(10_000);
+        let distinct = grammar.generate_distinct(50, &options).unwrap();
+        assert_eq!(distinct.sentences().len(), 20);
+        assert!(distinct.sentences().iter().all(|s| s.len() <= 2));
+        assert_eq!(distinct.shortfall(), 30);
+        assert!(distinct.is_exhausted());
+    }
+
+    #[test]
+    fn generates_distinct_until_stale() {
+        let grammar = Grammar::from_str(
+            "<start> ::= <a> | <a>
+            <a> ::= <undefined> | \"a\"",
+        )
+        .unwrap();
+        let options = GenerateOptions::new().max_len(20);
+        let distinct = grammar.generate_distinct(5, &options).unwrap();
+        // undefined nonterminals aren't counted, so sampling goes stale
+        assert_eq!(distinct.shortfall(), 3);
+        assert!(!distinct.is_exhausted());
+        let mut sentences = distinct.into_sentences();
+        sentences.sort();
+        assert_eq!(sentences, vec!["<undefined>", "a"]);
+
+        let grammar = Grammar::from_str("<nonterm> ::= <nonterm> | \"x\" <nonterm>").unwrap();
+        let options = GenerateOptions::new().max_depth(10).max_stale_attempts(5);
+        let distinct = grammar.generate_distinct(5, &options).unwrap();
+        assert!(distinct.sentences().is_empty());
+        assert_eq!(distinct.shortfall(), 5);
+        assert!(Grammar::new()
+            .generate_distinct(5, &GenerateOptions::new())
+            .is_err());
+    }
+
+    #[test]
+    fn samples_distinct_sentences() {
+        let grammar = Grammar::from_str(
+            "<dna> ::= <base> | <base> <dna>
+            <base> ::= \"A\" | \"C\" | \"G\" | \"T\"
+            <loop> ::= <loop>",
+        )
+        .unwrap();
+        let dna = Term::from_str("<dna>").unwrap();
+        let sentences = grammar.sample(&dna, 50, 7);
+        assert_eq!(sentences.len(), 50);
+        assert_eq!(sentences.iter().collect::<HashSet<_>>().len(), 50);
+        assert_eq!(grammar.sample(&dna, 50, 7), sentences);
+        assert_ne!(grammar.sample(&dna, 50, 8), sentences);
+
+        // the language of <base> is smaller than the sample
+        let mut bases = grammar.sample("base", 10, 7);
+        bases.sort();
+        assert_eq!(bases, vec!["A", "C", "G", "T"]);
+        assert!(grammar.sample(&dna, 0, 7).is_empty());
+        assert!(grammar.sample("loop", 5, 7).is_empty());
+        assert!(grammar
+            .sample(&Term::from_str("\"A\"").unwrap(), 5, 7)
+            .is_empty());
+    }
+
+    #[test]
+    fn limits_depth_and_length() {
+        let grammar = Grammar::from_str("<as> ::= \"a\" <as> | \"\"").unwrap();
+        let mut rng = rand::thread_rng();
+        for _ in 0..100 {
+            let options = GenerateOptions::new().max_depth(3);
+            let budget = Budget::new(&grammar, &options);
+            let mut limited = Limited::new(&options, &budget);
+            match traverse(&grammar, "as", &mut rng, &mut limited) {
+                Ok(()) => assert!(limited.sentence.len() < 3, "{}", limited.sentence),
+                Err(Error::LimitError(e)) => {
+                    assert_eq!(e.limit(), Limit::Depth(3));
+                    assert_eq!(e.depth(), 4);
+                    assert_eq!(e.partial(), "aaa");
+                    assert_eq!(
+                        e.to_string(),
+                        "Limit for depth of 3 reached: <as> -> <as> -> <as> -> <as>"
+                    );
+                }
+                Err(e) => panic!("should reach the depth limit: {:?}", e),
+            }
+
+            let options = GenerateOptions::new().max_len(2);
+            let budget = Budget::new(&grammar, &options);
+            let mut limited = Limited::new(&options, &budget);
+            match traverse(&grammar, "as", &mut rng, &mut limited) {
+                Ok(()) => assert!(limited.sentence.len() <= 2, "{}", limited.sentence),
+                Err(Error::LimitError(e)) => {
+                    assert_eq!(e.limit(), Limit::Length(2));
+                    assert_eq!(e.nonterminal(), Term::from_str("<as>").ok().as_ref());
+                    assert_eq!(e.depth(), 3);
+                    assert_eq!(e.partial(), "aa");
+                    assert_eq!(
+                        e.to_string(),
+                        "Limit for length of 2 characters reached: <as> -> <as> -> <as>"
+                    );
+                }
+                Err(e) => panic!("should reach the length limit: {:?}", e),
+            }
+        }
+    }
+
+    #[test]
+    fn separates_terminals() {
+        let grammar = Grammar::from_str(
+            "<stmts> ::= <stmt> <stmt> <stmt>
+            <stmt> ::= \"let\" \"x\" \"\" \"=\" [0-9] \";\"",
+        )
+        .unwrap();
+        let seed: &[_] = &[1, 2, 3, 4];
+        let default = GenerateOptions::new();
+        for _ in 0..10 {
+            let expected = grammar.generate_seeded(&mut SeedableRng::from_seed(seed));
+            let mut rng: StdRng = SeedableRng::from_seed(seed);
+            assert_eq!(grammar.generate_with_options(&default, &mut rng), expected);
+        }
+
+        let mut rng = rand::thread_rng();
+        let spaced = GenerateOptions::new().separator(" ");
+        let sentence = grammar.generate_with_options(&spaced, &mut rng).unwrap();
+        let tokens = sentence.split(' ').collect::<Vec<_>>();
+        assert_eq!(tokens.len(), 15, "{}", sentence);
+        assert_eq!(&tokens[..3], &["let", "x", "="]);
+
+        let statements = GenerateOptions::new()
+            .separator_with(|previous, _| String::from(if previous == ";" { "\n" } else { " " }));
+        let sentence = grammar
+            .generate_with_options(&statements, &mut rng)
+            .unwrap();
+        assert_eq!(sentence.lines().count(), 3, "{}", sentence);
+        assert!(sentence.lines().all(|line| line.starts_with("let x = ")));
+
+        let limited = GenerateOptions::new().separator("  ").max_len(27);
+        assert!(grammar.generate_with_options(&limited, &mut rng).is_err());
+    }
+
+    #[test]
+    fn traces_generation() {
+        let grammar = Grammar::from_str(
+            "<expr> ::= <num> | <expr> \"+\" <expr>
+            <num> ::= <digit> | <digit> <num>
+            <digit> ::= \"0\" | \"1\"",
+        )
+        .unwrap();
+        let seed: &[_] = &[5, 6, 7, 8];
+        let options = GenerateOptions::new().max_depth(8);
+        for _ in 0..10 {
+            let mut rng: StdRng = SeedableRng::from_seed(seed);
+            let expected = grammar.generate_with_options(&options, &mut rng);
+            let mut rng: StdRng = SeedableRng::from_seed(seed);
+            let traced = grammar.generate_traced(&options, &mut rng);
+            assert_eq!(traced.map(|(sentence, _)| sentence), expected);
+        }
+
+        let mut rng = rand::thread_rng();
+        let expr = Term::from_str("<expr>").unwrap();
+        let digit = Term::from_str("<digit>").unwrap();
+        for _ in 0..100 {
+            let (sentence, trace) = match grammar.generate_traced(&options, &mut rng) {
+                Ok(traced) => traced,
+                Err(e) => {
+                    assert!(matches!(e, Error::LimitError(_)));
+                    continue;
+                }
+            };
+            let digits = sentence.chars().filter(|&c| c != '+').count();
+            assert_eq!(trace.count(&digit, 0), sentence.matches('0').count());
+            assert_eq!(trace.count(&digit, 1), sentence.matches('1').count());
+            assert_eq!(trace.count(&expr, 1), sentence.matches('+').count());
+            assert_eq!(trace.counts().values().sum::<usize>(), trace.expansions());
+            assert!(trace.expansions() >= 2 * digits);
+            assert!(trace.max_depth() >= 2 && trace.max_depth() <= 8);
+        }
+    }
+
+    #[cfg(feature = "rayon")]
+    #[test]
+    fn generates_reproducible_batches() {
+        let grammar = Grammar::from_str(
+            "<expr> ::= <num> | <num> \"+\" <expr> | <undefined>
+            <num> ::= <digit> | <digit> <num>
+            <digit> ::= [0-9]",
+        )
+        .unwrap();
+        let base_seed = 0x1234_5678_9abc_def0;
+        let batch = grammar.generate_batch(1000, base_seed);
+        assert_eq!(batch.len(), 1000);
+        for (index, sentence) in batch.iter().enumerate() {
+            let seed: &[_] = &[0x1234_5678, 0x9abc_def0, index];
+            let expected = grammar.generate_seeded(&mut SeedableRng::from_seed(seed));
+            assert_eq!(*sentence, expected, "{}", index);
+        }
+        assert_eq!(grammar.generate_batch(1000, base_seed), batch);
+        assert_ne!(grammar.generate_batch(1000, base_seed + 1), batch);
+
+        assert!(grammar.generate_batch(0, base_seed).is_empty());
+        let failures = Grammar::new().generate_batch(3, base_seed);
+        assert_eq!(failures.len(), 3);
+        assert!(failures.iter().all(Result::is_err));
+    }
+
+    #[cfg(feature = "rayon")]
+    #[test]
+    fn generates_in_parallel() {
+        let grammar = Grammar::from_str(
+            "<expr> ::= <num> | <num> \"+\" <expr>
+            <num> ::= <digit> | <digit> <num>
+            <digit> ::= [0-9]",
+        )
+        .unwrap();
+        let seed = 0x1234_5678_9abc_def0;
+        let sums = grammar.generate_parallel("expr", 1000, seed).unwrap();
+        assert_eq!(sums.len(), 1000);
+        let expr = Nonterminal::from("expr");
+        assert!(sums.iter().all(|sum| grammar.count_parses(&expr, sum) > 0));
+        for (index, sum) in sums.iter().enumerate() {
+            let mut rng: StdRng = SeedableRng::from_seed(&[0x1234_5678, 0x9abc_def0, index][..]);
+            let mut expected = String::new();
+            traverse(&grammar, "expr", &mut rng, &mut expected).unwrap();
+            assert_eq!(*sum, expected, "{}", index);
+        }
+        assert_eq!(
+            grammar.generate_parallel("expr", 1000, seed),
+            Ok(sums.clone())
+        );
+        assert_ne!(grammar.generate_parallel("expr", 1000, seed + 1), Ok(sums));
+        let digits = grammar.generate_parallel("digit", 100, seed).unwrap();
+        assert!(digits.iter().all(|digit| digit.len() == 1));
+        assert_eq!(grammar.generate_parallel("expr", 0, seed), Ok(vec![]));
+
+        let forever = Grammar::from_str("<forever> ::= \"a\" <forever>").unwrap();
+        match forever.generate_parallel("forever", 4, seed) {
+            Err(Error::RecursionLimit(_)) => {}
+            result => panic!("should fail on the recursion limit: {:?}", result),
+        }
+    }
+
+    #[test]
+    fn satisfies_constraints() {
+        let grammar = Grammar::from_str(
+            "<list> ::= <item> | <item> \",\" <list>
+            <item> ::= \"X\" | <word>
+            <word> ::= [a-c] | [a-c] <word>",
+        )
+        .unwrap();
+        let at_most_one_x = |sentence: &str, _: &[Term]| sentence.matches('X').count() <= 1;
+        let options = GenerateOptions::new()
+            .max_depth(30)
+            .constraint(at_most_one_x);
+        let mut rng = rand::thread_rng();
+        let mut generated = 0;
+        for _ in 0..200 {
+            match grammar.generate_with_options(&options, &mut rng) {
+                Ok(sentence) => {
+                    assert!(sentence.matches('X').count() <= 1, "{}", sentence);
+                    generated += 1;
+                }
+                Err(e) => assert!(matches!(e, Error::LimitError(_) | Error::GenerateError(_))),
+            }
+        }
+        assert!(generated > 150, "only {} generated", generated);
+
+        // the derivation context holds the lhs being expanded
+        let words_are_short = |sentence: &str, lhs: &[Term]| {
+            let in_word = lhs.last() == Some(&Term::from_str("<word>").unwrap());
+            let word = sentence.rsplit(',').next().unwrap_or("");
+            !in_word || word.len() <= 3
+        };
+        let options = GenerateOptions::new()
+            .max_depth(30)
+            .constraint(words_are_short);
+        for _ in 0..100 {
+            if let Ok(sentence) = grammar.generate_with_options(&options, &mut rng) {
+                assert!(
+                    sentence.split(',').all(|word| word.len() <= 3),
+                    "{}",
+                    sentence
+                );
+            }
+        }
+
+        // the complete sentence is checked, even without terminals
+        let grammar = Grammar::from_str("<empty> ::= \"\"").unwrap();
+        let options = GenerateOptions::new().constraint(|sentence, _| !sentence.is_empty());
+        assert!(grammar.generate_with_options(&options, &mut rng).is_err());
+        let options = GenerateOptions::new()
+            .constraint(|_, _| false)
+            .constraint_retries(0);
+        assert!(grammar.generate_with_options(&options, &mut rng).is_err());
+    }
+
+    #[test]
+    fn generates_within_bytes() {
+        let grammar = Grammar::from_str(
+            "<s> ::= <items> \"end\"
+            <items> ::= <item> <items> | \"\"
+            <item> ::= \"ab\" | [α-ω] | <undefined> | %xC3.A9",
+        )
+        .unwrap();
+        let mut lens = HashSet::new();
+        for seed in 0..200 {
+            let sentence = grammar.generate_bounded(seed, 12).unwrap();
+            assert!(sentence.len() <= 12, "{:?}", sentence);
+            assert!(sentence.ends_with("end"), "{:?}", sentence);
+            lens.insert(sentence.len());
+            assert_eq!(grammar.generate_bounded(seed, 12), Ok(sentence));
+        }
+        assert!(lens.len() > 3);
+        assert_eq!(grammar.generate_bounded(0, 3), Ok(String::from("end")));
+        // a character class emits only characters which fit
+        let grammar = Grammar::from_str("<s> ::= [a-zα-ω]+").unwrap();
+        for seed in 0..100 {
+            let sentence = grammar.generate_bounded(seed, 3).unwrap();
+            assert!(grammar.verify_examples("s", &[&sentence], &[]).is_ok());
+            assert!(sentence.len() <= 3, "{:?}", sentence);
+        }
+    }
+
+    #[test]
+    fn generates_within_bytes_or_fails() {
+        let grammar = Grammar::from_str("<s> ::= \"abc\" <s> | <s> \"d\"").unwrap();
+        assert!(grammar.generate_bounded(0, 100).is_err());
+        let grammar = Grammar::from_str("<s> ::= \"abc\" | <s> \"d\"").unwrap();
+        assert!(grammar.generate_bounded(0, 2).is_err());
+        assert_eq!(grammar.generate_bounded(0, 3), Ok(String::from("abc")));
+        assert!(Grammar::new().generate_bounded(0, 10).is_err());
+
+        // expansions which emit nothing end all the same
+        let grammar = Grammar::from_str("<s> ::= <s> <s> | <s> | \"\" | \"a\"").unwrap();
+        for seed in 0..20 {
+            assert!(grammar.generate_bounded(seed, 5).unwrap().len() <= 5);
+        }
+    }
+
+    #[test]
+    fn generates_lazy_terms() {
+        let grammar = Grammar::from_str(
+            "<expr> ::= <num> | <num> \"+\" <expr> | \"(\" <expr> \")\" | <undefined>
+            <num> ::= <digit> | <digit> <num>
+            <digit> ::= [0-9]",
+        )
+        .unwrap();
+        for seed in 0..100u64 {
+            let seed = seed << 30 | seed;
+            let expected = grammar.generate_seeded(&mut SeedableRng::from_seed(
+                &[(seed >> 32) as usize, (seed & 0xffff_ffff) as usize][..],
+            ));
+            let sentence = grammar
+                .generate_terms(seed)
+                .map(|term| match term {
+                    Term::Terminal(terminal) => terminal,
+                    _ => panic!("should only emit terminals: {}", term),
+                })
+                .collect::<String>();
+            if let Ok(expected) = expected {
+                assert_eq!(sentence, expected);
+            }
+        }
+
+        let grammar = Grammar::from_str("<deep> ::= \"(\" <deep> \")\"").unwrap();
+        let terms = grammar.generate_terms(0).max_depth(50).collect::<Vec<_>>();
+        assert_eq!(terms.len(), 50);
+        assert_eq!(Grammar::new().generate_terms(0).next(), None);
+        let grammar = Grammar::from_str("<a> ::= \"a\" %weight 0").unwrap();
+        assert_eq!(grammar.generate_terms(0).next(), None);
+    }
+
+    #[test]
+    fn flags_truncated_terms() {
+        let grammar = Grammar::from_str("<ab> ::= \"a\" \"b\"").unwrap();
+        let mut terms = grammar.generate_terms(0);
+        assert_eq!(terms.by_ref().count(), 2);
+        assert!(!terms.truncated());
+
+        // on the depth limit
+        let grammar = Grammar::from_str("<deep> ::= \"(\" <deep> \")\"").unwrap();
+        let mut terms = grammar.generate_terms(0).max_depth(50);
+        assert!(!terms.truncated());
+        assert_eq!(terms.by_ref().count(), 50);
+        assert!(terms.truncated());
+
+        // on a regex, which can't be generated from
+        let regex = Term::Regex(String::from("[a-z]+"));
+        let mut grammar = Grammar::new();
+        grammar.add_production(Production::from_parts(
+            Term::Nonterminal(String::from("word")),
+            vec![expression::Expression::from_parts(vec![
+                Term::Terminal(String::from("a")),
+                regex,
+                Term::Terminal(String::from("b")),
+            ])],
+        ));
+        let mut terms = grammar.generate_terms(0);
+        assert_eq!(
+            terms.by_ref().collect::<Vec<_>>(),
+            vec![Term::Terminal(String::from("a"))]
+        );
+        assert!(terms.truncated());
+
+        // on an empty character class
+        let empty = Term::CharClass(CharClass::from_ranges(vec![]));
+        let mut grammar = Grammar::new();
+        grammar.add_production(Production::from_parts(
+            Term::Nonterminal(String::from("char")),
+            vec![expression::Expression::from_parts(vec![
+                Term::Terminal(String::from("a")),
+                empty,
+            ])],
+        ));
+        let mut terms = grammar.generate_terms(0);
+        assert_eq!(terms.by_ref().count(), 1);
+        assert!(terms.truncated());
+    }
+
+    /// A sink which fails once `capacity` bytes have been written
+    struct Full {
+        written: Vec<u8>,
+        capacity: usize,
+    }
+
+    impl io::Write for Full {
+        fn write(&mut self, buf: &[u8]) -> io::Result<usize> {
+            if self.written.len() + buf.len() > self.capacity {
+                return Err(io::Error::new(io::ErrorKind::WriteZero, "full"));
+            }
+            self.written.extend_from_slice(buf);
+            Ok(buf.len())
+        }
+
+        fn flush(&mut self) -> io::Result<()> {
+            Ok(())
+        }
+    }
+
+    #[test]
+    fn streams_into_writers() {
+        let grammar = Grammar::from_str(
+            "<dna> ::= <base> | <base> <dna>
+            <base> ::= \"A\" | \"C\" | \"G\" | \"T\"",
+        )
+        .unwrap();
+        let options = GenerateOptions::new().separator(" ");
+        for seed in 0..20 {
+            let seed: &[_] = &[seed];
+            let expected = grammar.generate_with_options(&options, &mut StdRng::from_seed(seed));
+            let mut output = vec![];
+            let mut rng: StdRng = SeedableRng::from_seed(seed);
+            grammar
+                .generate_into(&mut output, &options, &mut rng)
+                .unwrap();
+            assert_eq!(String::from_utf8(output).ok(), expected.ok());
+        }
+
+        // what was generated before failing has been written
+        let forever = Grammar::from_str("<as> ::= \"a\" <as>").unwrap();
+        let mut output = vec![];
+        let limited = GenerateOptions::new().max_len(5);
+        match forever.generate_into(&mut output, &limited, &mut rand::thread_rng()) {
+            Err(Error::LimitError(e)) => assert_eq!(e.partial(), ""),
+            result => panic!("should reach the length limit: {:?}", result),
+        }
+        assert_eq!(output, b"aaaaa");
+
+        let mut full = Full {
+            written: vec![],
+            capacity: 3,
+        };
+        match forever.generate_into(&mut full, &limited, &mut rand::thread_rng()) {
+            Err(Error::IoError(e)) => assert_eq!(e.kind(), io::ErrorKind::WriteZero),
+            result => panic!("should fail to write: {:?}", result),
+        }
+        assert_eq!(full.written, b"aaa");
+
+        // a constraint is only written once satisfied
+        let constrained = GenerateOptions::new()
+            .max_len(5)
+            .constraint(|sentence, _| sentence.len() < 3);
+        let mut output = vec![];
+        let result = forever.generate_into(&mut output, &constrained, &mut rand::thread_rng());
+        assert!(
+            matches!(result, Err(Error::GenerateError(_))),
+            "{:?}",
+            result
+        );
+        assert!(output.is_empty());
+    }
+
+    #[test]
+    fn generates_bytes() {
+        let grammar = Grammar::from_str(
+            "<packet> ::= %x7F.45 <payload> [a-c]
+            <payload> ::= %xFF <payload> | \"é\" | <undefined>",
+        )
+        .unwrap();
+        for seed in 0..20 {
+            let seed: &[_] = &[seed];
+            let bytes = grammar
+                .generate_bytes(&mut StdRng::from_seed(seed))
+                .unwrap();
+            assert!(bytes.starts_with(b"\x7FE"));
+            let payload = bytes[2..bytes.len() - 1]
+                .iter()
+                .skip_while(|&&byte| byte == 0xFF)
+                .cloned()
+                .collect::<Vec<u8>>();
+            assert!(
+                payload == "é".as_bytes() || payload == b"<undefined>",
+                "{:?}",
+                bytes
+            );
+            assert!(b"abc".contains(&bytes[bytes.len() - 1]));
+        }
+
+        // text of UTF-8 bytes generates the same either way
+        let grammar = Grammar::from_str("<hi> ::= %x68.69 | \"hey\" [a-z]").unwrap();
+        for seed in 0..20 {
+            let seed: &[_] = &[seed];
+            let bytes = grammar
+                .generate_bytes(&mut StdRng::from_seed(seed))
+                .unwrap();
+            let sentence = grammar
+                .generate_seeded(&mut StdRng::from_seed(seed))
+                .unwrap();
+            assert_eq!(bytes, sentence.into_bytes());
+        }
+
+        let grammar = Grammar::from_str("<bad> ::= \"a\" %xFF").unwrap();
+        match grammar.generate() {
+            Err(Error::GenerateError(message)) => assert!(message.contains("%xFF")),
+            result => panic!("should not generate bytes as text: {:?}", result),
+        }
+    }
+
+    #[test]
+    fn retries_reproducibly() {
+        let grammar = Grammar::from_str("<as> ::= \"a\" <as> | \"\"").unwrap();
+        let options = GenerateOptions::new().max_len(2);
+        for seed in 0..50 {
+            let retrying = options.clone().max_retries(100);
+            let (sentence, attempts) = grammar.generate_retrying(&retrying, seed).unwrap();
+            assert!(sentence.len() <= 2);
+            for failed in seed..seed + attempts as u64 - 1 {
+                let result = grammar.generate_retrying(&options, failed);
+                assert!(matches!(result, Err(Error::LimitError(_))), "{:?}", result);
+            }
+            let last = seed + attempts as u64 - 1;
+            assert_eq!(grammar.generate_retrying(&options, last), Ok((sentence, 1)));
+        }
+
+        let options = GenerateOptions::new()
+            .max_retries(4)
+            .constraint(|sentence, _| sentence.len() > 100);
+        match grammar.generate_retrying(&options.constraint_retries(0), 7) {
+            Err(Error::RetriesExhausted(5, e)) => {
+                assert!(matches!(*e, Error::GenerateError(_)))
+            }
+            result => panic!("should exhaust retries: {:?}", result),
+        }
+
+        // neither the deadline nor a missing start are worth retrying
+        let forever = Grammar::from_str("<forever> ::= \"a\" <forever>").unwrap();
+        let options = GenerateOptions::new()
+            .deadline(Instant::now())
+            .max_retries(10);
+        let result = forever.generate_with_options(&options, &mut rand::thread_rng());
+        assert!(matches!(result, Err(Error::LimitError(_))), "{:?}", result);
+        let result = Grammar::new().generate_retrying(&options, 0);
+        assert!(
+            matches!(result, Err(Error::GenerateError(_))),
+            "{:?}",
+            result
+        );
+    }
+
+    #[test]
+    fn stops_at_deadline_or_cancellation() {
+        let grammar = Grammar::from_str(
+            "<forever> ::= <a> <forever>
+            <a> ::= \"a\"",
+        )
+        .unwrap();
+        let mut rng = rand::thread_rng();
+
+        let deadline = Instant::now();
+        let options = GenerateOptions::new().deadline(deadline);
+        match grammar.generate_with_options(&options, &mut rng) {
+            Err(Error::LimitError(e)) => {
+                assert_eq!(e.limit(), Limit::Deadline(deadline));
+                assert_eq!(e.partial(), "");
+                assert_eq!(e.to_string(), "Deadline for generating reached: <forever>");
+            }
+            result => panic!("should reach the deadline: {:?}", result),
+        }
+        let options = GenerateOptions::new().deadline(Instant::now() + Duration::from_secs(60));
+        let sentence = grammar.generate_with_options(&options.max_len(5), &mut rng);
+        assert!(matches!(sentence, Err(Error::LimitError(ref e)) if e.limit() == Limit::Length(5)));
+
+        // cancelled mid-generation, here by the constraint rather than a thread
+        let cancel = Arc::new(AtomicBool::new(false));
+        let cancelling = cancel.clone();
+        let options =
+            GenerateOptions::new()
+                .cancel(cancel.clone())
+                .constraint(move |sentence, _| {
+                    if sentence.len() >= 10 {
+                        cancelling.store(true, Ordering::Relaxed);
+                    }
+                    true
+                });
+        match grammar.generate_with_options(&options, &mut rng) {
+            Err(Error::LimitError(e)) => {
+                assert_eq!(e.limit(), Limit::Cancelled);
+                assert!(e.partial().len() >= 10 && e.partial().len() < 10 + 64);
+            }
+            result => panic!("should be cancelled: {:?}", result),
+        }
+        assert!(cancel.load(Ordering::Relaxed));
+    }
+
+    #[test]
+    fn chooses_by_weight() {
+        let grammar = Grammar::from_str(
+            "<start> ::= \"a\" %weight 3 | \"b\" | \"c\" %weight 0 | \"d\" %weight 4",
+        )
+        .unwrap();
+        let mut rng = rand::thread_rng();
+        let mut counts = HashMap::new();
+        for sentence in grammar.generate_iter(&mut rng).take(8000) {
+            *counts.entry(sentence.unwrap()).or_insert(0) += 1;
+        }
+        assert!(!counts.contains_key("c"));
+        let a = counts["a"];
+        let b = counts["b"];
+        let d = counts["d"];
+        assert!(a > 2700 && a < 3300, "a generated {} times", a);
+        assert!(b > 800 && b < 1200, "b generated {} times", b);
+        assert!(d > 3700 && d < 4300, "d generated {} times", d);
+
+        let grammar = Grammar::from_str("<start> ::= \"a\" %weight 0 | \"b\" %weight 0").unwrap();
+        assert!(grammar.generate().is_err());
+    }
+
+    #[test]
+    fn respects_attempt_budget() {
+        let grammar = Grammar::from_str("<start> ::= <start> | \"a\" | \"b\"").unwrap();
+        let coverage = grammar
+            .generate_covering(&mut rand::thread_rng(), 1)
+            .unwrap();
+        assert_eq!(coverage.sentences().len(), 1);
+        assert!(!coverage.is_complete());
+
+        let grammar = Grammar::from_str("<nonterm> ::= <nonterm>").unwrap();
+        let coverage = grammar
+            .generate_covering(&mut rand::thread_rng(), 5)
+            .unwrap();
+        assert!(coverage.sentences().is_empty());
+        assert!(Grammar::new()
+            .generate_covering(&mut rand::thread_rng(), 5)
+            .is_err());
+    }
+}
@@ -1,22 +1,59 @@
+use counting::DerivationCounter;
 use error::Error;
 use expression::Expression;
-use parsers;
+use generation;
+use num_bigint::BigUint;
+use parsers::{self, ParseOptions};
 use production::Production;
 use rand::{thread_rng, Rng, SeedableRng, StdRng};
-use stacker;
+use std::collections::{HashMap, HashSet};
 use std::fmt;
 use std::slice;
 use std::str;
-use term::Term;
+use term::{self, Nonterminal, StartSymbol, Term};
 
-/// A Grammar is comprised of any number of Productions
-#[derive(Clone, Debug, Eq, Hash, PartialEq)]
+/// A Grammar is comprised of any number of Productions.
+///
+/// A `Grammar` is `Send` and `Sync`, holding no interior mutability, so one
+/// grammar may be shared by threads generating or parsing with it through an
+/// `Arc<Grammar>`. Any caching added later should keep it so, behind a
+/// `RwLock` rather than a `RefCell`.
+///
+/// Grammars are ordered lexicographically by their productions in the order
+/// they're defined, so grammars equal as sets of productions but defining
+/// them in another order differ.
+///
+/// # Example
+///
+/// ```rust
+/// extern crate bnf;
+/// use bnf::Grammar;
+/// use std::sync::Arc;
+/// use std::thread;
+///
+/// fn main() {
+///     let grammar = Arc::new(Grammar::from_str("<bit> ::= \"0\" | \"1\"").unwrap());
+///     let handles: Vec<_> = (0..4)
+///         .map(|_| {
+///             let grammar = Arc::clone(&grammar);
+///             thread::spawn(move || grammar.generate().unwrap())
+///         })
+///         .collect();
+///
+///     for handle in handles {
+///         let bit = handle.join().unwrap();
+///         assert!(bit == "0" || bit == "1");
+///     }
+/// }
+/// ```
+#[derive(Clone, Debug, Eq, Hash, Ord, PartialEq, PartialOrd)]
 pub struct Grammar {
     productions: Vec<Production>,
 }
 
 impl Grammar {
     /// Construct a new `Grammar`
+    #[allow(clippy::new_without_default)]
     pub fn new() -> Grammar {
         Grammar {
             productions: vec![],
@@ -28,14 +65,158 @@ impl Grammar {
         Grammar { productions: v }
     }
 
+    /// Construct a `Grammar` which takes ownership of `productions`, the
+    /// same as `Grammar::from_parts`
+    pub fn from_productions(productions: Vec<Production>) -> Grammar {
+        Grammar::from_parts(productions)
+    }
+
+    /// Take the `Production`s out of the `Grammar` without cloning them
+    pub fn into_productions(self) -> Vec<Production> {
+        self.productions
+    }
+
+    /// Construct a `Grammar` from `(lhs, alternatives)` pairs of plain strings.
+    ///
+    /// Every symbol which names a left hand side is a `Term::Nonterminal`,
+    /// any other symbol is a `Term::Terminal`.
+    ///
+    /// # Example
+    ///
+    /// ```rust
+    /// extern crate bnf;
+    /// use bnf::Grammar;
+    ///
+    /// fn main() {
+    ///     let grammar = Grammar::from_pairs(&[("S", &[&["a", "S", "b"], &["a", "b"]])]);
+    ///     let parsed = Grammar::from_str("<S> ::= \"a\" <S> \"b\" | \"a\" \"b\"").unwrap();
+    ///
+    ///     assert_eq!(grammar, parsed);
+    /// }
+    /// ```
+    pub fn from_pairs(pairs: &[(&str, &[&[&str]])]) -> Grammar {
+        let is_lhs = |symbol: &str| pairs.iter().any(|&(lhs, _)| lhs == symbol);
+
+        let productions = pairs
+            .iter()
+            .map(|&(lhs, alternatives)| {
+                let expressions = alternatives
+                    .iter()
+                    .map(|symbols| {
+                        let terms = symbols
+                            .iter()
+                            .map(|&symbol| {
+                                if is_lhs(symbol) {
+                                    Term::Nonterminal(String::from(symbol))
+                                } else {
+                                    Term::Terminal(String::from(symbol))
+                                }
+                            })
+                            .collect();
+                        Expression::from_parts(terms)
+                    })
+                    .collect();
+                Production::from_parts(Term::Nonterminal(String::from(lhs)), expressions)
+            })
+            .collect();
+
+        Grammar::from_parts(productions)
+    }
+
     // Get `Grammar` by parsing a string
+    #[allow(clippy::should_implement_trait)]
     pub fn from_str(s: &str) -> Result<Self, Error> {
         match parsers::grammar_complete(s.as_bytes()) {
             Result::Ok((_, o)) => Ok(o),
-            Result::Err(e) => Err(Error::from(e)),
+            Result::Err(e) => Err(parsers::parse_error(s.as_bytes(), e)),
+        }
+    }
+
+    /// Construct a `Grammar` from a `String`, parsing it as `options`
+    /// specifies
+    ///
+    /// # Example
+    ///
+    /// ```rust
+    /// extern crate bnf;
+    /// use bnf::{Expression, Grammar, ParseOptions};
+    ///
+    /// fn main() {
+    ///     let options = ParseOptions::new().epsilon("<empty>");
+    ///     let grammar = Grammar::from_str_with_options("<as> ::= \"a\" <as> | <empty>", &options).unwrap();
+    ///     let epsilon = grammar.productions_iter().flat_map(|prod| prod.rhs_iter()).nth(1);
+    ///
+    ///     assert_eq!(epsilon.map(Expression::len), Some(0));
+    ///     assert!(grammar.verify_examples("as", &["", "aaa"], &["b"]).is_ok());
+    /// }
+    /// ```
+    pub fn from_str_with_options(s: &str, options: &ParseOptions) -> Result<Self, Error> {
+        match parsers::grammar_complete_with(s.as_bytes(), options) {
+            Result::Ok((_, o)) => Ok(o),
+            Result::Err(e) => Err(parsers::parse_error(s.as_bytes(), e)),
         }
     }
 
+    /// Construct a `Grammar` from the productions of `s` which parse, along
+    /// with an error for each which doesn't, for reporting every problem at
+    /// once.
+    ///
+    /// Each line beginning with a production's lhs starts a new production,
+    /// and the lines following it up to the next such line are parsed with
+    /// it. Errors are `Error::ParseError`s naming the line the production
+    /// failing to parse begins on, numbered from 1.
+    ///
+    /// # Example
+    ///
+    /// ```rust
+    /// extern crate bnf;
+    /// use bnf::Grammar;
+    ///
+    /// fn main() {
+    ///     let (grammar, errors) = Grammar::from_str_lenient("<dna> ::= <base> | <base> <dna>
+    ///         <base> ::= \"A\" | \"C\" | \"G\" | \"T
+    ///         <base> ::= \"U\"");
+    ///
+    ///     assert_eq!(grammar, Grammar::from_str("<dna> ::= <base> | <base> <dna>
+    ///         <base> ::= \"U\"").unwrap());
+    ///     assert_eq!(errors.len(), 1);
+    ///     assert!(errors[0].to_string().starts_with("Line 2: "));
+    /// }
+    /// ```
+    pub fn from_str_lenient(s: &str) -> (Grammar, Vec<Error>) {
+        // the byte offset and number of each line which begins a production
+        let mut starts = vec![];
+        let mut offset = 0;
+        for (index, line) in s.split_inclusive('\n').enumerate() {
+            if index == 0 || parsers::prod_lhs(line.trim_start().as_bytes()).is_ok() {
+                starts.push((offset, index + 1));
+            }
+            offset += line.len();
+        }
+
+        let mut productions = vec![];
+        let mut errors = vec![];
+        for (index, &(start, line)) in starts.iter().enumerate() {
+            let end = starts.get(index + 1).map_or(s.len(), |&(end, _)| end);
+            let chunk = &s[start..end];
+            if chunk.trim().is_empty() {
+                continue;
+            }
+            match parsers::grammar_chunk(chunk.as_bytes()) {
+                Result::Ok((_, grammar)) => productions.extend(grammar.productions),
+                Result::Err(e) => errors.push(Error::ParseError(format!(
+                    "Line {}: {}",
+                    line,
+                    parsers::parse_error(s.as_bytes(), e)
+                ))),
+            }
+        }
+        (
+            Grammar::from_parts(parsers::desugar(productions, &ParseOptions::new())),
+            errors,
+        )
+    }
+
     /// Add `Production` to the `Grammar`
     pub fn add_production(&mut self, prod: Production) {
         self.productions.push(prod)
@@ -50,69 +231,465 @@ impl Grammar {
         }
     }
 
+    /// Keep only the `Production`s for which `f` returns true, in order, as
+    /// `Vec::retain` does
+    ///
+    /// # Example
+    ///
+    /// ```rust
+    /// extern crate bnf;
+    /// use bnf::Grammar;
+    ///
+    /// fn main() {
+    ///     let mut grammar = Grammar::from_str("<dna> ::= <base> | <base> <dna>
+    ///         <base> ::= \"A\" | \"C\" | \"G\" | \"T\"
+    ///         <unused> ::= \"U\"").unwrap();
+    ///     grammar.retain_productions(|production| production.lhs().to_string() != "<unused>");
+    ///
+    ///     assert_eq!(grammar, Grammar::from_str("<dna> ::= <base> | <base> <dna>
+    ///         <base> ::= \"A\" | \"C\" | \"G\" | \"T\"").unwrap());
+    /// }
+    /// ```
+    pub fn retain_productions<F>(&mut self, f: F)
+    where
+        F: Fn(&Production) -> bool,
+    {
+        self.productions.retain(f)
+    }
+
+    /// Keep only the `Expression`s for which `f`, given the `Production`
+    /// each is in, returns true, in order. Productions left without any
+    /// are kept, defining nothing.
+    ///
+    /// # Example
+    ///
+    /// ```rust
+    /// extern crate bnf;
+    /// use bnf::Grammar;
+    ///
+    /// fn main() {
+    ///     let mut grammar = Grammar::from_str("<dna> ::= <base> | <base> <dna>
+    ///         <base> ::= \"A\" | \"C\" | \"G\" | \"T\" | \"U\"").unwrap();
+    ///     grammar.retain_expressions(|_, expression| expression.to_string() != "\"U\"");
+    ///
+    ///     assert_eq!(grammar, Grammar::from_str("<dna> ::= <base> | <base> <dna>
+    ///         <base> ::= \"A\" | \"C\" | \"G\" | \"T\"").unwrap());
+    /// }
+    /// ```
+    pub fn retain_expressions<F>(&mut self, f: F)
+    where
+        F: Fn(&Production, &Expression) -> bool,
+    {
+        for production in self.productions_iter_mut() {
+            let retained: Vec<Expression> = production
+                .rhs_iter()
+                .filter(|expression| f(production, expression))
+                .cloned()
+                .collect();
+            if retained.len() < production.len() {
+                let mut rest = Production::from_parts(production.lhs().clone(), retained);
+                rest.copy_annotations(production);
+                *production = rest;
+            }
+        }
+    }
+
     /// Get iterator of the `Grammar`'s `Production`s
-    pub fn productions_iter(&self) -> Iter {
+    pub fn productions_iter(&self) -> Iter<'_> {
         Iter {
             iterator: self.productions.iter(),
         }
     }
 
     /// Get mutable iterator of the `Grammar`'s `Production`s
-    pub fn productions_iter_mut(&mut self) -> IterMut {
+    pub fn productions_iter_mut(&mut self) -> IterMut<'_> {
         IterMut {
             iterator: self.productions.iter_mut(),
         }
     }
 
-    fn eval_terminal(&self, term: &Term, rng: &mut StdRng) -> Result<String, Error> {
-        match *term {
-            Term::Nonterminal(ref nt) => self.traverse(&nt, rng),
-            Term::Terminal(ref t) => Ok(t.clone()),
+    /// Get the `Production`s defining `nonterminal`, in order
+    ///
+    /// # Example
+    ///
+    /// ```rust
+    /// extern crate bnf;
+    /// use bnf::{Grammar, Nonterminal};
+    ///
+    /// fn main() {
+    ///     let grammar = Grammar::from_str("<bit> ::= \"0\"
+    ///         <byte> ::= <bit> <bit> <bit> <bit> <bit> <bit> <bit> <bit>
+    ///         <bit> ::= \"1\"").unwrap();
+    ///     let bit = Nonterminal(String::from("bit"));
+    ///
+    ///     let productions = grammar.productions_for(&bit);
+    ///     assert_eq!(productions.len(), 2);
+    ///     assert!(productions.iter().all(|prod| *prod.lhs() == bit));
+    /// }
+    /// ```
+    pub fn productions_for(&self, nonterminal: &Nonterminal) -> Vec<&Production> {
+        self.productions_iter()
+            .filter(|prod| *prod.lhs() == *nonterminal)
+            .collect()
+    }
+
+    /// Whether any `Production` references `term`, as its lhs or within one
+    /// of its alternatives
+    ///
+    /// # Example
+    ///
+    /// ```rust
+    /// extern crate bnf;
+    /// use bnf::{Grammar, Term};
+    ///
+    /// fn main() {
+    ///     let grammar = Grammar::from_str("<dna> ::= <base> | <base> <dna>").unwrap();
+    ///
+    ///     assert!(grammar.uses(&Term::from_str("<dna>").unwrap()));
+    ///     assert!(grammar.uses(&Term::from_str("<base>").unwrap()));
+    ///     assert!(!grammar.uses(&Term::from_str("\"A\"").unwrap()));
+    /// }
+    /// ```
+    pub fn uses(&self, term: &Term) -> bool {
+        self.productions_iter()
+            .any(|prod| *prod.lhs() == *term || prod.rhs_iter().any(|expr| expr.contains(term)))
+    }
+
+    /// Get, for every nonterminal of the grammar, the names of the nonterminals
+    /// whose productions reference it in an alternative.
+    ///
+    /// Referencing names are listed once each, in grammar order. Nonterminals
+    /// which are only ever a lhs map to an empty list, and those without a
+    /// production are included when referenced.
+    ///
+    /// # Example
+    ///
+    /// ```rust
+    /// extern crate bnf;
+    /// use bnf::Grammar;
+    ///
+    /// fn main() {
+    ///     let grammar = Grammar::from_str("<dna> ::= <base> | <base> <dna>
+    ///         <base> ::= \"A\" | \"C\" | \"G\" | \"T\"").unwrap();
+    ///     let references = grammar.cross_reference();
+    ///
+    ///     assert_eq!(references["base"], vec!["dna"]);
+    ///     assert_eq!(references["dna"], vec!["dna"]);
+    /// }
+    /// ```
+    pub fn cross_reference(&self) -> HashMap<String, Vec<String>> {
+        let mut references: HashMap<String, Vec<String>> = HashMap::new();
+        for production in self.productions_iter() {
+            let lhs = match *production.lhs() {
+                Term::Nonterminal(ref nt) => nt,
+                Term::Terminal(_)
+                | Term::CaselessTerminal(_)
+                | Term::TerminalBytes(_)
+                | Term::CharClass(_)
+                | Term::Regex(_)
+                | Term::Repeat { .. } => continue,
+            };
+            references.entry(lhs.clone()).or_default();
+            for term in production
+                .rhs_iter()
+                .flat_map(|expr| expr.nested_terms_iter())
+            {
+                if let Term::Nonterminal(ref nt) = *term {
+                    let referencing = references.entry(nt.clone()).or_default();
+                    if !referencing.contains(lhs) {
+                        referencing.push(lhs.clone());
+                    }
+                }
+            }
         }
+        references
     }
 
-    fn traverse(&self, ident: &String, rng: &mut StdRng) -> Result<String, Error> {
-        const STACK_RED_ZONE: usize = 32 * 1024; // 32KB
-                                                 // heavy recursion happening, we've hit out tolerable threshold
-        if let Some(remaining) = stacker::remaining_stack() {
-            if remaining < STACK_RED_ZONE {
-                return Err(Error::RecursionLimit(format!(
-                    "Limit for recursion reached processing <{}>!",
-                    ident
-                )));
+    /// Get the alternatives repeated within a `Production`, once per
+    /// production they're repeated in, along with the production's lhs
+    ///
+    /// # Example
+    ///
+    /// ```rust
+    /// extern crate bnf;
+    /// use bnf::{Expression, Grammar, Term};
+    ///
+    /// fn main() {
+    ///     let grammar = Grammar::from_str("<a> ::= <b> | \"c\" | <b>").unwrap();
+    ///
+    ///     assert_eq!(
+    ///         grammar.duplicate_alternatives(),
+    ///         vec![(Term::from_str("<a>").unwrap(), Expression::from_str("<b>").unwrap())]
+    ///     );
+    /// }
+    /// ```
+    pub fn duplicate_alternatives(&self) -> Vec<(Term, Expression)> {
+        let mut duplicates = vec![];
+        for production in self.productions_iter() {
+            let expressions = production.rhs_iter().collect::<Vec<_>>();
+            for (index, expression) in expressions.iter().enumerate() {
+                let repeated = expressions[index + 1..].contains(expression);
+                if repeated && !expressions[..index].contains(expression) {
+                    duplicates.push((production.lhs().clone(), (*expression).clone()));
+                }
             }
         }
+        duplicates
+    }
 
-        let nonterm = Term::Nonterminal(ident.clone());
-        let production;
-        let find_lhs = self.productions_iter().find(|&x| x.lhs == nonterm);
+    /// Remove alternatives repeated within a `Production`, keeping the
+    /// first of each
+    pub fn dedup_alternatives(&mut self) {
+        for production in self.productions_iter_mut() {
+            let mut unique: Vec<Expression> = Vec::with_capacity(production.len());
+            for expression in production.rhs_iter() {
+                if !unique.contains(expression) {
+                    unique.push(expression.clone());
+                }
+            }
+            if unique.len() < production.len() {
+                let mut deduped = Production::from_parts(production.lhs().clone(), unique);
+                deduped.copy_annotations(production);
+                *production = deduped;
+            }
+        }
+    }
 
-        match find_lhs {
-            Some(p) => production = p,
-            None => return Ok(nonterm.to_string()),
+    /// Extract the sub-grammar of `Production`s reachable from `root`.
+    ///
+    /// The productions of `root` come first, making it the start symbol of
+    /// the new `Grammar`, followed by the other reachable productions in their
+    /// original order.
+    ///
+    /// # Example
+    ///
+    /// ```rust
+    /// extern crate bnf;
+    /// use bnf::Grammar;
+    ///
+    /// fn main() {
+    ///     let grammar = Grammar::from_str("<dna> ::= <base> | <base> <dna>
+    ///         <base> ::= \"A\" | \"C\" | \"G\" | \"T\"").unwrap();
+    ///     let base = grammar.subgrammar("base");
+    ///
+    ///     assert_eq!(base.to_string(), "<base> ::= \"A\" | \"C\" | \"G\" | \"T\"\n");
+    /// }
+    /// ```
+    pub fn subgrammar(&self, root: impl StartSymbol) -> Grammar {
+        let root = root.into_term();
+        let reachable = self.reachable_from(&root);
+        let (mut productions, rest): (Vec<Production>, Vec<Production>) = self
+            .productions
+            .iter()
+            .filter(|prod| reachable.contains(prod.lhs()))
+            .cloned()
+            .partition(|prod| *prod.lhs() == root);
+        productions.extend(rest);
+        Grammar::from_parts(productions)
+    }
+
+    /// Nonterminals reachable from `root`, including `root` itself
+    pub(crate) fn reachable_from<'a>(&'a self, root: &'a Term) -> HashSet<&'a Term> {
+        let mut reachable = HashSet::new();
+        let mut pending = vec![root];
+        while let Some(term) = pending.pop() {
+            if !reachable.insert(term) {
+                continue;
+            }
+            pending.extend(
+                self.productions_iter()
+                    .filter(|prod| *prod.lhs() == *term)
+                    .flat_map(|prod| prod.rhs_iter())
+                    .flat_map(|expr| expr.nested_terms_iter())
+                    .filter(|term| match **term {
+                        Term::Nonterminal(_) => !reachable.contains(term),
+                        Term::Terminal(_)
+                        | Term::CaselessTerminal(_)
+                        | Term::TerminalBytes(_)
+                        | Term::CharClass(_)
+                        | Term::Regex(_)
+                        | Term::Repeat { .. } => false,
+                    }),
+            );
         }
+        reachable
+    }
 
-        let expression;
-        let expressions = production.rhs_iter().collect::<Vec<&Expression>>();
+    /// Apply a single random derivation step to a sentential form.
+    ///
+    /// One nonterminal with a `Production` is picked at random from
+    /// `sentential_form` and replaced by one of its `Expression`s, also picked
+    /// at random. If no term can be expanded `Error::NoExpansionPossible` is
+    /// returned.
+    ///
+    /// # Example
+    ///
+    /// ```rust
+    /// extern crate bnf;
+    /// extern crate rand;
+    /// use bnf::{Grammar, Term};
+    ///
+    /// fn main() {
+    ///     let grammar = Grammar::from_str("<dna> ::= <base> | <base> <dna>
+    ///         <base> ::= \"A\" | \"C\" | \"G\" | \"T\"").unwrap();
+    ///     let form = vec![Term::Nonterminal(String::from("dna"))];
+    ///     let next = grammar.derive(&form, &mut rand::thread_rng()).unwrap();
+    ///
+    ///     assert!(next[0] == Term::Nonterminal(String::from("base")));
+    /// }
+    /// ```
+    pub fn derive<R: Rng>(
+        &self,
+        sentential_form: &[Term],
+        rng: &mut R,
+    ) -> Result<Vec<Term>, Error> {
+        let expandable = sentential_form
+            .iter()
+            .enumerate()
+            .filter_map(|(index, term)| self.find_expandable(term).map(|prod| (index, prod)))
+            .collect::<Vec<(usize, &Production)>>();
+
+        let (index, production) = match rng.choose(&expandable) {
+            Some(&(index, production)) => (index, production),
+            None => return Err(Self::no_expansion(sentential_form)),
+        };
 
+        let expressions = production.rhs_iter().collect::<Vec<&Expression>>();
         match rng.choose(&expressions) {
-            Some(e) => expression = e.clone(),
-            None => {
-                return Err(Error::GenerateError(String::from(
-                    "Couldn't select random Expression!",
-                )));
-            }
+            Some(expression) => Ok(expand_at(sentential_form, index, expression)),
+            None => Err(Self::no_expansion(sentential_form)),
         }
+    }
 
-        let mut result = String::new();
-        for term in expression.terms_iter() {
-            match self.eval_terminal(&term, rng) {
-                Ok(s) => result = result + &s,
-                Err(e) => return Err(e),
-            }
+    /// Count the derivations of strings of `len` characters from the start symbol.
+    ///
+    /// This counts derivations rather than distinct strings, which differ for
+    /// ambiguous grammars. See `DerivationCounter` for the details, and to
+    /// reuse the memoized counts across many lengths.
+    ///
+    /// # Example
+    ///
+    /// ```rust
+    /// extern crate bnf;
+    /// use bnf::Grammar;
+    ///
+    /// fn main() {
+    ///     let grammar = Grammar::from_str("<dna> ::= <base> | <base> <dna>
+    ///         <base> ::= \"A\" | \"C\" | \"G\" | \"T\"").unwrap();
+    ///
+    ///     assert_eq!(grammar.count_derivations(3).to_string(), "64");
+    /// }
+    /// ```
+    pub fn count_derivations(&self, len: usize) -> BigUint {
+        self.derivation_counter().count(len)
+    }
+
+    /// Sample a sentence of exactly `len` characters, uniformly among all
+    /// derivations of that length.
+    ///
+    /// Returns `None` when no sentence of that length exists. To sample
+    /// repeatedly without recounting, reuse a `DerivationCounter`.
+    ///
+    /// # Example
+    ///
+    /// ```rust
+    /// extern crate bnf;
+    /// extern crate rand;
+    /// use bnf::Grammar;
+    ///
+    /// fn main() {
+    ///     let grammar = Grammar::from_str("<dna> ::= <base> | <base> <dna>
+    ///         <base> ::= \"A\" | \"C\" | \"G\" | \"T\"").unwrap();
+    ///     let sentence = grammar.sample_uniform(5, &mut rand::thread_rng());
+    ///
+    ///     assert_eq!(sentence.map(|s| s.len()), Some(5));
+    ///     assert_eq!(grammar.sample_uniform(0, &mut rand::thread_rng()), None);
+    /// }
+    /// ```
+    pub fn sample_uniform<R: Rng>(&self, len: usize, rng: &mut R) -> Option<String> {
+        self.derivation_counter().sample(len, rng)
+    }
+
+    /// Get a `DerivationCounter` which memoizes counts across queries
+    pub fn derivation_counter(&self) -> DerivationCounter<'_> {
+        DerivationCounter::new(self)
+    }
+
+    /// Apply a single leftmost derivation step to a sentential form.
+    ///
+    /// The leftmost nonterminal with a `Production` is replaced by the first
+    /// `Expression` of that production. If no term can be expanded
+    /// `Error::NoExpansionPossible` is returned.
+    ///
+    /// # Example
+    ///
+    /// ```rust
+    /// extern crate bnf;
+    /// use bnf::{Expression, Grammar};
+    ///
+    /// fn main() {
+    ///     let grammar = Grammar::from_str("<dna> ::= <base> <dna> | <base>
+    ///         <base> ::= \"A\" | \"C\" | \"G\" | \"T\"").unwrap();
+    ///     let form = Expression::from_str("<base> <dna>").unwrap();
+    ///     let form = form.terms_iter().cloned().collect::<Vec<_>>();
+    ///     let next = grammar.derive_leftmost(&form).unwrap();
+    ///
+    ///     assert_eq!(Expression::from_parts(next).to_string(), "\"A\" <dna>");
+    /// }
+    /// ```
+    pub fn derive_leftmost(&self, sentential_form: &[Term]) -> Result<Vec<Term>, Error> {
+        let found = sentential_form
+            .iter()
+            .enumerate()
+            .find_map(|(index, term)| self.find_expandable(term).map(|prod| (index, prod)));
+        self.expand_first(sentential_form, found)
+    }
+
+    /// Apply a single rightmost derivation step to a sentential form.
+    ///
+    /// The rightmost nonterminal with a `Production` is replaced by the first
+    /// `Expression` of that production. If no term can be expanded
+    /// `Error::NoExpansionPossible` is returned.
+    pub fn derive_rightmost(&self, sentential_form: &[Term]) -> Result<Vec<Term>, Error> {
+        let found = sentential_form
+            .iter()
+            .enumerate()
+            .rev()
+            .find_map(|(index, term)| self.find_expandable(term).map(|prod| (index, prod)));
+        self.expand_first(sentential_form, found)
+    }
+
+    fn expand_first(
+        &self,
+        sentential_form: &[Term],
+        found: Option<(usize, &Production)>,
+    ) -> Result<Vec<Term>, Error> {
+        match found.and_then(|(index, prod)| prod.rhs_iter().next().map(|expr| (index, expr))) {
+            Some((index, expression)) => Ok(expand_at(sentential_form, index, expression)),
+            None => Err(Self::no_expansion(sentential_form)),
         }
+    }
 
-        return Ok(result);
+    /// Find the `Production` for a nonterminal, when it has any `Expression`s to expand to
+    fn find_expandable(&self, term: &Term) -> Option<&Production> {
+        match *term {
+            Term::Nonterminal(_) => self
+                .productions_iter()
+                .find(|prod| *prod.lhs() == *term && !prod.is_empty()),
+            Term::Terminal(_)
+            | Term::CaselessTerminal(_)
+            | Term::TerminalBytes(_)
+            | Term::CharClass(_)
+            | Term::Regex(_)
+            | Term::Repeat { .. } => None,
+        }
+    }
+
+    fn no_expansion(sentential_form: &[Term]) -> Error {
+        Error::NoExpansionPossible(format!(
+            "No nonterminal to expand in '{}'!",
+            Expression::from_parts(sentential_form.to_vec())
+        ))
     }
 
     /// Generate a random sentence from self and seed for random.
@@ -145,26 +722,10 @@ impl Grammar {
     /// }
     /// ```
     pub fn generate_seeded(&self, rng: &mut StdRng) -> Result<String, Error> {
-        let start_rule: String;
-        let first_production = self.productions_iter().nth(0);
-
-        match first_production {
-            Some(term) => match term.lhs {
-                Term::Nonterminal(ref nt) => start_rule = nt.clone(),
-                Term::Terminal(_) => {
-                    return Err(Error::GenerateError(format!(
-                        "Termainal type cannot define a production in '{}'!",
-                        term
-                    )));
-                }
-            },
-            None => {
-                return Err(Error::GenerateError(String::from(
-                    "Failed to get first production!",
-                )));
-            }
-        }
-        self.traverse(&start_rule, rng)
+        let start_rule = generation::start_symbol(self)?;
+        let mut sentence = String::new();
+        generation::traverse(self, start_rule, rng, &mut sentence)?;
+        Ok(sentence)
     }
 
     /// Generate a random sentence from self.
@@ -202,17 +763,282 @@ impl Grammar {
     }
 }
 
+/// Replace the term at `index` of a sentential form with the terms of `expression`
+fn expand_at(sentential_form: &[Term], index: usize, expression: &Expression) -> Vec<Term> {
+    let mut expanded = sentential_form[..index].to_vec();
+    expanded.extend(expression.terms_iter().cloned());
+    expanded.extend_from_slice(&sentential_form[index + 1..]);
+    expanded
+}
+
+impl From<Vec<Production>> for Grammar {
+    fn from(productions: Vec<Production>) -> Self {
+        Grammar::from_productions(productions)
+    }
+}
+
+impl From<Grammar> for Vec<Production> {
+    fn from(grammar: Grammar) -> Self {
+        grammar.into_productions()
+    }
+}
+
+/// How a nonterminal parsing desugars a group to is printed as the group
+#[derive(Clone, Copy)]
+enum Shape {
+    /// `( ... )`
+    Group,
+    /// `[ ... ]`, whose last alternative is `""`
+    Optional,
+}
+
+/// A term of a production as it was parsed, before groups were desugared
+pub(crate) enum Sugar<'a> {
+    Term(&'a Term),
+    /// One of the alternatives
+    Group(Vec<Alternative<'a>>),
+    /// One of the alternatives or nothing
+    Optional(Vec<Alternative<'a>>),
+    /// From a minimum to a maximum count of the term, or with no maximum
+    Repeat(usize, Option<usize>, Box<Sugar<'a>>),
+}
+
+/// An alternative of resugared terms
+pub(crate) struct Alternative<'a> {
+    pub terms: Vec<Sugar<'a>>,
+    pub weight: Option<u32>,
+}
+
+/// Get each production of `grammar` with its alternatives resugared, but
+/// those of the groups inlined into the productions before them
+pub(crate) fn resugar(grammar: &Grammar) -> Vec<(&Production, Vec<Alternative<'_>>)> {
+    let productions = &grammar.productions;
+    let mut resugar = Resugar::new(productions);
+    let mut resugared = vec![];
+    for (index, prod) in productions.iter().enumerate() {
+        // groups are only inlined into productions before them
+        if resugar.inlined.contains(&index) {
+            continue;
+        }
+        resugared.push((prod, resugar.alternatives(index, prod.len())));
+    }
+    resugared
+}
+
+/// Finds the nonterminals parsing desugars groups to: a nonterminal named
+/// like `<a (1)>` for the first group of `<a>`, defined by a single
+/// production after the one it's referenced by, once.
+struct Resugar<'a> {
+    productions: &'a [Production],
+    // the index of the production defining each nonterminal defined once
+    defined: HashMap<&'a str, usize>,
+    // how often each nonterminal is referenced
+    references: HashMap<&'a str, usize>,
+    counts: HashMap<&'a str, usize>,
+    inlined: HashSet<usize>,
+}
+
+impl<'a> Resugar<'a> {
+    fn new(productions: &'a [Production]) -> Resugar<'a> {
+        let mut definitions = HashMap::new();
+        let mut references = HashMap::new();
+        for (index, prod) in productions.iter().enumerate() {
+            if let Term::Nonterminal(ref name) = *prod.lhs() {
+                definitions
+                    .entry(name.as_str())
+                    .or_insert_with(Vec::new)
+                    .push(index);
+            }
+            for term in prod.rhs_iter().flat_map(|expr| expr.nested_terms_iter()) {
+                if let Term::Nonterminal(ref name) = *term {
+                    *references.entry(name.as_str()).or_insert(0) += 1;
+                }
+            }
+        }
+        let defined = definitions
+            .into_iter()
+            .filter(|(_, indices)| indices.len() == 1)
+            .map(|(name, indices)| (name, indices[0]))
+            .collect();
+        Resugar {
+            productions,
+            defined,
+            references,
+            counts: HashMap::new(),
+            inlined: HashSet::new(),
+        }
+    }
+
+    /// Get the production and shape of `term`, if it's the next group of
+    /// `parent`, defined after `index` and referenced nowhere else, then
+    /// counting it as a group of `parent`
+    fn group(&mut self, term: &Term, index: usize, parent: &'a str) -> Option<(usize, Shape)> {
+        let name = match *term {
+            Term::Nonterminal(ref name) => name.as_str(),
+            _ => return None,
+        };
+        let next = self.counts.get(parent).map_or(1, |count| count + 1);
+        if name != format!("{} ({})", parent, next) {
+            return None;
+        }
+        let group = *self.defined.get(name).filter(|&&group| group > index)?;
+        if self.references.get(name) != Some(&1) {
+            return None;
+        }
+
+        let alternatives = self.productions[group].rhs_iter().collect::<Vec<_>>();
+        let omitted = Expression::from_parts(vec![Term::Terminal(String::new())]);
+        let shape = match alternatives.last() {
+            None => return None,
+            Some(&last) if alternatives.len() > 1 && *last == omitted => Shape::Optional,
+            Some(_) => Shape::Group,
+        };
+        self.counts.insert(parent, next);
+        self.inlined.insert(group);
+        Some((group, shape))
+    }
+
+    fn inline(&mut self, group: usize, shape: Shape) -> Sugar<'a> {
+        let len = self.productions[group].len();
+        match shape {
+            Shape::Group => Sugar::Group(self.alternatives(group, len)),
+            Shape::Optional => Sugar::Optional(self.alternatives(group, len - 1)),
+        }
+    }
+
+    /// Get the first `len` alternatives of the production at `index`
+    fn alternatives(&mut self, index: usize, len: usize) -> Vec<Alternative<'a>> {
+        let productions = self.productions;
+        let prod = &productions[index];
+        let parent = match *prod.lhs() {
+            Term::Nonterminal(ref name) => name.as_str(),
+            _ => "",
+        };
+        prod.rhs_iter()
+            .take(len)
+            .map(|expr| {
+                let terms = expr.terms_iter().collect::<Vec<_>>();
+                Alternative {
+                    terms: self.terms(&terms, index, parent),
+                    weight: expr.weight(),
+                }
+            })
+            .collect()
+    }
+
+    fn terms(&mut self, terms: &[&'a Term], index: usize, parent: &'a str) -> Vec<Sugar<'a>> {
+        terms
+            .iter()
+            .map(|&term| match *term {
+                Term::Repeat {
+                    ref inner,
+                    min,
+                    max,
+                } => {
+                    let inner = inner.terms_iter().collect::<Vec<_>>();
+                    let mut repeated = self.terms(&inner, index, parent);
+                    // a group of a single unweighted alternative parses
+                    // back as the repeated terms themselves, so it's grouped
+                    // again
+                    let grouped = match repeated[..] {
+                        [Sugar::Group(ref alternatives)] => {
+                            alternatives.len() == 1 && alternatives[0].weight.is_none()
+                        }
+                        [_] => false,
+                        _ => true,
+                    };
+                    let repeated = if grouped {
+                        Sugar::Group(vec![Alternative {
+                            terms: repeated,
+                            weight: None,
+                        }])
+                    } else {
+                        repeated.remove(0)
+                    };
+                    Sugar::Repeat(min, max, Box::new(repeated))
+                }
+                _ => match self.group(term, index, parent) {
+                    Some((group, shape)) => self.inline(group, shape),
+                    None => Sugar::Term(term),
+                },
+            })
+            .collect()
+    }
+}
+
+impl<'a> fmt::Display for Sugar<'a> {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        // nested pieces are formatted by `f` itself, keeping its alternate flag
+        match *self {
+            Sugar::Term(term) => fmt::Display::fmt(term, f),
+            Sugar::Group(ref alternatives) => {
+                write!(f, "( ")?;
+                fmt::Display::fmt(&Alternatives(alternatives), f)?;
+                write!(f, " )")
+            }
+            Sugar::Optional(ref alternatives) => {
+                write!(f, "[ ")?;
+                fmt::Display::fmt(&Alternatives(alternatives), f)?;
+                write!(f, " ]")
+            }
+            Sugar::Repeat(min, max, ref repeated) => {
+                let (prefix, suffix) = term::repeat_affixes(min, max);
+                write!(f, "{}", prefix)?;
+                // only a term or group may be repeated, so others are grouped
+                match **repeated {
+                    Sugar::Repeat(..) => {
+                        write!(f, "( ")?;
+                        fmt::Display::fmt(&**repeated, f)?;
+                        write!(f, " )")?;
+                    }
+                    ref repeated => fmt::Display::fmt(repeated, f)?,
+                }
+                write!(f, "{}", suffix)
+            }
+        }
+    }
+}
+
+/// Displays alternatives separated by `|`
+struct Alternatives<'a, 'b>(&'b [Alternative<'a>]);
+
+impl<'a, 'b> fmt::Display for Alternatives<'a, 'b> {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        for (index, alternative) in self.0.iter().enumerate() {
+            if index > 0 {
+                write!(f, " | ")?;
+            }
+            if alternative.terms.is_empty() {
+                write!(f, "{}", parsers::EPSILON)?;
+            }
+            for (index, term) in alternative.terms.iter().enumerate() {
+                if index > 0 {
+                    write!(f, " ")?;
+                }
+                fmt::Display::fmt(term, f)?;
+            }
+            if let Some(weight) = alternative.weight {
+                write!(f, " %weight {}", weight)?;
+            }
+        }
+        Ok(())
+    }
+}
+
+/// Formats the grammar as it's parsed, resugaring its groups and
+/// repetitions, or in the alternate format, like `{:#}`, with terminals of
+/// characters which aren't printable as ABNF numeric values, like `%x0D.0A`,
+/// which parse back with `ParseOptions::numeric_values`
 impl fmt::Display for Grammar {
     fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
-        writeln!(
-            f,
-            "{}",
-            self.productions
-                .iter()
-                .map(|s| s.to_string())
-                .collect::<Vec<_>>()
-                .join("\n")
-        )
+        for (index, (prod, alternatives)) in resugar(self).into_iter().enumerate() {
+            if index > 0 {
+                writeln!(f)?;
+            }
+            write!(f, "{} ::= ", *prod.lhs())?;
+            fmt::Display::fmt(&Alternatives(&alternatives), f)?;
+        }
+        writeln!(f)
     }
 }
 
@@ -263,7 +1089,7 @@ mod tests {
         fn arbitrary<G: Gen>(g: &mut G) -> Self {
             let mut productions = Vec::<Production>::arbitrary(g);
             // grammar must always have atleast one production
-            if productions.len() < 1 {
+            if productions.is_empty() {
                 productions.push(Production::arbitrary(g));
             }
             Grammar { productions }
@@ -310,6 +1136,172 @@ mod tests {
         assert_eq!(g1, g2);
     }
 
+    #[test]
+    fn from_pairs() {
+        let grammar = Grammar::from_pairs(&[
+            ("dna", &[&["base"], &["base", "dna"]]),
+            ("base", &[&["A"], &["C"], &["G"], &["T"]]),
+        ]);
+        let parsed = Grammar::from_str(
+            "<dna> ::= <base> | <base> <dna>
+            <base> ::= \"A\" | \"C\" | \"G\" | \"T\"",
+        )
+        .unwrap();
+        assert_eq!(grammar, parsed);
+    }
+
+    #[test]
+    fn from_pairs_empty() {
+        assert_eq!(Grammar::from_pairs(&[]), Grammar::new());
+    }
+
+    #[test]
+    fn derive_single_step() {
+        let grammar = Grammar::from_str(
+            "<dna> ::= <base> | <base> <dna>
+            <base> ::= \"A\" | \"C\" | \"G\" | \"T\"",
+        )
+        .unwrap();
+        let dna = Term::Nonterminal(String::from("dna"));
+        let base = Term::Nonterminal(String::from("base"));
+        let start = vec![dna.clone()];
+        let mut rng = rand::thread_rng();
+
+        for _ in 0..100 {
+            let derived = grammar.derive(&start, &mut rng).unwrap();
+            assert!(
+                derived == vec![base.clone()] || derived == vec![base.clone(), dna.clone()],
+                "{:?} is not a single step from <dna>",
+                derived
+            );
+        }
+
+        let derived = grammar
+            .derive(&[Term::Terminal(String::from("A")), base.clone()], &mut rng)
+            .unwrap();
+        assert_eq!(derived.len(), 2);
+        assert_eq!(derived[0], Term::Terminal(String::from("A")));
+        assert!(grammar.derive(&derived, &mut rng).is_err());
+    }
+
+    #[test]
+    fn derive_no_expansion_possible() {
+        let grammar = Grammar::from_str("<start> ::= \"a\" <not-defined>").unwrap();
+        let form = vec![
+            Term::Terminal(String::from("a")),
+            Term::Nonterminal(String::from("not-defined")),
+        ];
+        match grammar.derive(&form, &mut rand::thread_rng()) {
+            Err(Error::NoExpansionPossible(_)) => (),
+            e => panic!("should be Error::NoExpansionPossible: {:?}", e),
+        }
+        match grammar.derive(&[], &mut rand::thread_rng()) {
+            Err(Error::NoExpansionPossible(_)) => (),
+            e => panic!("should be Error::NoExpansionPossible: {:?}", e),
+        }
+    }
+
+    #[test]
+    fn derive_leftmost_and_rightmost() {
+        let grammar = Grammar::from_str(
+            "<pair> ::= <base> <base>
+            <base> ::= \"A\" | \"C\" | \"G\" | \"T\"",
+        )
+        .unwrap();
+        let a = Term::Terminal(String::from("A"));
+        let base = Term::Nonterminal(String::from("base"));
+        let pair = vec![Term::Nonterminal(String::from("pair"))];
+
+        let form = grammar.derive_leftmost(&pair).unwrap();
+        assert_eq!(form, vec![base.clone(), base.clone()]);
+        assert_eq!(form, grammar.derive_rightmost(&pair).unwrap());
+
+        let leftmost = grammar.derive_leftmost(&form).unwrap();
+        assert_eq!(leftmost, vec![a.clone(), base.clone()]);
+        let rightmost = grammar.derive_rightmost(&form).unwrap();
+        assert_eq!(rightmost, vec![base.clone(), a.clone()]);
+
+        let done = grammar.derive_leftmost(&leftmost).unwrap();
+        assert_eq!(done, vec![a.clone(), a.clone()]);
+        match grammar.derive_rightmost(&done) {
+            Err(Error::NoExpansionPossible(_)) => (),
+            e => panic!("should be Error::NoExpansionPossible: {:?}", e),
+        }
+    }
+
+    #[test]
+    fn derive_leftmost_skips_undefined() {
+        let grammar = Grammar::from_str("<start> ::= <undefined> <start> | \"a\"").unwrap();
+        let form = grammar
+            .derive_leftmost(&[Term::Nonterminal(String::from("start"))])
+            .unwrap();
+        let next = grammar.derive_leftmost(&form).unwrap();
+        assert_eq!(
+            next,
+            vec![
+                Term::Nonterminal(String::from("undefined")),
+                Term::Nonterminal(String::from("undefined")),
+                Term::Nonterminal(String::from("start")),
+            ]
+        );
+    }
+
+    #[test]
+    fn duplicate_alternatives() {
+        let mut grammar = Grammar::from_str(
+            "<a> ::= <b> | \"c\" | <b> | <b> | \"c\"
+            <b> ::= \"b\" | \"bb\"
+            <a> ::= <b>",
+        )
+        .unwrap();
+        let a = Term::Nonterminal(String::from("a"));
+        assert_eq!(
+            grammar.duplicate_alternatives(),
+            vec![
+                (a.clone(), Expression::from_str("<b>").unwrap()),
+                (a.clone(), Expression::from_str("\"c\"").unwrap()),
+            ]
+        );
+
+        grammar.dedup_alternatives();
+        assert!(grammar.duplicate_alternatives().is_empty());
+        assert_eq!(
+            grammar.to_string(),
+            "<a> ::= <b> | \"c\"\n<b> ::= \"b\" | \"bb\"\n<a> ::= <b>\n"
+        );
+    }
+
+    #[test]
+    fn subgrammar() {
+        let grammar = Grammar::from_str(
+            "<program> ::= <statement> | <statement> <program>
+            <statement> ::= <expression> \";\"
+            <expression> ::= <term> | <term> \"+\" <expression>
+            <term> ::= <digit> | \"(\" <expression> \")\"
+            <digit> ::= \"0\" | \"1\"
+            <unused> ::= <digit>
+            <term> ::= \"x\"",
+        )
+        .unwrap();
+        let expected = Grammar::from_str(
+            "<expression> ::= <term> | <term> \"+\" <expression>
+            <term> ::= <digit> | \"(\" <expression> \")\"
+            <digit> ::= \"0\" | \"1\"
+            <term> ::= \"x\"",
+        )
+        .unwrap();
+        let expression = Term::Nonterminal(String::from("expression"));
+        assert_eq!(grammar.subgrammar(&expression), expected);
+        assert_eq!(
+            grammar.subgrammar(&expression).subgrammar(&expression),
+            expected
+        );
+
+        let undefined = Nonterminal::from("undefined");
+        assert_eq!(grammar.subgrammar(&undefined), Grammar::new());
+        assert_eq!(grammar.subgrammar("expression"), expected);
+    }
+
     #[test]
     fn add_production() {
         let lhs = Term::Nonterminal(String::from("dna"));
@@ -366,6 +1358,47 @@ mod tests {
         );
     }
 
+    #[test]
+    fn retain_productions_and_expressions() {
+        let mut grammar = Grammar::from_str(
+            "<a> ::= \"x\" | <b> | \"y\"
+            <b> ::= \"z\"
+            <a> ::= \"w\"",
+        )
+        .unwrap();
+        grammar
+            .productions_iter_mut()
+            .next()
+            .unwrap()
+            .set_annotation("k", "v");
+
+        let mut kept = grammar.clone();
+        kept.retain_productions(|production| production.lhs().to_string() == "<a>");
+        assert_eq!(
+            kept,
+            Grammar::from_str("<a> ::= \"x\" | <b> | \"y\"\n<a> ::= \"w\"").unwrap()
+        );
+
+        grammar.retain_expressions(|production, expression| {
+            production.lhs().to_string() == "<b>"
+                || expression
+                    .terms_iter()
+                    .all(|term| matches!(*term, Term::Terminal(_)))
+        });
+        assert_eq!(
+            grammar,
+            Grammar::from_str("<a> ::= \"x\" | \"y\"\n<b> ::= \"z\"\n<a> ::= \"w\"").unwrap()
+        );
+        let first = grammar.productions_iter().next().unwrap();
+        assert_eq!(first.annotation("k"), Some("v"));
+
+        grammar.retain_expressions(|_, _| false);
+        assert_eq!(grammar.productions_iter().count(), 3);
+        assert!(grammar.productions_iter().all(Production::is_empty));
+        grammar.retain_productions(|_| false);
+        assert_eq!(grammar, Grammar::new());
+    }
+
     #[test]
     fn remove_nonexistent_production() {
         let lhs = Term::Nonterminal(String::from("dna"));
@@ -398,6 +1431,35 @@ mod tests {
         );
     }
 
+    #[test]
+    fn parse_multiline_productions() {
+        let continued = Grammar::from_str(
+            "<dna> ::= <base> \\
+                | <base> \\
+                  <dna>
+            <base> ::= \"A\" | \"C\" \\\r
+                | \"G\" | \"T\"",
+        )
+        .unwrap();
+        let single_lines = Grammar::from_str(
+            "<dna> ::= <base> | <base> <dna>
+            <base> ::= \"A\" | \"C\" | \"G\" | \"T\"",
+        )
+        .unwrap();
+        assert_eq!(continued, single_lines);
+
+        // without a backslash, lines continue the production until a new lhs
+        let indented = Grammar::from_str(
+            "<dna> ::= <base>
+                | <base>
+                  <dna>
+            <base> ::= \"A\" | \"C\"
+                | \"G\" | \"T\"",
+        )
+        .unwrap();
+        assert_eq!(indented, single_lines);
+    }
+
     #[test]
     fn parse_error() {
         let grammar = Grammar::from_str("<almost_grammar> ::= <test");
@@ -441,6 +1503,174 @@ mod tests {
         assert_eq!(sentence.unwrap(), String::from("<not-used>"));
     }
 
+    #[test]
+    fn generate_char_class() {
+        let grammar = Grammar::from_str("<id> ::= [a-z_] [0-9]").unwrap();
+        for _ in 0..100 {
+            let sentence = grammar.generate().unwrap();
+            let chars = sentence.chars().collect::<Vec<_>>();
+            assert_eq!(chars.len(), 2, "{}", sentence);
+            assert!(chars[0] == '_' || chars[0].is_ascii_lowercase());
+            assert!(chars[1].is_ascii_digit());
+        }
+        assert_eq!(grammar.to_string(), "<id> ::= [a-z_] [0-9]\n");
+    }
+
+    #[test]
+    fn numeric_values_round_trip() {
+        let options = ParseOptions::new().numeric_values();
+        let grammar = Grammar::from_str_with_options(
+            "<line> ::= <digit>+ %x0D.0A | %d9 \"é\"
+            <digit> ::= %x30-39",
+            &options,
+        )
+        .unwrap();
+        assert_eq!(
+            grammar.to_string(),
+            "<line> ::= <digit>+ \"\\r\\n\" | \"\\t\" \"é\"\n<digit> ::= [0-9]\n"
+        );
+        assert_eq!(
+            format!("{:#}", grammar),
+            "<line> ::= <digit>+ %x0D.0A | %x09 \"é\"\n<digit> ::= [0-9]\n"
+        );
+        assert_eq!(
+            Grammar::from_str_with_options(&format!("{:#}", grammar), &options).unwrap(),
+            grammar
+        );
+        assert_eq!(Grammar::from_str(&grammar.to_string()).unwrap(), grammar);
+        assert_eq!(format!("{:#}", Grammar::new()), "\n");
+    }
+
+    #[test]
+    fn any_char_wildcard() {
+        let grammar = Grammar::from_str("<quoted> ::= \"'\" %any \"'\"").unwrap();
+        assert_eq!(grammar.to_string(), "<quoted> ::= \"'\" %any \"'\"\n");
+        assert_eq!(Grammar::from_str(&grammar.to_string()).unwrap(), grammar);
+
+        let quoted = Nonterminal::from("quoted");
+        for input in &["'a'", "'''", "'é'", "'\n'"] {
+            assert_eq!(grammar.count_parses(&quoted, input), 1, "{:?}", input);
+        }
+        for input in &["''", "'ab'"] {
+            assert_eq!(grammar.count_parses(&quoted, input), 0, "{:?}", input);
+        }
+
+        let generate = |seed: usize| {
+            let mut rng: StdRng = SeedableRng::from_seed(&[seed][..]);
+            grammar.generate_seeded(&mut rng).unwrap()
+        };
+        for seed in 0..20 {
+            let sentence = generate(seed);
+            let chars = sentence.chars().collect::<Vec<_>>();
+            assert_eq!(chars.len(), 3, "{}", sentence);
+            assert!((' '..='~').contains(&chars[1]), "{}", sentence);
+            assert_eq!(sentence, generate(seed));
+        }
+    }
+
+    #[test]
+    fn uses_terms() {
+        let grammar = Grammar::from_str(
+            "<dna> ::= <base> | <base> <dna>
+            <base> ::= \"A\" | [CG] | <undefined>",
+        )
+        .unwrap();
+        for used in &["<dna>", "<base>", "\"A\"", "[CG]", "<undefined>"] {
+            assert!(grammar.uses(&Term::from_str(used).unwrap()), "{}", used);
+        }
+        for unused in &["<A>", "\"dna\"", "[GC]", "\"\""] {
+            assert!(
+                !grammar.uses(&Term::from_str(unused).unwrap()),
+                "{}",
+                unused
+            );
+        }
+        assert!(!Grammar::new().uses(&Term::from_str("<dna>").unwrap()));
+    }
+
+    #[test]
+    fn from_str_lenient() {
+        let input = "
+            <expr> ::= <term> | <term> \"+\" <expr>
+                | <term> \"-\" <expr>
+
+            <term> ::= <factor> | <factor> \"*\"  )
+            <factor> ::= \"(\" <expr> \")\" | <num> <num> ::= [0-9]
+            garbage
+            <broken> ::=
+            <op> ::= \"+\" ; <op> ::= \"-\"";
+        let (grammar, errors) = Grammar::from_str_lenient(input);
+        let expected = Grammar::from_str(
+            "<expr> ::= <term> | <term> \"+\" <expr> | <term> \"-\" <expr>
+            <op> ::= \"+\" ; <op> ::= \"-\"",
+        )
+        .unwrap();
+        assert_eq!(grammar, expected);
+        let lines = errors
+            .iter()
+            .map(|e| match *e {
+                Error::ParseError(ref message) => message.split(':').next().unwrap(),
+                _ => panic!("should be a parse error: {:?}", e),
+            })
+            .collect::<Vec<_>>();
+        assert_eq!(lines, vec!["Line 5", "Line 6", "Line 8"]);
+
+        let (grammar, errors) = Grammar::from_str_lenient(input.trim());
+        assert_eq!(grammar, expected);
+        assert_eq!(errors.len(), 3);
+
+        let valid = "<a> ::= \"a\" <b>\n<b> ::= [b]\n";
+        let (grammar, errors) = Grammar::from_str_lenient(valid);
+        assert_eq!(grammar, Grammar::from_str(valid).unwrap());
+        assert!(errors.is_empty());
+        assert_eq!(Grammar::from_str_lenient(""), (Grammar::new(), vec![]));
+    }
+
+    #[test]
+    fn cross_reference() {
+        let grammar = Grammar::from_str(
+            "<expr> ::= <term> \"+\" <expr> | <term>
+            <term> ::= <factor> | <undefined> <expr>
+            <factor> ::= \"x\"
+            <unused> ::= <factor> <term> <factor>
+            <term> ::= <factor> \"*\" <term>",
+        )
+        .unwrap();
+        let references = grammar.cross_reference();
+        let expected = vec![
+            ("expr", vec!["expr", "term"]),
+            ("term", vec!["expr", "unused", "term"]),
+            ("factor", vec!["term", "unused"]),
+            ("undefined", vec!["term"]),
+            ("unused", vec![]),
+        ];
+        assert_eq!(references.len(), expected.len());
+        for (nt, referencing) in expected {
+            assert_eq!(references[nt], referencing, "{}", nt);
+        }
+        assert!(Grammar::new().cross_reference().is_empty());
+    }
+
+    #[test]
+    fn into_and_from_productions() {
+        let grammar = Grammar::from_str(
+            "<dna> ::= <base> | <base> <dna>
+            <base> ::= \"A\" | \"C\" | \"G\" | \"T\"",
+        )
+        .unwrap();
+        let mut productions = grammar.clone().into_productions();
+        assert_eq!(productions.len(), 2);
+        assert_eq!(Grammar::from_productions(productions.clone()), grammar);
+
+        productions.reverse();
+        let reversed = Grammar::from(productions);
+        assert_eq!(
+            *reversed.productions_iter().next().unwrap().lhs(),
+            Term::Nonterminal(String::from("base"))
+        );
+        assert_eq!(Vec::from(reversed).len(), 2);
+    }
+
     #[test]
     fn lhs_is_terminal_parse() {
         let grammar = Grammar::from_str("\"wrong place\" ::= <not-used>");
@@ -473,4 +1703,428 @@ mod tests {
         let sentence = grammar.generate();
         assert!(sentence.is_err(), "{:?} should be error", sentence);
     }
+
+    #[test]
+    fn parse_groups() {
+        let grammar = Grammar::from_str(
+            "<s> ::= \"a\" ( \"b\" | <c> \"d\" ) \"e\" | <c>
+            <c> ::= \"c\"",
+        )
+        .unwrap();
+        let desugared = Grammar::from_str(
+            "<s> ::= \"a\" <s (1)> \"e\" | <c>
+            <s (1)> ::= \"b\" | <c> \"d\"
+            <c> ::= \"c\"",
+        )
+        .unwrap();
+        assert_eq!(grammar, desugared);
+        assert_eq!(
+            grammar.to_string(),
+            "<s> ::= \"a\" ( \"b\" | <c> \"d\" ) \"e\" | <c>\n<c> ::= \"c\"\n"
+        );
+        assert_eq!(Grammar::from_str(&grammar.to_string()), Ok(grammar.clone()));
+        assert!(grammar
+            .verify_examples("s", &["abe", "acde", "c"], &["ae", "ace", "ab"])
+            .is_ok());
+        let sentence = grammar.generate().unwrap();
+        assert!(
+            ["abe", "acde", "c"].contains(&sentence.as_str()),
+            "{}",
+            sentence
+        );
+    }
+
+    #[test]
+    fn parse_nested_groups() {
+        let text = "<s> ::= ( ( \"a\" | \"b\" ) \"c\" | ( \"d\" ) ) ( \"e\" %weight 2 | \"f\" )\n";
+        let grammar = Grammar::from_str(text).unwrap();
+        let names: Vec<_> = grammar
+            .productions_iter()
+            .map(|prod| prod.lhs().to_string())
+            .collect();
+        assert_eq!(
+            names,
+            ["<s>", "<s (1)>", "<s (1) (1)>", "<s (1) (2)>", "<s (2)>"]
+        );
+        assert_eq!(grammar.to_string(), text);
+        let start = Nonterminal::from("s");
+        assert_eq!(grammar.count_parses(&start, "bce"), 1);
+        assert_eq!(grammar.count_parses(&start, "df"), 1);
+
+        // a group as the whole rhs, and a nonterminal using a name it would
+        let grammar = Grammar::from_str(
+            "<s> ::= ( \"a\" | \"b\" ) ; <t> ::= <s (1)> | ( <s> )
+            <s (1)> ::= \"c\"",
+        )
+        .unwrap();
+        let names: Vec<_> = grammar
+            .productions_iter()
+            .map(|prod| prod.lhs().to_string())
+            .collect();
+        assert_eq!(names, ["<s>", "<s (2)>", "<t>", "<t (1)>", "<s (1)>"]);
+        assert_eq!(Grammar::from_str(&grammar.to_string()), Ok(grammar.clone()));
+        assert!(grammar
+            .verify_examples("t", &["a", "b", "c"], &["", "ab"])
+            .is_ok());
+    }
+
+    #[test]
+    fn parse_group_errors() {
+        assert!(Grammar::from_str("<s> ::= ( )").is_err());
+        assert!(Grammar::from_str("<s> ::= ( \"a\"").is_err());
+        assert!(Grammar::from_str("<s> ::= \"a\" )").is_err());
+        assert!(Grammar::from_str("<s> ::= ( \"a\" ; \"b\" )").is_err());
+        assert!(Expression::from_str("( \"a\" )").is_err());
+        assert!(Production::from_str("<s> ::= ( \"a\" )").is_err());
+        assert!(Term::from_str("( \"a\" )").is_err());
+
+        let grammar = Grammar::from_str("<s> ::= \"a\" ( \"b\" | ε )");
+        assert!(grammar
+            .unwrap()
+            .verify_examples("s", &["a", "ab"], &["b"])
+            .is_ok());
+    }
+
+    #[test]
+    fn epsilon_alternatives() {
+        let text = "<s> ::= ε | \"a\" <s> ε \"b\" | ( ε %weight 2 | \"c\" )\n";
+        let grammar = Grammar::from_str(text).unwrap();
+        let empty = grammar.productions_iter().next().unwrap().rhs_iter().next();
+        assert_eq!(empty, Some(&Expression::new()));
+        assert_eq!(empty.unwrap().to_string(), "ε");
+        assert_eq!(
+            grammar.to_string(),
+            "<s> ::= ε | \"a\" <s> \"b\" | ( ε %weight 2 | \"c\" )\n"
+        );
+        assert_eq!(Grammar::from_str(&grammar.to_string()).unwrap(), grammar);
+
+        assert!(grammar
+            .verify_examples("s", &["", "ab", "aabb", "acb"], &["a", "ε"])
+            .is_ok());
+        let mut rng: StdRng = SeedableRng::from_seed(&[7][..]);
+        let sentence = grammar.generate_seeded(&mut rng).unwrap();
+        assert!(!sentence.contains('ε'));
+    }
+
+    #[test]
+    fn parse_optionals() {
+        let text =
+            "<num> ::= [ \"-\" | \"+\" ] <digits> ( \".\" [ <digits> ] | [ [ \"e\" ] \"!\" ] )
+<digits> ::= [0-9] [ <digits> ]
+";
+        let grammar = Grammar::from_str(text).unwrap();
+        let desugared = Grammar::from_str(
+            "<num> ::= <num (1)> <digits> <num (2)>
+            <num (1)> ::= \"-\" | \"+\" | \"\"
+            <num (2)> ::= \".\" <num (2) (1)> | <num (2) (2)>
+            <num (2) (1)> ::= <digits> | \"\"
+            <num (2) (2)> ::= <num (2) (2) (1)> \"!\" | \"\"
+            <num (2) (2) (1)> ::= \"e\" | \"\"
+            <digits> ::= [0-9] <digits (1)>
+            <digits (1)> ::= <digits> | \"\"",
+        )
+        .unwrap();
+        assert_eq!(grammar, desugared);
+        assert_eq!(grammar.to_string(), text);
+        assert!(grammar
+            .verify_examples(
+                "num",
+                &["1", "-12", "+3.", "4.56", "7!", "8e!"],
+                &["", "-", "1e", "1.2.3", "--1"]
+            )
+            .is_ok());
+        let sentence = grammar.generate().unwrap();
+        assert!(grammar.verify_examples("num", &[&sentence], &[]).is_ok());
+
+        // a class is still a class, and one which begins like a term prints
+        // escaped
+        let grammar = Grammar::from_str("<s> ::= [ab] [\\\"a\\\"] [ \\\"a\\\"]").unwrap();
+        let classes = grammar.to_string();
+        assert!(
+            classes.starts_with("<s> ::= [ab] [\\\"a\"] ["),
+            "{}",
+            classes
+        );
+        assert_eq!(Grammar::from_str(&classes), Ok(grammar));
+    }
+
+    #[test]
+    fn parse_nullable_optionals() {
+        let grammar = Grammar::from_str(
+            "<s> ::= [ <opt> ] [ [ <s> ] ] \"x\"
+            <opt> ::= [ \"o\" ] | <opt>",
+        )
+        .unwrap();
+        let s = Nonterminal::from("s");
+        assert_eq!(grammar.count_parses(&s, ""), 0);
+        assert!(grammar.count_parses(&s, "x") > 0);
+        assert!(grammar.count_parses(&s, "oxx") > 0);
+        assert!(grammar
+            .verify_examples("s", &["x", "ox", "xx", "ooxx"], &["o", "xo", "oxox"])
+            .is_ok());
+        assert_eq!(grammar.shortest_sentence(), Some(String::from("x")));
+        assert!(!grammar.is_finite(&s));
+        assert!(grammar.is_finite("opt"));
+        assert_eq!(
+            grammar.language("opt"),
+            Ok(vec![String::from(""), String::from("o")])
+        );
+        // a class of a space, not an empty optional
+        let space = Grammar::from_str("<s> ::= [ ]").unwrap();
+        assert!(space.verify_examples("s", &[" "], &[""]).is_ok());
+        assert!(Grammar::from_str("<s> ::= [ \"a\"").is_err());
+        assert!(Expression::from_str("[ \"a\" ]").is_err());
+    }
+
+    #[test]
+    fn parse_repetitions() {
+        let options = ParseOptions::new().ebnf();
+        let text = "<list> ::= \"[\" [ <item> { \",\" <item> } ] \"]\"
+<item> ::= [0-9]+ | <item>* \"!\" | { \"a\" | \"b\" %weight 2 } | ( \"x\" | \"y\" )+
+";
+        let grammar = Grammar::from_str_with_options(text, &options).unwrap();
+        let desugared = Grammar::from_str(
+            "<list> ::= \"[\" <list (1)> \"]\"
+            <list (1)> ::= <item> ( \",\" <item> )* | \"\"
+            <item> ::= [0-9]+ | <item>* \"!\" | <item (1)>* | <item (2)>+
+            <item (1)> ::= \"a\" | \"b\" %weight 2
+            <item (2)> ::= \"x\" | \"y\"",
+        )
+        .unwrap();
+        assert_eq!(grammar, desugared);
+        // braces print as the starred group, which needs no option
+        let printed = "<list> ::= \"[\" [ <item> ( \",\" <item> )* ] \"]\"
+<item> ::= [0-9]+ | <item>* \"!\" | ( \"a\" | \"b\" %weight 2 )* | ( \"x\" | \"y\" )+
+";
+        assert_eq!(grammar.to_string(), printed);
+        assert_eq!(Grammar::from_str(printed), Ok(grammar.clone()));
+        assert!(grammar
+            .verify_examples(
+                "list",
+                &["[]", "[12]", "[1,!,abba]", "[xyx,0!]", "[,]"],
+                &["[", "]", "[a1]", "[1!1]"]
+            )
+            .is_ok());
+
+        // repeating a group, and groups within repetitions
+        let text = "<s> ::= { [ \"a\" ] \"b\" }+ ( \"c\" %weight 3 )* { ( \"d\" ) }";
+        let grammar = Grammar::from_str_with_options(text, &options).unwrap();
+        assert_eq!(
+            grammar.to_string(),
+            "<s> ::= ( ( [ \"a\" ] \"b\" )* )+ ( \"c\" %weight 3 )* ( ( \"d\" ) )*\n"
+        );
+        assert_eq!(
+            Grammar::from_str_with_options(&grammar.to_string(), &options),
+            Ok(grammar.clone())
+        );
+        assert!(grammar
+            .verify_examples("s", &["", "b", "abbcd", "cc", "dd"], &["a", "bdc"])
+            .is_ok());
+
+        // braces only with the option, and never empty
+        assert!(Grammar::from_str("<s> ::= { \"a\" }").is_err());
+        assert!(Grammar::from_str_with_options("<s> ::= { }", &options).is_err());
+        assert!(Grammar::from_str_with_options("<s> ::= *", &options).is_err());
+        assert!(Grammar::from_str_with_options("<s> ::= { \"a\"", &options).is_err());
+        assert!(Expression::from_str("{ \"a\" }").is_err());
+    }
+
+    #[test]
+    fn parse_postfix_operators() {
+        let text = "<s> ::= <a>? \"b\"* ( \"c\" | <a> )+ [ \"d\" ]?
+<a> ::= ( \"x\" \"y\" )? | [0-9]*
+";
+        let grammar = Grammar::from_str(text).unwrap();
+        let desugared = Grammar::from_str(
+            "<s> ::= <s (1)> \"b\"* <s (2)>+ <s (3)>
+            <s (1)> ::= <a> | \"\"
+            <s (2)> ::= \"c\" | <a>
+            <s (3)> ::= <s (3) (1)> | \"\"
+            <s (3) (1)> ::= \"d\" | \"\"
+            <a> ::= <a (1)> | [0-9]*
+            <a (1)> ::= <a (1) (1)> | \"\"
+            <a (1) (1)> ::= \"x\" \"y\"",
+        )
+        .unwrap();
+        assert_eq!(grammar, desugared);
+        let starred = Term::Repeat {
+            inner: Box::new(Expression::from_parts(vec![Term::Terminal(String::from(
+                "b",
+            ))])),
+            min: 0,
+            max: None,
+        };
+        assert_eq!(
+            grammar
+                .productions_iter()
+                .next()
+                .unwrap()
+                .rhs_iter()
+                .next()
+                .unwrap()
+                .terms_iter()
+                .nth(1),
+            Some(&starred)
+        );
+        // an optional term prints as the optional group of it
+        let printed = "<s> ::= [ <a> ] \"b\"* ( \"c\" | <a> )+ [ [ \"d\" ] ]
+<a> ::= [ ( \"x\" \"y\" ) ] | [0-9]*
+";
+        assert_eq!(grammar.to_string(), printed);
+        assert_eq!(Grammar::from_str(printed), Ok(grammar.clone()));
+        assert!(grammar
+            .verify_examples(
+                "s",
+                &["", "c", "xybbc", "bcxy12d", "0c", "ccc"],
+                &["x", "cb", "xyyc", "cdd"]
+            )
+            .is_ok());
+        assert!(Grammar::from_str("<s> ::= \"a\"?+").is_err());
+        assert!(Grammar::from_str("<s> ::= ?").is_err());
+        assert!(Expression::from_str("\"a\"?").is_err());
+    }
+
+    #[test]
+    fn parse_counted_repetitions() {
+        let text = "<s> ::= 2*3\"a\" 0*\"b\" 2<c>
+<c> ::= 0*1[0-9]+ | 2*\"c\"
+";
+        let grammar = Grammar::from_str(text).unwrap();
+        let term = |text: &str| Term::from_str(text).unwrap();
+        let repeat = |inner: Term, min, max| Term::Repeat {
+            inner: Box::new(Expression::from_parts(vec![inner])),
+            min,
+            max,
+        };
+        let production = |lhs: &str, rhs: Vec<Vec<Term>>| {
+            Production::from_parts(
+                term(lhs),
+                rhs.into_iter().map(Expression::from_parts).collect(),
+            )
+        };
+        let desugared = Grammar::from_parts(vec![
+            production(
+                "<s>",
+                vec![vec![
+                    repeat(term("\"a\""), 2, Some(3)),
+                    repeat(term("\"b\""), 0, None),
+                    repeat(term("<c>"), 2, Some(2)),
+                ]],
+            ),
+            production(
+                "<c>",
+                vec![
+                    vec![repeat(repeat(term("[0-9]"), 0, Some(1)), 1, None)],
+                    vec![repeat(term("\"c\""), 2, None)],
+                ],
+            ),
+        ]);
+        assert_eq!(grammar, desugared);
+        let printed = "<s> ::= 2*3\"a\" \"b\"* 2<c>
+<c> ::= ( 0*1[0-9] )+ | 2*\"c\"
+";
+        assert_eq!(grammar.to_string(), printed);
+        assert_eq!(Grammar::from_str(printed), Ok(grammar.clone()));
+        for exact in &["<s> ::= 4[0-9a-f]\n", "<s> ::= 2*2( \"x\" | \"y\" )\n"] {
+            let grammar = Grammar::from_str(exact).unwrap();
+            let printed = grammar.to_string();
+            assert_eq!(Grammar::from_str(&printed), Ok(grammar));
+            assert!(!printed.contains('*'), "{}", printed);
+        }
+        assert!(grammar
+            .verify_examples(
+                "s",
+                &["aacc", "aaabbcccc", "aa1cc", "aa99ccc"],
+                &["acc", "aaaacc", "aac", "aaccb"]
+            )
+            .is_ok());
+        for seed in 0..20 {
+            let mut rng: StdRng = SeedableRng::from_seed(&[seed][..]);
+            let sentence = grammar.generate_seeded(&mut rng).unwrap();
+            assert!(grammar.verify_examples("s", &[&sentence], &[]).is_ok());
+        }
+
+        assert_eq!(
+            Grammar::from_str("<s> ::= \"b\"\n    | 3*2\"a\""),
+            Err(Error::ParseError(String::from(
+                "the repetition `3*2` has a maximum below its minimum at line 2, column 7"
+            )))
+        );
+        assert!(Grammar::from_str("<s> ::= 2*").is_err());
+        assert!(Grammar::from_str("<s> ::= 2 \"a\"").is_err());
+        assert!(Expression::from_str("2\"a\"").is_err());
+    }
+
+    #[test]
+    fn parse_nullable_repetitions() {
+        let options = ParseOptions::new().ebnf();
+        let grammar =
+            Grammar::from_str_with_options("<s> ::= { [ \"a\" ] } \"\"* \"b\"", &options).unwrap();
+        let s = Nonterminal::from("s");
+        // infinitely many ways to repeat nothing
+        assert_eq!(grammar.count_parses(&s, "b"), usize::MAX);
+        assert_eq!(grammar.count_parses(&s, "aab"), usize::MAX);
+        assert_eq!(grammar.count_parses(&s, "ba"), 0);
+        assert!(grammar
+            .verify_examples("s", &["b", "ab", "aab"], &["", "a", "bb"])
+            .is_ok());
+        assert_eq!(grammar.shortest_sentence(), Some(String::from("b")));
+        assert!(!grammar.is_finite(&s));
+        for seed in 0..20 {
+            let mut rng: StdRng = SeedableRng::from_seed(&[seed][..]);
+            let sentence = grammar.generate_seeded(&mut rng).unwrap();
+            assert!(grammar.verify_examples("s", &[&sentence], &[]).is_ok());
+        }
+    }
+
+    #[test]
+    fn generate_repetitions_within_limits() {
+        let options = ParseOptions::new().ebnf();
+        let grammar = Grammar::from_str_with_options(
+            "<s> ::= { <item> } ; <item> ::= \"a\"+ | \"(\" <s> \")\"",
+            &options,
+        )
+        .unwrap();
+        let limits = generation::GenerateOptions::new().max_depth(6).max_len(20);
+        for seed in 0..50 {
+            let mut rng: StdRng = SeedableRng::from_seed(&[seed][..]);
+            let sentence = grammar.generate_with_options(&limits, &mut rng).unwrap();
+            assert!(sentence.len() <= 20, "{}", sentence);
+            assert!(grammar.verify_examples("s", &[&sentence], &[]).is_ok());
+        }
+    }
+
+    #[test]
+    fn parse_char_ranges_and_negated_classes() {
+        let grammar = Grammar::from_str("<s> ::= \"a\"..\"c\" [^a-c] | \"α\"…\"ω\" \"+\"").unwrap();
+        assert_eq!(grammar.to_string(), "<s> ::= [a-c] [^a-c] | [α-ω] \"+\"\n");
+        assert_eq!(Grammar::from_str(&grammar.to_string()), Ok(grammar.clone()));
+        assert!(grammar
+            .verify_examples(
+                "s",
+                &["ad", "c\u{10FFFF}", "b\n", "λ+"],
+                &["aa", "a", "d", "A+"]
+            )
+            .is_ok());
+        for _ in 0..20 {
+            let sentence = grammar.generate().unwrap();
+            assert!(grammar.verify_examples("s", &[&sentence], &[]).is_ok());
+            assert!(sentence.chars().all(|c| !c.is_control()), "{:?}", sentence);
+        }
+    }
+
+    #[test]
+    fn orders_grammars() {
+        let grammar = |text: &str| Grammar::from_str(text).unwrap();
+        let a_then_b = grammar("<a> ::= \"x\"\n<b> ::= \"y\"");
+        let b_then_a = grammar("<b> ::= \"y\"\n<a> ::= \"x\"");
+        let a = grammar("<a> ::= \"x\"");
+        assert!(Grammar::new() < a);
+        assert!(a < a_then_b);
+        assert!(a_then_b < b_then_a);
+        assert_ne!(a_then_b, b_then_a);
+        let mut grammars = vec![b_then_a.clone(), a_then_b.clone(), a.clone()];
+        grammars.sort();
+        assert_eq!(grammars, vec![a, a_then_b, b_then_a]);
+    }
 }
@@ -0,0 +1,493 @@
+use grammar::Grammar;
+use production::Production;
+use std::collections::{HashMap, HashSet};
+use std::error;
+use std::fmt;
+use term::Term;
+
+/// A cycle of nonterminals which reference each other, preventing a
+/// topological order, see `Grammar::topological_sort`
+#[derive(Clone, Debug, Eq, PartialEq)]
+pub struct CycleError {
+    /// The nonterminals of the cycle in order of reference, beginning and
+    /// ending with the same nonterminal
+    pub cycle: Vec<String>,
+}
+
+impl fmt::Display for CycleError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        let cycle = self
+            .cycle
+            .iter()
+            .map(|nt| format!("<{}>", nt))
+            .collect::<Vec<_>>()
+            .join(" -> ");
+        write!(f, "Nonterminals reference each other in a cycle: {}", cycle)
+    }
+}
+
+impl error::Error for CycleError {
+    fn description(&self) -> &str {
+        "BNF cycle error"
+    }
+}
+
+/// Get the nonterminals of the grammar in order of first appearance, each
+/// with the nonterminals it directly references in order of first reference
+fn references(grammar: &Grammar) -> Vec<(&str, Vec<&str>)> {
+    let mut references: Vec<(&str, Vec<&str>)> = vec![];
+    let mut index: HashMap<&str, usize> = HashMap::new();
+    for production in grammar.productions_iter() {
+        let lhs = match *production.lhs() {
+            Term::Nonterminal(ref nt) => nt.as_str(),
+            Term::Terminal(_)
+            | Term::CaselessTerminal(_)
+            | Term::TerminalBytes(_)
+            | Term::CharClass(_)
+            | Term::Regex(_)
+            | Term::Repeat { .. } => continue,
+        };
+        let from = *index.entry(lhs).or_insert_with(|| {
+            references.push((lhs, vec![]));
+            references.len() - 1
+        });
+        for term in production
+            .rhs_iter()
+            .flat_map(|expr| expr.nested_terms_iter())
+        {
+            if let Term::Nonterminal(ref nt) = *term {
+                index.entry(nt.as_str()).or_insert_with(|| {
+                    references.push((nt.as_str(), vec![]));
+                    references.len() - 1
+                });
+                if !references[from].1.contains(&nt.as_str()) {
+                    references[from].1.push(nt.as_str());
+                }
+            }
+        }
+    }
+    references
+}
+
+/// Tarjan's algorithm, state for finding strongly connected components
+struct Tarjan<'r, 'a: 'r> {
+    references: &'r [(&'a str, Vec<&'a str>)],
+    index: &'r HashMap<&'a str, usize>,
+    visited: usize,
+    order: Vec<Option<usize>>,
+    low: Vec<usize>,
+    stack: Vec<usize>,
+    on_stack: Vec<bool>,
+    components: Vec<Vec<String>>,
+}
+
+impl<'r, 'a> Tarjan<'r, 'a> {
+    fn visit(&mut self, nt: usize) {
+        self.order[nt] = Some(self.visited);
+        self.low[nt] = self.visited;
+        self.visited += 1;
+        self.stack.push(nt);
+        self.on_stack[nt] = true;
+
+        for child in &self.references[nt].1 {
+            let child = self.index[child];
+            match self.order[child] {
+                None => {
+                    self.visit(child);
+                    self.low[nt] = self.low[nt].min(self.low[child]);
+                }
+                Some(order) if self.on_stack[child] => {
+                    self.low[nt] = self.low[nt].min(order);
+                }
+                Some(_) => (),
+            }
+        }
+
+        if Some(self.low[nt]) == self.order[nt] {
+            let at = self.stack.iter().position(|&on| on == nt).unwrap_or(0);
+            let mut component = self.stack.split_off(at);
+            component.sort();
+            for &on in &component {
+                self.on_stack[on] = false;
+            }
+            self.components.push(
+                component
+                    .into_iter()
+                    .map(|on| String::from(self.references[on].0))
+                    .collect(),
+            );
+        }
+    }
+}
+
+/// Index the productions of `grammar` by the key of each term `key` has one
+/// for, each production once per key
+fn index_productions<'a, F>(grammar: &'a Grammar, key: F) -> HashMap<String, Vec<&'a Production>>
+where
+    F: Fn(&'a Term) -> Option<&'a str>,
+{
+    let mut index: HashMap<String, Vec<&Production>> = HashMap::new();
+    for prod in grammar.productions_iter() {
+        let keys = prod
+            .rhs_iter()
+            .flat_map(|expr| expr.nested_terms_iter())
+            .filter_map(&key)
+            .collect::<HashSet<_>>();
+        for k in keys {
+            index.entry(String::from(k)).or_default().push(prod);
+        }
+    }
+    index
+}
+
+impl Grammar {
+    /// Get the nonterminals directly referenced by the alternatives of each
+    /// nonterminal, across every production sharing a lhs. Nonterminals
+    /// without a production reference nothing.
+    ///
+    /// # Example
+    ///
+    /// ```rust
+    /// extern crate bnf;
+    /// use bnf::Grammar;
+    ///
+    /// fn main() {
+    ///     let grammar = Grammar::from_str("<dna> ::= <base> | <base> <dna>
+    ///         <base> ::= \"A\" | \"C\" | \"G\" | \"T\"").unwrap();
+    ///     let graph = grammar.dependency_graph();
+    ///
+    ///     assert!(graph["dna"].contains("dna"));
+    ///     assert!(graph["dna"].contains("base"));
+    ///     assert!(graph["base"].is_empty());
+    /// }
+    /// ```
+    pub fn dependency_graph(&self) -> HashMap<String, HashSet<String>> {
+        references(self)
+            .into_iter()
+            .map(|(nt, children)| {
+                let children = children.into_iter().map(String::from).collect();
+                (String::from(nt), children)
+            })
+            .collect()
+    }
+
+    /// Get every production with an alternative referencing `target`, in
+    /// grammar order, the inverse of `Grammar::dependency_graph` for
+    /// nonterminals. A nonterminal's own productions are included only when
+    /// they're directly recursive.
+    ///
+    /// # Example
+    ///
+    /// ```rust
+    /// extern crate bnf;
+    /// use bnf::{Grammar, Term};
+    ///
+    /// fn main() {
+    ///     let grammar = Grammar::from_str("<dna> ::= <base> | <base> <dna>
+    ///         <base> ::= \"A\" | \"C\" | \"G\" | \"T\"").unwrap();
+    ///     let base = Term::from_str("<base>").unwrap();
+    ///     let referrers = grammar.referrers(&base);
+    ///
+    ///     assert_eq!(referrers.len(), 1);
+    ///     assert_eq!(*referrers[0].lhs(), Term::from_str("<dna>").unwrap());
+    ///     assert!(grammar.referrers(&Term::from_str("\"U\"").unwrap()).is_empty());
+    /// }
+    /// ```
+    pub fn referrers(&self, target: &Term) -> Vec<&Production> {
+        self.productions_iter()
+            .filter(|prod| prod.rhs_iter().any(|expr| expr.contains(target)))
+            .collect()
+    }
+
+    /// Index the productions by the text of each terminal they use, listing
+    /// each production once per terminal in grammar order, e.g. to see which
+    /// productions renaming a keyword affects.
+    ///
+    /// Caseless terminals are indexed by their text as written, and terminals
+    /// of bytes by their text when it's UTF-8. Character classes aren't
+    /// indexed.
+    ///
+    /// # Example
+    ///
+    /// ```rust
+    /// extern crate bnf;
+    /// use bnf::Grammar;
+    ///
+    /// fn main() {
+    ///     let grammar = Grammar::from_str("<stmt> ::= \"if\" <cond> \"then\" <stmt> | \"skip\"
+    ///         <cond> ::= \"true\" | \"not\" <cond>").unwrap();
+    ///     let by_terminal = grammar.productions_by_terminal();
+    ///
+    ///     assert_eq!(by_terminal.len(), 5);
+    ///     assert_eq!(by_terminal["then"][0].lhs().to_string(), "<stmt>");
+    /// }
+    /// ```
+    pub fn productions_by_terminal(&self) -> HashMap<String, Vec<&Production>> {
+        index_productions(self, Term::terminal_text)
+    }
+
+    /// Index the productions by the name of each nonterminal they reference,
+    /// listing each production once per nonterminal in grammar order, see
+    /// `Grammar::referrers`
+    pub fn productions_by_nonterminal_ref(&self) -> HashMap<String, Vec<&Production>> {
+        index_productions(self, |term| match *term {
+            Term::Nonterminal(ref nt) => Some(nt),
+            Term::Terminal(_)
+            | Term::CaselessTerminal(_)
+            | Term::TerminalBytes(_)
+            | Term::CharClass(_)
+            | Term::Regex(_)
+            | Term::Repeat { .. } => None,
+        })
+    }
+
+    /// Order the nonterminals so that each comes after every nonterminal it
+    /// references, suitable for processing a non-recursive grammar from its
+    /// leaves up. Ties are broken by order of first appearance.
+    ///
+    /// If nonterminals reference each other, directly or indirectly, the
+    /// first such cycle found is returned instead.
+    ///
+    /// # Example
+    ///
+    /// ```rust
+    /// extern crate bnf;
+    /// use bnf::Grammar;
+    ///
+    /// fn main() {
+    ///     let grammar = Grammar::from_str("<pair> ::= <base> <base>
+    ///         <base> ::= \"A\" | \"C\" | \"G\" | \"T\"").unwrap();
+    ///     assert_eq!(grammar.topological_sort().unwrap(), vec!["base", "pair"]);
+    ///
+    ///     let grammar = Grammar::from_str("<dna> ::= <base> | <base> <dna>
+    ///         <base> ::= \"A\" | \"C\" | \"G\" | \"T\"").unwrap();
+    ///     assert_eq!(grammar.topological_sort().unwrap_err().cycle, vec!["dna", "dna"]);
+    /// }
+    /// ```
+    pub fn topological_sort(&self) -> Result<Vec<String>, CycleError> {
+        let references = references(self);
+        let index: HashMap<&str, usize> = references
+            .iter()
+            .enumerate()
+            .map(|(i, &(nt, _))| (nt, i))
+            .collect();
+
+        let mut sorted = vec![];
+        let mut done = vec![false; references.len()];
+        for root in 0..references.len() {
+            if done[root] {
+                continue;
+            }
+            // depth first, the path holding each nonterminal and how many of
+            // its references have been visited
+            let mut path: Vec<(usize, usize)> = vec![(root, 0)];
+            while let Some(&mut (nt, ref mut visited)) = path.last_mut() {
+                match references[nt].1.get(*visited) {
+                    Some(child) => {
+                        *visited += 1;
+                        let child = index[child];
+                        if let Some(at) = path.iter().position(|&(on, _)| on == child) {
+                            let mut cycle = path[at..]
+                                .iter()
+                                .map(|&(on, _)| String::from(references[on].0))
+                                .collect::<Vec<_>>();
+                            cycle.push(String::from(references[child].0));
+                            return Err(CycleError { cycle });
+                        }
+                        if !done[child] {
+                            path.push((child, 0));
+                        }
+                    }
+                    None => {
+                        done[nt] = true;
+                        sorted.push(String::from(references[nt].0));
+                        path.pop();
+                    }
+                }
+            }
+        }
+        Ok(sorted)
+    }
+
+    /// Partition the nonterminals into strongly connected components of the
+    /// dependency graph, see `Grammar::dependency_graph`.
+    ///
+    /// Nonterminals of a component of more than one are mutually recursive,
+    /// and one alone is recursive only if it references itself. Each
+    /// component comes after every component it references, and lists its
+    /// nonterminals in order of first appearance.
+    ///
+    /// # Example
+    ///
+    /// ```rust
+    /// extern crate bnf;
+    /// use bnf::Grammar;
+    ///
+    /// fn main() {
+    ///     let grammar = Grammar::from_str("<expr> ::= <term> | <term> \"+\" <expr>
+    ///         <term> ::= <digit> | \"(\" <expr> \")\"
+    ///         <digit> ::= [0-9]").unwrap();
+    ///
+    ///     assert_eq!(
+    ///         grammar.strongly_connected_components(),
+    ///         vec![vec!["digit"], vec!["expr", "term"]]
+    ///     );
+    /// }
+    /// ```
+    pub fn strongly_connected_components(&self) -> Vec<Vec<String>> {
+        let references = references(self);
+        let index: HashMap<&str, usize> = references
+            .iter()
+            .enumerate()
+            .map(|(i, &(nt, _))| (nt, i))
+            .collect();
+        let mut tarjan = Tarjan {
+            references: &references,
+            index: &index,
+            visited: 0,
+            order: vec![None; references.len()],
+            low: vec![0; references.len()],
+            stack: vec![],
+            on_stack: vec![false; references.len()],
+            components: vec![],
+        };
+        for nt in 0..references.len() {
+            if tarjan.order[nt].is_none() {
+                tarjan.visit(nt);
+            }
+        }
+        tarjan.components
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn dependency_graph() {
+        let grammar = Grammar::from_str(
+            "<expr> ::= <term> \"+\" <expr> | <term>
+            <term> ::= <factor> | <undefined>
+            <factor> ::= \"x\"
+            <term> ::= <factor> \"*\" <term>",
+        )
+        .unwrap();
+        let graph = grammar.dependency_graph();
+        let expected: Vec<(&str, Vec<&str>)> = vec![
+            ("expr", vec!["expr", "term"]),
+            ("term", vec!["factor", "term", "undefined"]),
+            ("factor", vec![]),
+            ("undefined", vec![]),
+        ];
+        assert_eq!(graph.len(), expected.len());
+        for (nt, children) in expected {
+            let children: HashSet<String> = children.into_iter().map(String::from).collect();
+            assert_eq!(graph[nt], children, "{}", nt);
+        }
+    }
+
+    #[test]
+    fn referrers() {
+        let grammar = Grammar::from_str(
+            "<expr> ::= <term> \"+\" <expr> | <term>
+            <term> ::= <factor> | <undefined>
+            <factor> ::= \"x\" | \"(\" <expr> \")\"
+            <term> ::= <factor> \"*\" <term>",
+        )
+        .unwrap();
+        let productions = grammar.productions_iter().collect::<Vec<_>>();
+        let referrers = |term: &str| grammar.referrers(&Term::from_str(term).unwrap());
+        assert_eq!(referrers("<expr>"), vec![productions[0], productions[2]]);
+        assert_eq!(referrers("<term>"), vec![productions[0], productions[3]]);
+        assert_eq!(referrers("<factor>"), vec![productions[1], productions[3]]);
+        assert_eq!(referrers("<undefined>"), vec![productions[1]]);
+        assert_eq!(referrers("\"x\""), vec![productions[2]]);
+        assert!(referrers("<unused>").is_empty());
+    }
+
+    #[test]
+    fn productions_by_terms() {
+        let grammar = Grammar::from_str(
+            "<expr> ::= <term> \"+\" <expr> | <term> \"+\" \"+\"
+            <term> ::= %i\"x\" | <undefined> | [a-z] | %x2B
+            <term> ::= \"\" <term> | %xFF",
+        )
+        .unwrap();
+        let productions = grammar.productions_iter().collect::<Vec<_>>();
+        let by_terminal = grammar.productions_by_terminal();
+        let mut terminals = by_terminal.keys().cloned().collect::<Vec<_>>();
+        terminals.sort();
+        assert_eq!(terminals, vec!["", "+", "x"]);
+        assert_eq!(by_terminal["+"], vec![productions[0], productions[1]]);
+        assert_eq!(by_terminal["x"], vec![productions[1]]);
+        assert_eq!(by_terminal[""], vec![productions[2]]);
+
+        let by_nonterminal = grammar.productions_by_nonterminal_ref();
+        assert_eq!(by_nonterminal.len(), 3);
+        assert_eq!(by_nonterminal["term"], vec![productions[0], productions[2]]);
+        assert_eq!(by_nonterminal["expr"], vec![productions[0]]);
+        assert_eq!(by_nonterminal["undefined"], vec![productions[1]]);
+        assert!(Grammar::new().productions_by_terminal().is_empty());
+    }
+
+    #[test]
+    fn topological_sort() {
+        let grammar = Grammar::from_str(
+            "<sentence> ::= <noun> <verb> <noun>
+            <noun> ::= <article> <word> | <word>
+            <verb> ::= <word>
+            <article> ::= \"the\"
+            <word> ::= [a-z]",
+        )
+        .unwrap();
+        assert_eq!(
+            grammar.topological_sort().unwrap(),
+            vec!["article", "word", "noun", "verb", "sentence"]
+        );
+
+        let grammar = Grammar::from_str("<a> ::= <undefined> \"a\"").unwrap();
+        assert_eq!(grammar.topological_sort().unwrap(), vec!["undefined", "a"]);
+    }
+
+    #[test]
+    fn topological_sort_cycles() {
+        let grammar = Grammar::from_str(
+            "<a> ::= <b> | \"a\"
+            <b> ::= <c>
+            <c> ::= \"c\" | <d> <b>
+            <d> ::= \"d\"",
+        )
+        .unwrap();
+        let error = grammar.topological_sort().unwrap_err();
+        assert_eq!(error.cycle, vec!["b", "c", "b"]);
+        assert_eq!(
+            error.to_string(),
+            "Nonterminals reference each other in a cycle: <b> -> <c> -> <b>"
+        );
+    }
+
+    #[test]
+    fn strongly_connected_components() {
+        let grammar = Grammar::from_str(
+            "<a> ::= <b> | <e>
+            <b> ::= <c> \"b\"
+            <c> ::= <b> | <d>
+            <d> ::= <d> \"d\" | <undefined>
+            <e> ::= <a> | <c>
+            <f> ::= \"f\"",
+        )
+        .unwrap();
+        assert_eq!(
+            grammar.strongly_connected_components(),
+            vec![
+                vec!["undefined"],
+                vec!["d"],
+                vec!["b", "c"],
+                vec!["a", "e"],
+                vec!["f"],
+            ]
+        );
+        assert!(Grammar::new().strongly_connected_components().is_empty());
+    }
+}
@@ -0,0 +1,190 @@
+use grammar::Grammar;
+use markdown::anchor;
+use std::collections::HashSet;
+use std::fmt::Write;
+use term::{repeat_affixes, Term};
+
+/// Escape text for HTML and XML content and attribute values
+pub(crate) fn escape(text: &str) -> String {
+    text.replace('&', "&amp;")
+        .replace('<', "&lt;")
+        .replace('>', "&gt;")
+        .replace('"', "&quot;")
+}
+
+const STYLE: &str = "body { font-family: sans-serif; margin: 2em auto; max-width: 60em; }
+dl { font-family: monospace; font-size: 1.1em; }
+dt { margin-top: 1em; }
+dd { margin-left: 2em; }
+dt:target, dt:target + dd { background: #ffc; }
+a { text-decoration: none; }
+a:hover { text-decoration: underline; }
+.terminal { color: #a31515; }
+.undefined { color: #888; }
+.operator { color: #888; }
+";
+
+fn term_html(term: &Term, defined: &HashSet<&Term>) -> String {
+    match *term {
+        Term::Nonterminal(ref nt) if defined.contains(term) => format!(
+            "<a class=\"nonterminal\" href=\"#{}\">{}</a>",
+            anchor(nt),
+            escape(&term.to_string())
+        ),
+        Term::Nonterminal(_) => format!(
+            "<span class=\"nonterminal undefined\">{}</span>",
+            escape(&term.to_string())
+        ),
+        Term::Terminal(_)
+        | Term::CaselessTerminal(_)
+        | Term::TerminalBytes(_)
+        | Term::CharClass(_)
+        | Term::Regex(_) => format!(
+            "<span class=\"terminal\">{}</span>",
+            escape(&term.to_string())
+        ),
+        Term::Repeat {
+            ref inner,
+            min,
+            max,
+        } => {
+            let operand = match inner.terms_iter().collect::<Vec<_>>()[..] {
+                [term] if !matches!(*term, Term::Repeat { .. }) => term_html(term, defined),
+                _ => {
+                    let terms = inner
+                        .terms_iter()
+                        .map(|term| term_html(term, defined))
+                        .collect::<Vec<_>>();
+                    format!("( {} )", terms.join(" "))
+                }
+            };
+            let (prefix, suffix) = repeat_affixes(min, max);
+            let operator = |text: &str| match text {
+                "" => String::new(),
+                text => format!("<span class=\"operator\">{}</span>", text),
+            };
+            format!("{}{}{}", operator(&prefix), operand, operator(suffix))
+        }
+    }
+}
+
+impl Grammar {
+    /// Format the grammar as a self-contained HTML page titled `title`.
+    ///
+    /// Every nonterminal links to the definition of its production, with the
+    /// first production of each lhs being its definition.
+    ///
+    /// # Example
+    ///
+    /// ```rust
+    /// extern crate bnf;
+    /// use bnf::Grammar;
+    ///
+    /// fn main() {
+    ///     let grammar = Grammar::from_str("<dna> ::= <base> | <base> <dna>
+    ///         <base> ::= \"A\" | \"C\" | \"G\" | \"T\"").unwrap();
+    ///     let html = grammar.to_html("DNA");
+    ///
+    ///     assert!(html.contains("<title>DNA</title>"));
+    ///     assert!(html.contains("<dt id=\"base\">"));
+    ///     assert!(html.contains("href=\"#base\""));
+    /// }
+    /// ```
+    pub fn to_html(&self, title: &str) -> String {
+        let defined = self
+            .productions_iter()
+            .map(|prod| prod.lhs())
+            .collect::<HashSet<_>>();
+
+        let mut html = String::new();
+        let _ = write!(
+            html,
+            "<!DOCTYPE html>\n<html>\n<head>\n<meta charset=\"utf-8\">\n\
+             <title>{}</title>\n<style>\n{}</style>\n</head>\n<body>\n<h1>{}</h1>\n<dl>\n",
+            escape(title),
+            STYLE,
+            escape(title)
+        );
+
+        let mut anchored = HashSet::new();
+        for production in self.productions_iter() {
+            let lhs = escape(&production.lhs().to_string());
+            match *production.lhs() {
+                Term::Nonterminal(ref nt) if anchored.insert(nt) => {
+                    let id = anchor(nt);
+                    let _ = writeln!(
+                        html,
+                        "<dt id=\"{}\"><a class=\"nonterminal\" href=\"#{}\">{}</a> \
+                         <span class=\"operator\">::=</span></dt>",
+                        id, id, lhs
+                    );
+                }
+                _ => {
+                    let _ = writeln!(
+                        html,
+                        "<dt><span class=\"nonterminal\">{}</span> \
+                         <span class=\"operator\">::=</span></dt>",
+                        lhs
+                    );
+                }
+            }
+
+            for (index, expression) in production.rhs_iter().enumerate() {
+                let terms = expression
+                    .terms_iter()
+                    .map(|term| term_html(term, &defined))
+                    .collect::<Vec<_>>()
+                    .join(" ");
+                let separator = if index == 0 {
+                    ""
+                } else {
+                    "<span class=\"operator\">|</span> "
+                };
+                let _ = writeln!(html, "<dd>{}{}</dd>", separator, terms);
+            }
+        }
+
+        html.push_str("</dl>\n</body>\n</html>\n");
+        html
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn html_document() {
+        let grammar = Grammar::from_str(
+            "<expr> ::= <term> \"<\" <term> | <undefined>
+            <term> ::= \"&\" | [a-z]
+            <expr> ::= \"\"",
+        )
+        .unwrap();
+        let html = grammar.to_html("Expressions & <Terms>");
+
+        assert!(html.starts_with("<!DOCTYPE html>\n<html>"));
+        assert!(html.ends_with("</html>\n"));
+        assert!(html.contains("<title>Expressions &amp; &lt;Terms&gt;</title>"));
+        assert!(html
+            .contains("<dt id=\"expr\"><a class=\"nonterminal\" href=\"#expr\">&lt;expr&gt;</a> "));
+        assert_eq!(html.matches("<dt id=\"expr\">").count(), 1);
+        assert_eq!(html.matches("<dt").count(), 3);
+        assert!(html.contains(
+            "<dd><a class=\"nonterminal\" href=\"#term\">&lt;term&gt;</a> \
+             <span class=\"terminal\">&quot;&lt;&quot;</span> \
+             <a class=\"nonterminal\" href=\"#term\">&lt;term&gt;</a></dd>"
+        ));
+        assert!(html.contains(
+            "<dd><span class=\"operator\">|</span> \
+             <span class=\"nonterminal undefined\">&lt;undefined&gt;</span></dd>"
+        ));
+        assert!(html.contains("<span class=\"terminal\">[a-z]</span>"));
+        assert!(html.contains("<span class=\"terminal\">&quot;&amp;&quot;</span>"));
+    }
+
+    #[test]
+    fn escape_html() {
+        assert_eq!(escape("<a href=\"&\">"), "&lt;a href=&quot;&amp;&quot;&gt;");
+    }
+}
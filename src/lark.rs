@@ -0,0 +1,480 @@
+use grammar::{self, Alternative, Grammar, Sugar};
+use std::collections::{HashMap, HashSet};
+use std::str;
+use term::{CharClass, Nonterminal, Term};
+
+/// A regex which matches nothing, for what matches nothing
+const NOTHING: &str = "/(?!)/";
+
+/// Get `name` as the name of a Lark rule, in lowercase, or of a terminal,
+/// in uppercase, with runs of the characters a name can't have replaced by
+/// `_`
+fn lark_name(name: &str, terminal: bool) -> String {
+    let mut lark = String::new();
+    for c in name.chars() {
+        if c.is_ascii_alphanumeric() {
+            lark.push(if terminal {
+                c.to_ascii_uppercase()
+            } else {
+                c.to_ascii_lowercase()
+            });
+        } else if !lark.ends_with('_') {
+            lark.push('_');
+        }
+    }
+    let lark = lark.trim_matches('_');
+    let prefix = if terminal { "TERM" } else { "rule" };
+    match lark.chars().next() {
+        Some(c) if c.is_ascii_alphabetic() => String::from(lark),
+        Some(_) => format!("{}_{}", prefix, lark),
+        None => String::from(prefix),
+    }
+}
+
+/// Get `text` as a Lark string, with the characters which can't be in one
+/// escaped
+fn string(text: &str) -> String {
+    let mut string = String::from("\"");
+    for c in text.chars() {
+        match c {
+            '"' => string.push_str("\\\""),
+            '\\' => string.push_str("\\\\"),
+            '\n' => string.push_str("\\n"),
+            '\t' => string.push_str("\\t"),
+            '\r' => string.push_str("\\r"),
+            c if c.is_control() => string.push_str(&format!("\\x{:02x}", c as u32)),
+            c => string.push(c),
+        }
+    }
+    string.push('"');
+    string
+}
+
+/// Get `c` as it's written in a class of a regex
+fn class_char(c: char) -> String {
+    if c.is_ascii_graphic() && !"[]^-\\/".contains(c) {
+        c.to_string()
+    } else if c.is_ascii_graphic() {
+        format!("\\{}", c)
+    } else if (c as u32) > 0xffff {
+        format!("\\U{:08x}", c as u32)
+    } else {
+        format!("\\u{:04x}", c as u32)
+    }
+}
+
+/// Get `class` as ranges of strings like `"a".."z"`, or a regex when it's
+/// negated or empty
+fn class(class: &CharClass) -> String {
+    if class.is_empty() {
+        return String::from(NOTHING);
+    }
+    if class.is_negated() {
+        let ranges: String = class
+            .ranges()
+            .iter()
+            .map(|&(lo, hi)| {
+                if lo == hi {
+                    class_char(lo)
+                } else {
+                    format!("{}-{}", class_char(lo), class_char(hi))
+                }
+            })
+            .collect();
+        if ranges.is_empty() {
+            return String::from("/[\\s\\S]/");
+        }
+        return format!("/[^{}]/", ranges);
+    }
+    let ranges = class
+        .ranges()
+        .iter()
+        .map(|&(lo, hi)| {
+            if lo == hi {
+                string(&lo.to_string())
+            } else {
+                format!("{}..{}", string(&lo.to_string()), string(&hi.to_string()))
+            }
+        })
+        .collect::<Vec<_>>();
+    match ranges[..] {
+        [ref range] => range.clone(),
+        _ => format!("( {} )", ranges.join(" | ")),
+    }
+}
+
+/// Get the nonterminals `sugar` references
+fn references<'a>(sugar: &Sugar<'a>, referenced: &mut Vec<&'a str>) {
+    let mut alternatives = |alternatives: &[Alternative<'a>]| {
+        for alternative in alternatives {
+            for sugar in &alternative.terms {
+                references(sugar, referenced);
+            }
+        }
+    };
+    match *sugar {
+        Sugar::Term(Term::Nonterminal(name)) => referenced.push(name),
+        Sugar::Term(_) => {}
+        Sugar::Group(ref group) | Sugar::Optional(ref group) => alternatives(group),
+        Sugar::Repeat(_, _, ref repeated) => references(repeated, referenced),
+    }
+}
+
+/// Whether `sugar` matches the empty string, assuming the nonterminals it
+/// references don't
+fn is_nullable(sugar: &Sugar) -> bool {
+    match *sugar {
+        Sugar::Term(&Term::Terminal(ref text)) | Sugar::Term(&Term::CaselessTerminal(ref text)) => {
+            text.is_empty()
+        }
+        Sugar::Term(Term::TerminalBytes(bytes)) => bytes.is_empty(),
+        Sugar::Term(_) => false,
+        Sugar::Group(ref group) => group
+            .iter()
+            .any(|alternative| alternative.terms.iter().all(is_nullable)),
+        Sugar::Optional(_) => true,
+        Sugar::Repeat(min, _, ref repeated) => min == 0 || is_nullable(repeated),
+    }
+}
+
+/// Whether the nonterminal `name` may be a Lark terminal: it's defined,
+/// references only terminals, not itself, and doesn't match the empty
+/// string, which a Lark terminal can't
+fn is_terminal<'a>(
+    name: &'a str,
+    rules: &HashMap<&'a str, Vec<&Alternative<'a>>>,
+    memo: &mut HashMap<&'a str, Option<bool>>,
+) -> bool {
+    match memo.get(name) {
+        Some(&Some(terminal)) => return terminal,
+        // it references itself
+        Some(&None) => return false,
+        None => {}
+    }
+    let alternatives = match rules.get(name) {
+        Some(alternatives) if !alternatives.is_empty() => alternatives,
+        _ => return false,
+    };
+    memo.insert(name, None);
+    let mut referenced = vec![];
+    for alternative in alternatives {
+        for sugar in &alternative.terms {
+            references(sugar, &mut referenced);
+        }
+    }
+    let terminal = referenced
+        .into_iter()
+        .all(|referenced| is_terminal(referenced, rules, memo))
+        && !alternatives
+            .iter()
+            .any(|alternative| alternative.terms.iter().all(is_nullable));
+    memo.insert(name, Some(terminal));
+    terminal
+}
+
+/// Writes the resugared productions of a grammar as a Lark grammar
+struct Lark<'a> {
+    names: HashMap<&'a str, String>,
+}
+
+impl<'a> Lark<'a> {
+    /// Get the Lark name of every nonterminal in `resugared`, `start` being
+    /// named `start` and the others renamed to a name no other nonterminal
+    /// is, in uppercase when they're terminals
+    fn new(resugared: &[(&'a Term, &[Alternative<'a>])], start: &'a str) -> Lark<'a> {
+        let mut rules: HashMap<&str, Vec<&Alternative>> = HashMap::new();
+        let mut nonterminals = vec![start];
+        for &(lhs, alternatives) in resugared {
+            if let Term::Nonterminal(ref name) = *lhs {
+                nonterminals.push(name);
+                rules.entry(name).or_default().extend(alternatives);
+            }
+            for alternative in alternatives {
+                for sugar in &alternative.terms {
+                    references(sugar, &mut nonterminals);
+                }
+            }
+        }
+
+        let mut memo = HashMap::new();
+        let mut names = HashMap::new();
+        let mut taken = HashSet::new();
+        for nonterminal in nonterminals {
+            if names.contains_key(nonterminal) {
+                continue;
+            }
+            let base = if nonterminal == start {
+                String::from("start")
+            } else {
+                lark_name(nonterminal, is_terminal(nonterminal, &rules, &mut memo))
+            };
+            let mut renamed = base.clone();
+            let mut count = 1;
+            while !taken.insert(renamed.clone()) {
+                count += 1;
+                renamed = format!("{}_{}", base, count);
+            }
+            names.insert(nonterminal, renamed);
+        }
+        Lark { names }
+    }
+
+    fn term(&self, term: &Term) -> String {
+        match *term {
+            Term::Nonterminal(ref name) => self.names[name.as_str()].clone(),
+            Term::Terminal(ref text) => string(text),
+            Term::CaselessTerminal(ref text) => format!("{}i", string(text)),
+            Term::TerminalBytes(ref bytes) => match str::from_utf8(bytes) {
+                Ok(text) => string(text),
+                Err(_) => {
+                    let escaped: String = bytes
+                        .iter()
+                        .map(|byte| format!("\\x{:02x}", byte))
+                        .collect();
+                    format!("\"{}\"", escaped)
+                }
+            },
+            Term::CharClass(ref charclass) => class(charclass),
+            Term::Regex(ref pattern) => {
+                let mut escaped = String::new();
+                let mut chars = pattern.chars();
+                while let Some(c) = chars.next() {
+                    match c {
+                        '/' => escaped.push_str("\\/"),
+                        '\\' => {
+                            escaped.push('\\');
+                            escaped.extend(chars.next());
+                        }
+                        c => escaped.push(c),
+                    }
+                }
+                format!("/{}/", escaped)
+            }
+            Term::Repeat { .. } => unreachable!("repetitions are resugared"),
+        }
+    }
+
+    fn sugar(&self, sugar: &Sugar) -> String {
+        match *sugar {
+            Sugar::Term(term) => self.term(term),
+            Sugar::Group(ref alternatives) => format!("( {} )", self.alternatives(alternatives)),
+            Sugar::Optional(ref alternatives) => format!("[ {} ]", self.alternatives(alternatives)),
+            Sugar::Repeat(min, Some(max), ref repeated) if min == max => {
+                format!("{} ~ {}", self.operand(repeated), min)
+            }
+            Sugar::Repeat(min, Some(max), ref repeated) => {
+                format!("{} ~ {}..{}", self.operand(repeated), min, max)
+            }
+            // Lark has no unbounded repetition but `*` and `+`
+            Sugar::Repeat(0, None, ref repeated) => format!("{}*", self.operand(repeated)),
+            Sugar::Repeat(1, None, ref repeated) => format!("{}+", self.operand(repeated)),
+            Sugar::Repeat(min, None, ref repeated) => {
+                let operand = self.operand(repeated);
+                format!("{} ~ {} {}*", operand, min, operand)
+            }
+        }
+    }
+
+    /// Get `sugar` as the operand of a postfix operator, in parentheses
+    /// unless it's a single term or group already
+    fn operand(&self, sugar: &Sugar) -> String {
+        match *sugar {
+            Sugar::Term(_) | Sugar::Group(_) | Sugar::Optional(_) => self.sugar(sugar),
+            Sugar::Repeat(..) => {
+                format!("( {} )", self.sugar(sugar))
+            }
+        }
+    }
+
+    /// Get `alternatives` joined by `|`, each without the empty strings
+    /// Lark doesn't allow, so an empty alternative is written as nothing
+    fn alternatives(&self, alternatives: &[Alternative]) -> String {
+        alternatives
+            .iter()
+            .map(|alternative| {
+                alternative
+                    .terms
+                    .iter()
+                    .filter(|sugar| match **sugar {
+                        Sugar::Term(Term::Terminal(text)) => !text.is_empty(),
+                        _ => true,
+                    })
+                    .map(|sugar| self.sugar(sugar))
+                    .collect::<Vec<_>>()
+                    .join(" ")
+            })
+            .collect::<Vec<_>>()
+            .join(" | ")
+    }
+}
+
+impl Grammar {
+    /// Format the grammar for the Python parsing library Lark, with a line
+    /// like `name: "a" other | NUMBER` for each nonterminal, beginning with
+    /// the nonterminal `start`, which is renamed `start` as Lark expects.
+    ///
+    /// Nonterminals which only reference other terminals, not themselves,
+    /// and never match the empty string, like `<number> ::= [0-9]+`, become
+    /// Lark terminals named in uppercase, like `NUMBER`, and the others
+    /// rules named in lowercase. Names are renamed to what Lark allows, like
+    /// `<my rule>` to `my_rule`, and to no other's name. Groups, optional
+    /// groups and repetitions are written with `( )`, `[ ]`, `*`, `+` and
+    /// `~`, character classes as ranges like `"a".."z"`, or as a regex when
+    /// they're negated, caseless terminals with the flag `i`. Weights, which
+    /// Lark has no notation for, are left out. As Lark lexes terminals
+    /// before parsing rules, it may not accept every sentence this crate
+    /// parses when terminals overlap.
+    ///
+    /// # Example
+    ///
+    /// ```rust
+    /// extern crate bnf;
+    /// use bnf::Grammar;
+    ///
+    /// fn main() {
+    ///     let grammar = Grammar::from_str(
+    ///         "<sum> ::= <number> | <sum> \"+\" <number>
+    ///         <number> ::= [0-9]+ [ \".\" [0-9]+ ]",
+    ///     ).unwrap();
+    ///
+    ///     assert_eq!(
+    ///         grammar.to_lark("sum"),
+    ///         "start: NUMBER | start \"+\" NUMBER\nNUMBER: \"0\"..\"9\"+ [ \".\" \"0\"..\"9\"+ ]\n"
+    ///     );
+    /// }
+    /// ```
+    pub fn to_lark(&self, start: impl Into<Nonterminal>) -> String {
+        let start = start.into();
+        let start = start.name();
+        let resugared = grammar::resugar(self);
+        let productions = resugared
+            .iter()
+            .map(|&(prod, ref alternatives)| (prod.lhs(), &alternatives[..]))
+            .collect::<Vec<_>>();
+        let lark = Lark::new(&productions, start);
+
+        let mut rules: Vec<(&str, Vec<String>)> = vec![];
+        for &(lhs, alternatives) in &productions {
+            let name = match *lhs {
+                Term::Nonterminal(ref name) => name.as_str(),
+                _ => continue,
+            };
+            let alternatives = lark.alternatives(alternatives);
+            match rules.iter_mut().find(|rule| rule.0 == name) {
+                Some(rule) => rule.1.push(alternatives),
+                None => rules.push((name, vec![alternatives])),
+            }
+        }
+        // Lark begins from the rule `start`
+        if let Some(index) = rules.iter().position(|rule| rule.0 == start) {
+            let start = rules.remove(index);
+            rules.insert(0, start);
+        }
+        rules
+            .into_iter()
+            .map(|(name, alternatives)| {
+                let alternatives = alternatives
+                    .into_iter()
+                    .filter(|alternatives| !alternatives.is_empty())
+                    .collect::<Vec<_>>();
+                let alternatives = if alternatives.is_empty() {
+                    String::from(NOTHING)
+                } else {
+                    alternatives.join(" | ")
+                };
+                format!("{}: {}\n", lark.names[name], alternatives)
+            })
+            .collect()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use expression::Expression;
+    use production::Production;
+
+    #[test]
+    fn to_lark() {
+        let grammar = Grammar::from_str(
+            "<doc> ::= <item>* ( \"a\" | \"b\" %weight 2 )+ 2*3<Item> [ \"d\" \"e\" ] 3*<id>
+            <item> ::= <doc> \"\" | \"\" | %i\"If\" | 'tab\t\"'
+            <Item> ::= [a-z_] | [^\\]/]
+            <id> ::= <Item> <Item>+ | <doc> \"x\"
+            <id> ::= <letter> 1*2\"x\"
+            <letter> ::= [A-Za-z]",
+        )
+        .unwrap();
+        assert_eq!(
+            grammar.to_lark("id"),
+            "start: ITEM ITEM+ | doc \"x\" | LETTER \"x\" ~ 1..2
+doc: item* ( \"a\" | \"b\" )+ ITEM ~ 2..3 [ \"d\" \"e\" ] start ~ 3 start*
+item: doc |  | \"If\"i | \"tab\\t\\\"\"
+ITEM: ( \"a\"..\"z\" | \"_\" ) | /[^\\]\\/]/
+LETTER: ( \"A\"..\"Z\" | \"a\"..\"z\" )
+"
+        );
+    }
+
+    #[test]
+    fn lark_names() {
+        let mut grammar = Grammar::from_str(
+            "<my rule> ::= <My_Rule> | <1st> | <start> | <undefined> | <a>
+            <My_Rule> ::= \"a\" | <my_rule>
+            <start> ::= <my rule>
+            <1st> ::= \"c\"
+            <a> ::= <A> \"x\"
+            <A> ::= \"\" | \"y\"",
+        )
+        .unwrap();
+        grammar.add_production(Production::from_parts(
+            Term::Nonterminal(String::from("nothing")),
+            vec![],
+        ));
+        grammar.add_production(Production::from_parts(
+            Term::Nonterminal(String::from("bytes")),
+            vec![Expression::from_parts(vec![Term::TerminalBytes(vec![
+                0xff, 0x0a,
+            ])])],
+        ));
+        grammar.add_production(Production::from_parts(
+            Term::Nonterminal(String::from("path")),
+            vec![Expression::from_parts(vec![Term::Regex(String::from(
+                "[a-z]/[0-9]+",
+            ))])],
+        ));
+        assert_eq!(
+            grammar.to_lark("my rule"),
+            "start: my_rule | TERM_1ST | start_2 | undefined | a
+my_rule: \"a\" | my_rule_2
+start_2: start
+TERM_1ST: \"c\"
+a: a_2 \"x\"
+a_2:  | \"y\"
+nothing: /(?!)/
+BYTES: \"\\xff\\x0a\"
+PATH: /[a-z]\\/[0-9]+/
+"
+        );
+        assert_eq!(Grammar::new().to_lark("start"), "");
+    }
+
+    #[cfg(feature = "lark")]
+    #[test]
+    fn to_lark_and_back() {
+        let grammar = Grammar::from_str(
+            "<json> ::= <object> | <array> | <number> | \"null\"
+            <object> ::= \"{\" [ <pair> ( \",\" <pair> )* ] \"}\"
+            <pair> ::= <string> \":\" <json>
+            <array> ::= \"[\" [ <json> ( \",\" <json> )* ] \"]\"
+            <string> ::= '\"' [a-z]* '\"'
+            <number> ::= \"-\"? [0-9]+",
+        )
+        .unwrap();
+        let lark = Grammar::from_lark(&grammar.to_lark("json")).unwrap();
+        let good = ["null", "[1,-2,[]]", "{\"a\":{\"bc\":[null]}}"];
+        let bad = ["[1,]", "{\"a\"}", "--1", "{\"A\":1}"];
+        assert!(grammar.verify_examples("json", &good, &bad).is_ok());
+        assert!(lark.verify_examples("start", &good, &bad).is_ok());
+    }
+}
@@ -0,0 +1,676 @@
+use error::Error;
+use grammar::Grammar;
+use term::{CharClass, Term};
+
+/// A token of a Lark grammar, on the line it's on
+#[derive(Debug)]
+enum Token {
+    Name(String),
+    Define,
+    Or,
+    Open,
+    Close,
+    OpenOptional,
+    CloseOptional,
+    Postfix(char),
+    Tilde,
+    Range,
+    Number(usize),
+    Text(String, bool),
+    Regex(String, String),
+}
+
+/// An expression of a Lark grammar
+#[derive(Debug)]
+enum Node {
+    Name(String),
+    Text(String, bool),
+    Regex(String, String),
+    Class(CharClass),
+    Choice(Vec<Vec<Node>>),
+    Optional(Vec<Vec<Node>>),
+    Postfix(Box<Node>, char),
+    Repeat(Box<Node>, usize, usize),
+}
+
+impl Token {
+    /// Describe the token for errors
+    fn describe(&self) -> String {
+        match *self {
+            Token::Name(ref name) => name.clone(),
+            Token::Define => String::from("':'"),
+            Token::Or => String::from("'|'"),
+            Token::Open => String::from("'('"),
+            Token::Close => String::from("')'"),
+            Token::OpenOptional => String::from("'['"),
+            Token::CloseOptional => String::from("']'"),
+            Token::Postfix(operator) => format!("'{}'", operator),
+            Token::Tilde => String::from("'~'"),
+            Token::Range => String::from("'..'"),
+            Token::Number(number) => number.to_string(),
+            Token::Text(ref text, _) => format!("{:?}", text),
+            Token::Regex(ref pattern, _) => format!("/{}/", pattern),
+        }
+    }
+}
+
+fn error(line: usize, message: &str) -> Error {
+    Error::ParseError(format!("Line {}: {}", line, message))
+}
+
+/// Whether `c` may be in a name
+fn is_name_char(c: char) -> bool {
+    c.is_ascii_alphanumeric() || c == '_'
+}
+
+/// The terminals of Lark's `common` module which `%import` supports, in
+/// this crate's BNF
+fn common(name: &str) -> Option<String> {
+    let class = |ranges: &[(char, char)]| CharClass::from_ranges(ranges.to_vec()).to_string();
+    let letter = class(&[('A', 'Z'), ('a', 'z')]);
+    Some(match name {
+        "DIGIT" => class(&[('0', '9')]),
+        "HEXDIGIT" => class(&[('0', '9'), ('A', 'F'), ('a', 'f')]),
+        "INT" => format!("{}+", class(&[('0', '9')])),
+        "LCASE_LETTER" => class(&[('a', 'z')]),
+        "UCASE_LETTER" => class(&[('A', 'Z')]),
+        "LETTER" => letter,
+        "WORD" => format!("{}+", letter),
+        "CNAME" => format!(
+            "{} {}*",
+            class(&[('A', 'Z'), ('_', '_'), ('a', 'z')]),
+            class(&[('0', '9'), ('A', 'Z'), ('_', '_'), ('a', 'z')])
+        ),
+        "WS_INLINE" => format!("{}+", class(&[('\t', '\t'), (' ', ' ')])),
+        "WS" => format!("{}+", class(&[('\t', '\n'), ('\x0c', '\r'), (' ', ' ')])),
+        "NEWLINE" => format!(
+            "( {}? {} )+",
+            class(&[('\r', '\r')]),
+            class(&[('\n', '\n')])
+        ),
+        _ => return None,
+    })
+}
+
+/// Parse the rest of a string like `"a\"b"`, the opening `"` already read,
+/// along with whether it's followed by `i` for any case
+fn text<I>(chars: &mut ::std::iter::Peekable<I>, line: usize) -> Result<Token, Error>
+where
+    I: Iterator<Item = char> + Clone,
+{
+    let mut text = String::new();
+    loop {
+        match chars.next() {
+            Some('"') => break,
+            Some('\\') => match chars.next() {
+                Some('n') => text.push('\n'),
+                Some('t') => text.push('\t'),
+                Some('r') => text.push('\r'),
+                Some(escaped @ '"') | Some(escaped @ '\\') => text.push(escaped),
+                Some(other) => {
+                    text.push('\\');
+                    text.push(other);
+                }
+                None => return Err(error(line, "missing closing '\"'")),
+            },
+            Some('\n') | None => return Err(error(line, "missing closing '\"'")),
+            Some(c) => text.push(c),
+        }
+    }
+    // an `i` which isn't the beginning of a name makes the string caseless
+    let mut after = chars.clone();
+    let caseless = after.next() == Some('i') && !after.next().is_some_and(is_name_char);
+    if caseless {
+        chars.next();
+    }
+    Ok(Token::Text(text, caseless))
+}
+
+/// Parse the rest of a regex like `/[a-z]+/i`, the opening `/` already read
+fn regex<I>(chars: &mut ::std::iter::Peekable<I>, line: usize) -> Result<Token, Error>
+where
+    I: Iterator<Item = char>,
+{
+    let mut pattern = String::new();
+    loop {
+        match chars.next() {
+            Some('/') => break,
+            Some('\\') if chars.peek() == Some(&'/') => {
+                chars.next();
+                pattern.push('/');
+            }
+            Some('\\') => {
+                pattern.push('\\');
+                pattern.extend(chars.next());
+            }
+            Some('\n') | None => return Err(error(line, "missing closing '/'")),
+            Some(c) => pattern.push(c),
+        }
+    }
+    let mut flags = String::new();
+    while let Some(&flag) = chars.peek() {
+        if !"imslux".contains(flag) {
+            break;
+        }
+        flags.push(flag);
+        chars.next();
+    }
+    Ok(Token::Regex(pattern, flags))
+}
+
+/// Split a Lark grammar into tokens and the directives, like `%import
+/// common.WS`, which begin lines, skipping comments, the `?` and `!` which
+/// may prefix the name of a rule, priorities like the `.2` of `NAME.2`, and
+/// aliases like `-> name`
+#[allow(clippy::type_complexity)]
+fn tokenize(input: &str) -> Result<(Vec<(Token, usize)>, Vec<(String, usize)>), Error> {
+    let mut tokens = vec![];
+    let mut directives = vec![];
+    let mut line = 1;
+    let mut line_start = true;
+    let mut chars = input.chars().peekable();
+    while let Some(c) = chars.next() {
+        if c == '\n' {
+            line += 1;
+            line_start = true;
+            continue;
+        }
+        if c.is_whitespace() {
+            continue;
+        }
+        let first = line_start;
+        line_start = false;
+        let token = match c {
+            '/' if chars.peek() == Some(&'/') => {
+                while chars.peek().is_some_and(|&next| next != '\n') {
+                    chars.next();
+                }
+                line_start = first;
+                continue;
+            }
+            '%' if first => {
+                let mut directive = String::new();
+                while let Some(&next) = chars.peek() {
+                    if next == '\n' {
+                        break;
+                    }
+                    directive.push(next);
+                    chars.next();
+                }
+                directives.push((String::from(directive.trim()), line));
+                continue;
+            }
+            '?' | '!' if first => continue,
+            '-' if chars.peek() == Some(&'>') => {
+                chars.next();
+                while chars
+                    .peek()
+                    .is_some_and(|&next| next == ' ' || next == '\t')
+                {
+                    chars.next();
+                }
+                while chars.peek().is_some_and(|&next| is_name_char(next)) {
+                    chars.next();
+                }
+                continue;
+            }
+            '.' if chars.peek() == Some(&'.') => {
+                chars.next();
+                Token::Range
+            }
+            ':' => Token::Define,
+            '|' => Token::Or,
+            '(' => Token::Open,
+            ')' => Token::Close,
+            '[' => Token::OpenOptional,
+            ']' => Token::CloseOptional,
+            '?' | '*' | '+' => Token::Postfix(c),
+            '~' => Token::Tilde,
+            '"' => text(&mut chars, line)?,
+            '/' => regex(&mut chars, line)?,
+            '{' => return Err(error(line, "templates aren't supported")),
+            c if c.is_ascii_digit() => {
+                let mut digits = c.to_string();
+                while let Some(&next) = chars.peek().filter(|next| next.is_ascii_digit()) {
+                    digits.push(next);
+                    chars.next();
+                }
+                match digits.parse() {
+                    Ok(number) => Token::Number(number),
+                    Err(_) => return Err(error(line, &format!("invalid number {}", digits))),
+                }
+            }
+            c if is_name_char(c) => {
+                let mut name = c.to_string();
+                while let Some(&next) = chars.peek().filter(|&&next| is_name_char(next)) {
+                    name.push(next);
+                    chars.next();
+                }
+                // the priority of a rule or terminal, like `NAME.2`
+                let mut after = chars.clone();
+                if after.next() == Some('.') && after.next().is_some_and(|c| c.is_ascii_digit()) {
+                    chars.next();
+                    while chars.peek().is_some_and(|next| next.is_ascii_digit()) {
+                        chars.next();
+                    }
+                }
+                Token::Name(name)
+            }
+            c => return Err(error(line, &format!("unexpected {:?}", c))),
+        };
+        tokens.push((token, line));
+    }
+    Ok((tokens, directives))
+}
+
+/// Parses the tokens of one rule's expansions
+struct Parser<'a> {
+    tokens: &'a [(Token, usize)],
+    at: usize,
+    line: usize,
+}
+
+impl<'a> Parser<'a> {
+    fn peek(&self) -> Option<&'a Token> {
+        self.tokens.get(self.at).map(|(token, _)| token)
+    }
+
+    fn next(&mut self) -> Option<&'a Token> {
+        let token = self.tokens.get(self.at);
+        if let Some(&(_, line)) = token {
+            self.line = line;
+        }
+        self.at += 1;
+        token.map(|(token, _)| token)
+    }
+
+    fn choice(&mut self) -> Result<Vec<Vec<Node>>, Error> {
+        let mut alternatives = vec![self.sequence()?];
+        while let Some(&Token::Or) = self.peek() {
+            self.next();
+            alternatives.push(self.sequence()?);
+        }
+        Ok(alternatives)
+    }
+
+    fn sequence(&mut self) -> Result<Vec<Node>, Error> {
+        let mut sequence = vec![];
+        loop {
+            match self.peek() {
+                Some(&Token::Name(_))
+                | Some(&Token::Text(..))
+                | Some(&Token::Regex(..))
+                | Some(&Token::Open)
+                | Some(&Token::OpenOptional) => sequence.push(self.item()?),
+                _ => return Ok(sequence),
+            }
+        }
+    }
+
+    fn number(&mut self) -> Result<usize, Error> {
+        match self.next() {
+            Some(&Token::Number(number)) => Ok(number),
+            _ => Err(error(self.line, "expected a number after '~'")),
+        }
+    }
+
+    fn item(&mut self) -> Result<Node, Error> {
+        let mut node = match self.next() {
+            Some(Token::Name(name)) => Node::Name(name.clone()),
+            Some(Token::Text(text, caseless)) => match self.peek() {
+                Some(&Token::Range) => {
+                    self.next();
+                    let to = match self.next() {
+                        Some(Token::Text(to, _)) => to,
+                        _ => return Err(error(self.line, "expected a string after '..'")),
+                    };
+                    let mut ends = text.chars().chain(to.chars());
+                    match (ends.next(), ends.next(), ends.next()) {
+                        (Some(lo), Some(hi), None) if lo <= hi => {
+                            Node::Class(CharClass::from_ranges(vec![(lo, hi)]))
+                        }
+                        (Some(lo), Some(hi), None) => {
+                            return Err(error(
+                                self.line,
+                                &format!("reversed range {:?}..{:?}", lo, hi),
+                            ))
+                        }
+                        _ => {
+                            return Err(error(
+                                self.line,
+                                "a range must be between strings of one character",
+                            ))
+                        }
+                    }
+                }
+                _ => Node::Text(text.clone(), *caseless),
+            },
+            Some(Token::Regex(pattern, flags)) => Node::Regex(pattern.clone(), flags.clone()),
+            Some(&Token::Open) => {
+                let alternatives = self.choice()?;
+                match self.next() {
+                    Some(&Token::Close) => Node::Choice(alternatives),
+                    _ => return Err(error(self.line, "missing closing ')'")),
+                }
+            }
+            Some(&Token::OpenOptional) => {
+                let alternatives = self.choice()?;
+                match self.next() {
+                    Some(&Token::CloseOptional) => Node::Optional(alternatives),
+                    _ => return Err(error(self.line, "missing closing ']'")),
+                }
+            }
+            _ => return Err(error(self.line, "expected a name, string, regex or group")),
+        };
+        loop {
+            match self.peek() {
+                Some(&Token::Postfix(operator)) => {
+                    self.next();
+                    node = Node::Postfix(Box::new(node), operator);
+                }
+                Some(&Token::Tilde) => {
+                    self.next();
+                    let min = self.number()?;
+                    let max = match self.peek() {
+                        Some(&Token::Range) => {
+                            self.next();
+                            self.number()?
+                        }
+                        _ => min,
+                    };
+                    if max < min {
+                        return Err(error(
+                            self.line,
+                            &format!("reversed repetition {}..{}", min, max),
+                        ));
+                    }
+                    node = Node::Repeat(Box::new(node), min, max);
+                }
+                _ => return Ok(node),
+            }
+        }
+    }
+}
+
+fn alternatives(alternatives: &[Vec<Node>], line: usize) -> Result<String, Error> {
+    let mut written = vec![];
+    for alternative in alternatives {
+        let mut terms = vec![];
+        for node in alternative {
+            terms.push(write(node, line)?);
+        }
+        if terms.is_empty() {
+            terms.push(String::from("\"\""));
+        }
+        written.push(terms.join(" "));
+    }
+    Ok(written.join(" | "))
+}
+
+/// Write `node` in this crate's BNF
+fn write(node: &Node, line: usize) -> Result<String, Error> {
+    Ok(match *node {
+        Node::Name(ref name) => format!("<{}>", name),
+        Node::Text(ref text, true) => Term::CaselessTerminal(text.clone()).to_string(),
+        Node::Text(ref text, false) => Term::Terminal(text.clone()).to_string(),
+        Node::Regex(ref pattern, ref flags) => {
+            if !cfg!(feature = "regex") {
+                return Err(error(line, "regexes need the feature 'regex'"));
+            }
+            if pattern.contains("\"#") {
+                return Err(error(line, "regexes containing '\"#' aren't supported"));
+            }
+            let flags = flags.replace(['l', 'u'], "");
+            if flags.is_empty() {
+                format!("#\"{}\"#", pattern)
+            } else {
+                format!("#\"(?{}){}\"#", flags, pattern)
+            }
+        }
+        Node::Class(ref class) => class.to_string(),
+        Node::Choice(ref choice) => format!("( {} )", alternatives(choice, line)?),
+        Node::Optional(ref choice) => format!("[ {} ]", alternatives(choice, line)?),
+        Node::Postfix(ref inner, operator) => format!("{}{}", operand(inner, line)?, operator),
+        Node::Repeat(ref inner, min, max) => {
+            format!("{}*{}{}", min, max, operand(inner, line)?)
+        }
+    })
+}
+
+/// Write `node` as the operand of a repetition, grouped unless it's a
+/// single term or group already
+fn operand(node: &Node, line: usize) -> Result<String, Error> {
+    match *node {
+        Node::Postfix(..) | Node::Repeat(..) => Ok(format!("( {} )", write(node, line)?)),
+        _ => write(node, line),
+    }
+}
+
+impl Grammar {
+    /// Construct a `Grammar` from a grammar of the Python parsing library
+    /// Lark, with the feature `lark`.
+    ///
+    /// Rules like `list: "[" item ("," item)* "]"` and terminals like
+    /// `NUMBER: DIGIT+` both become productions, of nonterminals named as
+    /// they are, the rule `start` first when there is one. Strings may be
+    /// followed by `i` for any case and ranges like `"a".."z"` become
+    /// character classes. `[ ]` is optional, and `?`, `*`, `+` and
+    /// repetitions like `~ 2..4` are parsed like this crate's postfix
+    /// operators and counts are. Regexes like `/[a-z]+/i` need the feature
+    /// `regex`.
+    ///
+    /// What only matters to Lark's parsers is skipped: the `?` and `!`
+    /// prefixing rules, priorities like `NAME.2`, aliases like `-> name`,
+    /// and the directives `%ignore` and `%declare`. `%import` only
+    /// supports the terminals `DIGIT`, `HEXDIGIT`, `INT`, `LCASE_LETTER`,
+    /// `UCASE_LETTER`, `LETTER`, `WORD`, `CNAME`, `WS_INLINE`, `WS` and
+    /// `NEWLINE` of the module `common`, and templates aren't supported.
+    ///
+    /// # Example
+    ///
+    /// ```rust
+    /// extern crate bnf;
+    /// use bnf::Grammar;
+    ///
+    /// fn main() {
+    ///     let grammar = Grammar::from_lark(
+    ///         "?start: list
+    ///          list: \"[\" [item (\",\" item)*] \"]\"  // no trailing comma
+    ///          item: INT -> number
+    ///              | \"nil\"i
+    ///          %import common.INT
+    ///          %ignore \" \"",
+    ///     ).unwrap();
+    ///     let native = Grammar::from_str(
+    ///         "<start> ::= <list>
+    ///         <list> ::= \"[\" [ <item> ( \",\" <item> )* ] \"]\"
+    ///         <item> ::= <INT> | %i\"nil\"
+    ///         <INT> ::= [0-9]+",
+    ///     ).unwrap();
+    ///
+    ///     assert_eq!(grammar, native);
+    /// }
+    /// ```
+    pub fn from_lark(input: &str) -> Result<Grammar, Error> {
+        let (tokens, directives) = tokenize(input)?;
+        let mut starts = vec![];
+        for index in 0..tokens.len() {
+            if let (&(Token::Name(_), _), Some(&(Token::Define, _))) =
+                (&tokens[index], tokens.get(index + 1))
+            {
+                starts.push(index);
+            }
+        }
+        match tokens.first() {
+            Some(&(_, line)) if starts.first() != Some(&0) => {
+                return Err(error(line, "expected a rule like 'name: ...'"))
+            }
+            _ => {}
+        }
+
+        let mut rules = vec![];
+        for (index, &start) in starts.iter().enumerate() {
+            let end = starts.get(index + 1).cloned().unwrap_or(tokens.len());
+            let (name, line) = match tokens[start] {
+                (Token::Name(ref name), line) => (name, line),
+                _ => continue,
+            };
+            let mut parser = Parser {
+                tokens: &tokens[start + 2..end],
+                at: 0,
+                line,
+            };
+            let expansions = parser.choice()?;
+            if let Some(token) = parser.next() {
+                return Err(error(
+                    parser.line,
+                    &format!("unexpected {}", token.describe()),
+                ));
+            }
+            rules.push(format!(
+                "<{}> ::= {}\n",
+                name,
+                alternatives(&expansions, line)?
+            ));
+        }
+        // Lark begins from the rule `start`
+        if let Some(index) = rules
+            .iter()
+            .position(|rule| rule.starts_with("<start> ::="))
+        {
+            let start = rules.remove(index);
+            rules.insert(0, start);
+        }
+
+        for (directive, line) in directives {
+            let mut words = directive.split_whitespace();
+            match words.next() {
+                Some("ignore") | Some("declare") => continue,
+                Some("import") => {}
+                _ => {
+                    return Err(error(
+                        line,
+                        &format!("the directive %{} isn't supported", directive),
+                    ))
+                }
+            }
+            let rest = words.collect::<Vec<_>>().join(" ");
+            // `%import common.WS`, `%import common.WS -> SPACE` or
+            // `%import common (WS, DIGIT)`
+            let imports = match rest.split_once("->") {
+                Some((path, alias)) => vec![(String::from(path.trim()), Some(alias.trim()))],
+                None => match rest.split_once('(') {
+                    Some((module, names)) => names
+                        .trim_end_matches(')')
+                        .split(',')
+                        .map(|name| (format!("{}.{}", module.trim(), name.trim()), None))
+                        .collect(),
+                    None => vec![(rest.clone(), None)],
+                },
+            };
+            for (path, alias) in imports {
+                let name = match path.split_once('.') {
+                    Some(("common", name)) => name,
+                    _ => return Err(error(line, &format!("can't import {}", path))),
+                };
+                let definition =
+                    common(name).ok_or_else(|| error(line, &format!("can't import {}", path)))?;
+                rules.push(format!("<{}> ::= {}\n", alias.unwrap_or(name), definition));
+            }
+        }
+        Grammar::from_str(&rules.concat())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn from_lark() {
+        let grammar = Grammar::from_lark(
+            "// arithmetic
+            ?sum: product
+                | sum \"+\" product   -> add
+            ?product: atom (\"*\" atom)*
+            !atom: NUMBER | \"(\" sum \")\" | \"-\"~1..2 atom
+            start: sum
+            NUMBER.2: (\"0\"..\"9\")+ [\".\" DIGIT ~ 2]
+            %import common.DIGIT
+            %import common (WS, LETTER)
+            %import common.CNAME -> NAME
+            %ignore WS
+            %declare INDENT",
+        )
+        .unwrap();
+        let expected = Grammar::from_str(
+            "<start> ::= <sum>
+            <sum> ::= <product> | <sum> \"+\" <product>
+            <product> ::= <atom> ( \"*\" <atom> )*
+            <atom> ::= <NUMBER> | \"(\" <sum> \")\" | 1*2\"-\" <atom>
+            <NUMBER> ::= ( [0-9] )+ [ \".\" 2*2<DIGIT> ]
+            <DIGIT> ::= [0-9]
+            <WS> ::= [\t-\n\x0c-\r ]+
+            <LETTER> ::= [A-Za-z]
+            <NAME> ::= [A-Z_a-z] [0-9A-Z_a-z]*",
+        )
+        .unwrap();
+        assert_eq!(grammar, expected);
+        assert!(grammar
+            .verify_examples(
+                "start",
+                &["1+2*3", "--(4.56)", "7*-8"],
+                &["1.2", "1+", "(1"]
+            )
+            .is_ok());
+    }
+
+    #[test]
+    fn lark_strings() {
+        let grammar = Grammar::from_lark(
+            "start: \"say \\\"hi\\\"\\n\" | \"if\"i | \"if\" | \"\" | \"it's\" | \"'\\\"\\\\\"\n",
+        )
+        .unwrap();
+        let expected = Grammar::from_str(
+            "<start> ::= 'say \"hi\"\n' | %i\"if\" | \"if\" | \"\" | \"it's\" | \"'\\\"\\\\\"",
+        )
+        .unwrap();
+        assert_eq!(grammar, expected);
+        // a name beginning with `i` after a string isn't a flag
+        let grammar = Grammar::from_lark("start: \"a\"item\nitem: \"b\"").unwrap();
+        assert!(grammar.verify_examples("start", &["ab"], &["aB"]).is_ok());
+    }
+
+    #[cfg(feature = "regex")]
+    #[test]
+    fn lark_regexes() {
+        let grammar = Grammar::from_lark("start: /[a-z]+\\/[0-9]/i \";\"").unwrap();
+        assert!(grammar
+            .verify_examples("start", &["Ab/1;", "a/2;"], &["a/;", "1/1;"])
+            .is_ok());
+    }
+
+    #[test]
+    fn lark_errors() {
+        let error = |input: &str| match Grammar::from_lark(input) {
+            Err(Error::ParseError(message)) => message,
+            result => panic!("should fail to parse: {:?}", result),
+        };
+        assert_eq!(error("a: \"x\"\n)"), "Line 2: unexpected ')'");
+        assert!(error("\"x\" a: b").starts_with("Line 1: expected a rule"));
+        assert!(error("a: ( \"x\"").starts_with("Line 1: missing closing ')'"));
+        assert!(error("a: [ \"x\"").starts_with("Line 1: missing closing ']'"));
+        assert!(error("a: \"x").starts_with("Line 1: missing closing '\"'"));
+        assert!(error("a: \"z\"..\"a\"").starts_with("Line 1: reversed range"));
+        assert!(error("a: \"ab\"..\"c\"").starts_with("Line 1: a range must be"));
+        assert!(error("a: \"x\" ~ 3..2").starts_with("Line 1: reversed repetition"));
+        assert!(error("a: \"x\" ~").starts_with("Line 1: expected a number"));
+        assert!(error("a: \"x\"\n%import python.NAME").starts_with("Line 2: can't import"));
+        assert!(error("a: \"x\"\n%import common.NUMBER").starts_with("Line 2: can't import"));
+        assert!(error("a: \"x\"\n%override a: \"y\"").starts_with("Line 2: the directive"));
+        assert!(error("a: sep{x, \",\"}").starts_with("Line 1: templates"));
+        assert!(error("a: \"x\" ;").starts_with("Line 1: unexpected ';'"));
+        #[cfg(not(feature = "regex"))]
+        assert!(error("a: /x/").starts_with("Line 1: regexes need"));
+    }
+}
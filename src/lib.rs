@@ -28,6 +28,18 @@
 //!     <opt-apt-num> ::= <apt-num> | ""
 //! ```
 //!
+//! A production continues across lines until the next `<lhs> ::=` starts a
+//! new production, regardless of indentation. A line may also be ended with
+//! a `\` to explicitly continue the production onto the next line.
+//!
+//! A bracketed character class matches any single character of the class, so
+//! `[0-9]` stands for `"0" | "1" | ... | "9"`. Classes list characters and
+//! inclusive ranges, like `[a-zA-Z_]`, with `]`, `\` and `-` escaped by a `\`.
+//!
+//! An alternative may be followed by `%weight n` to make `generate` choose it
+//! in proportion to `n`, alternatives without a weight weighing 1, as in
+//! `<bit> ::= "0" %weight 3 | "1"`.
+//!
 //! ## Output
 //! Take the following grammar for DNA sequences to be input to this library's
 //! `parse` function.
@@ -50,7 +62,8 @@
 //!                         Nonterminal(
 //!                             "base"
 //!                         )
-//!                     ]
+//!                     ],
+//!                     weight: None
 //!                 },
 //!                 Expression {
 //!                     terms: [
@@ -60,7 +73,8 @@
 //!                         Nonterminal(
 //!                             "dna"
 //!                         )
-//!                     ]
+//!                     ],
+//!                     weight: None
 //!                 }
 //!             ]
 //!         },
@@ -74,28 +88,32 @@
 //!                         Terminal(
 //!                             "A"
 //!                         )
-//!                     ]
+//!                     ],
+//!                     weight: None
 //!                 },
 //!                 Expression {
 //!                     terms: [
 //!                         Terminal(
 //!                             "C"
 //!                         )
-//!                     ]
+//!                     ],
+//!                     weight: None
 //!                 },
 //!                 Expression {
 //!                     terms: [
 //!                         Terminal(
 //!                             "G"
 //!                         )
-//!                     ]
+//!                     ],
+//!                     weight: None
 //!                 },
 //!                 Expression {
 //!                     terms: [
 //!                         Terminal(
 //!                             "T"
 //!                         )
-//!                     ]
+//!                     ],
+//!                     weight: None
 //!                 }
 //!             ]
 //!         }
@@ -165,16 +183,63 @@
 
 #[macro_use]
 extern crate nom;
+extern crate num_bigint;
 extern crate rand;
+#[cfg(feature = "rayon")]
+extern crate rayon;
+#[cfg(feature = "regex")]
+extern crate regex_automata;
 extern crate stacker;
+mod analysis;
+#[cfg(test)]
+mod arbitrary;
+mod builtins;
+mod cfg;
+pub mod combinators;
+mod counting;
+mod earley;
 mod error;
+mod explore;
 mod expression;
+mod generation;
 mod grammar;
+mod graph;
+mod html;
+mod lark;
+#[cfg(feature = "lark")]
+mod lark_import;
+mod markdown;
+mod mutate;
+mod nfa;
 mod parsers;
+mod pest;
 mod production;
+mod railroad;
 mod term;
-pub use error::Error;
+mod tokenize;
+mod transform;
+mod tree;
+mod verify;
+mod w3c;
+#[cfg(feature = "w3c")]
+mod w3c_import;
+pub use analysis::GrammarStats;
+pub use counting::DerivationCounter;
+pub use earley::{EarleyChart, EarleyItem};
+pub use error::{Error, IoError};
+pub use explore::{ExploreEvent, Explorer};
 pub use expression::Expression;
+pub use generation::{
+    Coverage, DistinctSentences, GenerateIter, GenerateOptions, GenerateTerms, GenerationTrace,
+    Limit, LimitError,
+};
 pub use grammar::Grammar;
+pub use graph::CycleError;
+pub use mutate::MutateOptions;
+pub use nfa::Nfa;
+pub use parsers::ParseOptions;
 pub use production::Production;
-pub use term::Term;
+pub use railroad::RailroadNode;
+pub use term::{CharClass, Nonterminal, StartSymbol, Term, Terminal};
+pub use tree::{ParseTree, ParseTreeNode};
+pub use verify::VerifyResult;
@@ -0,0 +1,201 @@
+use grammar::Grammar;
+use std::collections::HashSet;
+use std::mem;
+use term::Term;
+
+/// Escape characters which Markdown would otherwise interpret
+fn escape(text: &str) -> String {
+    let mut escaped = String::with_capacity(text.len());
+    for c in text.chars() {
+        if "\\`*_[]<>|#".contains(c) {
+            escaped.push('\\');
+        }
+        escaped.push(c);
+    }
+    escaped
+}
+
+/// Get the anchor a nonterminal's definition is linked to by
+pub(crate) fn anchor(name: &str) -> String {
+    name.chars()
+        .map(|c| {
+            if c.is_alphanumeric() {
+                c.to_lowercase().next().unwrap_or(c)
+            } else {
+                '-'
+            }
+        })
+        .collect()
+}
+
+/// Format text as a code span, fenced by more backticks than it contains in a row
+fn code_span(text: &str) -> String {
+    let mut longest = 0;
+    let mut run = 0;
+    for c in text.chars() {
+        run = if c == '`' { run + 1 } else { 0 };
+        longest = longest.max(run);
+    }
+    let fence = "`".repeat(longest + 1);
+    let padding = if text.starts_with('`') || text.ends_with('`') {
+        " "
+    } else {
+        ""
+    };
+    // pipes end table cells even inside code spans
+    let text = text.replace('|', "\\|");
+    format!("{}{}{}{}{}", fence, padding, text, padding, fence)
+}
+
+fn term_cell(term: &Term, defined: &HashSet<&Term>) -> String {
+    match *term {
+        Term::Terminal(ref t) | Term::CaselessTerminal(ref t) if t.is_empty() => String::from("ε"),
+        Term::Terminal(ref t) | Term::CaselessTerminal(ref t) => code_span(t),
+        Term::CharClass(_) | Term::TerminalBytes(_) | Term::Regex(_) | Term::Repeat { .. } => {
+            code_span(&term.to_string())
+        }
+        Term::Nonterminal(ref nt) if defined.contains(term) => {
+            format!("[{}](#{})", escape(nt), anchor(nt))
+        }
+        Term::Nonterminal(ref nt) => escape(nt),
+    }
+}
+
+impl Grammar {
+    /// Format the grammar as a Markdown table, with a row for every
+    /// alternative of each `Production`.
+    ///
+    /// Terminals are formatted as code spans, and nonterminals as links to
+    /// the row defining them. Columns are padded to align in plain text.
+    ///
+    /// # Example
+    ///
+    /// ```rust
+    /// extern crate bnf;
+    /// use bnf::Grammar;
+    ///
+    /// fn main() {
+    ///     let grammar = Grammar::from_str("<dna> ::= <base> | <base> <dna>
+    ///         <base> ::= \"A\" | \"C\" | \"G\" | \"T\"").unwrap();
+    ///     let table = grammar.to_markdown_table();
+    ///
+    ///     assert!(table.starts_with("| Nonterminal "));
+    ///     assert!(table.contains("| [base](#base) [dna](#dna) |"));
+    /// }
+    /// ```
+    pub fn to_markdown_table(&self) -> String {
+        let defined = self
+            .productions_iter()
+            .map(|prod| prod.lhs())
+            .collect::<HashSet<_>>();
+
+        let mut rows = vec![];
+        let mut anchored = HashSet::new();
+        for production in self.productions_iter() {
+            let mut lhs = match *production.lhs() {
+                Term::Nonterminal(ref nt) if anchored.insert(nt) => {
+                    format!("<a id=\"{}\"></a>{}", anchor(nt), escape(nt))
+                }
+                Term::Nonterminal(ref nt) => escape(nt),
+                ref lhs => escape(&lhs.to_string()),
+            };
+
+            if production.is_empty() {
+                rows.push((mem::take(&mut lhs), String::new()));
+            }
+            for expression in production.rhs_iter() {
+                let alternative = expression
+                    .terms_iter()
+                    .map(|term| term_cell(term, &defined))
+                    .collect::<Vec<_>>()
+                    .join(" ");
+                // only the first row of a production names its lhs
+                rows.push((mem::take(&mut lhs), alternative));
+            }
+        }
+
+        let header = (String::from("Nonterminal"), String::from("Alternative"));
+        let width = |cell: &String| cell.chars().count();
+        let lhs_width = rows
+            .iter()
+            .map(|row| width(&row.0))
+            .fold(width(&header.0), usize::max);
+        let rhs_width = rows
+            .iter()
+            .map(|row| width(&row.1))
+            .fold(width(&header.1), usize::max);
+
+        let mut table = String::new();
+        let mut push_row = |lhs: &str, rhs: &str| {
+            table.push_str(&format!(
+                "| {}{} | {}{} |\n",
+                lhs,
+                " ".repeat(lhs_width - lhs.chars().count()),
+                rhs,
+                " ".repeat(rhs_width - rhs.chars().count())
+            ));
+        };
+        push_row(&header.0, &header.1);
+        push_row(&"-".repeat(lhs_width), &"-".repeat(rhs_width));
+        for (lhs, rhs) in &rows {
+            push_row(lhs, rhs);
+        }
+        table
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn markdown_table() {
+        let grammar = Grammar::from_str(
+            "<dna> ::= <base> | <base> <dna>
+            <base> ::= \"A\" | [CG] | \"\" | <undefined>",
+        )
+        .unwrap();
+        assert_eq!(
+            grammar.to_markdown_table(),
+            "| Nonterminal           | Alternative               |
+| --------------------- | ------------------------- |
+| <a id=\"dna\"></a>dna   | [base](#base)             |
+|                       | [base](#base) [dna](#dna) |
+| <a id=\"base\"></a>base | `A`                       |
+|                       | `[CG]`                    |
+|                       | ε                         |
+|                       | undefined                 |
+"
+        );
+    }
+
+    #[test]
+    fn escapes_cells() {
+        let grammar = Grammar::from_str(
+            "<a|b> ::= \"x|y\" | \"`\" | <my rule>
+            <my rule> ::= <a|b>
+            <my rule> ::= \"z\"",
+        )
+        .unwrap();
+        let table = grammar.to_markdown_table();
+
+        assert!(table.contains("| <a id=\"a-b\"></a>a\\|b "));
+        assert!(table.contains("| `x\\|y` "));
+        assert!(table.contains("| `` ` `` "));
+        assert!(table.contains("| [my rule](#my-rule) "));
+        assert_eq!(table.matches("<a id=\"my-rule\">").count(), 1);
+        assert!(table.contains("| my rule "));
+        assert_eq!(
+            table.lines().map(|line| line.chars().count()).max(),
+            table.lines().map(|line| line.chars().count()).min()
+        );
+    }
+
+    #[test]
+    fn empty_table() {
+        assert_eq!(
+            Grammar::new().to_markdown_table(),
+            "| Nonterminal | Alternative |\n| ----------- | ----------- |\n"
+        );
+    }
+}
@@ -0,0 +1,424 @@
+use analysis::shortest_sentences;
+use earley::{Chart, ParseCounter};
+use error::Error;
+use generation::{generate_tree_from, start_symbol};
+use grammar::Grammar;
+use rand::Rng;
+use term::Term;
+use tree::{ParseTree, ParseTreeNode};
+
+/// How `Grammar::mutate_with_options` mutates a sentence, by default
+/// regenerating one subtree with a bias towards small subtrees
+#[derive(Clone, Debug, Eq, PartialEq)]
+pub struct MutateOptions {
+    mutations: usize,
+    size_bias: u32,
+}
+
+impl MutateOptions {
+    /// Construct the default `MutateOptions`
+    pub fn new() -> MutateOptions {
+        MutateOptions {
+            mutations: 1,
+            size_bias: 1,
+        }
+    }
+
+    /// Regenerate `mutations` subtrees one after the other, 1 by default
+    pub fn mutations(mut self, mutations: usize) -> MutateOptions {
+        self.mutations = mutations;
+        self
+    }
+
+    /// Choose each subtree to regenerate with probability inversely
+    /// proportional to the `size_bias` power of its size, 1 by default.
+    ///
+    /// A subtree's size is one more than the number of characters it derives.
+    /// A bias of 0 chooses among all subtrees uniformly, and greater biases
+    /// prefer smaller subtrees more strongly.
+    pub fn size_bias(mut self, size_bias: u32) -> MutateOptions {
+        self.size_bias = size_bias;
+        self
+    }
+}
+
+impl Default for MutateOptions {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+/// Collect the path of child indices to every subtree of `tree`, along with
+/// the size of the subtree
+fn subtrees(tree: &ParseTree, path: &mut Vec<usize>, found: &mut Vec<(Vec<usize>, usize)>) {
+    found.push((path.clone(), tree.flatten().chars().count() + 1));
+    for (index, child) in tree.children_iter().enumerate() {
+        path.push(index);
+        node_subtrees(child, path, found);
+        path.pop();
+    }
+}
+
+/// Collect the path to every subtree of `node` as `subtrees` does, the path
+/// into a repetition going on with the index among its children
+fn node_subtrees(
+    node: &ParseTreeNode,
+    path: &mut Vec<usize>,
+    found: &mut Vec<(Vec<usize>, usize)>,
+) {
+    match *node {
+        ParseTreeNode::Terminal(_) => {}
+        ParseTreeNode::Nonterminal(ref subtree) => subtrees(subtree, path, found),
+        ParseTreeNode::Repeat(ref children) => {
+            for (index, child) in children.iter().enumerate() {
+                path.push(index);
+                node_subtrees(child, path, found);
+                path.pop();
+            }
+        }
+    }
+}
+
+/// Get the subtree of `tree` at `path`
+fn subtree_mut<'a>(tree: &'a mut ParseTree, path: &[usize]) -> Option<&'a mut ParseTree> {
+    match path.split_first() {
+        None => Some(tree),
+        Some((&index, rest)) => node_mut(tree.children_iter_mut().nth(index)?, rest),
+    }
+}
+
+/// Get the subtree of `node` at `path`
+fn node_mut<'a>(node: &'a mut ParseTreeNode, path: &[usize]) -> Option<&'a mut ParseTree> {
+    match *node {
+        ParseTreeNode::Terminal(_) => None,
+        ParseTreeNode::Nonterminal(ref mut subtree) => subtree_mut(subtree, path),
+        ParseTreeNode::Repeat(ref mut children) => {
+            let (&index, rest) = path.split_first()?;
+            node_mut(children.get_mut(index)?, rest)
+        }
+    }
+}
+
+/// A subtree of a parse tree, with the byte offsets of the text it derives
+/// and the indices of the subtrees it's nested within
+struct Span<'a> {
+    lhs: &'a Term,
+    start: usize,
+    end: usize,
+    ancestors: Vec<usize>,
+}
+
+/// Collect the spans of every subtree of `tree` in preorder, getting the
+/// offset after `tree`
+fn spans<'a>(
+    tree: &'a ParseTree,
+    start: usize,
+    ancestors: &mut Vec<usize>,
+    found: &mut Vec<Span<'a>>,
+) -> usize {
+    let index = found.len();
+    found.push(Span {
+        lhs: &tree.lhs,
+        start,
+        end: start,
+        ancestors: ancestors.clone(),
+    });
+
+    ancestors.push(index);
+    let mut end = start;
+    for child in tree.children_iter() {
+        end = node_spans(child, end, ancestors, found);
+    }
+    ancestors.pop();
+
+    found[index].end = end;
+    end
+}
+
+/// Collect the spans of every subtree of `node` as `spans` does, getting the
+/// offset after `node`
+fn node_spans<'a>(
+    node: &'a ParseTreeNode,
+    start: usize,
+    ancestors: &mut Vec<usize>,
+    found: &mut Vec<Span<'a>>,
+) -> usize {
+    match *node {
+        ParseTreeNode::Terminal(ref t) => start + t.len(),
+        ParseTreeNode::Nonterminal(ref subtree) => spans(subtree, start, ancestors, found),
+        ParseTreeNode::Repeat(ref children) => children
+            .iter()
+            .fold(start, |end, child| node_spans(child, end, ancestors, found)),
+    }
+}
+
+/// Parse `sentence` from the start symbol into a tree
+fn parse(grammar: &Grammar, sentence: &str) -> Result<ParseTree, Error> {
+    let start = start_symbol(grammar)?;
+    let chart = Chart::parse(grammar, start, sentence);
+    let tree = match chart.start() {
+        Some(start) => ParseCounter::new(&chart).tree(start, 0, chart.len()),
+        None => None,
+    };
+    tree.ok_or_else(|| {
+        Error::ParseError(format!("'{}' is not a sentence of the grammar!", sentence))
+    })
+}
+
+impl Grammar {
+    /// Shrink `sentence` to a shorter sentence of the grammar for which
+    /// `still_interesting` stays true, e.g. to minimize an input which
+    /// crashes a program under test.
+    ///
+    /// The sentence is parsed from the start symbol, the lhs of the first
+    /// production. Each reduction replaces the text of a subtree either by a
+    /// shortest sentence of the subtree's nonterminal, or by the text of a
+    /// subtree nested within it, which deletes repeated and optional
+    /// elements. Only reductions to fewer characters which still parse are
+    /// tried, and one is kept only when `still_interesting` returns true for
+    /// it, until no reduction is kept. `sentence` itself is assumed to be
+    /// interesting.
+    ///
+    /// The result is always a sentence of the grammar no longer than
+    /// `sentence`. Returns `Error::ParseError` when `sentence` isn't a
+    /// sentence of the grammar.
+    ///
+    /// # Example
+    ///
+    /// ```rust
+    /// extern crate bnf;
+    /// use bnf::Grammar;
+    ///
+    /// fn main() {
+    ///     let grammar = Grammar::from_str("<list> ::= <item> | <item> \",\" <list>
+    ///         <item> ::= \"0\" | [1-9] <item>").unwrap();
+    ///     let shrunk = grammar.shrink("30,8110,70", |s| s.contains('8')).unwrap();
+    ///
+    ///     assert_eq!(shrunk, "80");
+    /// }
+    /// ```
+    pub fn shrink<F>(&self, sentence: &str, mut still_interesting: F) -> Result<String, Error>
+    where
+        F: FnMut(&str) -> bool,
+    {
+        let shortest = shortest_sentences(self);
+        let mut sentence = String::from(sentence);
+        let mut tree = parse(self, &sentence)?;
+
+        'reduce: loop {
+            let len = sentence.chars().count();
+            let mut found = vec![];
+            spans(&tree, 0, &mut vec![], &mut found);
+
+            for (index, span) in found.iter().enumerate() {
+                let nested = found
+                    .iter()
+                    .filter(|other| other.ancestors.contains(&index))
+                    .map(|other| &sentence[other.start..other.end]);
+                let minimal = match *span.lhs {
+                    Term::Nonterminal(ref nt) => shortest.get(nt.as_str()).map(String::as_str),
+                    _ => None,
+                };
+
+                for replacement in minimal.into_iter().chain(nested) {
+                    let candidate = format!(
+                        "{}{}{}",
+                        &sentence[..span.start],
+                        replacement,
+                        &sentence[span.end..]
+                    );
+                    if candidate.chars().count() >= len {
+                        continue;
+                    }
+                    // text nested within a different nonterminal may not fit
+                    if let Ok(reduced) = parse(self, &candidate) {
+                        if still_interesting(&candidate) {
+                            tree = reduced;
+                            sentence = candidate;
+                            continue 'reduce;
+                        }
+                    }
+                }
+            }
+            return Ok(sentence);
+        }
+    }
+
+    /// Mutate `sentence` into another sentence of the grammar, by parsing it
+    /// and regenerating one of its subtrees.
+    ///
+    /// See `Grammar::mutate_with_options` for the details.
+    ///
+    /// # Example
+    ///
+    /// ```rust
+    /// extern crate bnf;
+    /// extern crate rand;
+    /// use bnf::Grammar;
+    ///
+    /// fn main() {
+    ///     let grammar = Grammar::from_str("<dna> ::= <base> | <base> <dna>
+    ///         <base> ::= \"A\" | \"C\" | \"G\" | \"T\"").unwrap();
+    ///     let mutated = grammar.mutate("GATTACA", &mut rand::thread_rng()).unwrap();
+    ///
+    ///     assert!(grammar.count_parses("dna", &mutated) > 0);
+    ///     assert!(grammar.mutate("GATTACA!", &mut rand::thread_rng()).is_err());
+    /// }
+    /// ```
+    pub fn mutate<R: Rng>(&self, sentence: &str, rng: &mut R) -> Result<String, Error> {
+        self.mutate_with_options(sentence, &MutateOptions::new(), rng)
+    }
+
+    /// Mutate `sentence` into another sentence of the grammar, as `options`
+    /// specifies.
+    ///
+    /// The sentence is parsed from the start symbol, the lhs of the first
+    /// production, and each mutation replaces a randomly chosen subtree with
+    /// one generated from the same nonterminal. The result is always a
+    /// sentence of the grammar, though it may happen to equal `sentence`.
+    ///
+    /// Returns `Error::ParseError` when `sentence` isn't a sentence of the
+    /// grammar, and any error from generating a replacement subtree, e.g. on
+    /// the recursion limit.
+    pub fn mutate_with_options<R: Rng>(
+        &self,
+        sentence: &str,
+        options: &MutateOptions,
+        rng: &mut R,
+    ) -> Result<String, Error> {
+        let mut tree = parse(self, sentence)?;
+
+        for _ in 0..options.mutations {
+            let mut found = vec![];
+            subtrees(&tree, &mut vec![], &mut found);
+            let weights = found
+                .iter()
+                .map(|&(_, size)| 1.0 / (size as f64).powi(options.size_bias as i32))
+                .collect::<Vec<f64>>();
+            let mut choice = rng.gen::<f64>() * weights.iter().sum::<f64>();
+            let mut chosen = found.len() - 1;
+            for (index, &weight) in weights.iter().enumerate() {
+                if choice < weight {
+                    chosen = index;
+                    break;
+                }
+                choice -= weight;
+            }
+
+            if let Some(subtree) = subtree_mut(&mut tree, &found[chosen].0) {
+                let regenerated = match subtree.lhs {
+                    Term::Nonterminal(ref nt) => generate_tree_from(self, nt, rng)?,
+                    _ => continue,
+                };
+                *subtree = regenerated;
+            }
+        }
+        Ok(tree.flatten())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use rand::{thread_rng, SeedableRng, StdRng};
+    use term::Nonterminal;
+
+    #[test]
+    fn mutates_subtrees() {
+        let grammar = Grammar::from_str(
+            "<pair> ::= <digit> \"+\" <digit>
+            <digit> ::= [0-9]",
+        )
+        .unwrap();
+        let seed: &[_] = &[1, 2, 3, 4];
+        let mut rng: StdRng = SeedableRng::from_seed(seed);
+        let mut changed = false;
+        for _ in 0..100 {
+            let mutated = grammar.mutate("1+2", &mut rng).unwrap();
+            let chars = mutated.chars().collect::<Vec<_>>();
+            assert_eq!(chars.len(), 3, "{}", mutated);
+            assert_eq!(chars[1], '+');
+            changed |= mutated != "1+2";
+        }
+        assert!(changed);
+    }
+
+    #[test]
+    fn mutation_options() {
+        let grammar = Grammar::from_str(
+            "<list> ::= <item> | <item> \",\" <list>
+            <item> ::= \"x\" | \"y\"",
+        )
+        .unwrap();
+        let mut rng = thread_rng();
+        let unchanged = MutateOptions::new().mutations(0);
+        assert_eq!(
+            grammar.mutate_with_options("x,y,x", &unchanged, &mut rng),
+            Ok(String::from("x,y,x"))
+        );
+
+        // a strong bias only regenerates the smallest subtrees, the items
+        // and the last list
+        let small = MutateOptions::new().size_bias(20).mutations(3);
+        for _ in 0..20 {
+            let mutated = grammar
+                .mutate_with_options("x,y,x", &small, &mut rng)
+                .unwrap();
+            let chars = mutated.chars().collect::<Vec<_>>();
+            assert!(chars.len() >= 5, "{}", mutated);
+            assert_eq!((chars[1], chars[3]), (',', ','), "{}", mutated);
+        }
+    }
+
+    #[test]
+    fn shrinks_sentences() {
+        let grammar = Grammar::from_str(
+            "<expr> ::= <term> | <term> \"+\" <expr>
+            <term> ::= <factor> | <factor> \"*\" <term>
+            <factor> ::= [0-9] | \"(\" <expr> \")\"",
+        )
+        .unwrap();
+        let expr = Nonterminal::from("expr");
+        let cases = [
+            ("1+2*(3+4)*5", "*", "0*5"),
+            ("1+2*(3+4)*5", "+", "3+4"),
+            ("1+2*(3+4)*5", "2", "2"),
+            ("1+2*(3+4)*5", "(", "(0)"),
+            ("1+2*(3+4)*5", "4", "4"),
+            ("(((7)))+1", "7", "7"),
+            ("1+2", "X", "1+2"),
+        ];
+        for &(sentence, needle, expected) in cases.iter() {
+            let mut calls = 0;
+            let shrunk = grammar
+                .shrink(sentence, |s| {
+                    calls += 1;
+                    assert!(grammar.count_parses(&expr, s) > 0, "{}", s);
+                    s.contains(needle)
+                })
+                .unwrap();
+            assert_eq!(shrunk, expected, "shrinking {}", sentence);
+            assert!(calls > 0);
+        }
+    }
+
+    #[test]
+    fn shrink_non_sentence() {
+        let grammar = Grammar::from_str("<ab> ::= \"a\" | \"b\" <ab>").unwrap();
+        match grammar.shrink("bc", |_| true) {
+            Err(Error::ParseError(_)) => (),
+            result => panic!("should not shrink a non-sentence: {:?}", result),
+        }
+        assert_eq!(grammar.shrink("bbba", |_| true), Ok(String::from("a")));
+    }
+
+    #[test]
+    fn mutate_non_sentence() {
+        let grammar = Grammar::from_str("<ab> ::= \"a\" | \"b\"").unwrap();
+        match grammar.mutate("c", &mut thread_rng()) {
+            Err(Error::ParseError(_)) => (),
+            result => panic!("should not mutate a non-sentence: {:?}", result),
+        }
+        assert!(Grammar::new().mutate("", &mut thread_rng()).is_err());
+    }
+}
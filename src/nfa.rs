@@ -0,0 +1,342 @@
+use error::Error;
+use grammar::Grammar;
+use std::collections::HashMap;
+use term::{CharClass, StartSymbol, Term};
+
+/// What a transition of an `Nfa` consumes
+#[derive(Clone, Debug, Eq, PartialEq)]
+enum Transition {
+    Epsilon,
+    Char(char),
+    Class(CharClass),
+}
+
+impl Transition {
+    fn matches(&self, c: char) -> bool {
+        match *self {
+            Transition::Epsilon => false,
+            Transition::Char(ch) => ch == c,
+            Transition::Class(ref class) => class.contains(c),
+        }
+    }
+}
+
+/// A nondeterministic finite automaton matching the language of a
+/// right-linear grammar, see `Grammar::to_nfa`
+#[derive(Clone, Debug, Eq, PartialEq)]
+pub struct Nfa {
+    start: usize,
+    accept: usize,
+    transitions: Vec<Vec<(Transition, usize)>>,
+}
+
+impl Nfa {
+    fn state(&mut self) -> usize {
+        self.transitions.push(vec![]);
+        self.transitions.len() - 1
+    }
+
+    fn transition(&mut self, from: usize, on: Transition, to: usize) {
+        self.transitions[from].push((on, to));
+    }
+
+    /// Add states matching `text` from `from`, returning the state reached
+    fn text(&mut self, from: usize, text: &str) -> usize {
+        text.chars().fold(from, |from, c| {
+            let to = self.state();
+            self.transition(from, Transition::Char(c), to);
+            to
+        })
+    }
+
+    /// Add states matching `text` in any case from `from`, returning the
+    /// state reached
+    fn caseless_text(&mut self, from: usize, text: &str) -> usize {
+        text.chars().fold(from, |from, c| {
+            let mut cases = vec![(c, c)];
+            let lower = c.to_lowercase().collect::<Vec<_>>();
+            let upper = c.to_uppercase().collect::<Vec<_>>();
+            // a character whose case is several characters matches only itself
+            for case in &[lower, upper] {
+                if case.len() == 1 {
+                    cases.push((case[0], case[0]));
+                }
+            }
+            let to = self.state();
+            let class = CharClass::from_ranges(cases);
+            self.transition(from, Transition::Class(class), to);
+            to
+        })
+    }
+
+    /// Add states matching `term` from `from`, returning the state reached,
+    /// with any nonterminal matching its own `<name>` text
+    fn term(&mut self, from: usize, term: &Term) -> Result<usize, Error> {
+        let to = match *term {
+            Term::Nonterminal(_) => self.text(from, &term.to_string()),
+            Term::Terminal(ref t) => self.text(from, t),
+            Term::CaselessTerminal(ref t) => self.caseless_text(from, t),
+            Term::TerminalBytes(_) => match term.terminal_text() {
+                Some(t) => self.text(from, t),
+                // bytes which aren't UTF-8 match no input
+                None => self.state(),
+            },
+            Term::CharClass(ref class) => {
+                let to = self.state();
+                self.transition(from, Transition::Class(class.clone()), to);
+                to
+            }
+            Term::Regex(_) => {
+                return Err(Error::NotRightLinear(format!(
+                    "Regex {} can't be part of a finite automaton!",
+                    term
+                )))
+            }
+            Term::Repeat {
+                ref inner,
+                min,
+                max,
+            } => {
+                let copy = |nfa: &mut Nfa, from| {
+                    inner
+                        .terms_iter()
+                        .try_fold(from, |from, term| nfa.term(from, term))
+                };
+                let mut at = from;
+                for _ in 0..min {
+                    at = copy(self, at)?;
+                }
+                let to = self.state();
+                match max {
+                    Some(max) => {
+                        for _ in min..max {
+                            self.transition(at, Transition::Epsilon, to);
+                            at = copy(self, at)?;
+                        }
+                        self.transition(at, Transition::Epsilon, to);
+                    }
+                    None => {
+                        self.transition(at, Transition::Epsilon, to);
+                        let back = copy(self, to)?;
+                        self.transition(back, Transition::Epsilon, to);
+                    }
+                }
+                to
+            }
+        };
+        Ok(to)
+    }
+
+    /// Get the number of states, including the start and accept states
+    pub fn len(&self) -> usize {
+        self.transitions.len()
+    }
+
+    /// Whether the automaton has no states, which is never the case
+    pub fn is_empty(&self) -> bool {
+        self.transitions.is_empty()
+    }
+
+    /// Add the states reachable from `states` by epsilon transitions
+    fn closure(&self, states: &mut [bool]) {
+        let mut stack = (0..states.len()).filter(|&s| states[s]).collect::<Vec<_>>();
+        while let Some(state) = stack.pop() {
+            for &(ref on, to) in &self.transitions[state] {
+                if *on == Transition::Epsilon && !states[to] {
+                    states[to] = true;
+                    stack.push(to);
+                }
+            }
+        }
+    }
+
+    /// Whether `input` is in the language of the automaton
+    pub fn accepts(&self, input: &str) -> bool {
+        let mut states = vec![false; self.len()];
+        states[self.start] = true;
+        self.closure(&mut states);
+        for c in input.chars() {
+            let mut next = vec![false; self.len()];
+            for state in (0..states.len()).filter(|&s| states[s]) {
+                for &(ref on, to) in &self.transitions[state] {
+                    if on.matches(c) {
+                        next[to] = true;
+                    }
+                }
+            }
+            self.closure(&mut next);
+            if !next.contains(&true) {
+                return false;
+            }
+            states = next;
+        }
+        states[self.accept]
+    }
+}
+
+impl Grammar {
+    /// Construct a nondeterministic finite automaton accepting the language
+    /// of `start`, failing with `Error::NotRightLinear` unless the grammar is
+    /// right-linear, see `Grammar::is_right_linear`, and has no regexes.
+    ///
+    /// Each nonterminal with a production becomes a state, along with a
+    /// state accepting once input ends. The alternatives of every production
+    /// sharing a lhs are included, and a nonterminal without a production
+    /// matches its own `<name>` text.
+    ///
+    /// # Example
+    ///
+    /// ```rust
+    /// extern crate bnf;
+    /// use bnf::Grammar;
+    ///
+    /// fn main() {
+    ///     let grammar = Grammar::from_str("<bits> ::= \"0\" <bits> | \"1\" <bits> | \"\"").unwrap();
+    ///     let nfa = grammar.to_nfa("bits").unwrap();
+    ///
+    ///     assert!(nfa.accepts("0110"));
+    ///     assert!(nfa.accepts(""));
+    ///     assert!(!nfa.accepts("012"));
+    ///
+    ///     let grammar = Grammar::from_str("<parens> ::= \"(\" <parens> \")\" | \"\"").unwrap();
+    ///     assert!(grammar.to_nfa("parens").is_err());
+    /// }
+    /// ```
+    pub fn to_nfa(&self, start: impl StartSymbol) -> Result<Nfa, Error> {
+        let start = start.into_term();
+        if !self.is_right_linear() {
+            return Err(Error::NotRightLinear(String::from(
+                "Grammar must be right-linear to construct a finite automaton!",
+            )));
+        }
+
+        let mut nfa = Nfa {
+            start: 0,
+            accept: 1,
+            transitions: vec![vec![], vec![]],
+        };
+        let mut states: HashMap<&str, usize> = HashMap::new();
+        for production in self.productions_iter() {
+            if let Term::Nonterminal(ref nt) = *production.lhs() {
+                if !states.contains_key(nt.as_str()) {
+                    let state = nfa.state();
+                    states.insert(nt, state);
+                }
+            }
+        }
+
+        let mut alternatives = vec![(nfa.start, vec![&start])];
+        for production in self.productions_iter() {
+            if let Term::Nonterminal(ref nt) = *production.lhs() {
+                let from = states[nt.as_str()];
+                alternatives.extend(
+                    production
+                        .rhs_iter()
+                        .map(|expr| (from, expr.terms_iter().collect())),
+                );
+            }
+        }
+
+        for (from, terms) in alternatives {
+            let mut at = from;
+            let mut to = nfa.accept;
+            for term in terms {
+                match *term {
+                    // right-linear, so this is the last term
+                    Term::Nonterminal(ref nt) if states.contains_key(nt.as_str()) => {
+                        to = states[nt.as_str()]
+                    }
+                    _ => at = nfa.term(at, term)?,
+                }
+            }
+            nfa.transition(at, Transition::Epsilon, to);
+        }
+        Ok(nfa)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn matches_right_linear_languages() {
+        let grammar = Grammar::from_str(
+            "<number> ::= <sign> | <digits>
+            <sign> ::= \"-\" <digits> | \"+\" <digits>
+            <digits> ::= [0-9] <digits> | [0-9] | [0-9] \".\" <fraction>
+            <fraction> ::= [0-9] | [0-9] <fraction> | \"e\" <undefined>
+            <digits> ::= \"0x\" <hex>
+            <hex> ::= [0-9a-f] <hex> | [0-9a-f]",
+        )
+        .unwrap();
+        let number = Term::from_str("<number>").unwrap();
+        let nfa = grammar.to_nfa(&number).unwrap();
+        for input in &["0", "-12", "+3.25", "1.e<undefined>", "0xff1", "-0x0"] {
+            assert!(nfa.accepts(input), "{}", input);
+            assert!(grammar.count_parses(&number, input) > 0, "{}", input);
+        }
+        for input in &["", "-", "1.", "+-1", "0xg", "1.e", "12 "] {
+            assert!(!nfa.accepts(input), "{}", input);
+            assert_eq!(grammar.count_parses(&number, input), 0, "{}", input);
+        }
+
+        let fraction = grammar.to_nfa("fraction").unwrap();
+        assert!(fraction.accepts("25"));
+        assert!(!fraction.accepts("1.25"));
+        assert_eq!(fraction.len(), nfa.len());
+    }
+
+    #[test]
+    fn matches_repetitions() {
+        let grammar = Grammar::from_str(
+            "<s> ::= 2*3\"ab\" 0*[0-9] <t>
+            <t> ::= 1*2[!?]",
+        )
+        .unwrap();
+        let nfa = grammar.to_nfa("s").unwrap();
+        for input in &["abab!", "ababab12?!", "abab0?", "abab9!?"] {
+            assert!(nfa.accepts(input), "{}", input);
+            assert!(grammar.count_parses("s", input) > 0, "{}", input);
+        }
+        for input in &["ab!", "abababab!", "abab", "abab!!!", "ab1ab!"] {
+            assert!(!nfa.accepts(input), "{}", input);
+            assert_eq!(grammar.count_parses("s", input), 0, "{}", input);
+        }
+    }
+
+    #[test]
+    fn matches_terminal_and_undefined_starts() {
+        let grammar = Grammar::from_str("<as> ::= \"a\" <as> | \"\"").unwrap();
+        let nfa = grammar.to_nfa(&Term::from_str("\"ab\"").unwrap()).unwrap();
+        assert!(nfa.accepts("ab"));
+        assert!(!nfa.accepts("a"));
+
+        let nfa = grammar.to_nfa("bs").unwrap();
+        assert!(nfa.accepts("<bs>"));
+        assert!(!nfa.accepts(""));
+    }
+
+    #[test]
+    fn matches_caseless_terminals() {
+        let grammar = Grammar::from_str("<kw> ::= %i\"if\" <kw> | %i\"ß\" | \"X\"").unwrap();
+        let nfa = grammar.to_nfa("kw").unwrap();
+        for input in &["ifX", "IFiFß", "ß"] {
+            assert!(nfa.accepts(input), "{}", input);
+        }
+        for input in &["ifx", "iff", "SS"] {
+            assert!(!nfa.accepts(input), "{}", input);
+        }
+    }
+
+    #[test]
+    fn rejects_grammars_which_are_not_right_linear() {
+        let grammar = Grammar::from_str(
+            "<as> ::= \"a\" <as> | \"\"
+            <bs> ::= <bs> \"b\" | \"\"",
+        )
+        .unwrap();
+        let result = grammar.to_nfa("as");
+        assert!(matches!(result, Err(Error::NotRightLinear(_))));
+    }
+}
@@ -1,7 +1,42 @@
+use error::Error;
 use expression::Expression;
 use grammar::Grammar;
+use nom::character::complete::{digit1, multispace1};
+use nom::error::ErrorKind;
+use nom::{Err, IResult};
 use production::Production;
-use term::Term;
+use std::collections::{HashMap, HashSet};
+use term::{CharClass, Term};
+
+// Stands in for nom's deprecated `ws!`, separating with `whitespace` instead
+macro_rules! ws (
+    ($i:expr, $($args:tt)*) => (
+        {
+            use nom::Err;
+
+            match sep!($i, whitespace, $($args)*) {
+                Err(e) => Err(e),
+                Ok((i1, o)) => match whitespace(i1) {
+                    Err(e) => Err(Err::convert(e)),
+                    Ok((i2, _)) => Ok((i2, o)),
+                },
+            }
+        }
+    )
+);
+
+// Whitespace, where a backslash ending a line continues onto the next
+named!(pub whitespace,
+    recognize!(
+        many0!(
+            alt!(
+                multispace1 |
+                complete!(tag!("\\\n")) |
+                complete!(tag!("\\\r\n"))
+            )
+        )
+    )
+);
 
 named!(pub prod_lhs< &[u8], Term >,
     do_parse!(
@@ -11,13 +46,138 @@ named!(pub prod_lhs< &[u8], Term >,
     )
 );
 
+// The text between `quote`s, decoding the escapes `\n`, `\t`, `\r`, `\\`,
+// `\"`, `\'` and `\u{1F600}`, and failing with `ErrorKind::Escaped` at an
+// invalid escape, or at an escaped `quote` when the text is never closed
+fn quoted(input: &[u8], quote: u8) -> IResult<&[u8], String> {
+    match input.first() {
+        Some(&c) if c == quote => {}
+        Some(_) => return Err(Err::Error((input, ErrorKind::Char))),
+        None => return Err(Err::Incomplete(::nom::Needed::Size(1))),
+    }
+    let mut text = vec![];
+    let mut escaped_quote = None;
+    let mut index = 1;
+    loop {
+        let c = match input.get(index) {
+            Some(&c) => c,
+            None => match escaped_quote {
+                Some(at) => return Err(Err::Failure((&input[at..], ErrorKind::Escaped))),
+                None => return Err(Err::Incomplete(::nom::Needed::Size(1))),
+            },
+        };
+        if c == quote {
+            break;
+        }
+        if c != b'\\' {
+            text.push(c);
+            index += 1;
+            continue;
+        }
+        let (decoded, len) = match input.get(index + 1) {
+            Some(b'n') => (Some('\n'), 2),
+            Some(b't') => (Some('\t'), 2),
+            Some(b'r') => (Some('\r'), 2),
+            Some(&c) if c == b'\\' || c == b'"' || c == b'\'' => {
+                if c == quote {
+                    escaped_quote = Some(index);
+                }
+                (Some(c as char), 2)
+            }
+            Some(b'u') => {
+                let digits = input[index + 2..]
+                    .strip_prefix(b"{")
+                    .and_then(|rest| rest.iter().position(|&c| c == b'}').map(|end| &rest[..end]))
+                    .filter(|digits| (1..=6).contains(&digits.len()));
+                let decoded = digits
+                    .and_then(|digits| ::std::str::from_utf8(digits).ok())
+                    .filter(|digits| digits.bytes().all(|c| c.is_ascii_hexdigit()))
+                    .and_then(|digits| u32::from_str_radix(digits, 16).ok())
+                    .and_then(::std::char::from_u32);
+                (decoded, 4 + digits.map_or(0, <[u8]>::len))
+            }
+            _ => (None, 0),
+        };
+        match decoded {
+            Some(decoded) => {
+                let mut buffer = [0; 4];
+                text.extend_from_slice(decoded.encode_utf8(&mut buffer).as_bytes());
+                index += len;
+            }
+            None => return Err(Err::Failure((&input[index..], ErrorKind::Escaped))),
+        }
+    }
+    let (rest, _) = whitespace(&input[index + 1..])?;
+    Ok((rest, String::from_utf8_lossy(&text).into_owned()))
+}
+
+// Convert `err`, from parsing `input`, to an `Error`, naming the line and
+// column of an invalid escape in a terminal or of a repetition whose
+// maximum is below its minimum
+pub fn parse_error(input: &[u8], err: Err<(&[u8], ErrorKind)>) -> Error {
+    let (at, kind) = match err {
+        Err::Failure((at, kind @ ErrorKind::Escaped))
+        | Err::Failure((at, kind @ ErrorKind::ManyMN)) => (at, kind),
+        err => return Error::from(err),
+    };
+    let offset = (at.as_ptr() as usize).wrapping_sub(input.as_ptr() as usize);
+    if offset > input.len() {
+        return Error::from((at, kind));
+    }
+    let before = String::from_utf8_lossy(&input[..offset]);
+    let line = before.matches('\n').count() + 1;
+    let column = before
+        .rsplit('\n')
+        .next()
+        .map_or(0, |line| line.chars().count())
+        + 1;
+
+    let text = String::from_utf8_lossy(at);
+    if kind == ErrorKind::ManyMN {
+        let bounds: String = text
+            .chars()
+            .take_while(|&c| c.is_ascii_digit() || c == '*')
+            .collect();
+        return Error::ParseError(format!(
+            "the repetition `{}` has a maximum below its minimum at line {}, column {}",
+            bounds, line, column
+        ));
+    }
+    let mut chars = text.chars().skip(1);
+    let message = match chars.next() {
+        Some(quote @ '"') | Some(quote @ '\'') => {
+            format!("the escape `\\{}` leaves its terminal unclosed", quote)
+        }
+        Some('u') => {
+            let digits: String = chars
+                .take_while(|&c| c != '}' && c != '"' && c != '\'' && !c.is_whitespace())
+                .collect();
+            let close = if text[2 + digits.len()..].starts_with('}') {
+                "}"
+            } else {
+                ""
+            };
+            format!("invalid escape `\\u{}{}`", digits, close)
+        }
+        Some(c) => format!("invalid escape `\\{}`", c),
+        None => String::from("a backslash ends the input"),
+    };
+    Error::ParseError(format!("{} at line {}, column {}", message, line, column))
+}
+
+// A quoted terminal, optionally prefixed by `%i` to ignore case or `%s` not to
 named!(pub terminal< &[u8], Term >,
     do_parse!(
-        t: alt!(
-            delimited!(char!('"'), take_until!("\""), ws!(char!('"'))) |
-            delimited!(char!('\''), take_until!("'"), ws!(char!('\'')))
-            ) >>
-        (Term::Terminal(String::from_utf8_lossy(t).into_owned()))
+        caseless: opt!(complete!(alt!(
+            value!(true, tag!("%i")) |
+            value!(false, tag!("%s"))
+            ))) >>
+        t: alt!(call!(quoted, b'"') | call!(quoted, b'\'')) >>
+        (if caseless == Some(true) {
+            Term::CaselessTerminal(t)
+        } else {
+            Term::Terminal(t)
+        })
     )
 );
 
@@ -29,7 +189,246 @@ named!(pub nonterminal< &[u8], Term >,
     )
 );
 
-named!(pub term< &[u8], Term >, alt!(terminal | nonterminal));
+// A bracketed class of characters, like `[a-zA-Z_]`
+pub fn char_class(input: &[u8]) -> IResult<&[u8], Term> {
+    let error = || Err(Err::Error((input, ErrorKind::Char)));
+    if input.first() != Some(&b'[') {
+        return error();
+    }
+
+    let mut end = None;
+    let mut index = 1;
+    while index < input.len() {
+        match input[index] {
+            b'\\' => index += 2,
+            b']' => {
+                end = Some(index);
+                break;
+            }
+            _ => index += 1,
+        }
+    }
+    let end = match end {
+        Some(end) => end,
+        None => return Err(Err::Incomplete(::nom::Needed::Size(1))),
+    };
+
+    let mut chars = vec![];
+    let body = String::from_utf8_lossy(&input[1..end]);
+    let mut body_chars = body.chars();
+    while let Some(c) = body_chars.next() {
+        match c {
+            '\\' => match body_chars.next() {
+                Some(escaped) => chars.push((escaped, true)),
+                None => return error(),
+            },
+            c => chars.push((c, false)),
+        }
+    }
+
+    // a `^` beginning a class negates it, unless it's all there is
+    let negated = chars.len() > 1 && chars[0] == ('^', false);
+    let mut ranges = vec![];
+    let mut index = if negated { 1 } else { 0 };
+    while index < chars.len() {
+        let lo = chars[index].0;
+        match (chars.get(index + 1), chars.get(index + 2)) {
+            (Some(&('-', false)), Some(&(hi, _))) => {
+                if hi < lo {
+                    return error();
+                }
+                ranges.push((lo, hi));
+                index += 3;
+            }
+            _ => {
+                ranges.push((lo, lo));
+                index += 1;
+            }
+        }
+    }
+    if ranges.is_empty() {
+        return error();
+    }
+
+    let class = if negated {
+        CharClass::negated(ranges)
+    } else {
+        CharClass::from_ranges(ranges)
+    };
+    let (rest, _) = whitespace(&input[end + 1..])?;
+    Ok((rest, Term::CharClass(class)))
+}
+
+// A range of characters between terminals of one character, like `"a".."z"`
+// or `"a"…"z"`, parsed as a class
+pub fn char_range(input: &[u8]) -> IResult<&[u8], Term> {
+    let error = || Err(Err::Error((input, ErrorKind::Char)));
+    let single = |term: &Term| match *term {
+        Term::Terminal(ref text) if text.chars().count() == 1 => text.chars().next(),
+        _ => None,
+    };
+    let (rest, lo) = match complete!(input, terminal) {
+        Ok((rest, ref lo)) => match single(lo) {
+            Some(lo) => (rest, lo),
+            None => return error(),
+        },
+        Err(_) => return error(),
+    };
+    let rest = match complete!(rest, ws!(alt!(tag!("..") | tag!("…")))) {
+        Ok((rest, _)) => rest,
+        Err(_) => return error(),
+    };
+    match complete!(rest, terminal) {
+        Ok((rest, ref hi)) => match single(hi) {
+            Some(hi) if lo <= hi => Ok((
+                rest,
+                Term::CharClass(CharClass::from_ranges(vec![(lo, hi)])),
+            )),
+            _ => error(),
+        },
+        Err(_) => error(),
+    }
+}
+
+// A terminal of bytes in hexadecimal separated by `.`, like `%xDE.AD`
+pub fn terminal_bytes(input: &[u8]) -> IResult<&[u8], Term> {
+    let error = || Err(Err::Error((input, ErrorKind::HexDigit)));
+    if !input.starts_with(b"%x") {
+        return error();
+    }
+
+    let mut bytes = vec![];
+    let mut index = 2;
+    loop {
+        let digits = input[index..]
+            .iter()
+            .take_while(|byte| byte.is_ascii_hexdigit())
+            .count();
+        if digits == 0 || digits > 2 {
+            return error();
+        }
+        let hex = String::from_utf8_lossy(&input[index..index + digits]);
+        match u8::from_str_radix(&hex, 16) {
+            Ok(byte) => bytes.push(byte),
+            Err(_) => return error(),
+        }
+        index += digits;
+        if input.get(index) == Some(&b'.') {
+            index += 1;
+        } else {
+            break;
+        }
+    }
+
+    let (rest, _) = whitespace(&input[index..])?;
+    Ok((rest, Term::TerminalBytes(bytes)))
+}
+
+// A value of the digits of `radix` beginning `input`, and how many there are
+fn numeric_digits(input: &[u8], radix: u32) -> Option<(u32, usize)> {
+    let digits = input
+        .iter()
+        .take_while(|&&byte| (byte as char).is_digit(radix))
+        .count();
+    let text = String::from_utf8_lossy(&input[..digits]);
+    u32::from_str_radix(&text, radix)
+        .ok()
+        .map(|value| (value, digits))
+}
+
+// An ABNF numeric value of characters by their code points, in hexadecimal,
+// decimal or binary, like `%x0D.0A` or `%d13.10` for a terminal of them, or
+// like `%x30-39` for the class of a range of them
+pub fn numeric_value(input: &[u8]) -> IResult<&[u8], Term> {
+    let radix = match (input.first(), input.get(1)) {
+        (Some(&b'%'), Some(&b'x')) | (Some(&b'%'), Some(&b'X')) => 16,
+        (Some(&b'%'), Some(&b'd')) | (Some(&b'%'), Some(&b'D')) => 10,
+        (Some(&b'%'), Some(&b'b')) | (Some(&b'%'), Some(&b'B')) => 2,
+        _ => return Err(Err::Error((input, ErrorKind::Tag))),
+    };
+    let invalid = || Err(Err::Failure((input, ErrorKind::Verify)));
+    let value = |index: usize| -> Option<(char, usize)> {
+        let (value, digits) = numeric_digits(&input[index..], radix)?;
+        ::std::char::from_u32(value).map(|c| (c, digits))
+    };
+
+    let (first, digits) = match value(2) {
+        Some(first) => first,
+        None if numeric_digits(&input[2..], radix).is_some() => return invalid(),
+        None => return Err(Err::Error((input, ErrorKind::Digit))),
+    };
+    let mut index = 2 + digits;
+    let term = if input.get(index) == Some(&b'-') {
+        let (last, digits) = match value(index + 1) {
+            Some((last, digits)) if last >= first => (last, digits),
+            _ => return invalid(),
+        };
+        index += 1 + digits;
+        Term::CharClass(CharClass::from_ranges(vec![(first, last)]))
+    } else {
+        let mut text = first.to_string();
+        while input.get(index) == Some(&b'.') {
+            let (c, digits) = match value(index + 1) {
+                Some(c) => c,
+                None => return invalid(),
+            };
+            text.push(c);
+            index += 1 + digits;
+        }
+        Term::Terminal(text)
+    };
+    let (rest, _) = whitespace(&input[index..])?;
+    Ok((rest, term))
+}
+
+// The wildcard `%any`, matching any one character, parsed as the class of
+// every character
+pub fn any_char(input: &[u8]) -> IResult<&[u8], Term> {
+    let error = || Err(Err::Error((input, ErrorKind::Tag)));
+    if !input.starts_with(b"%any") {
+        return error();
+    }
+    match input.get(4) {
+        Some(&byte) if byte.is_ascii_alphanumeric() || byte == b'_' => error(),
+        _ => {
+            let (rest, _) = whitespace(&input[4..])?;
+            Ok((rest, Term::CharClass(CharClass::any())))
+        }
+    }
+}
+
+// Whether `pattern` compiles to a regex, never without the feature `regex`
+#[cfg(feature = "regex")]
+fn is_regex(pattern: &str) -> bool {
+    ::term::compile_regex(pattern).is_some()
+}
+
+#[cfg(not(feature = "regex"))]
+fn is_regex(_pattern: &str) -> bool {
+    false
+}
+
+// A terminal of the text a regex matches, like `#"[a-z]+"#`
+pub fn regex_terminal(input: &[u8]) -> IResult<&[u8], Term> {
+    if !input.starts_with(b"#\"") {
+        return Err(Err::Error((input, ErrorKind::Tag)));
+    }
+    let body = &input[2..];
+    let end = match body.windows(2).position(|window| window == b"\"#") {
+        Some(end) => end,
+        None => return Err(Err::Incomplete(::nom::Needed::Size(2))),
+    };
+    let pattern = String::from_utf8_lossy(&body[..end]).into_owned();
+    if !is_regex(&pattern) {
+        return Err(Err::Failure((input, ErrorKind::Verify)));
+    }
+    let (rest, _) = whitespace(&body[end + 2..])?;
+    Ok((rest, Term::Regex(pattern)))
+}
+
+named!(pub term< &[u8], Term >,
+    alt!(regex_terminal | char_range | terminal | terminal_bytes | any_char | nonterminal | char_class)
+);
 
 named!(pub term_complete< &[u8], Term >,
     do_parse!(
@@ -39,6 +438,14 @@ named!(pub term_complete< &[u8], Term >,
     )
 );
 
+named_args!(pub expression_next_with<'a>(options: &'a ParseOptions)<()>,
+    do_parse!(
+        ws!(char!('|')) >>
+        peek!(complete!(call!(expression_with, options))) >>
+        ()
+    )
+);
+
 named!(pub expression_next,
     do_parse!(
         ws!(char!('|')) >>
@@ -47,22 +454,474 @@ named!(pub expression_next,
     )
 );
 
-named!(pub expression< &[u8], Expression >,
+fn parse_weight(digits: &[u8]) -> Result<u32, ::std::num::ParseIntError> {
+    String::from_utf8_lossy(digits).parse()
+}
+
+// A `%weight n` annotation following the terms of an expression
+named!(pub weight< &[u8], u32 >,
+    do_parse!(
+        ws!(tag!("%weight")) >>
+        w: map_res!(ws!(digit1), parse_weight) >>
+        (w)
+    )
+);
+
+// Options for parsing grammars, see `Grammar::from_str_with_options`
+#[derive(Clone, Debug, Default, Eq, PartialEq)]
+pub struct ParseOptions {
+    epsilons: Vec<String>,
+    ebnf: bool,
+    builtins: bool,
+    numeric_values: bool,
+}
+
+// The options of parsers without any
+static DEFAULT_OPTIONS: ParseOptions = ParseOptions {
+    epsilons: Vec::new(),
+    ebnf: false,
+    builtins: false,
+    numeric_values: false,
+};
+
+impl ParseOptions {
+    /// Construct the default `ParseOptions`, recognizing only `ε` as the
+    /// empty string
+    pub fn new() -> ParseOptions {
+        ParseOptions::default()
+    }
+
+    /// Recognize `keyword` as the empty string wherever a term may be, like
+    /// the `EMPTY` or `<empty>` of textbook notations, as well as `ε`, which
+    /// is always recognized.
+    ///
+    /// An alternative of only epsilon keywords is parsed as an empty
+    /// `Expression`, with no terms. Several keywords may be recognized.
+    pub fn epsilon(mut self, keyword: &str) -> ParseOptions {
+        self.epsilons.push(String::from(keyword));
+        // longer keywords first, so none is cut short by another
+        self.epsilons
+            .sort_by_key(|keyword| ::std::cmp::Reverse(keyword.len()));
+        self
+    }
+
+    /// Recognize the repetition of EBNF `{ ... }` for zero or more of a
+    /// group's alternatives, which a grammar prints repetitions of more than
+    /// one term as.
+    ///
+    /// Like the postfix `*` for zero or more, and `+` for one or more, of
+    /// the term before it, which parse without this option, a repetition is
+    /// parsed as a `Term::Repeat` with no maximum, so `{ "," <item> }` is
+    /// parsed as `( "," <item> )*`, and a group of more than one
+    /// alternative repeated is desugared like other groups.
+    pub fn ebnf(mut self) -> ParseOptions {
+        self.ebnf = true;
+        self
+    }
+
+    /// Define the built-in nonterminals, like `<digit>` and `<letter>`,
+    /// which the grammar references but doesn't define, as
+    /// `Grammar::add_builtins` does
+    pub fn builtins(mut self) -> ParseOptions {
+        self.builtins = true;
+        self
+    }
+
+    /// Read the numeric values of ABNF, as in RFC 5234, which give the code
+    /// points of characters in hexadecimal, decimal or binary: `%x41`,
+    /// `%d65` or `%b1000001` for the terminal `"A"`, a concatenation like
+    /// `%x0D.0A` for the terminal `"\r\n"`, or a range like `%x30-39` for
+    /// the class `[0-9]`.
+    ///
+    /// Without this option, `%x0D.0A` is a terminal of those bytes, as
+    /// `Term::TerminalBytes`, and `%d` and `%b` aren't read. A range whose
+    /// end is before its start, or a value which isn't a character, is an
+    /// error. The alternate format of a grammar, like `{:#}`, writes
+    /// terminals with characters which aren't printable as numeric values.
+    pub fn numeric_values(mut self) -> ParseOptions {
+        self.numeric_values = true;
+        self
+    }
+}
+
+// The epsilon keyword recognized without any options, which an empty
+// `Expression` is displayed as
+pub const EPSILON: &str = "ε";
+
+// One of the epsilon keywords, where it isn't the lhs of a production
+fn epsilon<'i>(input: &'i [u8], epsilons: &[String]) -> IResult<&'i [u8], ()> {
+    let keywords = epsilons.iter().map(String::as_str).chain(Some(EPSILON));
+    for keyword in keywords.filter(|keyword| !keyword.is_empty()) {
+        if !input.starts_with(keyword.as_bytes()) {
+            continue;
+        }
+        let (rest, _) = whitespace(&input[keyword.len()..])?;
+        if !rest.starts_with(b"::=") {
+            return Ok((rest, ()));
+        }
+    }
+    Err(Err::Error((input, ErrorKind::Tag)))
+}
+
+// Begins the name of the nonterminal a group stands in for until `desugar`,
+// which no parsed nonterminal can contain
+const GROUP: char = '>';
+
+// The alternatives of a group between `open` and `close`
+fn delimited_alternatives<'i>(
+    input: &'i [u8],
+    options: &ParseOptions,
+    open: char,
+    close: char,
+) -> IResult<&'i [u8], Vec<Expression>> {
+    delimited!(
+        input,
+        ws!(char!(open)),
+        many1!(complete!(call!(expression_with, options))),
+        ws!(char!(close))
+    )
+}
+
+// The bracket opening a parenthesized group, like `( "a" | <b> )`, an
+// optional group, like `[ "a" | <b> ]`, or with `ParseOptions::ebnf` a
+// repetition, like `{ "a" | <b> }`, and its alternatives, the last of an
+// optional group's being `""`
+fn group_alternatives<'i>(
+    input: &'i [u8],
+    options: &ParseOptions,
+) -> IResult<&'i [u8], (char, Vec<Expression>)> {
+    for &(open, close) in &[('(', ')'), ('[', ']'), ('{', '}')] {
+        if open == '{' && !options.ebnf {
+            continue;
+        }
+        match delimited_alternatives(input, options, open, close) {
+            Ok((rest, mut alternatives)) => {
+                if open == '[' {
+                    alternatives.push(omitted());
+                }
+                return Ok((rest, (open, alternatives)));
+            }
+            Err(Err::Error(_)) => continue,
+            Err(e) => return Err(e),
+        }
+    }
+    Err(Err::Error((input, ErrorKind::Alt)))
+}
+
+// The alternative deriving nothing which an optional group or repetition
+// may be instead
+fn omitted() -> Expression {
+    Expression::from_parts(vec![Term::Terminal(String::new())])
+}
+
+// Whether `text` is all a group, rather than a character class
+pub fn is_group(text: &str) -> bool {
+    match group_alternatives(text.as_bytes(), &DEFAULT_OPTIONS) {
+        Ok((rest, _)) => rest.is_empty(),
+        Err(_) => false,
+    }
+}
+
+// The stand-in for the nonterminal `desugar` defines the group of `text` as
+fn placeholder(text: &[u8]) -> Term {
+    Term::Nonterminal(format!("{}{}", GROUP, String::from_utf8_lossy(text)))
+}
+
+// A term or group, groups standing in for nonterminals until `desugar`
+fn grouped_term<'i>(input: &'i [u8], options: &ParseOptions) -> IResult<&'i [u8], Term> {
+    match complete!(input, call!(group_alternatives, options)) {
+        Ok((rest, _)) => Ok((rest, placeholder(&input[..input.len() - rest.len()]))),
+        Err(Err::Error(_)) => term_with(input, options),
+        // a class may begin like a group with an invalid escape, like `["\]`
+        Err(e) => term_with(input, options).or(Err(e)),
+    }
+}
+
+// A term, read as a numeric value first with `ParseOptions::numeric_values`
+fn term_with<'i>(input: &'i [u8], options: &ParseOptions) -> IResult<&'i [u8], Term> {
+    if options.numeric_values {
+        match numeric_value(input) {
+            Err(Err::Error(_)) => {}
+            result => return result,
+        }
+    }
+    term(input)
+}
+
+fn parse_count(digits: &[u8]) -> Result<usize, ::std::num::ParseIntError> {
+    String::from_utf8_lossy(digits).parse()
+}
+
+// The bounds of an ABNF repetition prefixing a term, like the `2*4` of
+// `2*4"x"` for 2 to 4 of it, the `2*` of `2*"x"` for at least 2, or the `3`
+// of `3"x"` for exactly 3
+named!(repetition< &[u8], (usize, Option<usize>) >,
+    do_parse!(
+        min: map_res!(digit1, parse_count) >>
+        max: opt!(complete!(preceded!(
+            char!('*'),
+            opt!(complete!(map_res!(digit1, parse_count)))
+        ))) >>
+        (min, max.unwrap_or(Some(min)))
+    )
+);
+
+// A term or group, which may be prefixed by a repetition of it, the
+// repetition standing in for a nonterminal until `desugar` like a group
+fn repeated_term<'i>(input: &'i [u8], options: &ParseOptions) -> IResult<&'i [u8], Term> {
+    let (rest, (min, max)) = match repetition(input) {
+        Ok(bounds) => bounds,
+        Err(_) => return grouped_term(input, options),
+    };
+    if max.is_some_and(|max| max < min) {
+        return Err(Err::Failure((input, ErrorKind::ManyMN)));
+    }
+    let (rest, _) = grouped_term(rest, options)?;
+    Ok((rest, placeholder(&input[..input.len() - rest.len()])))
+}
+
+// Get the text of a group which hasn't been desugared
+fn group_text(term: &Term) -> Option<&str> {
+    match *term {
+        Term::Nonterminal(ref name) if name.starts_with(GROUP) => Some(&name[GROUP.len_utf8()..]),
+        _ => None,
+    }
+}
+
+// Fail for a group in expressions parsed without a grammar to define it in
+pub fn reject_groups<'e, I>(expressions: I) -> Result<(), Error>
+where
+    I: IntoIterator<Item = &'e Expression>,
+{
+    let group = expressions
+        .into_iter()
+        .flat_map(|expr| expr.terms_iter())
+        .find_map(group_text);
+    match group {
+        Some(group) => Err(Error::ParseError(format!(
+            "Groups are only supported within a Grammar, found {}",
+            group.trim()
+        ))),
+        None => Ok(()),
+    }
+}
+
+// Replace each group of `productions` with a nonterminal named for the
+// nonterminal it's in and how many groups came before it there, like
+// `<a (1)>`, skipping names already taken. The production defining it
+// follows the production it's in. A repetition becomes a `Term::Repeat`
+// instead, its group desugared the same way.
+pub fn desugar(productions: Vec<Production>, options: &ParseOptions) -> Vec<Production> {
+    let mut taken = HashSet::new();
+    for prod in &productions {
+        let terms = prod.rhs_iter().flat_map(|expr| expr.nested_terms_iter());
+        for term in ::std::iter::once(prod.lhs()).chain(terms) {
+            if let Term::Nonterminal(ref name) = *term {
+                taken.insert(name.clone());
+            }
+        }
+    }
+
+    let mut counts = HashMap::new();
+    let mut desugared = vec![];
+    for prod in productions {
+        desugar_into(prod, options, &mut taken, &mut counts, &mut desugared);
+    }
+    desugared
+}
+
+// Get the name of the next group of `parent`
+pub(crate) fn fresh(
+    parent: &str,
+    taken: &mut HashSet<String>,
+    counts: &mut HashMap<String, usize>,
+) -> String {
+    let count = counts.entry(String::from(parent)).or_insert(0);
+    loop {
+        *count += 1;
+        let name = format!("{} ({})", parent, count);
+        if taken.insert(name.clone()) {
+            return name;
+        }
+    }
+}
+
+fn desugar_into(
+    mut prod: Production,
+    options: &ParseOptions,
+    taken: &mut HashSet<String>,
+    counts: &mut HashMap<String, usize>,
+    desugared: &mut Vec<Production>,
+) {
+    let parent = match *prod.lhs() {
+        Term::Nonterminal(ref name) => name.clone(),
+        ref lhs => lhs.to_string(),
+    };
+    let mut groups = vec![];
+    for expr in prod.rhs_iter_mut() {
+        for term in expr.terms_iter_mut() {
+            let text = match group_text(term) {
+                Some(text) => String::from(text.trim()),
+                None => continue,
+            };
+            *term = sugar(&text, &parent, options, taken, counts, &mut groups);
+        }
+    }
+    desugared.push(prod);
+    for group in groups {
+        desugar_into(group, options, taken, counts, desugared);
+    }
+}
+
+// Get the term standing in for the group of `text` in a production of
+// `parent`: a repetition of a term or group, like `2*4"x"`, `"x"*` or `{ "x"
+// }`, or else a nonterminal of a fresh name, its productions pushed to
+// `groups`
+fn sugar(
+    text: &str,
+    parent: &str,
+    options: &ParseOptions,
+    taken: &mut HashSet<String>,
+    counts: &mut HashMap<String, usize>,
+    groups: &mut Vec<Production>,
+) -> Term {
+    let repeat = |inner, min, max| Term::Repeat {
+        inner: Box::new(inner),
+        min,
+        max,
+    };
+    let postfix = match text.strip_suffix('*') {
+        Some(inner) => Some((inner, 0)),
+        None => text.strip_suffix('+').map(|inner| (inner, 1)),
+    };
+    if let Some((inner, min)) = postfix {
+        if let Ok((_, term)) = repeated_term(inner.as_bytes(), options) {
+            let inner = operand(term, parent, options, taken, counts, groups);
+            return repeat(inner, min, None);
+        }
+    }
+    // a repeated term or group never ends with a postfix operator
+    if !text.ends_with(|c| "?*+".contains(c)) {
+        if let Ok((inner, (min, max))) = repetition(text.as_bytes()) {
+            if let Ok((_, term)) = grouped_term(inner, options) {
+                let inner = operand(term, parent, options, taken, counts, groups);
+                return repeat(inner, min, max);
+            }
+        }
+    }
+    if let Ok((_, ('{', alternatives))) = group_alternatives(text.as_bytes(), options) {
+        let inner = alternative(alternatives, parent, options, taken, counts, groups);
+        return repeat(inner, 0, None);
+    }
+    let name = fresh(parent, taken, counts);
+    groups.extend(define(text, name.clone(), options));
+    Term::Nonterminal(name)
+}
+
+// Get the terms a repetition of `term` repeats: those of the alternatives
+// of a parenthesized group, or else the term itself, standing in for any
+// other group
+fn operand(
+    term: Term,
+    parent: &str,
+    options: &ParseOptions,
+    taken: &mut HashSet<String>,
+    counts: &mut HashMap<String, usize>,
+    groups: &mut Vec<Production>,
+) -> Expression {
+    let text = match group_text(&term) {
+        Some(text) => String::from(text.trim()),
+        None => return Expression::from_parts(vec![term]),
+    };
+    if let Ok((_, ('(', alternatives))) = group_alternatives(text.as_bytes(), options) {
+        return alternative(alternatives, parent, options, taken, counts, groups);
+    }
+    Expression::from_parts(vec![sugar(&text, parent, options, taken, counts, groups)])
+}
+
+// Get the terms of a group's single alternative, with its own groups
+// desugared, or else a nonterminal of a fresh name for the group of all of
+// `alternatives`
+fn alternative(
+    mut alternatives: Vec<Expression>,
+    parent: &str,
+    options: &ParseOptions,
+    taken: &mut HashSet<String>,
+    counts: &mut HashMap<String, usize>,
+    groups: &mut Vec<Production>,
+) -> Expression {
+    if alternatives.len() > 1 || alternatives[0].weight().is_some() {
+        let name = Term::Nonterminal(fresh(parent, taken, counts));
+        groups.push(Production::from_parts(name.clone(), alternatives));
+        return Expression::from_parts(vec![name]);
+    }
+    let mut inner = alternatives.remove(0);
+    for term in inner.terms_iter_mut() {
+        if let Some(text) = group_text(term).map(|text| String::from(text.trim())) {
+            *term = sugar(&text, parent, options, taken, counts, groups);
+        }
+    }
+    inner
+}
+
+// Get the production defining `name` as the group of `text`, a term which
+// is optional being the same as the optional group of it
+fn define(text: &str, name: String, options: &ParseOptions) -> Vec<Production> {
+    let lhs = Term::Nonterminal(name);
+    if let Some(inner) = text.strip_suffix('?') {
+        return match repeated_term(inner.as_bytes(), options) {
+            Ok((_, term)) => vec![Production::from_parts(
+                lhs,
+                vec![Expression::from_parts(vec![term]), omitted()],
+            )],
+            Err(_) => vec![],
+        };
+    }
+    match group_alternatives(text.as_bytes(), options) {
+        Ok((_, (_, alternatives))) => vec![Production::from_parts(lhs, alternatives)],
+        Err(_) => vec![],
+    }
+}
+
+// A term of an expression, or `None` for an epsilon keyword. It may be
+// followed by `?` to make it optional, or by `*` or `+` to repeat it.
+fn expression_term<'i>(input: &'i [u8], options: &ParseOptions) -> IResult<&'i [u8], Option<Term>> {
+    if let Ok((rest, _)) = epsilon(input, &options.epsilons) {
+        return Ok((rest, None));
+    }
+    let (rest, term) = repeated_term(input, options)?;
+    if let Ok((rest, _)) = ws!(rest, one_of!("?*+")) {
+        return Ok((rest, Some(placeholder(&input[..input.len() - rest.len()]))));
+    }
+    Ok((rest, Some(term)))
+}
+
+named_args!(pub expression_with<'a>(options: &'a ParseOptions)<Expression>,
     do_parse!(
-        peek!(term) >>
-        terms: many1!(complete!(term)) >>
+        peek!(call!(expression_term, options)) >>
+        terms: many1!(complete!(call!(expression_term, options))) >>
+        weight: opt!(complete!(weight)) >>
         ws!(
             alt!(
                 recognize!(peek!(complete!(eof!()))) |
                 recognize!(peek!(complete!(char!(';')))) |
-                expression_next |
+                recognize!(peek!(complete!(char!(')')))) |
+                recognize!(peek!(complete!(char!(']')))) |
+                recognize!(peek!(complete!(char!('}')))) |
+                recognize!(call!(expression_next_with, options)) |
                 recognize!(peek!(complete!(prod_lhs)))
             )
         ) >>
-        (Expression::from_parts(terms))
+        ({
+            let mut expression = Expression::from_parts(terms.into_iter().flatten().collect());
+            expression.set_weight(weight);
+            expression
+        })
     )
 );
 
+named!(pub expression< &[u8], Expression >, call!(expression_with, &DEFAULT_OPTIONS));
+
 named!(pub expression_complete< &[u8], Expression >,
     do_parse!(
         e: expression >>
@@ -71,10 +930,10 @@ named!(pub expression_complete< &[u8], Expression >,
     )
 );
 
-named!(pub production< &[u8], Production >,
+named_args!(pub production_with<'a>(options: &'a ParseOptions)<Production>,
     do_parse!(
         lhs: ws!(prod_lhs) >>
-        rhs: many1!(complete!(expression)) >>
+        rhs: many1!(complete!(call!(expression_with, options))) >>
         ws!(
             alt!(
                 recognize!(peek!(complete!(eof!()))) |
@@ -86,6 +945,8 @@ named!(pub production< &[u8], Production >,
     )
 );
 
+named!(pub production< &[u8], Production >, call!(production_with, &DEFAULT_OPTIONS));
+
 named!(pub production_complete< &[u8], Production >,
     do_parse!(
         p: production >>
@@ -94,15 +955,18 @@ named!(pub production_complete< &[u8], Production >,
     )
 );
 
-named!(pub grammar< &[u8], Grammar >,
+named_args!(pub grammar_with<'a>(options: &'a ParseOptions)<Grammar>,
     do_parse!(
-        peek!(production) >>
-        prods: many1!(complete!(production)) >>
+        peek!(call!(production_with, options)) >>
+        prods: many1!(complete!(call!(production_with, options))) >>
         (Grammar::from_parts(prods))
     )
 );
 
-named!(pub grammar_complete< &[u8], Grammar >,
+named!(pub grammar< &[u8], Grammar >, call!(grammar_with, &DEFAULT_OPTIONS));
+
+// A whole grammar, with its groups left for `desugar`
+named!(pub grammar_chunk< &[u8], Grammar >,
     do_parse!(
         g: grammar >>
         eof!() >>
@@ -110,6 +974,29 @@ named!(pub grammar_complete< &[u8], Grammar >,
     )
 );
 
+named!(pub grammar_complete< &[u8], Grammar >,
+    map!(grammar_chunk, |g| Grammar::from_parts(desugar(g.into_productions(), &DEFAULT_OPTIONS)))
+);
+
+// Parse a whole grammar, recognizing the keywords of `options`
+pub fn grammar_complete_with<'i>(
+    input: &'i [u8],
+    options: &ParseOptions,
+) -> IResult<&'i [u8], Grammar> {
+    do_parse!(
+        input,
+        g: call!(grammar_with, options) >>
+        eof!() >>
+        ({
+            let mut grammar = Grammar::from_parts(desugar(g.into_productions(), options));
+            if options.builtins {
+                grammar.add_builtins();
+            }
+            grammar
+        })
+    )
+}
+
 #[cfg(test)]
 mod tests {
     use super::*;
@@ -198,7 +1085,7 @@ mod tests {
             construct_production_tuple().0,
         ]);
 
-        (grammar_object, String::from(grammar_pattern))
+        (grammar_object, grammar_pattern)
     }
 
     #[test]
@@ -209,4 +1096,261 @@ mod tests {
             grammar(grammar_tuple.1.as_bytes()).unwrap().1
         );
     }
+
+    #[test]
+    fn char_class() {
+        let class = Term::CharClass(CharClass::from_ranges(vec![('0', '9'), ('_', '_')]));
+        assert_eq!(
+            super::char_class(b"[0-9_] <rest>"),
+            Ok((&b"<rest>"[..], class.clone()))
+        );
+        assert_eq!(term(b"[0-9_]\"\""), Ok((&b"\"\""[..], class)));
+        assert!(super::char_class(b"<nonterm>").is_err());
+    }
+
+    #[test]
+    fn numeric_values() {
+        let terminal = |text: &str| Term::Terminal(String::from(text));
+        assert_eq!(
+            numeric_value(b"%x0D.0A <rest>"),
+            Ok((&b"<rest>"[..], terminal("\r\n")))
+        );
+        assert_eq!(numeric_value(b"%d13.10"), Ok((&b""[..], terminal("\r\n"))));
+        assert_eq!(numeric_value(b"%b1000001"), Ok((&b""[..], terminal("A"))));
+        assert_eq!(numeric_value(b"%X1F600"), Ok((&b""[..], terminal("😀"))));
+        assert_eq!(
+            numeric_value(b"%x30-39"),
+            Ok((
+                &b""[..],
+                Term::CharClass(CharClass::from_ranges(vec![('0', '9')]))
+            ))
+        );
+
+        assert!(matches!(numeric_value(b"%x39-30"), Err(Err::Failure(_))));
+        assert!(matches!(numeric_value(b"%xD800"), Err(Err::Failure(_))));
+        assert!(matches!(numeric_value(b"%x41."), Err(Err::Failure(_))));
+        assert!(matches!(numeric_value(b"%b2"), Err(Err::Error(_))));
+        assert!(matches!(numeric_value(b"%i\"a\""), Err(Err::Error(_))));
+
+        // only read in place of bytes with the option
+        let options = ParseOptions::new().numeric_values();
+        let (_, expression) = expression_with(b"%x41.0A %d48-57 %i\"a\"", &options).unwrap();
+        assert_eq!(
+            expression,
+            Expression::from_parts(vec![
+                terminal("A\n"),
+                Term::CharClass(CharClass::from_ranges(vec![('0', '9')])),
+                Term::CaselessTerminal(String::from("a")),
+            ])
+        );
+        assert_eq!(
+            term(b"%x41.0A"),
+            Ok((&b""[..], Term::TerminalBytes(vec![0x41, 0x0A])))
+        );
+        assert!(expression_complete(b"%d13").is_err());
+    }
+
+    #[test]
+    fn any_char() {
+        let any = Term::CharClass(CharClass::any());
+        assert_eq!(
+            super::any_char(b"%any <rest>"),
+            Ok((&b"<rest>"[..], any.clone()))
+        );
+        assert_eq!(term(b"%any\"\""), Ok((&b"\"\""[..], any)));
+        assert!(term(b"%anything").is_err());
+        assert!(super::any_char(b"%x41").is_err());
+    }
+    #[test]
+    fn epsilon_keywords() {
+        let options = ParseOptions::new()
+            .epsilon("ε")
+            .epsilon("EMPTY")
+            .epsilon("<empty>");
+        let input = "<a> ::= ε | \"a\" <a>
+            <b> ::= EMPTY %weight 2 | \"b\" EMPTY <empty>
+            <c> ::= <empty> | ε EMPTY | \"ε\" | <emptyish>";
+        let mut weighted = Expression::new();
+        weighted.set_weight(Some(2));
+        let expected = vec![
+            Production::from_parts(
+                Term::Nonterminal(String::from("a")),
+                vec![
+                    Expression::new(),
+                    Expression::from_str("\"a\" <a>").unwrap(),
+                ],
+            ),
+            Production::from_parts(
+                Term::Nonterminal(String::from("b")),
+                vec![weighted, Expression::from_str("\"b\"").unwrap()],
+            ),
+            Production::from_parts(
+                Term::Nonterminal(String::from("c")),
+                vec![
+                    Expression::new(),
+                    Expression::new(),
+                    Expression::from_str("\"ε\"").unwrap(),
+                    Expression::from_str("<emptyish>").unwrap(),
+                ],
+            ),
+        ];
+        let (rest, grammar) = grammar_complete_with(input.as_bytes(), &options).unwrap();
+        assert!(rest.is_empty());
+        assert_eq!(grammar, Grammar::from_parts(expected));
+
+        // only `ε` is recognized by default, and no keyword as the lhs of a
+        // production
+        let (_, grammar) = grammar_complete("<a> ::= ε | \"a\" ε <a>".as_bytes()).unwrap();
+        assert_eq!(
+            grammar,
+            Grammar::from_parts(vec![Production::from_parts(
+                Term::Nonterminal(String::from("a")),
+                vec![
+                    Expression::new(),
+                    Expression::from_str("\"a\" <a>").unwrap()
+                ],
+            )])
+        );
+        assert!(grammar_complete("<a> ::= EMPTY".as_bytes()).is_err());
+        let (_, grammar) = grammar_complete_with(
+            "<a> ::= <empty> <empty> ::= \"x\"".as_bytes(),
+            &ParseOptions::new().epsilon("<empty>"),
+        )
+        .unwrap();
+        assert_eq!(grammar.productions_iter().count(), 2);
+    }
+
+    #[test]
+    fn builtin_nonterminals() {
+        let input = "<num> ::= <digit> | <digit> <num>";
+        let (_, grammar) =
+            grammar_complete_with(input.as_bytes(), &ParseOptions::new().builtins()).unwrap();
+        let digit = Production::from_parts(
+            Term::Nonterminal(String::from("digit")),
+            vec![Expression::from_parts(vec![Term::CharClass(
+                CharClass::from_ranges(vec![('0', '9')]),
+            )])],
+        );
+        assert_eq!(grammar.productions_iter().nth(1), Some(&digit));
+        let (_, grammar) = grammar_complete(input.as_bytes()).unwrap();
+        assert_eq!(grammar.productions_iter().count(), 1);
+    }
+
+    #[test]
+    fn terminal_escapes() {
+        let escaped = "\"a\\n\\t\\r\\\\\\\"\\'\\u{1F600}\\u{e9}\" '\\'\"'";
+        assert_eq!(
+            expression_complete(escaped.as_bytes()).unwrap().1,
+            Expression::from_parts(vec![
+                Term::Terminal(String::from("a\n\t\r\\\"'\u{1F600}\u{e9}")),
+                Term::Terminal(String::from("'\"")),
+            ])
+        );
+        assert_eq!(
+            term_complete(b"\"\\u{0}\"..\"\\u{1F}\"").unwrap().1,
+            Term::CharClass(CharClass::from_ranges(vec![('\0', '\u{1F}')]))
+        );
+
+        let error = |input: &str| match Grammar::from_str(input) {
+            Err(Error::ParseError(message)) => message,
+            result => panic!("{:?} should fail to parse: {:?}", input, result),
+        };
+        assert_eq!(
+            error("<a> ::= \"x\\q\""),
+            "invalid escape `\\q` at line 1, column 11"
+        );
+        assert_eq!(
+            error("<a> ::= \"x\"\n  | \"\\u{}\""),
+            "invalid escape `\\u{}` at line 2, column 6"
+        );
+        assert_eq!(
+            error("<a> ::= 'y\\u{110000}'"),
+            "invalid escape `\\u{110000}` at line 1, column 11"
+        );
+        assert_eq!(
+            error("<a> ::= \"\\u{1234567}\""),
+            "invalid escape `\\u{1234567}` at line 1, column 10"
+        );
+        assert_eq!(
+            error("<a> ::= \"\\u{d800}\""),
+            "invalid escape `\\u{d800}` at line 1, column 10"
+        );
+        assert_eq!(
+            error("<a> ::= \"\\u{12\""),
+            "invalid escape `\\u{12` at line 1, column 10"
+        );
+        assert_eq!(
+            error("<a> ::= \"\\u0041\""),
+            "invalid escape `\\u0041` at line 1, column 10"
+        );
+        // a lone backslash ending a terminal escapes its closing quote
+        assert_eq!(
+            error("<a> ::= \"x\\\""),
+            "the escape `\\\"` leaves its terminal unclosed at line 1, column 11"
+        );
+        assert_eq!(
+            error("<a> ::= \"x\\"),
+            "a backslash ends the input at line 1, column 11"
+        );
+        let (_, errors) = Grammar::from_str_lenient("<a> ::= \"a\"\n<b> ::= \"\\x\"");
+        assert_eq!(
+            errors,
+            vec![Error::ParseError(String::from(
+                "Line 2: invalid escape `\\x` at line 2, column 10"
+            ))]
+        );
+        // a class isn't a group with an invalid escape
+        assert_eq!(
+            expression_complete(b"[\"\\]a]").unwrap().1,
+            Expression::from_parts(vec![Term::CharClass(CharClass::from_ranges(vec![
+                ('"', '"'),
+                (']', ']'),
+                ('a', 'a')
+            ]))])
+        );
+        assert!(Term::from_str("\"\\\"").is_err());
+        assert!(Term::from_str("\"a").is_err());
+    }
+
+    #[test]
+    fn weighted_expression() {
+        let mut weighted = Expression::from_parts(vec![
+            Term::Nonterminal(String::from("b")),
+            Term::Terminal(String::from("c")),
+        ]);
+        weighted.set_weight(Some(3));
+        assert_eq!(
+            expression(b"<b> \"c\" %weight 3 | <d>"),
+            Ok((&b"<d>"[..], weighted.clone()))
+        );
+        assert_eq!(
+            expression_complete(b"<b> \"c\"%weight3"),
+            Ok((&b""[..], weighted))
+        );
+        assert!(expression_complete(b"<b> %weight").is_err());
+        assert!(expression_complete(b"<b> %weight -1").is_err());
+        assert!(expression_complete(b"<b> %weight 3 <c>").is_err());
+    }
+
+    #[test]
+    fn whitespace_line_continuation() {
+        assert_eq!(
+            whitespace(" \\\n\t\\\r\n <a>".as_bytes()),
+            Ok(("<a>".as_bytes(), " \\\n\t\\\r\n ".as_bytes()))
+        );
+        assert_eq!(
+            whitespace("\\ <a>".as_bytes()),
+            Ok(("\\ <a>".as_bytes(), "".as_bytes()))
+        );
+    }
+
+    #[test]
+    fn expression_line_continuation() {
+        let expression_tuple = construct_expression_tuple();
+        let continued = "<nonterminal-pattern> \\\n    \"terminal pattern\"";
+        assert_eq!(
+            expression_tuple.0,
+            expression(continued.as_bytes()).unwrap().1
+        );
+    }
 }
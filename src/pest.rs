@@ -0,0 +1,338 @@
+use grammar::{self, Alternative, Grammar, Sugar};
+use std::collections::{HashMap, HashSet};
+use std::str;
+use term::{CharClass, Term};
+
+/// An expression which matches nothing, for what matches nothing
+const NOTHING: &str = "!\"\"";
+
+/// The keywords of Rust, which pest can't name a rule, as it names a variant
+/// of its `Rule` enum for each
+const KEYWORDS: &[&str] = &[
+    "as", "async", "await", "break", "const", "continue", "crate", "dyn", "else", "enum", "extern",
+    "false", "fn", "for", "if", "impl", "in", "let", "loop", "match", "mod", "move", "mut", "pub",
+    "ref", "return", "self", "static", "struct", "super", "trait", "true", "type", "unsafe", "use",
+    "where", "while",
+];
+
+/// Get `name` as the name of a pest rule, with runs of the characters a
+/// name can't have replaced by `_`, in lowercase when it has no lowercase
+/// letters, as those of pest's builtin rules like `ANY` don't
+fn rule_name(name: &str) -> String {
+    let mut rule = String::new();
+    for c in name.chars() {
+        if c.is_ascii_alphanumeric() || c == '_' {
+            rule.push(c);
+        } else if !rule.ends_with('_') {
+            rule.push('_');
+        }
+    }
+    if !rule.contains(|c: char| c.is_ascii_lowercase()) {
+        rule = rule.to_ascii_lowercase();
+    }
+    let rule = rule.trim_matches('_');
+    match rule.chars().next() {
+        _ if KEYWORDS.contains(&rule) => format!("{}_", rule),
+        Some(c) if c.is_ascii_alphabetic() => String::from(rule),
+        Some(_) => format!("rule_{}", rule),
+        None => String::from("rule"),
+    }
+}
+
+/// Get `c` escaped as it's written in a pest string or character
+fn escaped(c: char, quote: char) -> String {
+    match c {
+        '\\' => String::from("\\\\"),
+        '\n' => String::from("\\n"),
+        '\t' => String::from("\\t"),
+        '\r' => String::from("\\r"),
+        c if c == quote => format!("\\{}", c),
+        c if c.is_control() => format!("\\u{{{:X}}}", c as u32),
+        c => c.to_string(),
+    }
+}
+
+/// Get `text` as a pest string
+fn string(text: &str) -> String {
+    let escaped: String = text.chars().map(|c| escaped(c, '"')).collect();
+    format!("\"{}\"", escaped)
+}
+
+/// Get `class` as its characters and ranges like `'a'..'z'`, or `ANY`
+fn class(class: &CharClass) -> String {
+    if class.is_empty() {
+        return String::from(NOTHING);
+    }
+    if class.is_any() {
+        return String::from("ANY");
+    }
+    let ranges = class
+        .ranges()
+        .iter()
+        .map(|&(lo, hi)| {
+            if lo == hi {
+                string(&lo.to_string())
+            } else {
+                format!("'{}'..'{}'", escaped(lo, '\''), escaped(hi, '\''))
+            }
+        })
+        .collect::<Vec<_>>();
+    let ranges = match ranges[..] {
+        [ref range] => range.clone(),
+        _ => format!("( {} )", ranges.join(" | ")),
+    };
+    if class.is_negated() {
+        format!("( !{} ~ ANY )", ranges)
+    } else {
+        ranges
+    }
+}
+
+/// Writes the resugared productions of a grammar as a pest grammar
+struct Pest<'a> {
+    names: HashMap<&'a Term, String>,
+}
+
+impl<'a> Pest<'a> {
+    /// Get the rule name of every nonterminal of `grammar`, renamed to a
+    /// name no other nonterminal is
+    fn new(grammar: &'a Grammar) -> Pest<'a> {
+        let mut nonterminals = vec![];
+        for prod in grammar.productions_iter() {
+            nonterminals.push(prod.lhs());
+            let terms = prod.rhs_iter().flat_map(|expr| expr.nested_terms_iter());
+            nonterminals.extend(terms.filter(|term| matches!(**term, Term::Nonterminal(_))));
+        }
+
+        let mut names = HashMap::new();
+        let mut taken = HashSet::new();
+        for term in nonterminals {
+            if names.contains_key(term) {
+                continue;
+            }
+            let base = match *term {
+                Term::Nonterminal(ref name) => rule_name(name),
+                _ => rule_name(&term.to_string()),
+            };
+            let mut renamed = base.clone();
+            let mut count = 1;
+            while !taken.insert(renamed.clone()) {
+                count += 1;
+                renamed = format!("{}_{}", base, count);
+            }
+            names.insert(term, renamed);
+        }
+        Pest { names }
+    }
+
+    fn term(&self, term: &Term) -> String {
+        match *term {
+            Term::Nonterminal(_) => self.names[term].clone(),
+            Term::Terminal(ref text) => string(text),
+            Term::CaselessTerminal(ref text) => format!("^{}", string(text)),
+            Term::TerminalBytes(ref bytes) => match str::from_utf8(bytes) {
+                Ok(text) => string(text),
+                // pest parses text, not bytes
+                Err(_) => String::from(NOTHING),
+            },
+            Term::CharClass(ref charclass) => class(charclass),
+            // pest has no regexes
+            Term::Regex(_) => String::from(NOTHING),
+            Term::Repeat { .. } => unreachable!("repetitions are resugared"),
+        }
+    }
+
+    fn sugar(&self, sugar: &Sugar) -> String {
+        match *sugar {
+            Sugar::Term(term) => self.term(term),
+            Sugar::Group(ref alternatives) => format!("( {} )", self.alternatives(alternatives)),
+            Sugar::Optional(ref alternatives) => format!("{}?", self.group(alternatives)),
+            Sugar::Repeat(min, Some(max), ref repeated) if min == max => {
+                format!("{}{{{}}}", self.operand(repeated), min)
+            }
+            Sugar::Repeat(min, Some(max), ref repeated) => {
+                format!("{}{{{}, {}}}", self.operand(repeated), min, max)
+            }
+            Sugar::Repeat(0, None, ref repeated) => format!("{}*", self.operand(repeated)),
+            Sugar::Repeat(1, None, ref repeated) => format!("{}+", self.operand(repeated)),
+            Sugar::Repeat(min, None, ref repeated) => {
+                format!("{}{{{}, }}", self.operand(repeated), min)
+            }
+        }
+    }
+
+    /// Get `alternatives` as the operand of a postfix operator, in
+    /// parentheses unless they're a single term
+    fn group(&self, alternatives: &[Alternative]) -> String {
+        match *alternatives {
+            [Alternative { ref terms, .. }] if terms.len() == 1 => self.operand(&terms[0]),
+            _ => format!("( {} )", self.alternatives(alternatives)),
+        }
+    }
+
+    /// Get `sugar` as the operand of a postfix operator, in parentheses
+    /// unless it's a single term or group already
+    fn operand(&self, sugar: &Sugar) -> String {
+        match *sugar {
+            Sugar::Term(_) | Sugar::Group(_) => self.sugar(sugar),
+            Sugar::Optional(_) | Sugar::Repeat(..) => {
+                format!("( {} )", self.sugar(sugar))
+            }
+        }
+    }
+
+    /// Get `alternatives` joined by `|`, each sequenced by `~` without its
+    /// empty strings, so an empty alternative is written as `""`
+    fn alternatives(&self, alternatives: &[Alternative]) -> String {
+        alternatives
+            .iter()
+            .map(|alternative| {
+                let sequence = alternative
+                    .terms
+                    .iter()
+                    .filter(|sugar| match **sugar {
+                        Sugar::Term(Term::Terminal(text)) => !text.is_empty(),
+                        _ => true,
+                    })
+                    .map(|sugar| self.sugar(sugar))
+                    .collect::<Vec<_>>();
+                if sequence.is_empty() {
+                    String::from("\"\"")
+                } else {
+                    sequence.join(" ~ ")
+                }
+            })
+            .collect::<Vec<_>>()
+            .join(" | ")
+    }
+}
+
+impl Grammar {
+    /// Format the grammar for the Rust parser generator pest, as a best
+    /// effort starting point for moving a grammar to it, with a rule like
+    /// `name = { "a" ~ other | '0'..'9'+ }` for each nonterminal.
+    ///
+    /// The productions of a nonterminal are joined into one rule, and
+    /// nonterminals are renamed to names pest allows, like `<my rule>` to
+    /// `my_rule`, `<DIGIT>`, which could be one of pest's builtins, to
+    /// `digit`, and `<type>` to `type_`. Groups, optional groups and
+    /// repetitions are written with `( )`, `?`, `*`, `+` and `{m, n}`,
+    /// character classes as ranges like `'a'..'z'`, negated ones like
+    /// `( !"a" ~ ANY )`, and caseless terminals like `^"select"`. Weights,
+    /// terminals of bytes which aren't UTF-8 and regexes have no notation
+    /// in pest, and the latter two are written as `!""`, which matches
+    /// nothing.
+    ///
+    /// Every rule is a normal rule: none are silent, atomic or the
+    /// `WHITESPACE` between tokens, and there are no actions, which pest
+    /// leaves to the code walking its pairs. As pest parses expressions
+    /// (PEG), taking the first alternative which matches and never
+    /// backtracking into it, it may not accept every sentence this crate
+    /// parses, such as `"ab"` by `"a" | "ab"`, until the alternatives are
+    /// reordered.
+    ///
+    /// # Example
+    ///
+    /// ```rust
+    /// extern crate bnf;
+    /// use bnf::Grammar;
+    ///
+    /// fn main() {
+    ///     let grammar = Grammar::from_str(
+    ///         "<list> ::= \"[\" [ <item> ( \",\" <item> )* ] \"]\"
+    ///         <item> ::= [0-9]+ | %i\"nil\"",
+    ///     ).unwrap();
+    ///
+    ///     assert_eq!(
+    ///         grammar.to_pest(),
+    ///         "list = { \"[\" ~ ( item ~ ( \",\" ~ item )* )? ~ \"]\" }\nitem = { '0'..'9'+ | ^\"nil\" }\n"
+    ///     );
+    /// }
+    /// ```
+    pub fn to_pest(&self) -> String {
+        let pest = Pest::new(self);
+        let mut rules: Vec<(&Term, Vec<String>)> = vec![];
+        for (prod, alternatives) in grammar::resugar(self) {
+            let alternatives = pest.alternatives(&alternatives);
+            match rules.iter_mut().find(|rule| *rule.0 == *prod.lhs()) {
+                Some(rule) => rule.1.push(alternatives),
+                None => rules.push((prod.lhs(), vec![alternatives])),
+            }
+        }
+        rules
+            .into_iter()
+            .map(|(lhs, alternatives)| {
+                let mut alternatives = alternatives
+                    .into_iter()
+                    .filter(|alternatives| !alternatives.is_empty())
+                    .collect::<Vec<_>>();
+                if alternatives.is_empty() {
+                    alternatives.push(String::from(NOTHING));
+                }
+                format!("{} = {{ {} }}\n", pest.names[lhs], alternatives.join(" | "))
+            })
+            .collect()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use expression::Expression;
+    use production::Production;
+
+    #[test]
+    fn to_pest() {
+        let grammar = Grammar::from_str(
+            "<doc> ::= <item>* ( \"a\" | \"b\" %weight 2 )+ 2*3<Item> [ \"d\" \"e\" ] 3*<id>
+            <item> ::= <doc> \"\" | \"\" | %i\"If\" | 'tab\t\"'
+            <Item> ::= [a-z_] | [^\\]'] | %any
+            <id> ::= <Item> <Item>+ | <doc> \"x\"
+            <id> ::= <letter> 1*2\"x\"
+            <letter> ::= [A-Za-z]",
+        )
+        .unwrap();
+        assert_eq!(
+            grammar.to_pest(),
+            "doc = { item* ~ ( \"a\" | \"b\" )+ ~ Item{2, 3} ~ ( \"d\" ~ \"e\" )? ~ id{3, } }
+item = { doc | \"\" | ^\"If\" | \"tab\\t\\\"\" }
+Item = { ( 'a'..'z' | \"_\" ) | ( !( \"]\" | \"'\" ) ~ ANY ) | ANY }
+id = { Item ~ Item+ | doc ~ \"x\" | letter ~ \"x\"{1, 2} }
+letter = { ( 'A'..'Z' | 'a'..'z' ) }
+"
+        );
+    }
+
+    #[test]
+    fn pest_names() {
+        let mut grammar = Grammar::from_str(
+            "<my rule> ::= <my_rule> | <1st> | <ANY> | <type> | <undefined>
+            <my_rule> ::= \"a\"
+            <ANY> ::= <any>
+            <any> ::= \"b\"",
+        )
+        .unwrap();
+        grammar.add_production(Production::from_parts(
+            Term::Nonterminal(String::from("nothing")),
+            vec![],
+        ));
+        grammar.add_production(Production::from_parts(
+            Term::Nonterminal(String::from("bytes")),
+            vec![
+                Expression::from_parts(vec![Term::TerminalBytes(vec![0xff])]),
+                Expression::from_parts(vec![Term::Regex(String::from("[a-z]+"))]),
+            ],
+        ));
+        assert_eq!(
+            grammar.to_pest(),
+            "my_rule = { my_rule_2 | rule_1st | any | type_ | undefined }
+my_rule_2 = { \"a\" }
+any = { any_2 }
+any_2 = { \"b\" }
+nothing = { !\"\" }
+bytes = { !\"\" | !\"\" }
+"
+        );
+        assert_eq!(Grammar::new().to_pest(), "");
+    }
+}
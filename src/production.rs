@@ -3,37 +3,103 @@
 use error::Error;
 use expression::Expression;
 use parsers;
+use std::cmp::Ordering;
+use std::collections::BTreeMap;
 use std::fmt;
+use std::hash::{Hash, Hasher};
 use std::slice;
 use std::str::FromStr;
-use term::Term;
+use term::{Nonterminal, Term};
 
 /// A Production is comprised of any number of Expressions
-#[derive(Clone, Debug, Eq, Hash, PartialEq)]
+///
+/// The `lhs` is a `Term` so that existing code keeps compiling, though only
+/// a `Term::Nonterminal` defines anything. `Production::define` and
+/// `Production::nonterminal` take and give a `Nonterminal` instead, and are
+/// preferred over `from_parts`. The public `lhs` field is deprecated in favor
+/// of `Production::lhs` and `Production::set_lhs`, and will become private
+/// in a later release.
+///
+/// A production may carry annotations, pairs of a key and a value like the
+/// id of the rule of a specification it was transcribed from. They aren't
+/// part of the grammar, so they're neither printed nor compared, and are
+/// kept by the transformations of a grammar for the productions they keep.
+///
+/// Productions are ordered by their lhs, then lexicographically by their
+/// alternatives, ignoring annotations as equality does.
+#[derive(Clone, Debug)]
 pub struct Production {
+    #[deprecated(
+        note = "read the lhs with `Production::lhs` or `Production::nonterminal`, \
+                and set it with `Production::set_lhs`"
+    )]
     pub lhs: Term,
     rhs: Vec<Expression>,
+    annotations: BTreeMap<String, String>,
 }
 
 impl Production {
     /// Construct a new `Production`
     pub fn new() -> Production {
-        Production {
-            lhs: Term::Nonterminal(String::new()),
-            rhs: vec![],
-        }
+        Production::from_parts(Term::Nonterminal(String::new()), vec![])
     }
 
     /// Construct an `Production` from `Expression`s
+    #[allow(deprecated)]
     pub fn from_parts(t: Term, e: Vec<Expression>) -> Production {
-        Production { lhs: t, rhs: e }
+        Production {
+            lhs: t,
+            rhs: e,
+            annotations: BTreeMap::new(),
+        }
+    }
+
+    /// Construct a `Production` defining `lhs` by `Expression`s, which unlike
+    /// `Production::from_parts` can't be given a terminal to define
+    pub fn define(lhs: Nonterminal, rhs: Vec<Expression>) -> Production {
+        Production::from_parts(Term::from(lhs), rhs)
+    }
+
+    /// Get the lhs of the `Production`, the `Term` it defines
+    #[allow(deprecated)]
+    pub fn lhs(&self) -> &Term {
+        &self.lhs
+    }
+
+    /// Set the nonterminal the `Production` defines
+    ///
+    /// # Example
+    ///
+    /// ```
+    /// extern crate bnf;
+    /// use bnf::{Nonterminal, Production};
+    ///
+    /// fn main() {
+    ///     let mut production = Production::from_str("<bit> ::= \"0\" | \"1\"").unwrap();
+    ///     production.set_lhs(Nonterminal::from("digit"));
+    ///
+    ///     assert_eq!(production.to_string(), "<digit> ::= \"0\" | \"1\"");
+    /// }
+    /// ```
+    #[allow(deprecated)]
+    pub fn set_lhs(&mut self, lhs: Nonterminal) {
+        self.lhs = Term::from(lhs);
+    }
+
+    /// Get the nonterminal the `Production` defines, or `None` when its lhs
+    /// is some other `Term`
+    pub fn nonterminal(&self) -> Option<Nonterminal> {
+        match *self.lhs() {
+            Term::Nonterminal(ref name) => Some(Nonterminal(name.clone())),
+            _ => None,
+        }
     }
 
     // Get `Production` by parsing a string
     pub fn from_str(s: &str) -> Result<Self, Error> {
         match parsers::production_complete(s.as_bytes()) {
-            Result::Ok((_, o)) => Ok(o),
-            Result::Err(e) => Err(Error::from(e)),
+            Result::Ok((_, o)) => parsers::reject_groups(o.rhs_iter()).map(|_| o),
+            Result::Err(e) => Err(parsers::parse_error(s.as_bytes(), e)),
         }
     }
 
@@ -54,14 +120,14 @@ impl Production {
     }
 
     /// Get iterator of the `Production`'s right hand side `Expression`s
-    pub fn rhs_iter(&self) -> Iter {
+    pub fn rhs_iter(&self) -> Iter<'_> {
         Iter {
             iterator: self.rhs.iter(),
         }
     }
 
     /// Get mutable iterator of the `Production`'s right hand side `Expression`s
-    pub fn rhs_iter_mut(&mut self) -> IterMut {
+    pub fn rhs_iter_mut(&mut self) -> IterMut<'_> {
         IterMut {
             iterator: self.rhs.iter_mut(),
         }
@@ -75,6 +141,80 @@ impl Production {
     pub fn is_empty(&self) -> bool {
         self.rhs.is_empty()
     }
+
+    /// Get the value of the annotation `key`, if the `Production` has one
+    ///
+    /// # Example
+    ///
+    /// ```
+    /// extern crate bnf;
+    /// use bnf::Production;
+    ///
+    /// fn main() {
+    ///     let mut production = Production::from_str("<digit> ::= [0-9]").unwrap();
+    ///     production.set_annotation("rule", "RFC 5234 B.1");
+    ///
+    ///     assert_eq!(production.annotation("rule"), Some("RFC 5234 B.1"));
+    ///     assert_eq!(production.annotation("deprecated"), None);
+    ///     assert_eq!(production, Production::from_str("<digit> ::= [0-9]").unwrap());
+    /// }
+    /// ```
+    pub fn annotation(&self, key: &str) -> Option<&str> {
+        self.annotations.get(key).map(String::as_str)
+    }
+
+    /// Annotate the `Production` with `value` for `key`, getting the value it
+    /// replaced, if any
+    pub fn set_annotation(&mut self, key: &str, value: &str) -> Option<String> {
+        self.annotations
+            .insert(String::from(key), String::from(value))
+    }
+
+    /// Remove the annotation `key`, getting its value, if any
+    pub fn remove_annotation(&mut self, key: &str) -> Option<String> {
+        self.annotations.remove(key)
+    }
+
+    /// Get iterator of the `Production`'s annotations, as pairs of a key and
+    /// a value in order of their keys
+    pub fn annotations_iter(&self) -> impl Iterator<Item = (&str, &str)> {
+        self.annotations
+            .iter()
+            .map(|(key, value)| (key.as_str(), value.as_str()))
+    }
+
+    /// Give the `Production` the annotations of `other`, which it replaces
+    pub(crate) fn copy_annotations(&mut self, other: &Production) {
+        self.annotations = other.annotations.clone();
+    }
+}
+
+// annotations aren't part of the grammar, so they're ignored
+impl PartialEq for Production {
+    fn eq(&self, other: &Production) -> bool {
+        self.lhs() == other.lhs() && self.rhs == other.rhs
+    }
+}
+
+impl Eq for Production {}
+
+impl PartialOrd for Production {
+    fn partial_cmp(&self, other: &Production) -> Option<Ordering> {
+        Some(self.cmp(other))
+    }
+}
+
+impl Ord for Production {
+    fn cmp(&self, other: &Production) -> Ordering {
+        (self.lhs(), &self.rhs).cmp(&(other.lhs(), &other.rhs))
+    }
+}
+
+impl Hash for Production {
+    fn hash<H: Hasher>(&self, state: &mut H) {
+        self.lhs().hash(state);
+        self.rhs.hash(state);
+    }
 }
 
 impl Default for Production {
@@ -88,10 +228,13 @@ impl fmt::Display for Production {
         write!(
             f,
             "{} ::= {}",
-            self.lhs.to_string(),
+            self.lhs(),
             self.rhs
                 .iter()
-                .map(|s| s.to_string())
+                .map(|expression| match f.alternate() {
+                    true => format!("{:#}", expression),
+                    false => expression.to_string(),
+                })
                 .collect::<Vec<_>>()
                 .join(" | ")
         )
@@ -140,18 +283,15 @@ mod tests {
 
     impl Arbitrary for Production {
         fn arbitrary<G: Gen>(g: &mut G) -> Self {
-            let lhs_str = String::arbitrary(g)
-                .chars()
-                .filter(|&c| (c != '>'))
-                .collect();
+            let lhs_str = String::arbitrary(g).chars().filter(|&c| c != '>').collect();
 
             let lhs = Term::Nonterminal(lhs_str);
 
             let mut rhs = Vec::<Expression>::arbitrary(g);
-            if rhs.len() < 1 {
+            if rhs.is_empty() {
                 rhs.push(Expression::arbitrary(g));
             }
-            Production { lhs, rhs }
+            Production::from_parts(lhs, rhs)
         }
     }
 
@@ -164,6 +304,50 @@ mod tests {
         }
     }
 
+    #[test]
+    fn annotations() {
+        let mut prod = Production::from_str("<bit> ::= \"0\" | \"1\"").unwrap();
+        let plain = prod.clone();
+        assert_eq!(prod.set_annotation("rule", "B.1"), None);
+        assert_eq!(
+            prod.set_annotation("rule", "B.2"),
+            Some(String::from("B.1"))
+        );
+        prod.set_annotation("deprecated", "");
+        assert_eq!(prod.annotation("rule"), Some("B.2"));
+        assert_eq!(
+            prod.annotations_iter().collect::<Vec<_>>(),
+            vec![("deprecated", ""), ("rule", "B.2")]
+        );
+
+        // annotations aren't printed or compared
+        assert_eq!(prod.to_string(), plain.to_string());
+        assert_eq!(prod, plain);
+        let mut set = ::std::collections::HashSet::new();
+        set.insert(plain);
+        assert!(set.contains(&prod));
+
+        assert_eq!(prod.clone().annotation("deprecated"), Some(""));
+        assert_eq!(prod.remove_annotation("deprecated"), Some(String::new()));
+        assert_eq!(prod.remove_annotation("deprecated"), None);
+    }
+
+    #[test]
+    fn defines_nonterminals() {
+        let expr = Expression::from_str("\"0\" \"1\"").unwrap();
+        let bit = Nonterminal(String::from("bit"));
+        let prod = Production::define(bit.clone(), vec![expr.clone()]);
+        assert_eq!(prod, Production::from_str("<bit> ::= \"0\" \"1\"").unwrap());
+        assert_eq!(prod.nonterminal(), Some(bit.clone()));
+
+        let mut prod = Production::from_parts(Term::Terminal(String::from("bit")), vec![expr]);
+        assert_eq!(prod.nonterminal(), None);
+        assert_eq!(*prod.lhs(), Term::Terminal(String::from("bit")));
+        prod.set_lhs(bit.clone());
+        assert_eq!(*prod.lhs(), Term::Nonterminal(String::from("bit")));
+        assert_eq!(prod.nonterminal(), Some(bit));
+    }
+
     #[test]
     fn to_string_and_back() {
         QuickCheck::new()
@@ -181,13 +365,13 @@ mod tests {
         ]);
         let p1: Production = Production::from_parts(lhs1, vec![rhs1]);
 
-        let lhs2: Term = Term::Nonterminal(String::from("STRING A"));
+        let lhs2 = Nonterminal::from("STRING A");
         let rhs2: Expression = Expression::from_parts(vec![
             Term::Terminal(String::from("STRING B")),
             Term::Nonterminal(String::from("STRING C")),
         ]);
         let mut p2: Production = Production::new();
-        p2.lhs = lhs2;
+        p2.set_lhs(lhs2);
         p2.add_to_rhs(rhs2);
 
         assert_eq!(p1, p2);
@@ -306,4 +490,28 @@ mod tests {
             e => panic!("invalid production should be parsing error: {:?}", e),
         }
     }
+
+    #[test]
+    fn orders_productions() {
+        let production = |text: &str| Production::from_str(text).unwrap();
+        let mut annotated = production("<a> ::= \"b\"");
+        annotated.set_annotation("rule", "1");
+        let mut productions = vec![
+            production("<b> ::= \"a\""),
+            production("<a> ::= \"b\" | \"a\""),
+            production("<a> ::= \"b\""),
+            production("<a> ::= \"a\" | \"b\""),
+        ];
+        productions.sort();
+        assert_eq!(
+            productions,
+            vec![
+                production("<a> ::= \"a\" | \"b\""),
+                production("<a> ::= \"b\""),
+                production("<a> ::= \"b\" | \"a\""),
+                production("<b> ::= \"a\""),
+            ]
+        );
+        assert_eq!(annotated.cmp(&productions[1]), Ordering::Equal);
+    }
 }
@@ -0,0 +1,516 @@
+use grammar::Grammar;
+use html::escape;
+use std::fmt::Write;
+use term::{Nonterminal, Term};
+
+const CHAR_WIDTH: usize = 8;
+const BOX_PADDING: usize = 10;
+const BOX_HEIGHT: usize = 22;
+const GAP: usize = 20;
+const ARC: usize = 10;
+const ROW_GAP: usize = 10;
+const MARGIN: usize = 20;
+const TITLE_HEIGHT: usize = 20;
+
+/// A node of a railroad (syntax) diagram
+#[derive(Clone, Debug, Eq, Hash, PartialEq)]
+pub enum RailroadNode {
+    /// Text to be matched, drawn as a rounded box
+    Terminal(String),
+    /// A reference to another rule, drawn as a square box
+    Nonterminal(String),
+    /// A path matching nothing
+    Skip,
+    /// Nodes followed one after another
+    Sequence(Vec<RailroadNode>),
+    /// Alternative paths, the first one drawn on the main line
+    Choice(Vec<RailroadNode>),
+    /// A node matched one or more times, drawn with a path back around it
+    Loop(Box<RailroadNode>),
+}
+
+impl RailroadNode {
+    /// Get the horizontal space the node takes up
+    pub fn width(&self) -> usize {
+        match *self {
+            RailroadNode::Terminal(ref text) | RailroadNode::Nonterminal(ref text) => {
+                text.chars().count() * CHAR_WIDTH + 2 * BOX_PADDING
+            }
+            RailroadNode::Skip => GAP,
+            RailroadNode::Sequence(ref nodes) => {
+                nodes.iter().map(|node| node.width()).sum::<usize>()
+                    + GAP * nodes.len().saturating_sub(1)
+            }
+            RailroadNode::Choice(ref nodes) => {
+                nodes.iter().map(|node| node.width()).max().unwrap_or(0) + 4 * ARC
+            }
+            RailroadNode::Loop(ref node) => node.width() + 2 * ARC,
+        }
+    }
+
+    /// Get the vertical space the node takes up above its main line
+    pub fn height_above(&self) -> usize {
+        match *self {
+            RailroadNode::Terminal(_) | RailroadNode::Nonterminal(_) => BOX_HEIGHT / 2,
+            RailroadNode::Skip => 0,
+            RailroadNode::Sequence(ref nodes) => nodes
+                .iter()
+                .map(|node| node.height_above())
+                .max()
+                .unwrap_or(0),
+            RailroadNode::Choice(ref nodes) => nodes.first().map_or(0, |node| node.height_above()),
+            RailroadNode::Loop(ref node) => node.height_above(),
+        }
+    }
+
+    /// Get the vertical space the node takes up below its main line
+    pub fn height_below(&self) -> usize {
+        match *self {
+            RailroadNode::Terminal(_) | RailroadNode::Nonterminal(_) => BOX_HEIGHT / 2,
+            RailroadNode::Skip => 0,
+            RailroadNode::Sequence(ref nodes) => nodes
+                .iter()
+                .map(|node| node.height_below())
+                .max()
+                .unwrap_or(0),
+            RailroadNode::Choice(ref nodes) => match nodes.last() {
+                Some(last) => row_offsets(nodes).last().unwrap_or(&0) + last.height_below(),
+                None => 0,
+            },
+            RailroadNode::Loop(ref node) => loop_offset(node),
+        }
+    }
+
+    /// Draw the node as SVG, entering its main line from the left at `(x, y)`
+    fn render(&self, x: usize, y: usize, svg: &mut String) {
+        match *self {
+            RailroadNode::Terminal(ref text) | RailroadNode::Nonterminal(ref text) => {
+                let width = self.width();
+                let (class, radius) = match *self {
+                    RailroadNode::Terminal(_) => ("terminal", BOX_HEIGHT / 2),
+                    _ => ("nonterminal", 0),
+                };
+                let _ = writeln!(
+                    svg,
+                    "<g class=\"{}\"><rect x=\"{}\" y=\"{}\" width=\"{}\" height=\"{}\" rx=\"{}\"/>\
+                     <text x=\"{}\" y=\"{}\">{}</text></g>",
+                    class,
+                    x,
+                    y - BOX_HEIGHT / 2,
+                    width,
+                    BOX_HEIGHT,
+                    radius,
+                    x + width / 2,
+                    y + 4,
+                    escape(text)
+                );
+            }
+            RailroadNode::Skip => line(x, y, x + GAP, svg),
+            RailroadNode::Sequence(ref nodes) => {
+                let mut x = x;
+                for (index, node) in nodes.iter().enumerate() {
+                    if index > 0 {
+                        line(x, y, x + GAP, svg);
+                        x += GAP;
+                    }
+                    node.render(x, y, svg);
+                    x += node.width();
+                }
+            }
+            RailroadNode::Choice(ref nodes) => {
+                let width = self.width();
+                let inner = width - 4 * ARC;
+                for (index, (node, offset)) in nodes.iter().zip(row_offsets(nodes)).enumerate() {
+                    let row_y = y + offset;
+                    if index == 0 {
+                        line(x, y, x + 2 * ARC, svg);
+                    } else {
+                        let _ = writeln!(
+                            svg,
+                            "<path d=\"M{} {} q{} 0 {} {} v{} q0 {} {} {}\"/>",
+                            x,
+                            y,
+                            ARC,
+                            ARC,
+                            ARC,
+                            row_y - y - 2 * ARC,
+                            ARC,
+                            ARC,
+                            ARC
+                        );
+                    }
+                    node.render(x + 2 * ARC, row_y, svg);
+
+                    let end = x + 2 * ARC + node.width();
+                    line(end, row_y, x + 2 * ARC + inner, svg);
+                    if index == 0 {
+                        line(x + width - 2 * ARC, y, x + width, svg);
+                    } else {
+                        let _ = writeln!(
+                            svg,
+                            "<path d=\"M{} {} q{} 0 {} -{} v-{} q0 -{} {} -{}\"/>",
+                            x + width - 2 * ARC,
+                            row_y,
+                            ARC,
+                            ARC,
+                            ARC,
+                            row_y - y - 2 * ARC,
+                            ARC,
+                            ARC,
+                            ARC
+                        );
+                    }
+                }
+            }
+            RailroadNode::Loop(ref node) => {
+                let width = self.width();
+                let offset = loop_offset(node);
+                line(x, y, x + ARC, svg);
+                node.render(x + ARC, y, svg);
+                line(x + ARC + node.width(), y, x + width, svg);
+                let _ = writeln!(
+                    svg,
+                    "<path d=\"M{} {} q{} 0 {} {} v{} q0 {} -{} {} h-{} q-{} 0 -{} -{} v-{} q0 -{} {} -{}\"/>",
+                    x + width - ARC,
+                    y,
+                    ARC,
+                    ARC,
+                    ARC,
+                    offset - 2 * ARC,
+                    ARC,
+                    ARC,
+                    ARC,
+                    width - 2 * ARC,
+                    ARC,
+                    ARC,
+                    ARC,
+                    offset - 2 * ARC,
+                    ARC,
+                    ARC,
+                    ARC
+                );
+            }
+        }
+    }
+}
+
+/// Get how far below the main line each alternative of a choice is drawn,
+/// leaving room for the arcs into and out of the row
+fn row_offsets(nodes: &[RailroadNode]) -> Vec<usize> {
+    let mut offsets = Vec::with_capacity(nodes.len());
+    let mut offset = 0;
+    for (index, node) in nodes.iter().enumerate() {
+        if index > 0 {
+            let spacing = nodes[index - 1].height_below() + ROW_GAP + node.height_above();
+            offset += spacing.max(2 * ARC);
+        }
+        offsets.push(offset);
+    }
+    offsets
+}
+
+/// Get how far below the main line the path back around a loop is drawn
+fn loop_offset(node: &RailroadNode) -> usize {
+    (node.height_below() + ROW_GAP).max(2 * ARC)
+}
+
+fn line(from: usize, y: usize, to: usize, svg: &mut String) {
+    if to > from {
+        let _ = writeln!(svg, "<path d=\"M{} {} h{}\"/>", from, y, to - from);
+    }
+}
+
+fn term_node(term: &Term) -> RailroadNode {
+    match *term {
+        Term::Terminal(ref t) | Term::CaselessTerminal(ref t) if t.is_empty() => RailroadNode::Skip,
+        Term::Terminal(ref t) | Term::CaselessTerminal(ref t) => RailroadNode::Terminal(t.clone()),
+        Term::Nonterminal(ref nt) => RailroadNode::Nonterminal(nt.clone()),
+        Term::CharClass(_) | Term::TerminalBytes(_) | Term::Regex(_) => {
+            RailroadNode::Terminal(term.to_string())
+        }
+        Term::Repeat {
+            ref inner,
+            min,
+            max,
+        } => {
+            let copy = sequence(inner.terms_iter().map(term_node).collect());
+            let mut nodes = vec![copy.clone(); min];
+            match max {
+                Some(max) => nodes.extend(
+                    (min..max)
+                        .map(|_| RailroadNode::Choice(vec![copy.clone(), RailroadNode::Skip])),
+                ),
+                // the last required copy loops, or else the loop is skipped
+                None => match nodes.pop() {
+                    Some(_) => nodes.push(RailroadNode::Loop(Box::new(copy))),
+                    None => nodes.push(RailroadNode::Choice(vec![
+                        RailroadNode::Loop(Box::new(copy)),
+                        RailroadNode::Skip,
+                    ])),
+                },
+            }
+            sequence(nodes)
+        }
+    }
+}
+
+/// Join nodes into one, leaving out any paths matching nothing and splicing
+/// in the nodes of any sequence
+fn sequence(nodes: Vec<RailroadNode>) -> RailroadNode {
+    let mut nodes = nodes
+        .into_iter()
+        .flat_map(|node| match node {
+            RailroadNode::Sequence(nodes) => nodes,
+            RailroadNode::Skip => vec![],
+            node => vec![node],
+        })
+        .collect::<Vec<_>>();
+    match nodes.len() {
+        0 => RailroadNode::Skip,
+        1 => nodes.remove(0),
+        _ => RailroadNode::Sequence(nodes),
+    }
+}
+
+impl Grammar {
+    /// Get the railroad diagram of each nonterminal reachable from `start`,
+    /// `start` first and the rest in grammar order.
+    ///
+    /// The alternatives of every production sharing a lhs are combined into
+    /// one diagram.
+    pub fn railroad_nodes(&self, start: impl Into<Nonterminal>) -> Vec<(String, RailroadNode)> {
+        let subgrammar = self.subgrammar(start);
+
+        let mut rules: Vec<(String, Vec<RailroadNode>)> = vec![];
+        for production in subgrammar.productions_iter() {
+            let name = match *production.lhs() {
+                Term::Nonterminal(ref nt) => nt,
+                _ => continue,
+            };
+            let alternatives = production
+                .rhs_iter()
+                .map(|expression| sequence(expression.terms_iter().map(term_node).collect()));
+
+            match rules.iter().position(|(rule, _)| rule == name) {
+                Some(index) => rules[index].1.extend(alternatives),
+                None => rules.push((name.clone(), alternatives.collect())),
+            }
+        }
+
+        rules
+            .into_iter()
+            .map(|(name, mut alternatives)| {
+                let node = match alternatives.len() {
+                    0 => RailroadNode::Skip,
+                    1 => alternatives.remove(0),
+                    _ => RailroadNode::Choice(alternatives),
+                };
+                (name, node)
+            })
+            .collect()
+    }
+
+    /// Draw the nonterminals reachable from `start` as railroad diagrams, in a
+    /// single SVG document.
+    ///
+    /// # Example
+    ///
+    /// ```rust
+    /// extern crate bnf;
+    /// use bnf::Grammar;
+    ///
+    /// fn main() {
+    ///     let grammar = Grammar::from_str("<dna> ::= <base> | <base> <dna>
+    ///         <base> ::= \"A\" | \"C\" | \"G\" | \"T\"").unwrap();
+    ///     let svg = grammar.to_railroad_diagram("dna");
+    ///
+    ///     assert!(svg.starts_with("<svg"));
+    ///     assert!(svg.contains(">base</text>"));
+    /// }
+    /// ```
+    pub fn to_railroad_diagram(&self, start: impl Into<Nonterminal>) -> String {
+        let rules = self.railroad_nodes(start);
+
+        let mut body = String::new();
+        let mut width = 0;
+        let mut y = MARGIN;
+        for (name, node) in &rules {
+            let _ = writeln!(
+                body,
+                "<text class=\"rule\" x=\"{}\" y=\"{}\">{}</text>",
+                MARGIN,
+                y + TITLE_HEIGHT / 2,
+                escape(name)
+            );
+            y += TITLE_HEIGHT + node.height_above();
+
+            // rules begin and end with a short bar across the main line
+            let _ = writeln!(body, "<path d=\"M{} {} v{}\"/>", MARGIN, y - ARC, 2 * ARC);
+            line(MARGIN, y, MARGIN + GAP, &mut body);
+            node.render(MARGIN + GAP, y, &mut body);
+            let end = MARGIN + GAP + node.width();
+            line(end, y, end + GAP, &mut body);
+            let _ = writeln!(
+                body,
+                "<path d=\"M{} {} v{}\"/>",
+                end + GAP,
+                y - ARC,
+                2 * ARC
+            );
+
+            width = width.max(end + GAP + MARGIN);
+            y += node.height_below() + MARGIN;
+        }
+
+        let mut svg = String::new();
+        let _ = writeln!(
+            svg,
+            "<svg xmlns=\"http://www.w3.org/2000/svg\" width=\"{}\" height=\"{}\" viewBox=\"0 0 {} {}\">",
+            width, y, width, y
+        );
+        svg.push_str(
+            "<style>path, rect { fill: none; stroke: black; stroke-width: 2; } \
+             text { font: 14px monospace; text-anchor: middle; } \
+             text.rule { font-weight: bold; text-anchor: start; }</style>\n",
+        );
+        svg.push_str(&body);
+        svg.push_str("</svg>\n");
+        svg
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn terminal(text: &str) -> RailroadNode {
+        RailroadNode::Terminal(String::from(text))
+    }
+
+    fn nonterminal(name: &str) -> RailroadNode {
+        RailroadNode::Nonterminal(String::from(name))
+    }
+
+    #[test]
+    fn nodes_of_reachable_rules() {
+        let grammar = Grammar::from_str(
+            "<unused> ::= \"x\"
+            <dna> ::= <base> | <base> <dna>
+            <base> ::= \"A\" | \"C\" | [GT] | \"\"",
+        )
+        .unwrap();
+        let rules = grammar.railroad_nodes("dna");
+
+        assert_eq!(
+            rules,
+            vec![
+                (
+                    String::from("dna"),
+                    RailroadNode::Choice(vec![
+                        nonterminal("base"),
+                        RailroadNode::Sequence(vec![nonterminal("base"), nonterminal("dna")]),
+                    ])
+                ),
+                (
+                    String::from("base"),
+                    RailroadNode::Choice(vec![
+                        terminal("A"),
+                        terminal("C"),
+                        terminal("[GT]"),
+                        RailroadNode::Skip,
+                    ])
+                ),
+            ]
+        );
+        assert!(grammar.railroad_nodes("undefined").is_empty());
+    }
+
+    #[test]
+    fn merges_productions_sharing_lhs() {
+        let grammar = Grammar::from_str(
+            "<bit> ::= \"0\"
+            <bit> ::= \"1\"",
+        )
+        .unwrap();
+        assert_eq!(
+            grammar.railroad_nodes("bit"),
+            vec![(
+                String::from("bit"),
+                RailroadNode::Choice(vec![terminal("0"), terminal("1")])
+            )]
+        );
+    }
+
+    #[test]
+    fn nodes_of_repetitions() {
+        let grammar = Grammar::from_str("<s> ::= 2*3\"a\" 0*<b> | 2*( \"c\" \"\" )").unwrap();
+        let optional = |node: RailroadNode| RailroadNode::Choice(vec![node, RailroadNode::Skip]);
+        let repeated = |node: RailroadNode| RailroadNode::Loop(Box::new(node));
+        assert_eq!(
+            grammar.railroad_nodes("s"),
+            vec![(
+                String::from("s"),
+                RailroadNode::Choice(vec![
+                    RailroadNode::Sequence(vec![
+                        terminal("a"),
+                        terminal("a"),
+                        optional(terminal("a")),
+                        optional(repeated(nonterminal("b"))),
+                    ]),
+                    RailroadNode::Sequence(vec![terminal("c"), repeated(terminal("c"))]),
+                ])
+            )]
+        );
+
+        let looped = repeated(terminal("a"));
+        assert_eq!(looped.width(), terminal("a").width() + 2 * ARC);
+        assert_eq!(looped.height_below(), BOX_HEIGHT / 2 + ROW_GAP);
+        let svg = Grammar::from_str("<s> ::= 1*\"a\"")
+            .unwrap()
+            .to_railroad_diagram("s");
+        assert_eq!(svg.matches("class=\"terminal\"").count(), 1);
+    }
+
+    #[test]
+    fn node_dimensions() {
+        let a = terminal("a");
+        assert_eq!(a.width(), CHAR_WIDTH + 2 * BOX_PADDING);
+        assert_eq!(a.height_above() + a.height_below(), BOX_HEIGHT);
+
+        let sequence = RailroadNode::Sequence(vec![a.clone(), a.clone()]);
+        assert_eq!(sequence.width(), 2 * a.width() + GAP);
+        assert_eq!(sequence.height_below(), BOX_HEIGHT / 2);
+
+        let choice = RailroadNode::Choice(vec![sequence.clone(), a.clone(), RailroadNode::Skip]);
+        assert_eq!(choice.width(), sequence.width() + 4 * ARC);
+        assert_eq!(choice.height_above(), BOX_HEIGHT / 2);
+        assert_eq!(
+            choice.height_below(),
+            BOX_HEIGHT / 2 + (ROW_GAP + BOX_HEIGHT) + ROW_GAP
+        );
+    }
+
+    #[test]
+    fn svg_diagram() {
+        let grammar = Grammar::from_str(
+            "<expr> ::= <term> \"<\" <term> | <term>
+            <term> ::= '\"' | \"&\"",
+        )
+        .unwrap();
+        let svg = grammar.to_railroad_diagram("expr");
+
+        assert!(svg.starts_with("<svg xmlns=\"http://www.w3.org/2000/svg\""));
+        assert!(svg.ends_with("</svg>\n"));
+        assert!(svg.contains("<text class=\"rule\" x=\"20\" y=\"30\">expr</text>"));
+        assert!(svg.contains(">term</text>"));
+        assert!(svg.contains(">&lt;</text>"));
+        assert!(svg.contains(">&quot;</text>"));
+        assert!(svg.contains(">&amp;</text>"));
+        assert_eq!(svg.matches("class=\"terminal\"").count(), 3);
+        assert_eq!(svg.matches("class=\"nonterminal\"").count(), 3);
+
+        let empty = Grammar::new().to_railroad_diagram("expr");
+        assert!(empty.starts_with("<svg"));
+        assert!(!empty.contains("<g"));
+    }
+}
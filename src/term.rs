@@ -1,23 +1,545 @@
 #![allow(clippy::should_implement_trait)]
 
 use error::Error;
+use expression::Expression;
 use parsers;
+use rand::Rng;
+#[cfg(feature = "regex")]
+use regex_automata::{meta::Regex, MatchKind};
+use std::borrow::Cow;
+use std::convert::TryFrom;
 use std::fmt;
 use std::str::FromStr;
 
-/// A Term can represent a Terminal or Nonterminal node
-#[derive(Clone, Debug, Eq, Hash, PartialEq)]
+/// A Term can represent a Terminal or Nonterminal node, or a class of single
+/// character terminals.
+///
+/// A `CaselessTerminal`, written with ABNF's `%i` prefix like `%i"select"`,
+/// matches its text in any case, and generates it as written. The `%s`
+/// prefix of a case-sensitive terminal is accepted too, and parses to a
+/// plain `Terminal`.
+///
+/// A `TerminalBytes` is a terminal of raw bytes, which need not be UTF-8,
+/// written in hexadecimal like `%xDE.AD.BE.EF`. Only `Grammar::generate_bytes`
+/// generates those which aren't UTF-8.
+///
+/// A `Regex` is a terminal of the text a regular expression matches, written
+/// like `#"[a-z_][a-z0-9_]*"#`, which only parses with the feature `regex`.
+/// Parsing input matches the longest text the expression matches where it
+/// is, never a shorter one, so `#"a+"# "a"` matches no input. Generating
+/// from one fails.
+///
+/// A `Repeat` is from `min` to `max` of the terms of its inner expression in
+/// a row, or at least `min` of them without a `max`, written with an ABNF
+/// count like `2*4"x"`, `2*"x"` or `3"x"`, or with a postfix `*` or `+`
+/// for a `min` of 0 or 1 without a `max`, like `"x"*`. An inner expression
+/// of several terms is written parenthesized, like `2*4( "x" "y" )`.
+///
+/// Terms are ordered by their kind first, in the order the variants are
+/// declared: terminals before nonterminals, before classes, caseless
+/// terminals, bytes, regexes and repetitions. Terms of a kind are ordered by
+/// their text, bytes or class, and repetitions by their inner expression and
+/// then their bounds. The order is stable across releases, a new kind
+/// of term being ordered after the others.
+#[derive(Clone, Debug, Eq, Hash, Ord, PartialEq, PartialOrd)]
 pub enum Term {
     Terminal(String),
     Nonterminal(String),
+    CharClass(CharClass),
+    CaselessTerminal(String),
+    TerminalBytes(Vec<u8>),
+    Regex(String),
+    Repeat {
+        inner: Box<Expression>,
+        min: usize,
+        max: Option<usize>,
+    },
+}
+
+/// The name of a nonterminal, without its angle brackets, for APIs which
+/// only make sense of a nonterminal, like the lhs of `Production::define`.
+///
+/// It converts to a `Term::Nonterminal` and back, the conversion of any
+/// other `Term` failing with the term itself, and displays as `<name>`. APIs
+/// taking the start symbol of a derivation take an `Into<Nonterminal>`, so
+/// a name like `"expr"` converts to one too, or a `StartSymbol` where they
+/// took a `&Term` before.
+#[derive(Clone, Debug, Eq, Hash, Ord, PartialEq, PartialOrd)]
+pub struct Nonterminal(pub String);
+
+/// The start symbol of a derivation, for APIs which took it as a `&Term`:
+/// a `&Term` as before, or a `Nonterminal` or anything converting to one,
+/// like a name. A term which isn't a nonterminal derives only itself.
+pub trait StartSymbol {
+    /// Get the term the derivation starts from
+    fn into_term(self) -> Term;
+}
+
+impl StartSymbol for &Term {
+    fn into_term(self) -> Term {
+        self.clone()
+    }
+}
+
+impl<T: Into<Nonterminal>> StartSymbol for T {
+    fn into_term(self) -> Term {
+        Term::from(self.into())
+    }
+}
+
+/// The text of a case-sensitive terminal, which converts to a
+/// `Term::Terminal` and back, and displays quoted as the term does
+#[derive(Clone, Debug, Eq, Hash, Ord, PartialEq, PartialOrd)]
+pub struct Terminal(pub String);
+
+impl Nonterminal {
+    /// Get the name of the nonterminal
+    pub fn name(&self) -> &str {
+        &self.0
+    }
+}
+
+impl Terminal {
+    /// Get the text of the terminal
+    pub fn text(&self) -> &str {
+        &self.0
+    }
+}
+
+impl From<&str> for Nonterminal {
+    fn from(name: &str) -> Nonterminal {
+        Nonterminal(String::from(name))
+    }
+}
+
+impl From<String> for Nonterminal {
+    fn from(name: String) -> Nonterminal {
+        Nonterminal(name)
+    }
+}
+
+impl From<&Nonterminal> for Nonterminal {
+    fn from(nonterminal: &Nonterminal) -> Nonterminal {
+        nonterminal.clone()
+    }
+}
+
+impl From<Nonterminal> for Term {
+    fn from(nonterminal: Nonterminal) -> Term {
+        Term::Nonterminal(nonterminal.0)
+    }
+}
+
+impl From<Terminal> for Term {
+    fn from(terminal: Terminal) -> Term {
+        Term::Terminal(terminal.0)
+    }
+}
+
+impl TryFrom<Term> for Nonterminal {
+    type Error = Term;
+
+    fn try_from(term: Term) -> Result<Nonterminal, Term> {
+        match term {
+            Term::Nonterminal(name) => Ok(Nonterminal(name)),
+            term => Err(term),
+        }
+    }
+}
+
+impl TryFrom<Term> for Terminal {
+    type Error = Term;
+
+    fn try_from(term: Term) -> Result<Terminal, Term> {
+        match term {
+            Term::Terminal(text) => Ok(Terminal(text)),
+            term => Err(term),
+        }
+    }
+}
+
+impl PartialEq<Term> for Nonterminal {
+    fn eq(&self, term: &Term) -> bool {
+        match *term {
+            Term::Nonterminal(ref name) => *name == self.0,
+            _ => false,
+        }
+    }
+}
+
+impl PartialEq<Nonterminal> for Term {
+    fn eq(&self, nonterminal: &Nonterminal) -> bool {
+        nonterminal == self
+    }
+}
+
+impl PartialEq<Term> for Terminal {
+    fn eq(&self, term: &Term) -> bool {
+        match *term {
+            Term::Terminal(ref text) => *text == self.0,
+            _ => false,
+        }
+    }
+}
+
+impl PartialEq<Terminal> for Term {
+    fn eq(&self, terminal: &Terminal) -> bool {
+        terminal == self
+    }
+}
+
+impl fmt::Display for Nonterminal {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "<{}>", self.0)
+    }
+}
+
+impl fmt::Display for Terminal {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write_quoted(f, &self.0)
+    }
+}
+
+const SURROGATES: (u32, u32) = (0xD800, 0xDFFF);
+
+/// A set of characters written as a bracketed class, like `[a-zA-Z_]`, made
+/// of single characters and inclusive ranges. A negated class, like `[^"]`,
+/// is the set of every other character.
+///
+/// `]`, `\` and `-` are written escaped with a `\` inside a class, and so is
+/// a `^` beginning a class which isn't negated.
+///
+/// Generating from a negated class chooses among the printable ASCII
+/// characters it has, and only when it has none among every character it
+/// has.
+///
+/// Classes are ordered by their ranges as written, a class just before its
+/// negation.
+#[derive(Clone, Debug, Eq, Hash, Ord, PartialEq, PartialOrd)]
+pub struct CharClass {
+    ranges: Vec<(char, char)>,
+    negated: bool,
+}
+
+/// The printable ASCII characters, which generating from a negated class
+/// prefers
+const PRINTABLE: (char, char) = (' ', '~');
+
+impl CharClass {
+    /// Construct a `CharClass` from inclusive ranges, reversed ranges being
+    /// swapped into order
+    pub fn from_ranges(ranges: Vec<(char, char)>) -> CharClass {
+        CharClass {
+            ranges: ranges
+                .into_iter()
+                .map(|(lo, hi)| if lo <= hi { (lo, hi) } else { (hi, lo) })
+                .collect(),
+            negated: false,
+        }
+    }
+
+    /// Construct a negated `CharClass`, of every character outside the
+    /// inclusive ranges
+    pub fn negated(ranges: Vec<(char, char)>) -> CharClass {
+        CharClass {
+            negated: true,
+            ..CharClass::from_ranges(ranges)
+        }
+    }
+
+    /// Construct the class of every character, the wildcard written `%any`,
+    /// which is the negated class of no ranges
+    pub fn any() -> CharClass {
+        CharClass::negated(vec![])
+    }
+
+    /// Whether the class is the wildcard of every character
+    pub fn is_any(&self) -> bool {
+        self.negated && self.ranges.is_empty()
+    }
+
+    /// Get the inclusive ranges of the class, which a negated class has
+    /// every character but
+    pub fn ranges(&self) -> &[(char, char)] {
+        &self.ranges
+    }
+
+    /// Whether the class is negated
+    pub fn is_negated(&self) -> bool {
+        self.negated
+    }
+
+    /// Whether `c` is in the class
+    pub fn contains(&self, c: char) -> bool {
+        self.ranges.iter().any(|&(lo, hi)| lo <= c && c <= hi) != self.negated
+    }
+
+    /// Get the ranges of the characters in the class
+    pub(crate) fn members(&self) -> Cow<'_, [(char, char)]> {
+        if !self.negated {
+            return Cow::Borrowed(&self.ranges);
+        }
+        let mut excluded = self.ranges.clone();
+        excluded.sort();
+        let mut members = vec![];
+        let mut next = Some('\0');
+        for (lo, hi) in excluded {
+            if let Some(from) = next {
+                if from < lo {
+                    members.extend(before(lo).map(|to| (from, to)));
+                }
+            }
+            next = match next {
+                Some(from) if from > hi => Some(from),
+                _ => after(hi),
+            };
+        }
+        members.extend(next.map(|from| (from, ::std::char::MAX)));
+        Cow::Owned(members)
+    }
+
+    /// Get the number of characters in the class, counting characters of
+    /// overlapping ranges once per range
+    pub fn len(&self) -> usize {
+        self.members().iter().map(|&range| range_len(range)).sum()
+    }
+
+    /// Whether the class has no characters
+    pub fn is_empty(&self) -> bool {
+        self.members().is_empty()
+    }
+
+    /// Get the `n`th character of the class, in range order
+    pub(crate) fn nth(&self, n: usize) -> Option<char> {
+        nth(&self.members(), n)
+    }
+
+    /// Get a character of the class, the first printable one of a negated
+    /// class
+    pub(crate) fn example(&self) -> Option<char> {
+        match self.printable() {
+            Some(printable) => nth(&printable, 0),
+            None => self.nth(0),
+        }
+    }
+
+    /// Choose a character of the class at random
+    pub(crate) fn choose<R: Rng>(&self, rng: &mut R) -> Option<char> {
+        let members = match self.printable() {
+            Some(printable) => printable,
+            None => self.members().into_owned(),
+        };
+        let len = members.iter().map(|&range| range_len(range)).sum();
+        if len == 0 {
+            None
+        } else {
+            nth(&members, rng.gen_range(0, len))
+        }
+    }
+
+    /// Get the ranges of the printable ASCII characters of a negated class,
+    /// if it has any
+    fn printable(&self) -> Option<Vec<(char, char)>> {
+        if !self.negated {
+            return None;
+        }
+        let printable = self
+            .members()
+            .iter()
+            .filter(|&&(lo, hi)| lo <= PRINTABLE.1 && hi >= PRINTABLE.0)
+            .map(|&(lo, hi)| (lo.max(PRINTABLE.0), hi.min(PRINTABLE.1)))
+            .collect::<Vec<_>>();
+        if printable.is_empty() {
+            None
+        } else {
+            Some(printable)
+        }
+    }
+}
+
+/// Get the `n`th character of `ranges`, in range order
+fn nth(ranges: &[(char, char)], mut n: usize) -> Option<char> {
+    for &(lo, hi) in ranges {
+        let len = range_len((lo, hi));
+        if n < len {
+            let mut code = lo as u32 + n as u32;
+            if (lo as u32) < SURROGATES.0 && code >= SURROGATES.0 {
+                code += SURROGATES.1 - SURROGATES.0 + 1;
+            }
+            return ::std::char::from_u32(code);
+        }
+        n -= len;
+    }
+    None
+}
+
+/// Get the character before `c`, skipping surrogates
+fn before(c: char) -> Option<char> {
+    match c as u32 {
+        0 => None,
+        code if code == SURROGATES.1 + 1 => ::std::char::from_u32(SURROGATES.0 - 1),
+        code => ::std::char::from_u32(code - 1),
+    }
+}
+
+/// Get the character after `c`, skipping surrogates
+fn after(c: char) -> Option<char> {
+    match c as u32 {
+        code if code == SURROGATES.0 - 1 => ::std::char::from_u32(SURROGATES.1 + 1),
+        code => ::std::char::from_u32(code + 1),
+    }
+}
+
+fn range_len((lo, hi): (char, char)) -> usize {
+    let (lo, hi) = (lo as u32, hi as u32);
+    let mut len = hi - lo + 1;
+    if lo < SURROGATES.0 && hi > SURROGATES.1 {
+        len -= SURROGATES.1 - SURROGATES.0 + 1;
+    }
+    len as usize
+}
+
+/// Get the length of the prefix of `input` which is `text` regardless of case
+pub(crate) fn caseless_prefix(text: &str, input: &str) -> Option<usize> {
+    let mut rest = input.chars();
+    let mut len = 0;
+    for c in text.chars() {
+        let next = rest.next()?;
+        if !c.to_lowercase().eq(next.to_lowercase()) {
+            return None;
+        }
+        len += next.len_utf8();
+    }
+    Some(len)
+}
+
+/// Compile `pattern` to find the longest text it matches where a search
+/// begins, or fail if it isn't a valid regex
+#[cfg(feature = "regex")]
+pub(crate) fn compile_regex(pattern: &str) -> Option<Regex> {
+    Regex::builder()
+        .configure(Regex::config().match_kind(MatchKind::All))
+        .build(pattern)
+        .ok()
+}
+
+/// Whether the regex `pattern` matches all of `text`, never without the
+/// feature `regex`
+#[cfg(feature = "regex")]
+pub(crate) fn regex_matches(pattern: &str, text: &str) -> bool {
+    use regex_automata::{Anchored, Input};
+
+    let input = Input::new(text).anchored(Anchored::Yes);
+    compile_regex(pattern)
+        .and_then(|regex| regex.find(input))
+        .is_some_and(|found| found.end() == text.len())
+}
+
+#[cfg(not(feature = "regex"))]
+pub(crate) fn regex_matches(_pattern: &str, _text: &str) -> bool {
+    false
+}
+
+impl fmt::Display for CharClass {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        // a class beginning like a term is escaped, so it isn't parsed as an
+        // optional group like `[ "a" ]`
+        fn escaped(text: &mut String, c: char, leading: bool) {
+            match c {
+                ']' | '\\' | '-' => text.push('\\'),
+                '"' | '\'' | '%' | '<' | '[' | '(' | 'ε' if leading => text.push('\\'),
+                _ => {}
+            }
+            text.push(c);
+        }
+
+        // `[^]` would be parsed as the class of `^`
+        if self.is_any() {
+            return write!(f, "%any");
+        }
+
+        let mut text = String::from("[");
+        if self.negated {
+            text.push('^');
+        }
+        let mut leading = !self.negated;
+        for (index, &(lo, hi)) in self.ranges.iter().enumerate() {
+            // a class beginning with `^` would be negated
+            if index == 0 && lo == '^' && !self.negated {
+                text.push('\\');
+            }
+            escaped(&mut text, lo, leading);
+            leading = leading && lo == hi && lo.is_whitespace();
+            if lo != hi {
+                text.push('-');
+                escaped(&mut text, hi, false);
+            }
+        }
+        text.push(']');
+
+        // nor is one beginning with a count, like `[2"a"]`, parsed as an
+        // optional repetition
+        let first = text.find(|c: char| c != '[' && !c.is_whitespace());
+        if let Some(first) = first {
+            let counted = text[first..].starts_with(|c: char| c.is_ascii_digit());
+            if !self.negated && counted && parsers::is_group(&text) {
+                text.insert(first, '\\');
+            }
+        }
+        write!(f, "{}", text)
+    }
+}
+
+/// Get `text` as it's written between `quote`s, with `quote`, backslashes
+/// and control characters escaped so that it's all on one line
+fn escape(text: &str, quote: char) -> String {
+    let mut escaped = String::new();
+    for c in text.chars() {
+        match c {
+            '\\' => escaped.push_str("\\\\"),
+            '\n' => escaped.push_str("\\n"),
+            '\t' => escaped.push_str("\\t"),
+            '\r' => escaped.push_str("\\r"),
+            c if c == quote => {
+                escaped.push('\\');
+                escaped.push(c);
+            }
+            c if c.is_control() => escaped.push_str(&format!("\\u{{{:X}}}", c as u32)),
+            c => escaped.push(c),
+        }
+    }
+    escaped
+}
+
+/// Write the text of a terminal between single quotes when it has double
+/// quotes but no single ones, and between double quotes otherwise
+fn write_quoted(f: &mut fmt::Formatter, text: &str) -> fmt::Result {
+    let quote = if text.contains('"') && !text.contains('\'') {
+        '\''
+    } else {
+        '"'
+    };
+    write!(f, "{}{}{}", quote, escape(text, quote), quote)
 }
 
 impl Term {
+    /// Get the text a terminal matches, unless it's bytes which aren't UTF-8
+    /// or not a terminal at all
+    pub(crate) fn terminal_text(&self) -> Option<&str> {
+        match *self {
+            Term::Terminal(ref t) | Term::CaselessTerminal(ref t) => Some(t),
+            Term::TerminalBytes(ref bytes) => ::std::str::from_utf8(bytes).ok(),
+            Term::Nonterminal(_) | Term::CharClass(_) | Term::Regex(_) | Term::Repeat { .. } => {
+                None
+            }
+        }
+    }
+
     // Get `Term` by parsing a string
     pub fn from_str(s: &str) -> Result<Self, Error> {
         match parsers::term_complete(s.as_bytes()) {
             Result::Ok((_, o)) => Ok(o),
-            Result::Err(e) => Err(Error::from(e)),
+            Result::Err(e) => Err(parsers::parse_error(s.as_bytes(), e)),
         }
     }
 }
@@ -29,41 +551,127 @@ impl FromStr for Term {
     }
 }
 
+/// Formats the term as it's parsed, or in the alternate format, like `{:#}`,
+/// a terminal with characters which aren't printable as the ABNF numeric
+/// value of their code points, like `%x0D.0A`, which parses back with
+/// `ParseOptions::numeric_values`
 impl fmt::Display for Term {
     fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
         match *self {
-            Term::Terminal(ref s) => {
-                if s.contains('"') {
-                    write!(f, "'{}'", s)
-                } else {
-                    write!(f, "\"{}\"", s)
+            Term::Terminal(ref s) if f.alternate() && s.chars().any(char::is_control) => {
+                write!(f, "%x")?;
+                for (index, c) in s.chars().enumerate() {
+                    if index > 0 {
+                        write!(f, ".")?;
+                    }
+                    write!(f, "{:02X}", c as u32)?;
                 }
+                Ok(())
+            }
+            Term::Terminal(ref s) | Term::CaselessTerminal(ref s) => {
+                if let Term::CaselessTerminal(_) = *self {
+                    write!(f, "%i")?;
+                }
+                write_quoted(f, s)
             }
             Term::Nonterminal(ref s) => write!(f, "<{}>", s),
+            Term::CharClass(ref class) => write!(f, "{}", class),
+            Term::TerminalBytes(ref bytes) => {
+                write!(f, "%x")?;
+                for (index, byte) in bytes.iter().enumerate() {
+                    if index > 0 {
+                        write!(f, ".")?;
+                    }
+                    write!(f, "{:02X}", byte)?;
+                }
+                Ok(())
+            }
+            Term::Regex(ref pattern) => write!(f, "#\"{}\"#", pattern),
+            Term::Repeat {
+                ref inner,
+                min,
+                max,
+            } => {
+                let (prefix, suffix) = repeat_affixes(min, max);
+                write!(f, "{}", prefix)?;
+                // only a term or a group may be repeated, so the terms of
+                // any other inner expression are grouped
+                match inner.terms_iter().collect::<Vec<_>>()[..] {
+                    [term] if !matches!(*term, Term::Repeat { .. }) => fmt::Display::fmt(term, f)?,
+                    _ => {
+                        write!(f, "( ")?;
+                        fmt::Display::fmt(&**inner, f)?;
+                        write!(f, " )")?;
+                    }
+                }
+                write!(f, "{}", suffix)
+            }
         }
     }
 }
 
+/// Get the count of a `Repeat` as it's written around the repeated term:
+/// `*` after it for zero or more, `+` for one or more, and otherwise a
+/// count before it, like `2*4`
+pub(crate) fn repeat_affixes(min: usize, max: Option<usize>) -> (String, &'static str) {
+    match (min, max) {
+        (0, None) => (String::new(), "*"),
+        (1, None) => (String::new(), "+"),
+        (min, Some(max)) if max == min => (min.to_string(), ""),
+        (min, Some(max)) => (format!("{}*{}", min, max), ""),
+        (min, None) => (format!("{}*", min), ""),
+    }
+}
+
 #[cfg(test)]
 mod tests {
     extern crate quickcheck;
 
     use self::quickcheck::{Arbitrary, Gen, QuickCheck, TestResult};
     use super::*;
+    use expression::Expression;
+    use grammar::Grammar;
+    use production::Production;
 
     impl Arbitrary for Term {
         fn arbitrary<G: Gen>(g: &mut G) -> Self {
+            if u8::arbitrary(g) % 7 == 0 {
+                let mut bytes = Vec::<u8>::arbitrary(g);
+                if bytes.is_empty() {
+                    bytes.push(u8::arbitrary(g));
+                }
+                return Term::TerminalBytes(bytes);
+            }
+            if u8::arbitrary(g) % 3 == 0 {
+                let mut ranges = Vec::<(char, char)>::arbitrary(g);
+                if ranges.is_empty() {
+                    ranges.push(('a', 'z'));
+                }
+                return Term::CharClass(if bool::arbitrary(g) {
+                    CharClass::negated(ranges)
+                } else {
+                    CharClass::from_ranges(ranges)
+                });
+            }
+
             let mut term = String::arbitrary(g);
             if bool::arbitrary(g) {
-                term = term.chars().filter(|&c| (c != '>')).collect();
+                term = term.chars().filter(|&c| c != '>').collect();
                 Term::Nonterminal(term)
             } else {
-                if term.contains('"') {
-                    term = term.chars().filter(|&c| c != '\'').collect();
-                } else if term.contains('\'') {
-                    term = term.chars().filter(|&c| c != '"').collect();
+                // quotes in the text decide which quotes a terminal is
+                // printed between, and both need escaping
+                let quotes = ["", "\"", "'", "'\""][usize::arbitrary(g) % 4];
+                let split = term
+                    .char_indices()
+                    .map(|(i, _)| i)
+                    .nth(usize::arbitrary(g) % 4);
+                term.insert_str(split.unwrap_or(term.len()), quotes);
+                if bool::arbitrary(g) {
+                    Term::Terminal(term)
+                } else {
+                    Term::CaselessTerminal(term)
                 }
-                Term::Terminal(term)
             }
         }
     }
@@ -90,6 +698,137 @@ mod tests {
         );
     }
 
+    #[test]
+    fn parse_case_prefixes() {
+        let select = Term::from_str("%i\"select\"").unwrap();
+        assert_eq!(select, Term::CaselessTerminal(String::from("select")));
+        assert_eq!(select.to_string(), "%i\"select\"");
+        assert_eq!(
+            Term::from_str("%i'say \"hi\"'").unwrap().to_string(),
+            "%i'say \"hi\"'"
+        );
+        assert_eq!(
+            Term::from_str("%s\"Select\""),
+            Ok(Term::Terminal(String::from("Select")))
+        );
+        assert!(Term::from_str("%x\"select\"").is_err());
+        assert!(Term::from_str("%i<select>").is_err());
+    }
+
+    #[cfg(feature = "regex")]
+    #[test]
+    fn parse_regexes() {
+        let ident = Term::from_str("#\"[a-z_][a-z0-9_]*\"#").unwrap();
+        assert_eq!(ident, Term::Regex(String::from("[a-z_][a-z0-9_]*")));
+        assert_eq!(ident.to_string(), "#\"[a-z_][a-z0-9_]*\"#");
+        assert_eq!(
+            Term::from_str("#\"\"[^\"]*\"\"#"),
+            Ok(Term::Regex(String::from("\"[^\"]*\"")))
+        );
+        assert_eq!(ident.terminal_text(), None);
+        for invalid in &["#\"[a-z\"#", "#\"a(\"#", "#\"a\"", "#a#"] {
+            assert!(Term::from_str(invalid).is_err(), "{}", invalid);
+        }
+    }
+
+    #[cfg(not(feature = "regex"))]
+    #[test]
+    fn parse_regexes_only_with_feature() {
+        assert!(Term::from_str("#\"[a-z]+\"#").is_err());
+        assert_eq!(
+            Term::Regex(String::from("[a-z]+")).to_string(),
+            "#\"[a-z]+\"#"
+        );
+    }
+
+    #[test]
+    fn parse_bytes() {
+        let magic = Term::from_str("%xDE.ad.B.EF").unwrap();
+        assert_eq!(magic, Term::TerminalBytes(vec![0xDE, 0xAD, 0x0B, 0xEF]));
+        assert_eq!(magic.to_string(), "%xDE.AD.0B.EF");
+        assert_eq!(Term::from_str("%x00"), Ok(Term::TerminalBytes(vec![0])));
+        for invalid in &["%x", "%x.", "%xDE.", "%x100", "%xDG", "%xDE..AD"] {
+            assert!(Term::from_str(invalid).is_err(), "{}", invalid);
+        }
+        assert_eq!(magic.terminal_text(), None);
+        assert_eq!(
+            Term::TerminalBytes(b"ok".to_vec()).terminal_text(),
+            Some("ok")
+        );
+    }
+
+    #[test]
+    fn parse_negated_classes() {
+        let class = match Term::from_str("[^\"a-c]") {
+            Ok(Term::CharClass(class)) => class,
+            term => panic!("should be a class: {:?}", term),
+        };
+        assert!(class.is_negated());
+        assert_eq!(class.ranges(), &[('"', '"'), ('a', 'c')][..]);
+        assert!(class.contains('d') && class.contains('é') && class.contains('\0'));
+        assert!(!class.contains('b') && !class.contains('"'));
+        assert_eq!(class.len(), 0x110000 - 0x800 - 4);
+        assert_eq!(class.example(), Some(' '));
+        assert_eq!(class.nth(0), Some('\0'));
+        assert_eq!(Term::CharClass(class.clone()).to_string(), "[^\"a-c]");
+
+        // `^` alone, escaped or not first is itself
+        let caret = Term::CharClass(CharClass::from_ranges(vec![('^', '^')]));
+        assert_eq!(Term::from_str("[^]"), Ok(caret.clone()));
+        assert_eq!(caret.to_string(), "[\\^]");
+        assert_eq!(Term::from_str("[\\^a]").unwrap().to_string(), "[\\^a]");
+        assert_eq!(Term::from_str("[a^]").unwrap().to_string(), "[a^]");
+
+        // the negated class of nothing is the wildcard of every character
+        let any = Term::from_str("%any").unwrap();
+        assert_eq!(any, Term::CharClass(CharClass::any()));
+        assert_eq!(any.to_string(), "%any");
+        assert!(CharClass::any().is_any() && !class.is_any());
+        assert!(CharClass::any().contains('\0') && CharClass::any().contains(char::MAX));
+
+        // negated classes of everything but surrogates, or of nothing else
+        let all = CharClass::negated(vec![('\0', '\u{d7ff}'), ('\u{e000}', char::MAX)]);
+        assert!(all.is_empty());
+        assert_eq!(all.example(), None);
+        let ascii = CharClass::negated(vec![(' ', '~')]);
+        assert_eq!(ascii.example(), Some('\0'));
+        let mut rng = ::rand::thread_rng();
+        for _ in 0..100 {
+            let c = class.choose(&mut rng).unwrap();
+            assert!(class.contains(c) && (' '..='~').contains(&c), "{:?}", c);
+            assert!(ascii.contains(ascii.choose(&mut rng).unwrap()));
+        }
+    }
+
+    #[test]
+    fn parse_char_ranges() {
+        let digits = Term::CharClass(CharClass::from_ranges(vec![('0', '9')]));
+        assert_eq!(Term::from_str("\"0\"..\"9\""), Ok(digits.clone()));
+        assert_eq!(Term::from_str("'0' … '9'"), Ok(digits.clone()));
+        assert_eq!(digits.to_string(), "[0-9]");
+        let greek = Term::from_str("\"α\"..\"ω\"").unwrap();
+        assert_eq!(greek.to_string(), "[α-ω]");
+        for invalid in &[
+            "\"9\"..\"0\"",
+            "\"ab\"..\"z\"",
+            "\"a\"..",
+            "%i\"a\"..\"z\"",
+            "\"a\"..<z>",
+        ] {
+            assert!(Term::from_str(invalid).is_err(), "{}", invalid);
+        }
+    }
+
+    #[test]
+    fn caseless_prefixes() {
+        assert_eq!(caseless_prefix("select", "SeLeCt *"), Some(6));
+        assert_eq!(caseless_prefix("ß", "ßa"), Some(2));
+        assert_eq!(caseless_prefix("é", "É"), Some(2));
+        assert_eq!(caseless_prefix("select", "selec"), None);
+        assert_eq!(caseless_prefix("select", "delete"), None);
+        assert_eq!(caseless_prefix("", "x"), Some(0));
+    }
+
     #[test]
     fn parse_error() {
         let incomplete = Term::from_str("<dna");
@@ -154,6 +893,144 @@ mod tests {
         assert_eq!(Ok(Term::Terminal(String::from("'"))), quote_term);
     }
 
+    #[test]
+    fn parse_char_class() {
+        assert_eq!(
+            Ok(Term::CharClass(CharClass::from_ranges(vec![
+                ('a', 'z'),
+                ('A', 'Z'),
+                ('_', '_'),
+            ]))),
+            Term::from_str("[a-zA-Z_]")
+        );
+        assert_eq!(
+            Ok(Term::CharClass(CharClass::from_ranges(vec![
+                (']', ']'),
+                ('\\', '\\'),
+                ('-', '-'),
+                ('+', '+'),
+            ]))),
+            Term::from_str("[\\]\\\\\\-+]")
+        );
+        assert_eq!(
+            Ok(Term::CharClass(CharClass::from_ranges(vec![
+                ('-', '-'),
+                ('a', 'a'),
+                ('-', '-'),
+            ]))),
+            Term::from_str("[-a-]")
+        );
+        assert!(Term::from_str("[]").is_err());
+        assert!(Term::from_str("[z-a]").is_err());
+        assert!(Term::from_str("[0-9").is_err());
+    }
+
+    #[test]
+    fn char_class_matches_digits() {
+        let digits = match Term::from_str("[0-9]") {
+            Ok(Term::CharClass(class)) => class,
+            t => panic!("should be a char class: {:?}", t),
+        };
+        for digit in "0123456789".chars() {
+            assert!(digits.contains(digit), "{} should match", digit);
+        }
+        for other in "/:aZ \u{0660}".chars() {
+            assert!(!digits.contains(other), "{} should not match", other);
+        }
+        assert_eq!(digits.len(), 10);
+        assert_eq!(
+            (0..10).map(|n| digits.nth(n).unwrap()).collect::<String>(),
+            "0123456789"
+        );
+        assert_eq!(digits.nth(10), None);
+    }
+
+    #[test]
+    fn char_class_skips_surrogates() {
+        let class = CharClass::from_ranges(vec![('\u{D7FF}', '\u{E000}')]);
+        assert_eq!(class.len(), 2);
+        assert_eq!(class.nth(1), Some('\u{E000}'));
+    }
+
+    #[test]
+    fn char_class_to_string() {
+        let class = Term::from_str("[0-9_\\]a-]").unwrap();
+        assert_eq!(class.to_string(), "[0-9_\\]a\\-]");
+        assert_eq!(Term::from_str(&class.to_string()), Ok(class));
+
+        // neither an epsilon nor a counted repetition is read in a class
+        for (text, printed) in &[
+            ("[2\"a\"]", "[\\2\"a\"]"),
+            ("[ 2*3<a>]", "[ \\2*3<a>]"),
+            ("[ε]", "[\\ε]"),
+            ("[2-3\"]", "[2-3\"]"),
+            ("[0-9]", "[0-9]"),
+        ] {
+            let class = Term::from_str(text).unwrap();
+            assert_eq!(class.to_string(), *printed);
+            let prod = Production::from_parts(
+                Term::Nonterminal(String::from("s")),
+                vec![Expression::from_parts(vec![class])],
+            );
+            assert_eq!(Production::from_str(&prod.to_string()), Ok(prod));
+        }
+    }
+
+    #[test]
+    fn repeat_to_string_and_back() {
+        let repeat = |terms, min, max| Term::Repeat {
+            inner: Box::new(Expression::from_parts(terms)),
+            min,
+            max,
+        };
+        let a = Term::Terminal(String::from("a"));
+        for (term, printed) in [
+            (repeat(vec![a.clone()], 2, Some(2)), "2\"a\""),
+            (repeat(vec![a.clone()], 0, Some(3)), "0*3\"a\""),
+            (
+                repeat(vec![a.clone(), a.clone()], 1, None),
+                "( \"a\" \"a\" )+",
+            ),
+            (repeat(vec![a.clone()], 0, None), "\"a\"*"),
+            (
+                repeat(vec![repeat(vec![a], 2, None)], 3, Some(3)),
+                "3( 2*\"a\" )",
+            ),
+        ] {
+            assert_eq!(term.to_string(), printed);
+            let prod = Production::from_parts(
+                Term::Nonterminal(String::from("s")),
+                vec![Expression::from_parts(vec![term])],
+            );
+            let grammar = Grammar::from_str(&prod.to_string()).unwrap();
+            assert_eq!(grammar.productions_iter().next(), Some(&prod));
+        }
+    }
+
+    #[test]
+    fn typed_terms() {
+        let digit = Nonterminal(String::from("digit"));
+        let term = Term::from(digit.clone());
+        assert_eq!(term, Term::Nonterminal(String::from("digit")));
+        assert!(term == digit);
+        assert_eq!(digit.to_string(), term.to_string());
+        assert_eq!(Nonterminal::try_from(term.clone()), Ok(digit.clone()));
+        assert_eq!(Terminal::try_from(term.clone()), Err(term));
+        assert_eq!(Nonterminal::from("digit"), digit);
+        assert_eq!(Nonterminal::from(String::from("digit")), digit);
+        assert_eq!(Nonterminal::from(&digit), digit);
+
+        let quote = Terminal(String::from("\"a"));
+        let term = Term::from(quote.clone());
+        assert_eq!(quote.to_string(), "'\"a'");
+        assert_eq!(quote.to_string(), term.to_string());
+        assert_eq!(Terminal::try_from(term.clone()), Ok(quote.clone()));
+        assert_eq!(Nonterminal::try_from(term.clone()), Err(term));
+        let caseless = Term::CaselessTerminal(String::from("\"a"));
+        assert!(quote != caseless);
+        assert_eq!(Terminal::try_from(caseless.clone()), Err(caseless));
+    }
+
     #[test]
     fn quote_term_to_string_and_back() {
         let quote = Term::Terminal(String::from("\""));
@@ -161,4 +1038,48 @@ mod tests {
         let from_string = Term::from_str(&to_string);
         assert_eq!(Ok(Term::Terminal(String::from("\""))), from_string);
     }
+
+    #[test]
+    fn escaped_term_to_string_and_back() {
+        let terms = [
+            (
+                Term::Terminal(String::from("a\nb\t\r\\")),
+                "\"a\\nb\\t\\r\\\\\"",
+            ),
+            (Term::Terminal(String::from("'\"")), "\"'\\\"\""),
+            (Term::Terminal(String::from("\"\\")), "'\"\\\\'"),
+            (
+                Term::CaselessTerminal(String::from("\u{0}\u{7f}\u{1F600}")),
+                "%i\"\\u{0}\\u{7F}\u{1F600}\"",
+            ),
+        ];
+        for (term, text) in terms.iter() {
+            assert_eq!(term.to_string(), *text);
+            assert_eq!(Term::from_str(text).as_ref(), Ok(term));
+        }
+    }
+
+    #[test]
+    fn orders_terms() {
+        let mut terms: Vec<Term> = vec![
+            "%x61", "%i\"a\"", "[b]", "[a]", "<b>", "<a>", "\"b\"", "\"a\"",
+        ]
+        .into_iter()
+        .map(|term| Term::from_str(term).unwrap())
+        .collect();
+        terms.push(Term::Regex(String::from("a")));
+        terms.sort();
+        let sorted: Vec<String> = terms.iter().map(Term::to_string).collect();
+        assert_eq!(
+            sorted,
+            vec!["\"a\"", "\"b\"", "<a>", "<b>", "[a]", "[b]", "%i\"a\"", "%x61", "#\"a\"#"]
+        );
+        assert!(
+            CharClass::from_ranges(vec![('a', 'b')]) < CharClass::from_ranges(vec![('a', 'c')])
+        );
+        // a negated class is ordered by its ranges as written, like others
+        let a = CharClass::from_ranges(vec![('a', 'a')]);
+        let not_a = CharClass::negated(vec![('a', 'a')]);
+        assert!(a < not_a && not_a < CharClass::from_ranges(vec![('b', 'b')]));
+    }
 }
@@ -0,0 +1,142 @@
+use error::Error;
+use grammar::Grammar;
+use std::collections::HashSet;
+use term::{caseless_prefix, CharClass, Term};
+
+impl Grammar {
+    /// Split `input` into tokens by the terminals of the grammar.
+    ///
+    /// At each position the longest terminal which matches is taken as the
+    /// next token (maximal munch). A character class matches a single
+    /// character, and only when no terminal matches, so the wildcard `%any`
+    /// never swallows the text of a terminal. Empty terminals are never
+    /// tokens. Returns `Error::TokenizeError` at the first position
+    /// which nothing matches.
+    ///
+    /// # Example
+    ///
+    /// ```rust
+    /// extern crate bnf;
+    /// use bnf::Grammar;
+    ///
+    /// fn main() {
+    ///     let grammar = Grammar::from_str("<cmp> ::= <var> \"<\" <var> | <var> \"<=\" <var>
+    ///         <var> ::= [a-z]").unwrap();
+    ///
+    ///     assert_eq!(grammar.tokenize("a<=b").unwrap(), vec!["a", "<=", "b"]);
+    ///     assert!(grammar.tokenize("a=b").is_err());
+    /// }
+    /// ```
+    pub fn tokenize(&self, input: &str) -> Result<Vec<String>, Error> {
+        let mut terminals = HashSet::new();
+        let mut classes: Vec<&CharClass> = vec![];
+        for production in self.productions_iter() {
+            for term in production
+                .rhs_iter()
+                .flat_map(|expr| expr.nested_terms_iter())
+            {
+                match *term {
+                    Term::Terminal(ref t) if !t.is_empty() => {
+                        terminals.insert((t.as_str(), false));
+                    }
+                    Term::CaselessTerminal(ref t) if !t.is_empty() => {
+                        terminals.insert((t.as_str(), true));
+                    }
+                    Term::TerminalBytes(_) => {
+                        if let Some(t) = term.terminal_text().filter(|t| !t.is_empty()) {
+                            terminals.insert((t, false));
+                        }
+                    }
+                    Term::CharClass(ref class) => classes.push(class),
+                    _ => (),
+                }
+            }
+        }
+
+        let mut tokens = vec![];
+        let mut rest = input;
+        while let Some(c) = rest.chars().next() {
+            let longest = terminals
+                .iter()
+                .filter_map(|&(t, caseless)| match caseless {
+                    true => caseless_prefix(t, rest),
+                    false if rest.starts_with(t) => Some(t.len()),
+                    false => None,
+                })
+                .max();
+            let len = match longest {
+                Some(len) => len,
+                None if classes.iter().any(|class| class.contains(c)) => c.len_utf8(),
+                None => {
+                    return Err(Error::TokenizeError(format!(
+                        "No terminal matches at byte {} of input: {:?}",
+                        input.len() - rest.len(),
+                        rest
+                    )))
+                }
+            };
+            tokens.push(String::from(&rest[..len]));
+            rest = &rest[len..];
+        }
+        Ok(tokens)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn tokenize_maximal_munch() {
+        let grammar = Grammar::from_str(
+            "<expr> ::= <num> <op> <expr> | <num> | \"\"
+            <op> ::= \"+\" | \"++\" | \"-\"
+            <num> ::= \"1\" | \"12\" | \"é\"",
+        )
+        .unwrap();
+        assert_eq!(
+            grammar.tokenize("12+++1-é").unwrap(),
+            vec!["12", "++", "+", "1", "-", "é"]
+        );
+        assert_eq!(grammar.tokenize("").unwrap(), Vec::<String>::new());
+    }
+
+    #[test]
+    fn tokenize_char_classes() {
+        let grammar = Grammar::from_str(
+            "<word> ::= [a-z] <word> | \"if\"
+            <word> ::= \"then\"",
+        )
+        .unwrap();
+        assert_eq!(
+            grammar.tokenize("ifxthen").unwrap(),
+            vec!["if", "x", "then"]
+        );
+    }
+
+    #[test]
+    fn tokenize_any_char() {
+        let grammar = Grammar::from_str("<text> ::= \"/*\" | \"*/\" | %any <text> | \"\"").unwrap();
+        assert_eq!(grammar.tokenize("/*é*/").unwrap(), vec!["/*", "é", "*/"]);
+    }
+
+    #[test]
+    fn tokenize_caseless_terminals() {
+        let grammar =
+            Grammar::from_str("<words> ::= %i\"if\" | %i\"iff\" | \"THE\" | [a-z]").unwrap();
+        assert_eq!(
+            grammar.tokenize("IfFthe").unwrap(),
+            vec!["IfF", "t", "h", "e"]
+        );
+        assert!(grammar.tokenize("IfFTHE").is_ok());
+    }
+
+    #[test]
+    fn tokenize_unmatched_input() {
+        let grammar = Grammar::from_str("<ab> ::= \"a\" | \"b\" <ab>").unwrap();
+        match grammar.tokenize("abXa") {
+            Err(Error::TokenizeError(message)) => assert!(message.contains("byte 2")),
+            result => panic!("should fail to tokenize: {:?}", result),
+        }
+    }
+}
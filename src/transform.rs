@@ -0,0 +1,857 @@
+use expression::Expression;
+use grammar::Grammar;
+use parsers::fresh;
+use production::Production;
+use std::collections::{HashMap, HashSet};
+use term::Term;
+
+/// Get the alternatives of every production sharing each lhs nonterminal
+fn rules(grammar: &Grammar) -> HashMap<&str, Vec<&Expression>> {
+    let mut rules: HashMap<&str, Vec<&Expression>> = HashMap::new();
+    for production in grammar.productions_iter() {
+        if let Term::Nonterminal(ref nt) = *production.lhs() {
+            rules
+                .entry(nt.as_str())
+                .or_default()
+                .extend(production.rhs_iter());
+        }
+    }
+    rules
+}
+
+/// Get the nonterminal an alternative of only one defined nonterminal is
+fn unit<'a>(
+    expression: &'a Expression,
+    rules: &HashMap<&str, Vec<&Expression>>,
+) -> Option<&'a str> {
+    let mut terms = expression.terms_iter();
+    match (terms.next(), terms.next()) {
+        (Some(Term::Nonterminal(nt)), None) if rules.contains_key(nt.as_str()) => Some(nt),
+        _ => None,
+    }
+}
+
+/// Add `expression` to `alternatives`, following it through unit alternatives
+/// to the alternatives which aren't, unless they're already `seen`
+fn inline<'a>(
+    expression: &'a Expression,
+    rules: &HashMap<&'a str, Vec<&'a Expression>>,
+    seen: &mut HashSet<&'a str>,
+    alternatives: &mut Vec<Expression>,
+) {
+    match unit(expression, rules) {
+        Some(nt) => {
+            if seen.insert(nt) {
+                for expression in &rules[nt] {
+                    inline(expression, rules, seen, alternatives);
+                }
+            }
+        }
+        None => {
+            if !alternatives.contains(expression) {
+                alternatives.push(expression.clone());
+            }
+        }
+    }
+}
+
+/// Add the alternatives of `terms` with every nonterminal of `inlined`
+/// replaced by one of its alternatives to `alternatives`
+fn unfold_terms(
+    terms: &[&Term],
+    rules: &HashMap<&str, Vec<&Expression>>,
+    inlined: &HashSet<&str>,
+    weight: Option<u32>,
+    alternatives: &mut Vec<Expression>,
+) {
+    let mut sequences: Vec<Vec<Term>> = vec![vec![]];
+    for term in terms {
+        sequences = match **term {
+            Term::Nonterminal(ref nt) if inlined.contains(nt.as_str()) => sequences
+                .iter()
+                .flat_map(|sequence| {
+                    rules[nt.as_str()].iter().map(move |expression| {
+                        let mut sequence = sequence.clone();
+                        sequence.extend(expression.terms_iter().cloned());
+                        sequence
+                    })
+                })
+                .collect(),
+            _ => sequences
+                .into_iter()
+                .map(|mut sequence| {
+                    sequence.push((*term).clone());
+                    sequence
+                })
+                .collect(),
+        };
+    }
+    alternatives.extend(sequences.into_iter().map(|sequence| {
+        let mut expression = Expression::from_parts(sequence);
+        expression.set_weight(weight);
+        expression
+    }));
+}
+
+/// Get the productions of `lhs` with `alternatives` left factored, the
+/// production of `lhs` followed by those of the fresh nonterminals named
+/// after it for the tails of alternatives sharing a prefix
+fn left_factor(
+    lhs: Term,
+    alternatives: Vec<Expression>,
+    taken: &mut HashSet<String>,
+) -> Vec<Production> {
+    // alternatives grouped by their first term, in order of appearance
+    let mut groups: Vec<Vec<Expression>> = vec![];
+    for expression in alternatives {
+        let first = expression.terms_iter().next().cloned();
+        let group = groups
+            .iter_mut()
+            .find(|group| first.is_some() && group[0].terms_iter().next().cloned() == first);
+        match group {
+            Some(group) => group.push(expression),
+            None => groups.push(vec![expression]),
+        }
+    }
+
+    let mut factored = vec![];
+    let mut tails = vec![];
+    for group in groups {
+        if group.len() == 1 {
+            factored.extend(group);
+            continue;
+        }
+        let terms = group
+            .iter()
+            .map(|expression| expression.terms_iter().cloned().collect::<Vec<_>>())
+            .collect::<Vec<_>>();
+        let prefix = (1..)
+            .take_while(|&len| {
+                terms
+                    .iter()
+                    .all(|other| other.len() >= len && other[len - 1] == terms[0][len - 1])
+            })
+            .last()
+            .unwrap_or(1);
+
+        let mut name = match lhs {
+            Term::Nonterminal(ref nt) => format!("{}'", nt),
+            _ => String::from("'"),
+        };
+        while !taken.insert(name.clone()) {
+            name.push('\'');
+        }
+        let tail = Term::Nonterminal(name);
+
+        let weighted = group.iter().any(|expression| expression.weight().is_some());
+        let weight = group
+            .iter()
+            .map(|expression| expression.weight().unwrap_or(1))
+            .sum();
+        let mut head = Expression::from_parts(terms[0][..prefix].to_vec());
+        head.add_term(tail.clone());
+        head.set_weight(if weighted { Some(weight) } else { None });
+        factored.push(head);
+
+        let rests = group
+            .iter()
+            .zip(terms)
+            .map(|(expression, terms)| {
+                let mut rest = Expression::from_parts(terms[prefix..].to_vec());
+                rest.set_weight(expression.weight());
+                rest
+            })
+            .collect();
+        // empty rests become `""` once the tail is factored, so they aren't
+        // grouped together as sharing a first term
+        let mut factored_tails = left_factor(tail, rests, taken);
+        for rest in factored_tails[0].rhs_iter_mut() {
+            if rest.is_empty() {
+                rest.add_term(Term::Terminal(String::new()));
+            }
+        }
+        tails.extend(factored_tails);
+    }
+
+    let mut productions = vec![Production::from_parts(lhs, factored)];
+    productions.extend(tails);
+    productions
+}
+
+/// Replace each repetition of `expression` by the first nonterminal of a
+/// chain named after `parent`, adding the productions of the chain to `chains`
+fn expand_terms(
+    expression: &mut Expression,
+    parent: &str,
+    taken: &mut HashSet<String>,
+    counts: &mut HashMap<String, usize>,
+    chains: &mut Vec<Production>,
+) {
+    for term in expression.terms_iter_mut() {
+        let (mut inner, min, max) = match *term {
+            Term::Repeat {
+                ref inner,
+                min,
+                max,
+            } => ((**inner).clone(), min, max),
+            _ => continue,
+        };
+        expand_terms(&mut inner, parent, taken, counts, chains);
+
+        // a nonterminal for the rest after each number of copies, up to
+        // `max`, or `min` for a repetition without one
+        let last = max.unwrap_or(min);
+        let links = (0..=last)
+            .map(|_| Term::Nonterminal(fresh(parent, taken, counts)))
+            .collect::<Vec<_>>();
+        for (copies, link) in links.iter().enumerate() {
+            let mut rhs = vec![];
+            if max.is_none_or(|max| copies < max) {
+                let mut copy = inner.clone();
+                copy.add_term(links[last.min(copies + 1)].clone());
+                rhs.push(copy);
+            }
+            if copies >= min {
+                rhs.push(Expression::from_parts(vec![Term::Terminal(String::new())]));
+            }
+            chains.push(Production::from_parts(link.clone(), rhs));
+        }
+        *term = links[0].clone();
+    }
+}
+
+impl Grammar {
+    /// Consume the grammar, getting a grammar of what `f` returns for each of
+    /// its productions in order.
+    ///
+    /// # Example
+    ///
+    /// ```rust
+    /// extern crate bnf;
+    /// use bnf::{Grammar, Term};
+    ///
+    /// fn main() {
+    ///     let grammar = Grammar::from_str("<dna> ::= <base> | <base> <dna>
+    ///         <base> ::= \"a\" | \"c\" | \"g\" | \"t\"").unwrap();
+    ///     let uppercased = grammar.map_productions(|mut production| {
+    ///         for expression in production.rhs_iter_mut() {
+    ///             for term in expression.terms_iter_mut() {
+    ///                 if let Term::Terminal(ref mut t) = *term {
+    ///                     *t = t.to_uppercase();
+    ///                 }
+    ///             }
+    ///         }
+    ///         production
+    ///     });
+    ///
+    ///     let expected = Grammar::from_str("<dna> ::= <base> | <base> <dna>
+    ///         <base> ::= \"A\" | \"C\" | \"G\" | \"T\"").unwrap();
+    ///     assert_eq!(uppercased, expected);
+    /// }
+    /// ```
+    pub fn map_productions<F>(self, f: F) -> Grammar
+    where
+        F: FnMut(Production) -> Production,
+    {
+        Grammar::from_parts(self.into_productions().into_iter().map(f).collect())
+    }
+
+    /// Get a grammar of what `f` returns for each expression of the grammar,
+    /// given along with the production it's in. Productions keep their lhs,
+    /// order and annotations, and each its alternatives in order.
+    ///
+    /// # Example
+    ///
+    /// ```rust
+    /// extern crate bnf;
+    /// use bnf::{Expression, Grammar, Term};
+    ///
+    /// fn main() {
+    ///     let grammar = Grammar::from_str("<list> ::= <item> | <item> <list>
+    ///         <item> ::= \"x\"").unwrap();
+    ///     let spaced = grammar.apply_transform(|production, expression| {
+    ///         let mut spaced = expression.clone();
+    ///         if *production.lhs() == Term::from_str("<item>").unwrap() {
+    ///             spaced.add_term(Term::from_str("\" \"").unwrap());
+    ///         }
+    ///         spaced
+    ///     });
+    ///
+    ///     let expected = Grammar::from_str("<list> ::= <item> | <item> <list>
+    ///         <item> ::= \"x\" \" \"").unwrap();
+    ///     assert_eq!(spaced, expected);
+    /// }
+    /// ```
+    pub fn apply_transform<F>(&self, f: F) -> Grammar
+    where
+        F: Fn(&Production, &Expression) -> Expression,
+    {
+        let productions = self
+            .productions_iter()
+            .map(|production| {
+                let rhs = production
+                    .rhs_iter()
+                    .map(|expression| f(production, expression))
+                    .collect();
+                let mut transformed = Production::from_parts(production.lhs().clone(), rhs);
+                transformed.copy_annotations(production);
+                transformed
+            })
+            .collect();
+        Grammar::from_parts(productions)
+    }
+
+    /// Get a copy of the grammar with each unit alternative, one of only a
+    /// nonterminal, replaced in place by the alternatives of that
+    /// nonterminal, which are followed in turn through any chain of unit
+    /// alternatives to those which aren't.
+    ///
+    /// Every production sharing the inlined lhs is inlined from. Alternatives
+    /// which would repeat one already in the production are dropped, as are
+    /// unit alternatives which only lead back to a nonterminal already
+    /// inlined. Nonterminals without a production are kept, as are
+    /// productions no longer referenced.
+    ///
+    /// # Example
+    ///
+    /// ```rust
+    /// extern crate bnf;
+    /// use bnf::Grammar;
+    ///
+    /// fn main() {
+    ///     let grammar = Grammar::from_str("<expr> ::= <term> | <expr> \"+\" <term>
+    ///         <term> ::= <factor>
+    ///         <factor> ::= <num> | \"(\" <expr> \")\"
+    ///         <num> ::= [0-9]").unwrap();
+    ///     let expected = Grammar::from_str("<expr> ::= [0-9] | \"(\" <expr> \")\" | <expr> \"+\" <term>
+    ///         <term> ::= [0-9] | \"(\" <expr> \")\"
+    ///         <factor> ::= [0-9] | \"(\" <expr> \")\"
+    ///         <num> ::= [0-9]").unwrap();
+    ///
+    ///     assert_eq!(grammar.inline_unit_productions(), expected);
+    /// }
+    /// ```
+    pub fn inline_unit_productions(&self) -> Grammar {
+        let rules = rules(self);
+        let productions = self
+            .productions_iter()
+            .map(|production| {
+                let lhs = match *production.lhs() {
+                    Term::Nonterminal(ref nt) => nt.as_str(),
+                    Term::Terminal(_)
+                    | Term::CaselessTerminal(_)
+                    | Term::TerminalBytes(_)
+                    | Term::CharClass(_)
+                    | Term::Regex(_)
+                    | Term::Repeat { .. } => return production.clone(),
+                };
+                let mut seen = Some(lhs).into_iter().collect();
+                let mut alternatives = vec![];
+                for expression in production.rhs_iter() {
+                    inline(expression, &rules, &mut seen, &mut alternatives);
+                }
+                let mut inlined = Production::from_parts(production.lhs().clone(), alternatives);
+                inlined.copy_annotations(production);
+                inlined
+            })
+            .collect();
+        Grammar::from_parts(productions)
+    }
+
+    /// Get a copy of the grammar with the alternatives of each production
+    /// left factored, so no two begin with the same term.
+    ///
+    /// Alternatives sharing a first term are replaced by their longest common
+    /// prefix followed by a fresh nonterminal, named for the lhs with a `'`
+    /// appended until the name isn't taken, like `<a'>`. Its production
+    /// follows the one factored, with the rest of each alternative, or `""`
+    /// for none, and is factored in turn. The factored alternative weighs
+    /// as much as the alternatives it replaces together, when any of them
+    /// has a weight. Productions sharing an lhs are factored separately.
+    ///
+    /// # Example
+    ///
+    /// ```rust
+    /// extern crate bnf;
+    /// use bnf::Grammar;
+    ///
+    /// fn main() {
+    ///     let grammar = Grammar::from_str("<stmt> ::= \"if\" <cond> \"then\" <stmt>
+    ///             | \"if\" <cond> \"then\" <stmt> \"else\" <stmt> | \"skip\"
+    ///         <cond> ::= \"c\"").unwrap();
+    ///     let expected = Grammar::from_str("<stmt> ::= \"if\" <cond> \"then\" <stmt> <stmt'> | \"skip\"
+    ///         <stmt'> ::= \"\" | \"else\" <stmt>
+    ///         <cond> ::= \"c\"").unwrap();
+    ///
+    ///     assert_eq!(grammar.left_factor(), expected);
+    /// }
+    /// ```
+    pub fn left_factor(&self) -> Grammar {
+        let mut taken = HashSet::new();
+        for production in self.productions_iter() {
+            let terms = production
+                .rhs_iter()
+                .flat_map(|expression| expression.nested_terms_iter());
+            for term in Some(production.lhs()).into_iter().chain(terms) {
+                if let Term::Nonterminal(ref nt) = *term {
+                    taken.insert(nt.clone());
+                }
+            }
+        }
+
+        let productions = self
+            .productions_iter()
+            .flat_map(|production| {
+                let mut factored = left_factor(
+                    production.lhs().clone(),
+                    production.rhs_iter().cloned().collect(),
+                    &mut taken,
+                );
+                factored[0].copy_annotations(production);
+                factored
+            })
+            .collect();
+        Grammar::from_parts(productions)
+    }
+
+    /// Get a copy of the grammar after `steps` rounds of inlining every
+    /// reference to a non-recursive nonterminal, replacing each alternative
+    /// referencing one with an alternative for every way to replace those
+    /// references with one of their alternatives.
+    ///
+    /// A nonterminal is recursive when it references itself, directly or
+    /// through other nonterminals, see `Grammar::strongly_connected_components`.
+    /// Every production sharing an lhs is inlined from, and an inlined
+    /// alternative keeps the weight of the alternative it's inlined into.
+    /// Nonterminals without a production are kept, as are productions no
+    /// longer referenced and references within a repetition. Rounds stop
+    /// early once nothing is left to inline.
+    ///
+    /// # Example
+    ///
+    /// ```rust
+    /// extern crate bnf;
+    /// use bnf::Grammar;
+    ///
+    /// fn main() {
+    ///     let grammar = Grammar::from_str("<pair> ::= <bit> <bit> | <pair> <pair>
+    ///         <bit> ::= <zero> | \"1\"
+    ///         <zero> ::= \"0\"").unwrap();
+    ///     let expected = Grammar::from_str("<pair> ::= \"0\" \"0\" | \"0\" \"1\" | \"1\" \"0\" | \"1\" \"1\" | <pair> <pair>
+    ///         <bit> ::= \"0\" | \"1\"
+    ///         <zero> ::= \"0\"").unwrap();
+    ///
+    ///     assert_ne!(grammar.unfold(1), expected);
+    ///     assert_eq!(grammar.unfold(2), expected);
+    /// }
+    /// ```
+    pub fn unfold(&self, steps: usize) -> Grammar {
+        let mut grammar = self.clone();
+        for _ in 0..steps {
+            let graph = grammar.dependency_graph();
+            let recursive = grammar
+                .strongly_connected_components()
+                .into_iter()
+                .filter(|component| {
+                    component.len() > 1 || graph[&component[0]].contains(&component[0])
+                })
+                .flatten()
+                .collect::<HashSet<String>>();
+            let rules = rules(&grammar);
+            let inlined = rules
+                .keys()
+                .cloned()
+                .filter(|nt| !recursive.contains(*nt))
+                .collect::<HashSet<&str>>();
+
+            let mut unfolded = false;
+            let productions = grammar
+                .productions_iter()
+                .map(|production| {
+                    let mut alternatives = vec![];
+                    for expression in production.rhs_iter() {
+                        let terms = expression.terms_iter().collect::<Vec<_>>();
+                        let inlines = terms.iter().any(|term| match **term {
+                            Term::Nonterminal(ref nt) => inlined.contains(nt.as_str()),
+                            Term::Terminal(_)
+                            | Term::CaselessTerminal(_)
+                            | Term::TerminalBytes(_)
+                            | Term::CharClass(_)
+                            | Term::Regex(_)
+                            | Term::Repeat { .. } => false,
+                        });
+                        if inlines {
+                            unfolded = true;
+                            unfold_terms(
+                                &terms,
+                                &rules,
+                                &inlined,
+                                expression.weight(),
+                                &mut alternatives,
+                            );
+                        } else {
+                            alternatives.push(expression.clone());
+                        }
+                    }
+                    let mut rewritten =
+                        Production::from_parts(production.lhs().clone(), alternatives);
+                    rewritten.copy_annotations(production);
+                    rewritten
+                })
+                .collect::<Vec<_>>();
+            if !unfolded {
+                break;
+            }
+            grammar = Grammar::from_parts(productions);
+        }
+        grammar
+    }
+
+    /// Get a copy of the grammar with every repetition replaced by a chain
+    /// of fresh nonterminals, for tools which only understand plain BNF.
+    ///
+    /// Each nonterminal of the chain derives the rest of the repetition
+    /// after a number of copies: another copy followed by the next
+    /// nonterminal, while there are fewer than `max` copies, and `""` once
+    /// there are at least `min`. Copies past `min` of a repetition without
+    /// a `max` go on with the same nonterminal, so the chain grows with the
+    /// counts, never with their square. Nonterminals are named after the lhs
+    /// of the repetition, like `<pair (1)>`, until the name isn't taken.
+    /// The grammar's productions keep their order and their alternatives,
+    /// with the productions of the chains after them.
+    ///
+    /// # Example
+    ///
+    /// ```rust
+    /// extern crate bnf;
+    /// use bnf::Grammar;
+    ///
+    /// fn main() {
+    ///     let grammar = Grammar::from_str("<pair> ::= 1*2\"x\"").unwrap();
+    ///     let expected = Grammar::from_str("<pair> ::= <pair (1)>
+    ///         <pair (1)> ::= \"x\" <pair (2)>
+    ///         <pair (2)> ::= \"x\" <pair (3)> | \"\"
+    ///         <pair (3)> ::= \"\"").unwrap();
+    ///
+    ///     assert_eq!(grammar.expand_repetitions(), expected);
+    /// }
+    /// ```
+    pub fn expand_repetitions(&self) -> Grammar {
+        let mut taken = HashSet::new();
+        for production in self.productions_iter() {
+            let terms = production
+                .rhs_iter()
+                .flat_map(|expression| expression.nested_terms_iter());
+            for term in Some(production.lhs()).into_iter().chain(terms) {
+                if let Term::Nonterminal(ref nt) = *term {
+                    taken.insert(nt.clone());
+                }
+            }
+        }
+
+        let mut counts = HashMap::new();
+        let mut chains = vec![];
+        let mut productions = self
+            .productions_iter()
+            .map(|production| {
+                let parent = match *production.lhs() {
+                    Term::Nonterminal(ref nt) => nt.clone(),
+                    ref lhs => lhs.to_string(),
+                };
+                let mut expanded = production.clone();
+                for expression in expanded.rhs_iter_mut() {
+                    expand_terms(expression, &parent, &mut taken, &mut counts, &mut chains);
+                }
+                expanded
+            })
+            .collect::<Vec<_>>();
+        productions.extend(chains);
+        Grammar::from_parts(productions)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    extern crate quickcheck;
+
+    use self::quickcheck::{QuickCheck, TestResult};
+    use super::*;
+    use arbitrary::{inputs, SmallGrammar};
+    use term::Nonterminal;
+
+    /// Whether `transformed` recognizes the same short inputs as `grammar`
+    fn same_language(grammar: &Grammar, transformed: &Grammar) -> TestResult {
+        let start = Nonterminal::from("n0");
+        let differing = inputs(3).into_iter().find(|input| {
+            (grammar.count_parses(&start, input) > 0)
+                != (transformed.count_parses(&start, input) > 0)
+        });
+        match differing {
+            Some(input) => TestResult::error(format!(
+                "{:?} is recognized by only one of\n{}\nand\n{}",
+                input, grammar, transformed
+            )),
+            None => TestResult::passed(),
+        }
+    }
+
+    fn prop_inline_unit_productions_preserves_language(small: SmallGrammar) -> TestResult {
+        same_language(&small.0, &small.0.inline_unit_productions())
+    }
+
+    #[test]
+    fn transforms_keep_annotations() {
+        let mut grammar = Grammar::from_str(
+            "<s> ::= <a> | \"x\" <a> | \"x\" \"y\" | \"x\" \"y\"
+            <a> ::= \"a\"",
+        )
+        .unwrap();
+        for (index, production) in grammar.productions_iter_mut().enumerate() {
+            production.set_annotation("rule", &index.to_string());
+        }
+        let rules = |grammar: &Grammar| {
+            grammar
+                .productions_iter()
+                .map(|production| production.annotation("rule").map(String::from))
+                .collect::<Vec<_>>()
+        };
+        let annotated = vec![Some(String::from("0")), Some(String::from("1"))];
+
+        assert_eq!(rules(&grammar.inline_unit_productions()), annotated);
+        assert_eq!(rules(&grammar.unfold(1)), annotated);
+        // the factored tails are new productions
+        assert_eq!(
+            rules(&grammar.left_factor()),
+            vec![Some(String::from("0")), None, None, Some(String::from("1"))]
+        );
+        grammar.dedup_alternatives();
+        assert_eq!(rules(&grammar), annotated);
+    }
+
+    #[test]
+    fn inline_unit_productions_preserves_language() {
+        QuickCheck::new().quickcheck(
+            prop_inline_unit_productions_preserves_language as fn(SmallGrammar) -> TestResult,
+        )
+    }
+
+    fn prop_unfold_preserves_language(small: SmallGrammar) -> TestResult {
+        same_language(&small.0, &small.0.unfold(2))
+    }
+
+    #[test]
+    fn unfold_preserves_language() {
+        QuickCheck::new()
+            .quickcheck(prop_unfold_preserves_language as fn(SmallGrammar) -> TestResult)
+    }
+
+    fn prop_left_factor_preserves_language(small: SmallGrammar) -> TestResult {
+        same_language(&small.0, &small.0.left_factor())
+    }
+
+    #[test]
+    fn left_factor_preserves_language() {
+        QuickCheck::new()
+            .quickcheck(prop_left_factor_preserves_language as fn(SmallGrammar) -> TestResult)
+    }
+
+    #[test]
+    fn maps_productions_in_order() {
+        let grammar = Grammar::from_str(
+            "<a> ::= \"a\" | <b>
+            <b> ::= \"b\"
+            <a> ::= \"a2\"",
+        )
+        .unwrap();
+        let mut seen = vec![];
+        let mapped = grammar.clone().map_productions(|production| {
+            seen.push(production.lhs().to_string());
+            Production::from_parts(production.lhs().clone(), vec![])
+        });
+        assert_eq!(seen, vec!["<a>", "<b>", "<a>"]);
+        assert!(mapped.productions_iter().all(Production::is_empty));
+        assert_eq!(
+            grammar.clone().map_productions(|production| production),
+            grammar
+        );
+        assert_eq!(
+            Grammar::new().map_productions(|_| Production::new()),
+            Grammar::new()
+        );
+    }
+
+    #[test]
+    fn applies_transforms_to_expressions() {
+        let mut grammar = Grammar::from_str(
+            "<a> ::= \"a\" | <b> %weight 2
+            <b> ::= \"b\" | \"\"",
+        )
+        .unwrap();
+        grammar
+            .productions_iter_mut()
+            .next()
+            .unwrap()
+            .set_annotation("k", "v");
+        let reversed = grammar.apply_transform(|production, expression| {
+            let mut terms: Vec<Term> = expression.terms_iter().cloned().collect();
+            terms.reverse();
+            terms.push(production.lhs().clone());
+            Expression::from_parts(terms)
+        });
+        assert_eq!(
+            reversed,
+            Grammar::from_str(
+                "<a> ::= \"a\" <a> | <b> <a>
+                <b> ::= \"b\" <b> | \"\" <b>"
+            )
+            .unwrap()
+        );
+        assert_eq!(
+            reversed.productions_iter().next().unwrap().annotation("k"),
+            Some("v")
+        );
+        assert_eq!(
+            grammar.apply_transform(|_, expression| expression.clone()),
+            grammar
+        );
+    }
+
+    #[test]
+    fn inlines_unit_chains() {
+        let grammar = Grammar::from_str(
+            "<start> ::= <a> | <undefined> | \"s\"
+            <a> ::= <b> | \"a\"
+            <b> ::= <c> | \"s\" | <a>
+            <c> ::= \"c\" <c> | <start>
+            <a> ::= \"a2\"",
+        )
+        .unwrap();
+        let expected = Grammar::from_str(
+            "<start> ::= \"c\" <c> | \"s\" | \"a\" | \"a2\" | <undefined>
+            <a> ::= \"c\" <c> | <undefined> | \"s\" | \"a\"
+            <b> ::= \"c\" <c> | \"a\" | \"a2\" | <undefined> | \"s\"
+            <c> ::= \"c\" <c> | \"s\" | \"a\" | \"a2\" | <undefined>
+            <a> ::= \"a2\"",
+        )
+        .unwrap();
+        let inlined = grammar.inline_unit_productions();
+        assert_eq!(inlined, expected, "{}", inlined);
+        assert_eq!(inlined.inline_unit_productions(), inlined);
+    }
+
+    #[test]
+    fn inlines_unit_cycles() {
+        let grammar = Grammar::from_str(
+            "<a> ::= <b>
+            <b> ::= <a>",
+        )
+        .unwrap();
+        let inlined = grammar.inline_unit_productions();
+        assert!(inlined.productions_iter().all(Production::is_empty));
+        assert_eq!(inlined.productions_iter().count(), 2);
+    }
+
+    #[test]
+    fn unfolds_non_recursive_nonterminals() {
+        let grammar = Grammar::from_str(
+            "<list> ::= <item> | <item> \",\" <list> %weight 2
+            <item> ::= <sign> <digit> | <undefined>
+            <sign> ::= \"-\" | \"\"
+            <digit> ::= [0-9]
+            <sign> ::= \"+\"
+            <never> ::= <none> <digit>
+            <none> ::= <none>",
+        )
+        .unwrap();
+        let expected = Grammar::from_str(
+            "<list> ::= \"-\" [0-9] | \"\" [0-9] | \"+\" [0-9] | <undefined>
+                | \"-\" [0-9] \",\" <list> %weight 2 | \"\" [0-9] \",\" <list> %weight 2
+                | \"+\" [0-9] \",\" <list> %weight 2 | <undefined> \",\" <list> %weight 2
+            <item> ::= \"-\" [0-9] | \"\" [0-9] | \"+\" [0-9] | <undefined>
+            <sign> ::= \"-\" | \"\"
+            <digit> ::= [0-9]
+            <sign> ::= \"+\"
+            <never> ::= <none> [0-9]
+            <none> ::= <none>",
+        )
+        .unwrap();
+        assert_eq!(grammar.unfold(0), grammar);
+        let unfolded = grammar.unfold(2);
+        assert_eq!(unfolded, expected, "{}", unfolded);
+        assert_eq!(grammar.unfold(5), expected);
+
+        let grammar = Grammar::from_str(
+            "<a> ::= <b> <b> | \"a\"
+            <b> ::= <c>
+            <c> ::= <empty>
+            <empty> ::= <empty> \"x\"",
+        )
+        .unwrap();
+        let expected = Grammar::from_str(
+            "<a> ::= <empty> <empty> | \"a\"
+            <b> ::= <empty>
+            <c> ::= <empty>
+            <empty> ::= <empty> \"x\"",
+        )
+        .unwrap();
+        assert_eq!(grammar.unfold(3), expected);
+    }
+
+    #[test]
+    fn left_factors_common_prefixes() {
+        let grammar = Grammar::from_str(
+            "<a> ::= \"x\" \"y\" \"z\" | \"x\" \"y\" <b> %weight 3 | \"x\" | \"w\" | <b>
+            <a'> ::= \"taken\"
+            <b> ::= <b> \"b\" | <b> | \"b\"",
+        )
+        .unwrap();
+        let expected = Grammar::from_str(
+            "<a> ::= \"x\" <a''> %weight 5 | \"w\" | <b>
+            <a''> ::= \"y\" <a'''> %weight 4 | \"\"
+            <a'''> ::= \"z\" | <b> %weight 3
+            <a'> ::= \"taken\"
+            <b> ::= <b> <b'> | \"b\"
+            <b'> ::= \"b\" | \"\"",
+        )
+        .unwrap();
+        let factored = grammar.left_factor();
+        assert_eq!(factored, expected, "{}", factored);
+        assert_eq!(factored.left_factor(), factored);
+        assert_eq!(Grammar::new().left_factor(), Grammar::new());
+    }
+
+    #[test]
+    fn expands_repetitions() {
+        let grammar = Grammar::from_str(
+            "<s> ::= 2*\"a\" <s (1)> | 0*1( \"b\" 2\"c\" )
+            <s (1)> ::= \"d\"",
+        )
+        .unwrap();
+        let expected = Grammar::from_str(
+            "<s> ::= <s (2)> <s (1)> | <s (8)>
+            <s (1)> ::= \"d\"
+            <s (2)> ::= \"a\" <s (3)>
+            <s (3)> ::= \"a\" <s (4)>
+            <s (4)> ::= \"a\" <s (4)> | \"\"
+            <s (5)> ::= \"c\" <s (6)>
+            <s (6)> ::= \"c\" <s (7)>
+            <s (7)> ::= \"\"
+            <s (8)> ::= \"b\" <s (5)> <s (9)> | \"\"
+            <s (9)> ::= \"\"",
+        )
+        .unwrap();
+        let expanded = grammar.expand_repetitions();
+        assert_eq!(expanded, expected, "{}", expanded);
+        assert_eq!(expanded.expand_repetitions(), expanded);
+
+        let s = Nonterminal::from("s");
+        for input in &["", "aad", "aaaad", "bcc", "ad", "bc", "bccbcc"] {
+            assert_eq!(
+                expanded.count_parses(&s, input),
+                grammar.count_parses(&s, input),
+                "{}",
+                input
+            );
+        }
+    }
+}
@@ -0,0 +1,362 @@
+use error::Error;
+use expression::Expression;
+use grammar::Grammar;
+use std::fmt;
+use std::slice;
+use term::{caseless_prefix, regex_matches, Term};
+
+/// A derivation of a nonterminal, recording the `Expression` it was expanded
+/// to and what each of the expression's terms derived
+#[derive(Clone, Debug, Eq, Hash, PartialEq)]
+pub struct ParseTree {
+    pub lhs: Term,
+    pub expression: Expression,
+    alternative: Option<usize>,
+    children: Vec<ParseTreeNode>,
+}
+
+/// A child of a `ParseTree`, deriving one term of its expression
+#[derive(Clone, Debug, Eq, Hash, PartialEq)]
+pub enum ParseTreeNode {
+    /// Text emitted for a terminal, a character class, or a nonterminal
+    /// without a production
+    Terminal(String),
+    /// Derivation of a nonterminal
+    Nonterminal(ParseTree),
+    /// Derivations of the copies of a repetition's inner expression, one
+    /// child per term of each copy in order
+    Repeat(Vec<ParseTreeNode>),
+}
+
+impl ParseTree {
+    /// Construct a `ParseTree` from one child per term of `expression`
+    pub fn from_parts(
+        lhs: Term,
+        expression: Expression,
+        children: Vec<ParseTreeNode>,
+    ) -> ParseTree {
+        ParseTree {
+            lhs,
+            expression,
+            alternative: None,
+            children,
+        }
+    }
+
+    /// Get the index of the tree's expression among the alternatives of the
+    /// production it's from, for telling alternatives apart when building
+    /// an AST. Trees built by parsing or generating have one, while those
+    /// from `from_parts` don't until it's set.
+    pub fn alternative_index(&self) -> Option<usize> {
+        self.alternative
+    }
+
+    /// Set the index of the tree's expression among the alternatives of the
+    /// production it's from
+    pub fn set_alternative_index(&mut self, index: Option<usize>) {
+        self.alternative = index
+    }
+
+    /// Add a child for the next term of the tree's expression
+    pub fn push_child(&mut self, child: ParseTreeNode) {
+        self.children.push(child)
+    }
+
+    /// Replace the children from `index` on by a repetition of them
+    pub(crate) fn repeat_children(&mut self, index: usize) {
+        let copies = self.children.split_off(index);
+        self.children.push(ParseTreeNode::Repeat(copies));
+    }
+
+    /// Get iterator of the tree's children
+    pub fn children_iter(&self) -> Iter<'_> {
+        Iter {
+            iterator: self.children.iter(),
+        }
+    }
+
+    /// Get mutable iterator of the tree's children
+    pub fn children_iter_mut(&mut self) -> IterMut<'_> {
+        IterMut {
+            iterator: self.children.iter_mut(),
+        }
+    }
+
+    /// Get the sentence derived by the tree, its terminals in order
+    pub fn flatten(&self) -> String {
+        let mut sentence = String::new();
+        self.flatten_into(&mut sentence);
+        sentence
+    }
+
+    fn flatten_into(&self, sentence: &mut String) {
+        for child in &self.children {
+            child.flatten_into(sentence);
+        }
+    }
+}
+
+impl ParseTreeNode {
+    fn flatten_into(&self, sentence: &mut String) {
+        match *self {
+            ParseTreeNode::Terminal(ref t) => sentence.push_str(t),
+            ParseTreeNode::Nonterminal(ref tree) => tree.flatten_into(sentence),
+            ParseTreeNode::Repeat(ref children) => {
+                for child in children {
+                    child.flatten_into(sentence);
+                }
+            }
+        }
+    }
+}
+
+impl fmt::Display for ParseTree {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "{}", self.flatten())
+    }
+}
+
+impl Grammar {
+    /// Check that `tree` is a derivation allowed by the grammar.
+    ///
+    /// Every nonterminal must be expanded to one of its alternatives, with a
+    /// child per term: terminals as their text, character classes as one of
+    /// their characters, regexes as text they match, nonterminals without a
+    /// production as `<name>`, the same as generation emits, and repetitions
+    /// as a child per term of each of from `min` to `max` copies of their
+    /// inner expression.
+    pub fn validate_tree(&self, tree: &ParseTree) -> Result<(), Error> {
+        let is_alternative = self
+            .productions_iter()
+            .filter(|prod| *prod.lhs() == tree.lhs)
+            .any(|prod| prod.rhs_iter().any(|expr| *expr == tree.expression));
+        if !is_alternative {
+            return Err(Error::InvalidTree(format!(
+                "'{}' is not an alternative of {}!",
+                tree.expression, tree.lhs
+            )));
+        }
+
+        let terms = tree.expression.terms_iter().collect::<Vec<_>>();
+        if terms.len() != tree.children.len() {
+            return Err(Error::InvalidTree(format!(
+                "{} expanded to '{}' should have {} children, not {}!",
+                tree.lhs,
+                tree.expression,
+                terms.len(),
+                tree.children.len()
+            )));
+        }
+
+        self.validate_children(&terms, &tree.children, &tree.expression)
+    }
+
+    /// Check that each of `children` is a derivation of the term at its
+    /// place among `terms` of `expression`
+    fn validate_children(
+        &self,
+        terms: &[&Term],
+        children: &[ParseTreeNode],
+        expression: &Expression,
+    ) -> Result<(), Error> {
+        for (&term, child) in terms.iter().zip(children) {
+            let valid = match (term, child) {
+                (Term::Terminal(t), ParseTreeNode::Terminal(s)) => t == s,
+                (Term::TerminalBytes(bytes), ParseTreeNode::Terminal(s)) => {
+                    s.as_bytes() == &bytes[..]
+                }
+                (Term::CaselessTerminal(t), ParseTreeNode::Terminal(s)) => {
+                    caseless_prefix(t, s) == Some(s.len())
+                }
+                (Term::CharClass(class), ParseTreeNode::Terminal(s)) => {
+                    let mut chars = s.chars();
+                    match (chars.next(), chars.next()) {
+                        (Some(c), None) => class.contains(c),
+                        _ => false,
+                    }
+                }
+                (Term::Regex(pattern), ParseTreeNode::Terminal(s)) => regex_matches(pattern, s),
+                (Term::Nonterminal(_), ParseTreeNode::Nonterminal(subtree)) => {
+                    if subtree.lhs != *term {
+                        false
+                    } else {
+                        self.validate_tree(subtree)?;
+                        true
+                    }
+                }
+                (Term::Nonterminal(_), ParseTreeNode::Terminal(s)) => {
+                    !self.productions_iter().any(|prod| *prod.lhs() == *term)
+                        && *s == term.to_string()
+                }
+                (Term::Repeat { inner, min, max }, ParseTreeNode::Repeat(nodes)) => {
+                    let inner_terms = inner.terms_iter().collect::<Vec<_>>();
+                    let copies = match inner_terms.len() {
+                        0 if nodes.is_empty() => Some(*min),
+                        0 => None,
+                        len if nodes.len() % len == 0 => Some(nodes.len() / len),
+                        _ => None,
+                    };
+                    match copies {
+                        Some(copies) if copies >= *min && max.is_none_or(|max| copies <= max) => {
+                            for copy in nodes.chunks(inner_terms.len().max(1)) {
+                                self.validate_children(&inner_terms, copy, inner)?;
+                            }
+                            true
+                        }
+                        _ => false,
+                    }
+                }
+                _ => false,
+            };
+            if !valid {
+                return Err(Error::InvalidTree(format!(
+                    "{:?} doesn't derive {} in '{}'!",
+                    child, term, expression
+                )));
+            }
+        }
+        Ok(())
+    }
+}
+
+pub struct Iter<'a> {
+    iterator: slice::Iter<'a, ParseTreeNode>,
+}
+
+impl<'a> Iterator for Iter<'a> {
+    type Item = &'a ParseTreeNode;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        self.iterator.next()
+    }
+}
+
+pub struct IterMut<'a> {
+    iterator: slice::IterMut<'a, ParseTreeNode>,
+}
+
+impl<'a> Iterator for IterMut<'a> {
+    type Item = &'a mut ParseTreeNode;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        self.iterator.next()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn tree(lhs: &str, expression: &str, children: Vec<ParseTreeNode>) -> ParseTree {
+        ParseTree::from_parts(
+            Term::Nonterminal(String::from(lhs)),
+            Expression::from_str(expression).unwrap(),
+            children,
+        )
+    }
+
+    fn leaf(text: &str) -> ParseTreeNode {
+        ParseTreeNode::Terminal(String::from(text))
+    }
+
+    fn grammar() -> Grammar {
+        Grammar::from_str(
+            "<pair> ::= <digit> \"+\" <digit> | <undefined>
+            <digit> ::= [0-9]",
+        )
+        .unwrap()
+    }
+
+    #[test]
+    fn flatten_tree() {
+        let pair = tree(
+            "pair",
+            "<digit> \"+\" <digit>",
+            vec![
+                ParseTreeNode::Nonterminal(tree("digit", "[0-9]", vec![leaf("1")])),
+                leaf("+"),
+                ParseTreeNode::Nonterminal(tree("digit", "[0-9]", vec![leaf("2")])),
+            ],
+        );
+        assert_eq!(pair.flatten(), "1+2");
+        assert_eq!(pair.to_string(), "1+2");
+        assert!(grammar().validate_tree(&pair).is_ok());
+        assert!(grammar()
+            .validate_tree(&tree("pair", "<undefined>", vec![leaf("<undefined>")]))
+            .is_ok());
+    }
+
+    #[test]
+    fn invalid_trees() {
+        let grammar = grammar();
+        let invalid = vec![
+            tree("pair", "\"+\"", vec![leaf("+")]),
+            tree("digit", "[0-9]", vec![]),
+            tree("digit", "[0-9]", vec![leaf("a")]),
+            tree("digit", "[0-9]", vec![leaf("12")]),
+            tree("pair", "<undefined>", vec![leaf("undefined")]),
+            tree(
+                "pair",
+                "<digit> \"+\" <digit>",
+                vec![
+                    ParseTreeNode::Nonterminal(tree("digit", "[0-9]", vec![leaf("1")])),
+                    leaf("-"),
+                    ParseTreeNode::Nonterminal(tree("digit", "[0-9]", vec![leaf("2")])),
+                ],
+            ),
+            tree(
+                "pair",
+                "<digit> \"+\" <digit>",
+                vec![
+                    ParseTreeNode::Nonterminal(tree("digit", "[0-9]", vec![leaf("1")])),
+                    leaf("+"),
+                    ParseTreeNode::Nonterminal(tree("digit", "[0-9]", vec![leaf("x")])),
+                ],
+            ),
+        ];
+
+        for tree in invalid {
+            match grammar.validate_tree(&tree) {
+                Err(Error::InvalidTree(_)) => (),
+                result => panic!("{:?} should be invalid: {:?}", tree, result),
+            }
+        }
+    }
+
+    #[test]
+    fn validate_repetitions() {
+        let grammar = Grammar::from_str(
+            "<pair> ::= 1*2<digit>
+            <digit> ::= [0-9]",
+        )
+        .unwrap();
+        let digit = |text| ParseTreeNode::Nonterminal(tree("digit", "[0-9]", vec![leaf(text)]));
+        let expression = grammar
+            .productions_iter()
+            .flat_map(|prod| prod.rhs_iter())
+            .next();
+        let pair = |digits| {
+            ParseTree::from_parts(
+                Term::Nonterminal(String::from("pair")),
+                expression.unwrap().clone(),
+                vec![ParseTreeNode::Repeat(digits)],
+            )
+        };
+        assert_eq!(grammar.validate_tree(&pair(vec![digit("1")])), Ok(()));
+        assert_eq!(pair(vec![digit("1"), digit("2")]).flatten(), "12");
+        assert_eq!(
+            grammar.validate_tree(&pair(vec![digit("1"), digit("2")])),
+            Ok(())
+        );
+        for digits in [
+            vec![],
+            vec![digit("1"), digit("2"), digit("3")],
+            vec![leaf("1")],
+        ] {
+            match grammar.validate_tree(&pair(digits)) {
+                Err(Error::InvalidTree(_)) => (),
+                result => panic!("should be invalid: {:?}", result),
+            }
+        }
+    }
+}
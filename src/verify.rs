@@ -0,0 +1,128 @@
+use earley::Chart;
+use grammar::Grammar;
+use std::fmt;
+use term::Nonterminal;
+
+/// Examples which a grammar got wrong, see `Grammar::verify_examples`
+#[derive(Clone, Debug, Default, Eq, PartialEq)]
+pub struct VerifyResult {
+    rejected: Vec<String>,
+    accepted: Vec<String>,
+}
+
+impl VerifyResult {
+    /// Whether every example was verified
+    pub fn is_ok(&self) -> bool {
+        self.rejected.is_empty() && self.accepted.is_empty()
+    }
+
+    /// Get the positive examples which aren't in the language
+    pub fn rejected_positives(&self) -> &[String] {
+        &self.rejected
+    }
+
+    /// Get the negative examples which are in the language
+    pub fn accepted_negatives(&self) -> &[String] {
+        &self.accepted
+    }
+}
+
+impl fmt::Display for VerifyResult {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        if self.is_ok() {
+            return write!(f, "all examples verified");
+        }
+        for example in &self.rejected {
+            writeln!(f, "should accept: {:?}", example)?;
+        }
+        for example in &self.accepted {
+            writeln!(f, "should reject: {:?}", example)?;
+        }
+        Ok(())
+    }
+}
+
+impl Grammar {
+    /// Check that every `positive` example is in the language of nonterminal
+    /// `start`, and that no `negative` example is.
+    ///
+    /// The alternatives of every production sharing a lhs are parsed with,
+    /// and a nonterminal without a production matches its own `<name>` text.
+    ///
+    /// # Example
+    ///
+    /// ```rust
+    /// extern crate bnf;
+    /// use bnf::Grammar;
+    ///
+    /// fn main() {
+    ///     let grammar = Grammar::from_str("<dna> ::= <base> | <base> <dna>
+    ///         <base> ::= \"A\" | \"C\" | \"G\" | \"T\"").unwrap();
+    ///     let result = grammar.verify_examples("dna", &["GATTACA", "T"], &["", "GATTACA!"]);
+    ///     assert!(result.is_ok(), "{}", result);
+    ///
+    ///     let result = grammar.verify_examples("dna", &["U"], &["A"]);
+    ///     assert_eq!(result.rejected_positives(), ["U"]);
+    ///     assert_eq!(result.accepted_negatives(), ["A"]);
+    /// }
+    /// ```
+    pub fn verify_examples(
+        &self,
+        start: impl Into<Nonterminal>,
+        positive: &[&str],
+        negative: &[&str],
+    ) -> VerifyResult {
+        let start = start.into();
+        let accepts = |example: &str| Chart::parse(self, start.name(), example).accepts();
+        VerifyResult {
+            rejected: positive
+                .iter()
+                .filter(|example| !accepts(example))
+                .map(|&example| String::from(example))
+                .collect(),
+            accepted: negative
+                .iter()
+                .filter(|example| accepts(example))
+                .map(|&example| String::from(example))
+                .collect(),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn verify_examples() {
+        let grammar = Grammar::from_str(
+            "<expr> ::= <num> | <expr> \"+\" <expr> | \"(\" <expr> \")\"
+            <num> ::= [0-9] | [0-9] <num>",
+        )
+        .unwrap();
+        let result = grammar.verify_examples(
+            "expr",
+            &["1", "12+3", "(1+(2))", "1+", "x"],
+            &["", "()", "1++2", "(1)"],
+        );
+        assert!(!result.is_ok());
+        assert_eq!(result.rejected_positives(), ["1+", "x"]);
+        assert_eq!(result.accepted_negatives(), ["(1)"]);
+        assert_eq!(
+            result.to_string(),
+            "should accept: \"1+\"\nshould accept: \"x\"\nshould reject: \"(1)\"\n"
+        );
+
+        let result = grammar.verify_examples("num", &["123"], &["1+2"]);
+        assert!(result.is_ok());
+        assert_eq!(result.to_string(), "all examples verified");
+    }
+
+    #[test]
+    fn verify_undefined_start() {
+        let grammar = Grammar::from_str("<a> ::= \"a\"").unwrap();
+        let result = grammar.verify_examples("undefined", &["a"], &["<undefined>"]);
+        assert_eq!(result.rejected_positives(), ["a"]);
+        assert!(result.accepted_negatives().is_empty());
+    }
+}
@@ -0,0 +1,375 @@
+use grammar::{self, Alternative, Grammar, Sugar};
+use std::collections::{HashMap, HashSet};
+use std::str;
+use term::{CharClass, Term};
+
+/// A class of no characters, for what matches nothing
+const NOTHING: &str = "[^#x0-#x10FFFF]";
+
+/// Whether `name` may be written as a symbol of W3C EBNF as it is
+fn is_symbol(name: &str) -> bool {
+    let mut chars = name.chars();
+    match chars.next() {
+        Some(c) if c.is_alphabetic() || c == '_' => {}
+        _ => return false,
+    }
+    chars.all(|c| c.is_alphanumeric() || "_.-".contains(c))
+}
+
+/// Get `name` as a symbol, with runs of the characters a symbol can't have
+/// replaced by `_`
+fn symbol(name: &str) -> String {
+    let mut symbol = String::new();
+    for c in name.chars() {
+        if c.is_alphanumeric() || "_.-".contains(c) {
+            symbol.push(c);
+        } else if !symbol.ends_with('_') {
+            symbol.push('_');
+        }
+    }
+    let symbol = symbol.trim_end_matches('_');
+    match symbol.chars().next() {
+        Some(c) if c.is_alphabetic() || c == '_' => String::from(symbol),
+        _ => format!("_{}", symbol),
+    }
+}
+
+/// Get `c` as it's written in a character class
+fn class_char(c: char) -> String {
+    if c.is_ascii_graphic() && !"[]^-#\\".contains(c) {
+        c.to_string()
+    } else {
+        format!("#x{:X}", c as u32)
+    }
+}
+
+/// Get the quoted strings and characters a terminal of `text` is written
+/// as, characters which aren't printable or can't be quoted being written
+/// like `#xA`
+fn text_units(text: &str) -> Vec<String> {
+    if text.is_empty() {
+        return vec![String::from("\"\"")];
+    }
+    let mut units = vec![];
+    let mut run = String::new();
+    let quote = |run: &mut String, units: &mut Vec<String>| {
+        if run.is_empty() {
+            return;
+        }
+        let quote = if run.contains('"') { '\'' } else { '"' };
+        units.push(format!("{}{}{}", quote, run, quote));
+        run.clear();
+    };
+    for c in text.chars() {
+        if c.is_control() || (c == '\'' && run.contains('"')) || (c == '"' && run.contains('\'')) {
+            quote(&mut run, &mut units);
+        }
+        if c.is_control() {
+            units.push(format!("#x{:X}", c as u32));
+        } else {
+            run.push(c);
+        }
+    }
+    quote(&mut run, &mut units);
+    units
+}
+
+/// Writes the resugared productions of a grammar as W3C EBNF
+struct W3c<'a> {
+    symbols: HashMap<&'a Term, String>,
+}
+
+impl<'a> W3c<'a> {
+    /// Get the symbol of every nonterminal of `grammar`, those which aren't
+    /// symbols already being renamed to a symbol no other nonterminal is
+    fn new(grammar: &'a Grammar) -> W3c<'a> {
+        let mut nonterminals = vec![];
+        for prod in grammar.productions_iter() {
+            nonterminals.push(prod.lhs());
+            let terms = prod.rhs_iter().flat_map(|expr| expr.nested_terms_iter());
+            nonterminals.extend(terms.filter(|term| matches!(**term, Term::Nonterminal(_))));
+        }
+        let name = |term: &Term| match *term {
+            Term::Nonterminal(ref name) => name.clone(),
+            _ => term.to_string(),
+        };
+
+        let mut symbols = HashMap::new();
+        let mut taken = HashSet::new();
+        for &term in &nonterminals {
+            let name = name(term);
+            if is_symbol(&name) {
+                taken.insert(name.clone());
+                symbols.insert(term, name);
+            }
+        }
+        for &term in &nonterminals {
+            if symbols.contains_key(term) {
+                continue;
+            }
+            let base = symbol(&name(term));
+            let mut renamed = base.clone();
+            let mut count = 1;
+            while !taken.insert(renamed.clone()) {
+                count += 1;
+                renamed = format!("{}_{}", base, count);
+            }
+            symbols.insert(term, renamed);
+        }
+        W3c { symbols }
+    }
+
+    /// Get what `term` is written as, in one or more units to be sequenced
+    fn term(&self, term: &Term) -> Vec<String> {
+        match *term {
+            Term::Nonterminal(_) => vec![self.symbols[term].clone()],
+            Term::Terminal(ref text) => text_units(text),
+            Term::CaselessTerminal(ref text) => {
+                let mut units = vec![];
+                let mut run = String::new();
+                for c in text.chars() {
+                    let (mut upper, mut lower) = (c.to_uppercase(), c.to_lowercase());
+                    match (upper.next(), upper.next(), lower.next(), lower.next()) {
+                        (Some(upper), None, Some(lower), None) if upper != lower => {
+                            if !run.is_empty() {
+                                units.extend(text_units(&run));
+                                run.clear();
+                            }
+                            units.push(format!("[{}{}]", class_char(upper), class_char(lower)));
+                        }
+                        _ => run.push(c),
+                    }
+                }
+                if !run.is_empty() || units.is_empty() {
+                    units.extend(text_units(&run));
+                }
+                units
+            }
+            Term::TerminalBytes(ref bytes) => match str::from_utf8(bytes) {
+                Ok(text) => text_units(text),
+                Err(_) => bytes.iter().map(|byte| format!("#x{:X}", byte)).collect(),
+            },
+            Term::CharClass(ref class) => vec![self.class(class)],
+            Term::Regex(_) => vec![String::from(NOTHING)],
+            Term::Repeat { .. } => unreachable!("repetitions are resugared"),
+        }
+    }
+
+    fn class(&self, class: &CharClass) -> String {
+        if class.is_empty() {
+            return String::from(NOTHING);
+        }
+        if class.ranges().is_empty() {
+            return String::from("[#x0-#x10FFFF]");
+        }
+        let ranges: String = class
+            .ranges()
+            .iter()
+            .map(|&(lo, hi)| {
+                if lo == hi {
+                    class_char(lo)
+                } else {
+                    format!("{}-{}", class_char(lo), class_char(hi))
+                }
+            })
+            .collect();
+        if class.is_negated() {
+            format!("[^{}]", ranges)
+        } else {
+            format!("[{}]", ranges)
+        }
+    }
+
+    /// Get what `sugar` is written as, in one or more units to be sequenced
+    fn sugar(&self, sugar: &Sugar) -> Vec<String> {
+        match *sugar {
+            Sugar::Term(term) => self.term(term),
+            Sugar::Group(ref alternatives) => {
+                vec![format!("( {} )", self.alternatives(alternatives))]
+            }
+            Sugar::Optional(ref alternatives) => vec![format!("{}?", self.operand(alternatives))],
+            // W3C EBNF has no counted repetition, so it's written out
+            Sugar::Repeat(min, max, ref repeated) => {
+                let units = self.sugar(repeated);
+                let unit = match (&**repeated, &units[..]) {
+                    (&Sugar::Term(_), [unit]) | (&Sugar::Group(_), [unit]) => unit.clone(),
+                    _ => format!("( {} )", units.join(" ")),
+                };
+                let mut units = vec![unit.clone(); min];
+                match max {
+                    Some(max) => units.extend(vec![format!("{}?", unit); max - min]),
+                    // the last required copy repeats, or else any copy does
+                    None => match units.pop() {
+                        Some(_) => units.push(format!("{}+", unit)),
+                        None => units.push(format!("{}*", unit)),
+                    },
+                }
+                units
+            }
+        }
+    }
+
+    /// Get `alternatives` as the operand of a postfix operator, in
+    /// parentheses unless they're a single term of a single unit
+    fn operand(&self, alternatives: &[Alternative]) -> String {
+        if let [Alternative { ref terms, .. }] = *alternatives {
+            if let [ref sugar @ Sugar::Term(_)] = terms[..] {
+                if let [ref unit] = self.sugar(sugar)[..] {
+                    return unit.clone();
+                }
+            }
+        }
+        format!("( {} )", self.alternatives(alternatives))
+    }
+
+    fn alternatives(&self, alternatives: &[Alternative]) -> String {
+        alternatives
+            .iter()
+            .map(|alternative| {
+                let units = alternative
+                    .terms
+                    .iter()
+                    .flat_map(|sugar| self.sugar(sugar))
+                    .collect::<Vec<_>>();
+                if units.is_empty() {
+                    String::from("\"\"")
+                } else {
+                    units.join(" ")
+                }
+            })
+            .collect::<Vec<_>>()
+            .join(" | ")
+    }
+}
+
+impl Grammar {
+    /// Format the grammar in the EBNF notation of W3C specifications, like
+    /// XML's, with a line like `symbol ::= "a" other | [0-9]+` for each
+    /// nonterminal.
+    ///
+    /// Groups, optional groups and repetitions, see `ParseOptions::ebnf`, are
+    /// written with `( )`, `?`, `*` and `+`. The productions of a nonterminal
+    /// are joined into one, and nonterminals whose names aren't W3C symbols
+    /// are renamed, like `<my rule>` to `my_rule`. Characters which can't be
+    /// quoted are written like `#xA`, caseless terminals as a class for each
+    /// letter, like `[Ss] [Ee] [Tt]`. Weights, which W3C EBNF has no
+    /// notation for, are left out, and so are regexes, written as a class of
+    /// no characters.
+    ///
+    /// # Example
+    ///
+    /// ```rust
+    /// extern crate bnf;
+    /// use bnf::{Grammar, ParseOptions};
+    ///
+    /// fn main() {
+    ///     let options = ParseOptions::new().ebnf();
+    ///     let grammar = Grammar::from_str_with_options(
+    ///         "<list> ::= \"[\" [ <item> { \",\" <item> } ] \"]\"
+    ///         <item> ::= [0-9]+ | %i\"nil\"",
+    ///         &options,
+    ///     ).unwrap();
+    ///
+    ///     assert_eq!(
+    ///         grammar.to_w3c_ebnf(),
+    ///         "list ::= \"[\" ( item ( \",\" item )* )? \"]\"\nitem ::= [0-9]+ | [Nn] [Ii] [Ll]\n"
+    ///     );
+    /// }
+    /// ```
+    pub fn to_w3c_ebnf(&self) -> String {
+        let w3c = W3c::new(self);
+        let mut rules: Vec<(&Term, Vec<String>)> = vec![];
+        for (prod, alternatives) in grammar::resugar(self) {
+            let alternatives = w3c.alternatives(&alternatives);
+            match rules.iter_mut().find(|rule| *rule.0 == *prod.lhs()) {
+                Some(rule) => rule.1.push(alternatives),
+                None => rules.push((prod.lhs(), vec![alternatives])),
+            }
+        }
+        rules
+            .into_iter()
+            .map(|(lhs, alternatives)| {
+                let mut alternatives = alternatives
+                    .into_iter()
+                    .filter(|alternatives| !alternatives.is_empty())
+                    .collect::<Vec<_>>();
+                if alternatives.is_empty() {
+                    alternatives.push(String::from(NOTHING));
+                }
+                format!("{} ::= {}\n", w3c.symbols[lhs], alternatives.join(" | "))
+            })
+            .collect()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use expression::Expression;
+    use parsers::ParseOptions;
+    use production::Production;
+
+    #[test]
+    fn w3c_ebnf() {
+        let options = ParseOptions::new().ebnf();
+        let mut grammar = Grammar::from_str_with_options(
+            "<doc> ::= <item>* ( \"a\" | \"b\" %weight 2 )+ { \"c\" <item> }+ [ \"d\" \"e\" ]
+            <item> ::= [a-z_] [\\]\\-^#] | \"\" | %i\"If\" | \"tab\t\" | '\"' \"'\"
+            <item> ::= <doc> ( \"x\" )+",
+            &options,
+        )
+        .unwrap();
+        let quotes = Expression::from_parts(vec![
+            Term::Terminal(String::from("\"'")),
+            Term::CharClass(CharClass::from_ranges(vec![])),
+            Term::CharClass(CharClass::negated(vec![('"', '"'), ('\u{e9}', '\u{e9}')])),
+            Term::TerminalBytes(vec![0xff, 0x0a]),
+        ]);
+        grammar.add_production(Production::from_parts(
+            Term::Nonterminal(String::from("item")),
+            vec![quotes, Expression::new()],
+        ));
+        assert_eq!(
+            grammar.to_w3c_ebnf(),
+            "doc ::= item* ( \"a\" | \"b\" )+ ( ( \"c\" item )* )+ ( \"d\" \"e\" )?
+item ::= [a-z_] [#x5D#x2D#x5E#x23] | \"\" | [Ii] [Ff] | \"tab\" #x9 | '\"' \"'\" \
+| doc \"x\"+ | '\"' \"'\" [^#x0-#x10FFFF] [^\"#xE9] #xFF #xA | \"\"
+"
+        );
+    }
+
+    #[test]
+    fn counted_repetitions() {
+        let grammar = Grammar::from_str("<s> ::= 2*4\"a\" 1*( \"b\" \"c\" ) 0*1<s>").unwrap();
+        assert_eq!(
+            grammar.to_w3c_ebnf(),
+            "s ::= \"a\" \"a\" \"a\"? \"a\"? ( \"b\" \"c\" )+ s?\n"
+        );
+    }
+
+    #[test]
+    fn renames_nonterminals() {
+        let grammar = Grammar::from_str(
+            "<my rule> ::= <my_rule> | <1st> | <my rule (2)> | <undefined>
+            <my_rule> ::= \"a\" | <my_rule_2>
+            <my rule (2)> ::= \"b\"
+            <1st> ::= \"c\"",
+        )
+        .unwrap();
+        let mut grammar = grammar;
+        grammar.add_production(Production::from_parts(
+            Term::Nonterminal(String::from("empty")),
+            vec![],
+        ));
+        assert_eq!(
+            grammar.to_w3c_ebnf(),
+            "my_rule_3 ::= my_rule | _1st | my_rule_2_2 | undefined
+my_rule ::= \"a\" | my_rule_2
+my_rule_2_2 ::= \"b\"
+_1st ::= \"c\"
+empty ::= [^#x0-#x10FFFF]
+"
+        );
+        assert_eq!(Grammar::new().to_w3c_ebnf(), "");
+    }
+}
@@ -0,0 +1,671 @@
+use error::Error;
+use grammar::Grammar;
+use parsers::ParseOptions;
+use std::collections::{HashMap, HashSet};
+use term::{CharClass, Term};
+
+/// A token of W3C EBNF, on the line it's on
+#[derive(Debug)]
+enum Token {
+    Symbol(String),
+    Define,
+    Or,
+    Minus,
+    Postfix(char),
+    Open,
+    Close,
+    Text(String),
+    Class(CharClass),
+}
+
+/// An expression of W3C EBNF
+#[derive(Clone, Debug)]
+enum Node {
+    Symbol(String),
+    Text(String),
+    Class(CharClass),
+    Choice(Vec<Vec<Node>>),
+    Postfix(Box<Node>, char),
+    Difference(Box<Node>, Box<Node>),
+}
+
+impl Token {
+    /// Describe the token for errors
+    fn describe(&self) -> String {
+        match *self {
+            Token::Symbol(ref name) => name.clone(),
+            Token::Define => String::from("'::='"),
+            Token::Or => String::from("'|'"),
+            Token::Minus => String::from("'-'"),
+            Token::Postfix(operator) => format!("'{}'", operator),
+            Token::Open => String::from("'('"),
+            Token::Close => String::from("')'"),
+            Token::Text(ref text) => format!("{:?}", text),
+            Token::Class(ref class) => class.to_string(),
+        }
+    }
+}
+
+fn error(line: usize, message: &str) -> Error {
+    Error::ParseError(format!("Line {}: {}", line, message))
+}
+
+/// Parse the digits of a character like `#x20`, the `#x` already read
+fn hex_char<I>(chars: &mut ::std::iter::Peekable<I>, line: usize) -> Result<char, Error>
+where
+    I: Iterator<Item = char>,
+{
+    let mut digits = String::new();
+    while let Some(&c) = chars.peek() {
+        if !c.is_ascii_hexdigit() {
+            break;
+        }
+        digits.push(c);
+        chars.next();
+    }
+    u32::from_str_radix(&digits, 16)
+        .ok()
+        .and_then(::std::char::from_u32)
+        .ok_or_else(|| error(line, &format!("invalid character #x{}", digits)))
+}
+
+/// Parse the body of a class like `[^a-z#x20]`, the `[` already read
+fn class<I>(chars: &mut ::std::iter::Peekable<I>, line: usize) -> Result<CharClass, Error>
+where
+    I: Iterator<Item = char>,
+{
+    let negated = chars.peek() == Some(&'^');
+    if negated {
+        chars.next();
+    }
+    let read = |chars: &mut ::std::iter::Peekable<I>| match chars.next() {
+        Some('#') if chars.peek() == Some(&'x') => {
+            chars.next();
+            hex_char(chars, line).map(Some)
+        }
+        Some(']') => Ok(None),
+        Some(c) => Ok(Some(c)),
+        None => Err(error(line, "missing closing ']'")),
+    };
+    let mut ranges = vec![];
+    while let Some(lo) = read(chars)? {
+        if chars.peek() != Some(&'-') {
+            ranges.push((lo, lo));
+            continue;
+        }
+        chars.next();
+        match read(chars)? {
+            Some(hi) if lo <= hi => ranges.push((lo, hi)),
+            Some(hi) => return Err(error(line, &format!("reversed range {:?}-{:?}", lo, hi))),
+            // a `-` ending the class is itself
+            None => {
+                ranges.push((lo, lo));
+                ranges.push(('-', '-'));
+                break;
+            }
+        }
+    }
+    if ranges.is_empty() {
+        return Err(error(line, "empty character class"));
+    }
+    Ok(if negated {
+        CharClass::negated(ranges)
+    } else {
+        CharClass::from_ranges(ranges)
+    })
+}
+
+/// Whether `c` may be in a symbol
+fn is_name_char(c: char) -> bool {
+    c.is_alphanumeric() || "_.-:".contains(c)
+}
+
+/// Split W3C EBNF into tokens, skipping comments, the numbers of rules like
+/// `[1]` which begin lines, and constraints like `[ wfc: ... ]`
+fn tokenize(input: &str) -> Result<Vec<(Token, usize)>, Error> {
+    let mut tokens = vec![];
+    let mut line = 1;
+    let mut line_start = true;
+    let mut chars = input.chars().peekable();
+    while let Some(c) = chars.next() {
+        if c == '\n' {
+            line += 1;
+            line_start = true;
+            continue;
+        }
+        if c.is_whitespace() {
+            continue;
+        }
+        let first = line_start;
+        line_start = false;
+        let token = match c {
+            '/' if chars.peek() == Some(&'*') => {
+                chars.next();
+                let mut last = ' ';
+                loop {
+                    match chars.next() {
+                        Some('/') if last == '*' => break,
+                        Some(next) => {
+                            line += (next == '\n') as usize;
+                            last = next;
+                        }
+                        None => return Err(error(line, "unterminated comment")),
+                    }
+                }
+                line_start = first;
+                continue;
+            }
+            ':' => {
+                if chars.next() != Some(':') || chars.next() != Some('=') {
+                    return Err(error(line, "expected '::='"));
+                }
+                Token::Define
+            }
+            '|' => Token::Or,
+            '-' => Token::Minus,
+            '?' | '*' | '+' => Token::Postfix(c),
+            '(' => Token::Open,
+            ')' => Token::Close,
+            '"' | '\'' => {
+                let mut text = String::new();
+                loop {
+                    match chars.next() {
+                        Some(next) if next == c => break,
+                        Some(next) => text.push(next),
+                        None => return Err(error(line, &format!("missing closing {:?}", c))),
+                    }
+                }
+                Token::Text(text)
+            }
+            '#' if chars.peek() == Some(&'x') => {
+                chars.next();
+                Token::Text(hex_char(&mut chars, line)?.to_string())
+            }
+            '[' => {
+                let rest = chars.clone().collect::<String>();
+                let bracketed = rest.split(']').next().unwrap_or("");
+                let trimmed = bracketed.trim_start().to_lowercase();
+                let number = !bracketed.is_empty() && bracketed.chars().all(|c| c.is_ascii_digit());
+                if (first && number) || trimmed.starts_with("wfc:") || trimmed.starts_with("vc:") {
+                    for skipped in chars.by_ref() {
+                        line += (skipped == '\n') as usize;
+                        if skipped == ']' {
+                            break;
+                        }
+                    }
+                    line_start = first;
+                    continue;
+                }
+                Token::Class(class(&mut chars, line)?)
+            }
+            c if is_name_char(c) => {
+                let mut name = c.to_string();
+                while let Some(&next) = chars.peek() {
+                    // a `::=` following a name isn't part of it
+                    if !is_name_char(next) || (next == ':' && chars.clone().nth(1) == Some(':')) {
+                        break;
+                    }
+                    name.push(next);
+                    chars.next();
+                }
+                Token::Symbol(name)
+            }
+            c => return Err(error(line, &format!("unexpected {:?}", c))),
+        };
+        tokens.push((token, line));
+    }
+    Ok(tokens)
+}
+
+/// Parses the tokens of one rule's expression
+struct Parser<'a> {
+    tokens: &'a [(Token, usize)],
+    at: usize,
+    line: usize,
+}
+
+impl<'a> Parser<'a> {
+    fn peek(&self) -> Option<&'a Token> {
+        self.tokens.get(self.at).map(|(token, _)| token)
+    }
+
+    fn next(&mut self) -> Option<&'a Token> {
+        let token = self.tokens.get(self.at);
+        if let Some(&(_, line)) = token {
+            self.line = line;
+        }
+        self.at += 1;
+        token.map(|(token, _)| token)
+    }
+
+    fn choice(&mut self) -> Result<Vec<Vec<Node>>, Error> {
+        let mut alternatives = vec![self.sequence()?];
+        while let Some(&Token::Or) = self.peek() {
+            self.next();
+            alternatives.push(self.sequence()?);
+        }
+        Ok(alternatives)
+    }
+
+    fn sequence(&mut self) -> Result<Vec<Node>, Error> {
+        let mut sequence = vec![];
+        loop {
+            match self.peek() {
+                Some(&Token::Symbol(_)) | Some(&Token::Text(_)) | Some(&Token::Class(_)) => {}
+                Some(&Token::Open) => {}
+                _ => return Ok(sequence),
+            }
+            let item = self.item()?;
+            if let Some(&Token::Minus) = self.peek() {
+                self.next();
+                let excluded = self.item()?;
+                sequence.push(Node::Difference(Box::new(item), Box::new(excluded)));
+            } else {
+                sequence.push(item);
+            }
+        }
+    }
+
+    fn item(&mut self) -> Result<Node, Error> {
+        let mut node = match self.next() {
+            Some(Token::Symbol(name)) => Node::Symbol(name.clone()),
+            Some(Token::Text(text)) => Node::Text(text.clone()),
+            Some(Token::Class(class)) => Node::Class(class.clone()),
+            Some(&Token::Open) => {
+                let alternatives = self.choice()?;
+                match self.next() {
+                    Some(&Token::Close) => Node::Choice(alternatives),
+                    _ => return Err(error(self.line, "missing closing ')'")),
+                }
+            }
+            _ => return Err(error(self.line, "expected a symbol, string, class or '('")),
+        };
+        while let Some(&Token::Postfix(operator)) = self.peek() {
+            self.next();
+            node = Node::Postfix(Box::new(node), operator);
+        }
+        Ok(node)
+    }
+}
+
+/// Computes the character sets of differences like `Char - '&'`
+struct Sets<'a> {
+    rules: HashMap<&'a str, Vec<&'a [Vec<Node>]>>,
+    visiting: HashSet<&'a str>,
+}
+
+impl<'a> Sets<'a> {
+    /// Get the characters `node` matches, if it matches one character of a set
+    fn set(&mut self, node: &'a Node) -> Option<CharClass> {
+        match *node {
+            Node::Class(ref class) => Some(class.clone()),
+            Node::Text(ref text) if text.chars().count() == 1 => {
+                let c = text.chars().next()?;
+                Some(CharClass::from_ranges(vec![(c, c)]))
+            }
+            Node::Choice(ref alternatives) => self.union(alternatives.iter().map(|alt| &alt[..])),
+            Node::Symbol(ref name) if self.visiting.insert(name) => {
+                let definitions = self.rules.get(name.as_str()).cloned().unwrap_or_default();
+                let set = self.union(definitions.into_iter().flatten().map(|alt| &alt[..]));
+                self.visiting.remove(name.as_str());
+                set
+            }
+            Node::Difference(ref node, ref excluded) => self.difference(node, excluded),
+            _ => None,
+        }
+    }
+
+    fn union<I>(&mut self, alternatives: I) -> Option<CharClass>
+    where
+        I: Iterator<Item = &'a [Node]>,
+    {
+        let mut ranges = vec![];
+        let mut any = false;
+        for alternative in alternatives {
+            match *alternative {
+                [ref node] => {
+                    let set = self.set(node)?;
+                    ranges.extend(set.members().iter().cloned());
+                    any = true;
+                }
+                _ => return None,
+            }
+        }
+        if any {
+            Some(CharClass::from_ranges(merge(ranges)))
+        } else {
+            None
+        }
+    }
+
+    fn difference(&mut self, node: &'a Node, excluded: &'a Node) -> Option<CharClass> {
+        let (set, excluded) = (self.set(node)?, self.set(excluded)?);
+        if set.is_negated() && !excluded.is_negated() {
+            let mut ranges = set.ranges().to_vec();
+            ranges.extend(excluded.ranges().iter().cloned());
+            return Some(CharClass::negated(merge(ranges)));
+        }
+        let mut ranges = vec![];
+        for &(lo, hi) in merge(set.members().to_vec()).iter() {
+            let mut from = lo as u32;
+            for &(ex_lo, ex_hi) in merge(excluded.members().to_vec()).iter() {
+                let (ex_lo, ex_hi) = (ex_lo as u32, ex_hi as u32);
+                if ex_hi < from || ex_lo > hi as u32 {
+                    continue;
+                }
+                if ex_lo > from {
+                    ranges.extend(char_range(from, ex_lo - 1));
+                }
+                from = ex_hi + 1;
+            }
+            if from <= hi as u32 {
+                ranges.extend(char_range(from, hi as u32));
+            }
+        }
+        Some(CharClass::from_ranges(ranges))
+    }
+}
+
+/// Get the range of the characters from `lo` to `hi`, moved off surrogates
+fn char_range(lo: u32, hi: u32) -> Option<(char, char)> {
+    let lo = if (0xD800..=0xDFFF).contains(&lo) {
+        0xE000
+    } else {
+        lo
+    };
+    let hi = if (0xD800..=0xDFFF).contains(&hi) {
+        0xD7FF
+    } else {
+        hi
+    };
+    match (::std::char::from_u32(lo), ::std::char::from_u32(hi)) {
+        (Some(lo), Some(hi)) if lo <= hi => Some((lo, hi)),
+        _ => None,
+    }
+}
+
+/// Sort and join overlapping or adjacent ranges
+fn merge(mut ranges: Vec<(char, char)>) -> Vec<(char, char)> {
+    ranges.sort();
+    let mut merged: Vec<(char, char)> = vec![];
+    for (lo, hi) in ranges {
+        match merged.last_mut() {
+            Some(last) if lo as u32 <= last.1 as u32 + 1 => last.1 = last.1.max(hi),
+            _ => merged.push((lo, hi)),
+        }
+    }
+    merged
+}
+
+/// Writes nodes in this crate's EBNF, see `ParseOptions::ebnf`
+struct Writer<'a> {
+    sets: Sets<'a>,
+}
+
+impl<'a> Writer<'a> {
+    fn alternatives(
+        &mut self,
+        alternatives: &'a [Vec<Node>],
+        line: usize,
+    ) -> Result<String, Error> {
+        let mut written = vec![];
+        for alternative in alternatives {
+            let mut terms = vec![];
+            for node in alternative {
+                terms.push(self.node(node, line)?);
+            }
+            if terms.is_empty() {
+                terms.push(String::from("\"\""));
+            }
+            written.push(terms.join(" "));
+        }
+        Ok(written.join(" | "))
+    }
+
+    fn node(&mut self, node: &'a Node, line: usize) -> Result<String, Error> {
+        Ok(match *node {
+            Node::Symbol(ref name) => format!("<{}>", name),
+            Node::Text(ref text) => Term::Terminal(text.clone()).to_string(),
+            Node::Class(ref class) => class.to_string(),
+            Node::Choice(ref alternatives) => {
+                format!("( {} )", self.alternatives(alternatives, line)?)
+            }
+            Node::Difference(..) => match self.sets.set(node) {
+                Some(ref class) if !class.is_empty() => class.to_string(),
+                Some(_) => return Err(error(line, "difference of no characters")),
+                None => {
+                    return Err(error(
+                        line,
+                        "only differences between sets of characters are supported",
+                    ))
+                }
+            },
+            Node::Postfix(ref inner, operator) => {
+                let bracket = match operator {
+                    '?' => Some(('[', ']')),
+                    '*' => Some(('{', '}')),
+                    _ => None,
+                };
+                match (bracket, &**inner) {
+                    (Some((open, close)), Node::Choice(alternatives)) => {
+                        format!(
+                            "{} {} {}",
+                            open,
+                            self.alternatives(alternatives, line)?,
+                            close
+                        )
+                    }
+                    (Some((open, close)), inner) => {
+                        format!("{} {} {}", open, self.node(inner, line)?, close)
+                    }
+                    // `( x* )+` is written as `{ x }+`
+                    (None, Node::Choice(alternatives)) => match alternatives[..] {
+                        [ref alternative] => match alternative[..] {
+                            [ref repeated @ Node::Postfix(_, '?')]
+                            | [ref repeated @ Node::Postfix(_, '*')] => {
+                                format!("{}+", self.node(repeated, line)?)
+                            }
+                            _ => format!("{}+", self.node(inner, line)?),
+                        },
+                        _ => format!("{}+", self.node(inner, line)?),
+                    },
+                    (None, &Node::Postfix(_, '+')) => format!("( {} )+", self.node(inner, line)?),
+                    (None, inner) => format!("{}+", self.node(inner, line)?),
+                }
+            }
+        })
+    }
+}
+
+impl Grammar {
+    /// Construct a `Grammar` from the EBNF notation of W3C specifications,
+    /// like XML's, where each rule is like `symbol ::= "a" other | [0-9]+`,
+    /// with the feature `w3c`.
+    ///
+    /// Strings are quoted with `"` or `'`, and `#x20` is the character of
+    /// that code, also within classes like `[^#x20<&]`. `?`, `*` and `+`
+    /// are parsed as `ParseOptions::ebnf` parses `[ ]`, `{ }` and `+`, to
+    /// the same nonterminals. A difference `A - B` is only supported between
+    /// sets of characters, like `Char - '&'` where `Char` is defined by
+    /// classes and characters only. Comments like `/* ... */`, numbers of
+    /// rules beginning lines like `[1]`, and constraints like `[ wfc: ... ]`
+    /// are skipped.
+    ///
+    /// # Example
+    ///
+    /// ```rust
+    /// extern crate bnf;
+    /// use bnf::{Grammar, ParseOptions};
+    ///
+    /// fn main() {
+    ///     let grammar = Grammar::from_w3c_ebnf(
+    ///         "[1] list ::= '[' ( item ( ',' item )* )? ']'  /* no trailing comma */
+    ///          [2] item ::= [#x30-#x39]+ | \"nil\"",
+    ///     ).unwrap();
+    ///     let native = Grammar::from_str_with_options(
+    ///         "<list> ::= \"[\" [ <item> { \",\" <item> } ] \"]\"
+    ///         <item> ::= [0-9]+ | \"nil\"",
+    ///         &ParseOptions::new().ebnf(),
+    ///     ).unwrap();
+    ///
+    ///     assert_eq!(grammar, native);
+    /// }
+    /// ```
+    pub fn from_w3c_ebnf(input: &str) -> Result<Grammar, Error> {
+        let tokens = tokenize(input)?;
+        let mut starts = vec![];
+        for index in 0..tokens.len() {
+            if let (&(Token::Symbol(_), _), Some(&(Token::Define, _))) =
+                (&tokens[index], tokens.get(index + 1))
+            {
+                starts.push(index);
+            }
+        }
+        match tokens.first() {
+            Some(&(_, line)) if starts.first() != Some(&0) => {
+                return Err(error(line, "expected a rule like 'symbol ::= ...'"))
+            }
+            _ => {}
+        }
+
+        let mut rules = vec![];
+        for (index, &start) in starts.iter().enumerate() {
+            let end = starts.get(index + 1).cloned().unwrap_or(tokens.len());
+            let (name, line) = match tokens[start] {
+                (Token::Symbol(ref name), line) => (name, line),
+                _ => continue,
+            };
+            let mut parser = Parser {
+                tokens: &tokens[start + 2..end],
+                at: 0,
+                line,
+            };
+            let alternatives = parser.choice()?;
+            if let Some(token) = parser.next() {
+                return Err(error(
+                    parser.line,
+                    &format!("unexpected {}", token.describe()),
+                ));
+            }
+            rules.push((name.as_str(), alternatives, line));
+        }
+
+        if rules.is_empty() {
+            return Ok(Grammar::new());
+        }
+        let mut definitions: HashMap<&str, Vec<&[Vec<Node>]>> = HashMap::new();
+        for &(name, ref alternatives, _) in &rules {
+            definitions.entry(name).or_default().push(alternatives);
+        }
+        let mut writer = Writer {
+            sets: Sets {
+                rules: definitions,
+                visiting: HashSet::new(),
+            },
+        };
+        let mut text = String::new();
+        for &(name, ref alternatives, line) in &rules {
+            let alternatives = writer.alternatives(alternatives, line)?;
+            text.push_str(&format!("<{}> ::= {}\n", name, alternatives));
+        }
+        Grammar::from_str_with_options(&text, &ParseOptions::new().ebnf())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use term::Term;
+
+    #[test]
+    fn from_w3c_ebnf() {
+        let grammar = Grammar::from_w3c_ebnf(
+            "/* from XML 1.0 */
+            [2]   Char     ::= #x9 | #xA | #xD | [#x20-#xD7FF] | [#xE000-#xFFFD] | [#x10000-#x10FFFF]
+            [14]  CharData ::= [^<&]* - ([^<&]* ']]>' [^<&]*)",
+        );
+        match grammar {
+            Err(Error::ParseError(message)) => {
+                assert!(message.starts_with("Line 3: "), "{}", message)
+            }
+            result => panic!("should fail to parse: {:?}", result),
+        }
+
+        let grammar = Grammar::from_w3c_ebnf(
+            "[2] Char ::= #x9 | #xA | #xD | [#x20-#xD7FF] | [#xE000-#xFFFD] | [#x10000-#x10FFFF]
+            [15] Comment ::= '<!--' ((Char - '-') | ('-' (Char - '-')))* '-->'
+            [27] Misc ::= Comment | S    [ vc: Misc ]
+            [3] S ::= (#x20 | #x9 | #xD | #xA)+
+            text ::= [^<&\"] - [#x0-#x1F]",
+        )
+        .unwrap();
+        let expected = Grammar::from_str_with_options(
+            "<Char> ::= \"\t\" | \"\n\" | \"\r\" | [ -\u{d7ff}] | [\u{e000}-\u{fffd}] | [\u{10000}-\u{10ffff}]
+            <Comment> ::= \"<!--\" { ( [\t-\n\r -,.-\u{d7ff}\u{e000}-\u{fffd}\u{10000}-\u{10ffff}] ) \\
+                | ( \"-\" ( [\t-\n\r -,.-\u{d7ff}\u{e000}-\u{fffd}\u{10000}-\u{10ffff}] ) ) } \"-->\"
+            <Misc> ::= <Comment> | <S>
+            <S> ::= ( \" \" | \"\t\" | \"\r\" | \"\n\" )+
+            <text> ::= [^\0-\u{1f}\"&<]",
+            &ParseOptions::new().ebnf(),
+        )
+        .unwrap();
+        assert_eq!(grammar, expected);
+        assert!(grammar
+            .verify_examples(
+                "Comment",
+                &["<!---->", "<!-- a-b -->"],
+                &["<!-- a--b -->", "<!--->"]
+            )
+            .is_ok());
+        assert!(grammar
+            .verify_examples("text", &["a", "é"], &["<", "\"", "\n"])
+            .is_ok());
+    }
+
+    #[test]
+    fn round_trips_w3c_ebnf() {
+        let options = ParseOptions::new().ebnf();
+        let grammar = Grammar::from_str_with_options(
+            "<doc> ::= <item>* ( \"a\" | \"b\" )+ { \"c\" <item> }+ [ \"d\" \"e\" ] | \"\"
+            <item> ::= [a-z_] [\\]\\-^#] | [^\"é] | %i\"If\" | \"tab\\\\\" | '\"' \"'\" | [ <doc> ]+",
+            &options,
+        )
+        .unwrap();
+        let w3c = grammar.to_w3c_ebnf();
+        let caseless = Term::CaselessTerminal(String::from("If"));
+        let parsed = Grammar::from_w3c_ebnf(&w3c).unwrap();
+        assert_eq!(parsed.to_w3c_ebnf(), w3c);
+        assert!(parsed
+            .productions_iter()
+            .flat_map(|prod| prod.rhs_iter().flat_map(|expr| expr.terms_iter()))
+            .all(|term| *term != caseless));
+        let uncased = grammar.to_string().replace("%i\"If\"", "[Ii] [Ff]");
+        assert_eq!(
+            Grammar::from_str_with_options(&uncased, &options),
+            Ok(parsed)
+        );
+    }
+
+    #[test]
+    fn w3c_ebnf_errors() {
+        let error = |input: &str| match Grammar::from_w3c_ebnf(input) {
+            Err(Error::ParseError(message)) => message,
+            result => panic!("should fail to parse: {:?}", result),
+        };
+        assert_eq!(error("a ::= 'x'\n)"), "Line 2: unexpected ')'");
+        assert!(error("'x' a ::= b").starts_with("Line 1: expected a rule"));
+        assert!(error("a ::= ( 'x'").starts_with("Line 1: missing closing ')'"));
+        assert!(error("a ::=\n 'x").starts_with("Line 2: missing closing"));
+        assert!(error("a ::= [z-a]").starts_with("Line 1: reversed range"));
+        assert!(error("a ::= [a-z\n").starts_with("Line 1: missing closing ']'"));
+        assert!(error("a ::= #xD800").starts_with("Line 1: invalid character"));
+        assert!(error("a ::= 'x' /* unterminated").starts_with("Line 1: unterminated comment"));
+        assert!(error("a ::= 'x' - b").starts_with("Line 1: only differences"));
+        assert!(error("a ::= 'x' - 'x'").starts_with("Line 1: difference of no characters"));
+        assert!(error("a ::= 'x' ;").starts_with("Line 1: unexpected ';'"));
+        assert_eq!(
+            Grammar::from_w3c_ebnf(" /* nothing */ "),
+            Ok(Grammar::new())
+        );
+    }
+}
@@ -0,0 +1,115 @@
+#![cfg(feature = "cli")]
+
+use std::fs;
+use std::path::PathBuf;
+use std::process::{Command, Output};
+
+/// Write `grammar` to a file of its own, named for the test
+fn grammar_file(name: &str, grammar: &str) -> PathBuf {
+    let path = std::env::temp_dir().join(format!("bnf-cli-{}-{}.bnf", name, std::process::id()));
+    fs::write(&path, grammar).unwrap();
+    path
+}
+
+fn run(args: &[&str]) -> Output {
+    Command::new(env!("CARGO_BIN_EXE_bnf-cli"))
+        .args(args)
+        .output()
+        .unwrap()
+}
+
+fn stdout(output: &Output) -> String {
+    String::from_utf8_lossy(&output.stdout).into_owned()
+}
+
+const DNA: &str = "<dna> ::= <base> | <base> <dna>
+<base> ::= \"A\" | \"C\" | \"G\" | \"T\"
+";
+
+#[test]
+fn validates() {
+    let path = grammar_file("valid", DNA);
+    let output = run(&["validate", path.to_str().unwrap()]);
+    assert!(output.status.success());
+    assert!(stdout(&output).ends_with(": 2 productions\n"));
+
+    let path = grammar_file("invalid", "<a> ::= <b> \"x\"\n<c> ::= garbage\n");
+    let output = run(&["validate", path.to_str().unwrap()]);
+    assert_eq!(output.status.code(), Some(1));
+    let stderr = String::from_utf8_lossy(&output.stderr);
+    assert!(stderr.contains("Line 2: "), "{}", stderr);
+    assert!(
+        stderr.contains("<b> is never defined, but used by <a>"),
+        "{}",
+        stderr
+    );
+
+    let output = run(&["validate", "no such file.bnf"]);
+    assert_eq!(output.status.code(), Some(2));
+}
+
+#[test]
+fn generates() {
+    let path = grammar_file("generate", DNA);
+    let path = path.to_str().unwrap();
+    let output = run(&["generate", path, "--start", "base", "--count", "20"]);
+    assert!(output.status.success());
+    let sentences = stdout(&output);
+    assert_eq!(sentences.lines().count(), 20);
+    assert!(sentences
+        .lines()
+        .all(|base| "ACGT".contains(base) && base.len() == 1));
+
+    let output = run(&["generate", path]);
+    assert_eq!(stdout(&output).lines().count(), 1);
+    assert_eq!(
+        run(&["generate", path, "--start", "rna"]).status.code(),
+        Some(2)
+    );
+    assert_eq!(
+        run(&["generate", path, "--count", "x"]).status.code(),
+        Some(2)
+    );
+}
+
+#[test]
+fn checks() {
+    let path = grammar_file("check", DNA);
+    let path = path.to_str().unwrap();
+    let output = run(&["check", path, "GATTACA"]);
+    assert!(output.status.success());
+    assert_eq!(stdout(&output), "accepted from <dna>, with 1 parse\n");
+    assert_eq!(run(&["check", path, "GATTACA!"]).status.code(), Some(1));
+    assert_eq!(
+        run(&["check", path, "GA", "--start", "<base>"])
+            .status
+            .code(),
+        Some(1)
+    );
+}
+
+#[test]
+fn summarizes() {
+    let path = grammar_file("stats", DNA);
+    let output = run(&["stats", path.to_str().unwrap()]);
+    assert!(output.status.success());
+    let stats = stdout(&output);
+    for line in &[
+        "productions: 2",
+        "nonterminals: 2",
+        "distinct terminals: 4",
+        "alternatives: 6",
+        "min alternatives: 2",
+        "max alternatives: 4",
+        "branching factor: 3.00",
+        "finite from <dna>: false",
+        "shortest sentence: \"A\"",
+    ] {
+        assert!(
+            stats.lines().any(|stat| stat == *line),
+            "{}\n{}",
+            line,
+            stats
+        );
+    }
+}
@@ -44,7 +44,7 @@ const BNF_FOR_BNF: &str = "
                             | \"%\" | \"&\" | \"(\" | \")\" | \"*\" | \"+\"
                             | \",\" | \"-\" | \".\" | \"/\" | \":\" | \";\"
                             |\">\" | \"=\" | \"<\" | \"?\" | \"@\" | \"[\"
-                            | \"\\\" | \"]\" | \"^\" | \"_\" | \"`\"
+                            | \"\\\\\\\\\" | \"]\" | \"^\" | \"_\" | \"`\"
                             | \"{{\" | \"}}\" | \"~\"
         <character1>     ::= <character> | \"'\"
         <character2>     ::= <character> | '\"'
@@ -12,7 +12,7 @@ fn iterate_grammar() {
 
     let left_hand_terms: Vec<&Term> = dna_grammar
         .productions_iter()
-        .map(|ref prod| &prod.lhs)
+        .map(|prod| prod.lhs())
         .collect();
 
     // should be as many left hand terms as productions
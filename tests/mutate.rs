@@ -0,0 +1,125 @@
+extern crate bnf;
+extern crate quickcheck;
+extern crate rand;
+
+use bnf::{Error, Grammar, MutateOptions, Nonterminal};
+use quickcheck::{QuickCheck, TestResult};
+use rand::{SeedableRng, StdRng};
+
+const GRAMMARS: &[&str] = &[
+    "<dna> ::= <base> | <base> <dna>
+    <base> ::= \"A\" | \"C\" | \"G\" | \"T\"",
+    "<expr> ::= <term> | <term> \"+\" <expr>
+    <term> ::= <factor> | <factor> \"*\" <term>
+    <factor> ::= <digit> | \"(\" <expr> \")\"
+    <digit> ::= [0-9]",
+    "<list> ::= \"[\" <items> \"]\"
+    <items> ::= \"\" | <item> | <item> \",\" <items>
+    <item> ::= <list> %weight 1 | <word> %weight 4
+    <word> ::= <undefined> | \"\" <letter> <word> | <letter>
+    <letter> ::= [a-zé]",
+];
+
+// Mutating a generated sentence should always give a sentence which parses
+fn prop_mutation_parses(seed: Vec<usize>, mutations: usize, size_bias: u32) -> TestResult {
+    if seed.is_empty() {
+        return TestResult::discard();
+    }
+    let mut rng: StdRng = SeedableRng::from_seed(&seed[..]);
+    let options = MutateOptions::new()
+        .mutations(mutations % 5)
+        .size_bias(size_bias % 4);
+
+    for input in GRAMMARS {
+        let grammar = Grammar::from_str(input).unwrap();
+        let start = grammar
+            .productions_iter()
+            .next()
+            .unwrap()
+            .nonterminal()
+            .unwrap();
+        let sentence = match grammar.generate_seeded(&mut rng) {
+            Ok(sentence) => sentence,
+            Err(_) => continue,
+        };
+        match grammar.mutate_with_options(&sentence, &options, &mut rng) {
+            Ok(mutated) => {
+                if grammar.count_parses(&start, &mutated) == 0 {
+                    return TestResult::error(format!(
+                        "{:?} mutated from {:?} doesn't parse",
+                        mutated, sentence
+                    ));
+                }
+            }
+            // regenerating a subtree may hit the recursion limit
+            Err(Error::RecursionLimit(_)) => (),
+            Err(e) => return TestResult::error(format!("{:?} failed: {}", sentence, e)),
+        }
+    }
+    TestResult::passed()
+}
+
+#[test]
+fn test_mutations_parse() {
+    QuickCheck::new()
+        .tests(300)
+        .quickcheck(prop_mutation_parses as fn(Vec<usize>, usize, u32) -> TestResult)
+}
+
+// Shrinking a generated sentence should give a sentence which parses, is no
+// longer, and is still interesting
+fn prop_shrink_parses(seed: Vec<usize>) -> TestResult {
+    if seed.is_empty() {
+        return TestResult::discard();
+    }
+    let mut rng: StdRng = SeedableRng::from_seed(&seed[..]);
+
+    for input in GRAMMARS {
+        let grammar = Grammar::from_str(input).unwrap();
+        let start = grammar
+            .productions_iter()
+            .next()
+            .unwrap()
+            .nonterminal()
+            .unwrap();
+        let sentence = match grammar.generate_seeded(&mut rng) {
+            Ok(sentence) => sentence,
+            Err(_) => continue,
+        };
+        let last = sentence.chars().last();
+        let interesting = |s: &str| s.chars().last() == last;
+        let shrunk = match grammar.shrink(&sentence, interesting) {
+            Ok(shrunk) => shrunk,
+            Err(e) => return TestResult::error(format!("{:?} failed: {}", sentence, e)),
+        };
+        let valid = grammar.count_parses(&start, &shrunk) > 0
+            && shrunk.chars().count() <= sentence.chars().count()
+            && interesting(&shrunk);
+        if !valid {
+            return TestResult::error(format!("{:?} shrunk to {:?}", sentence, shrunk));
+        }
+    }
+    TestResult::passed()
+}
+
+#[test]
+fn test_shrinks_parse() {
+    QuickCheck::new()
+        .tests(100)
+        .quickcheck(prop_shrink_parses as fn(Vec<usize>) -> TestResult)
+}
+
+#[test]
+fn mutate_parses_all_productions() {
+    let grammar = Grammar::from_str(
+        "<s> ::= \"a\" <s> | \"a\"
+        <s> ::= \"b\"",
+    )
+    .unwrap();
+    let s = Nonterminal::from("s");
+    let mut rng = rand::thread_rng();
+    for _ in 0..20 {
+        let mutated = grammar.mutate("aab", &mut rng).unwrap();
+        assert!(grammar.count_parses(&s, &mutated) > 0, "{}", mutated);
+    }
+}
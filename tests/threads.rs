@@ -0,0 +1,55 @@
+extern crate bnf;
+extern crate rand;
+
+use bnf::{Error, GenerateOptions, Grammar, Nonterminal, ParseOptions, ParseTree, Term};
+use rand::{SeedableRng, StdRng};
+use std::sync::Arc;
+use std::thread;
+
+fn assert_send_sync<T: Send + Sync>() {}
+
+#[test]
+fn types_are_send_and_sync() {
+    assert_send_sync::<Grammar>();
+    assert_send_sync::<Term>();
+    assert_send_sync::<ParseTree>();
+    assert_send_sync::<ParseOptions>();
+    assert_send_sync::<GenerateOptions>();
+    assert_send_sync::<Error>();
+}
+
+#[test]
+fn share_grammar_across_threads() {
+    let grammar = Arc::new(
+        Grammar::from_str(
+            "<sum> ::= <num> | <num> \"+\" <sum>
+            <num> ::= \"1\" | \"2\" | \"3\"",
+        )
+        .unwrap(),
+    );
+    let sum = Nonterminal::from("sum");
+
+    let handles: Vec<_> = (0..4)
+        .map(|seed| {
+            let grammar = Arc::clone(&grammar);
+            let sum = sum.clone();
+            thread::spawn(move || {
+                let mut rng: StdRng = SeedableRng::from_seed(&[seed][..]);
+                let sentence = grammar.generate_seeded(&mut rng).unwrap();
+                assert!(grammar.count_parses(&sum, &sentence) > 0, "{}", sentence);
+                sentence
+            })
+        })
+        .collect();
+    let sentences: Vec<String> = handles
+        .into_iter()
+        .map(|handle| handle.join().unwrap())
+        .collect();
+
+    // the same seeds generate the same sentences on this thread
+    for (seed, sentence) in sentences.iter().enumerate() {
+        let mut rng: StdRng = SeedableRng::from_seed(&[seed][..]);
+        assert_eq!(grammar.generate_seeded(&mut rng).as_ref(), Ok(sentence));
+    }
+    assert_eq!(Arc::strong_count(&grammar), 1);
+}